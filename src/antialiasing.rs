@@ -0,0 +1,216 @@
+//! Antialiased lines for 256 color modes.
+//!
+//! Lines are drawn with Xiaolin Wu's algorithm, which splits the coverage
+//! of every step of the line between the two pixels it falls between. The
+//! coverage is turned into a color with an `IntensityRamp`, either a range
+//! of palette entries set up as a gradient, or the palette entries closest
+//! to blends between the line color and a known background color.
+
+use crate::{
+    colors::Palette,
+    drawing::{clip_line, Point},
+    writers::PrimitiveDrawing,
+};
+
+/// The number of intensity levels of an `IntensityRamp`.
+pub const INTENSITY_LEVELS: usize = 16;
+
+/// Full coverage of a pixel, in 16.16 fixed point.
+const FULL_COVERAGE: u32 = 0x1_0000;
+
+/// The colors pixels are drawn with at increasing coverage by an
+/// antialiased line, from the background at level 0 to the line color at
+/// level `INTENSITY_LEVELS - 1`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IntensityRamp {
+    colors: [u8; INTENSITY_LEVELS],
+}
+
+impl IntensityRamp {
+    /// Creates a new `IntensityRamp` from the `count` palette entries
+    /// starting at `first`, which should fade from the background color
+    /// to the line color.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is 0 or the entries go past the end of the palette.
+    pub const fn from_palette_range(first: u8, count: u8) -> IntensityRamp {
+        assert!(
+            count > 0 && first as usize + count as usize <= 256,
+            "palette range must hold between 1 and 256 colors"
+        );
+        let mut colors = [0; INTENSITY_LEVELS];
+        let mut level = 0;
+        while level < INTENSITY_LEVELS {
+            let offset = level * (count as usize - 1) / (INTENSITY_LEVELS - 1);
+            colors[level] = first + offset as u8;
+            level += 1;
+        }
+        IntensityRamp { colors }
+    }
+
+    /// Creates a new `IntensityRamp` of the colors in `palette` closest to
+    /// the blends of `color` over `background`, for lines drawn over a
+    /// solid background without setting up a gradient in the palette.
+    pub fn blended(palette: &Palette<256>, background: u8, color: u8) -> IntensityRamp {
+        let rgb = palette.to_rgb();
+        let (background, color) = (rgb[background as usize], rgb[color as usize]);
+        let mut colors = [0; INTENSITY_LEVELS];
+        for (level, ramp_color) in colors.iter_mut().enumerate() {
+            let blend = |background: u8, color: u8| {
+                let range = INTENSITY_LEVELS as i32 - 1;
+                let value =
+                    background as i32 + (color as i32 - background as i32) * level as i32 / range;
+                value as u8
+            };
+            let mix = (
+                blend(background.0, color.0),
+                blend(background.1, color.1),
+                blend(background.2, color.2),
+            );
+            *ramp_color = palette.nearest(mix);
+        }
+        IntensityRamp { colors }
+    }
+
+    /// Returns the color of the given intensity `level`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` isn't less than `INTENSITY_LEVELS`.
+    pub const fn get_color(&self, level: usize) -> u8 {
+        self.colors[level]
+    }
+
+    /// Returns the color for `coverage` out of `FULL_COVERAGE`, rounded to
+    /// the nearest level.
+    fn color_for(&self, coverage: u32) -> u8 {
+        let range = INTENSITY_LEVELS as u32 - 1;
+        self.colors[((coverage * range + FULL_COVERAGE / 2) / FULL_COVERAGE) as usize]
+    }
+}
+
+/// Implementations of this trait can draw antialiased lines in 256 color modes.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "graphics-modes"))]
+/// # {
+/// use vga::antialiasing::{AntialiasedDrawing, IntensityRamp};
+/// use vga::colors::DEFAULT_PALETTE;
+/// use vga::writers::{Graphics320x200x256, GraphicsWriter};
+///
+/// let mode = Graphics320x200x256::new();
+/// mode.set_mode();
+/// mode.clear_screen(0x00);
+///
+/// // The default palette has a gray ramp from 0x10 to 0x1F.
+/// let gray = IntensityRamp::from_palette_range(0x10, 16);
+/// mode.draw_line_aa((10, 10), (300, 57), &gray);
+///
+/// let red = IntensityRamp::blended(&DEFAULT_PALETTE, 0x00, 0x28);
+/// mode.draw_line_aa((10, 20), (300, 190), &red);
+/// # }
+/// ```
+pub trait AntialiasedDrawing: PrimitiveDrawing<u8> {
+    /// Draws an antialiased line from `start` to `end` with the colors of
+    /// `ramp`, clipped to the clip bounds. Pixels the line doesn't cover
+    /// are left unchanged.
+    fn draw_line_aa(&self, start: Point<isize>, end: Point<isize>, ramp: &IntensityRamp) {
+        let bounds = self.get_clip_bounds();
+        let ((mut x0, mut y0), (mut x1, mut y1)) = match clip_line(start, end, bounds) {
+            Some(line) => line,
+            None => return,
+        };
+
+        // Step along the longer axis, left to right.
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            core::mem::swap(&mut x0, &mut y0);
+            core::mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            core::mem::swap(&mut x0, &mut x1);
+            core::mem::swap(&mut y0, &mut y1);
+        }
+        let plot = |x: isize, y: isize, coverage: u32| {
+            let (x, y) = if steep { (y, x) } else { (x, y) };
+            if coverage > 0 && x >= 0 && y >= 0 && bounds.contains(x as usize, y as usize) {
+                self.set_pixel(x as usize, y as usize, ramp.color_for(coverage));
+            }
+        };
+
+        let delta_x = x1 - x0;
+        let gradient = if delta_x == 0 {
+            0
+        } else {
+            ((y1 - y0) << 16) / delta_x
+        };
+        plot(x0, y0, FULL_COVERAGE);
+        let mut y = (y0 << 16) + gradient;
+        for x in x0 + 1..x1 {
+            let fraction = (y & 0xFFFF) as u32;
+            plot(x, y >> 16, FULL_COVERAGE - fraction);
+            plot(x, (y >> 16) + 1, fraction);
+            y += gradient;
+        }
+        if x1 != x0 {
+            plot(x1, y1, FULL_COVERAGE);
+        }
+    }
+}
+
+impl<W: PrimitiveDrawing<u8>> AntialiasedDrawing for W {}
+
+#[cfg(all(test, feature = "global", feature = "graphics-modes"))]
+mod test {
+    use super::*;
+    use crate::{
+        colors::DEFAULT_PALETTE,
+        mock::lock_for_test,
+        writers::{Graphics320x200x256, GraphicsWriter, Screen},
+    };
+
+    #[test]
+    fn test_intensity_ramp() {
+        let ramp = IntensityRamp::from_palette_range(0x10, 16);
+        assert_eq!([ramp.get_color(0), ramp.get_color(15)], [0x10, 0x1F]);
+        let ramp = IntensityRamp::from_palette_range(0x20, 4);
+        assert_eq!([ramp.get_color(0), ramp.get_color(7)], [0x20, 0x21]);
+        assert_eq!(ramp.get_color(15), 0x23);
+
+        let ramp = IntensityRamp::blended(&DEFAULT_PALETTE, 0x00, 0x0F);
+        assert_eq!([ramp.get_color(0), ramp.get_color(15)], [0x00, 0x0F]);
+        assert_eq!(ramp.color_for(FULL_COVERAGE / 2), ramp.get_color(8));
+    }
+
+    #[test]
+    fn test_draw_line_aa() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+        let frame_buffer = mode.get_frame_buffer();
+        let pixel = |x, y| unsafe {
+            frame_buffer
+                .add(Graphics320x200x256::offset_of(x, y))
+                .read()
+        };
+        let ramp = IntensityRamp::from_palette_range(0x10, 16);
+
+        // Halfway between two rows, both pixels are half covered.
+        mode.draw_line_aa((4, 2), (0, 0), &ramp);
+        assert_eq!([pixel(0, 0), pixel(4, 2)], [0x1F, 0x1F]);
+        assert_eq!([pixel(1, 0), pixel(1, 1), pixel(1, 2)], [0x18, 0x18, 0]);
+        assert_eq!([pixel(2, 1), pixel(2, 0), pixel(2, 2)], [0x1F, 0, 0]);
+
+        // Steep lines step along y.
+        mode.draw_line_aa((10, 0), (11, 4), &ramp);
+        assert_eq!([pixel(10, 1), pixel(11, 1)], [0x1B, 0x14]);
+        assert_eq!([pixel(10, 2), pixel(11, 2)], [0x18, 0x18]);
+
+        mode.draw_line_aa((-10, 199), (-1, 150), &ramp);
+        assert_eq!(pixel(0, 199), 0);
+    }
+}
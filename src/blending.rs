@@ -0,0 +1,152 @@
+//! Simulated translucency for 256 color modes.
+//!
+//! A `BlendTable` holds the palette index closest to the 50% blend of
+//! every pair of colors in a palette, so translucent pixels only cost a
+//! read, a table lookup and a write. The table must be rebuilt whenever
+//! the palette changes.
+
+#[cfg(feature = "graphics-modes")]
+use crate::writers::Graphics320x200x256;
+use crate::{
+    colors::{rgb_distance, Palette},
+    drawing::Rect,
+    writers::{GraphicsWriter, Screen},
+};
+#[cfg(feature = "modex")]
+use crate::{registers::ReadPlane, writers::Graphics320x240x256};
+use alloc::{boxed::Box, vec};
+#[cfg(feature = "modex")]
+use core::convert::TryFrom;
+use core::fmt;
+
+const COLOR_COUNT: usize = 256;
+
+/// A lookup table of 50% blends between every pair of colors in a palette.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "graphics-modes"))]
+/// # {
+/// use vga::blending::{BlendTable, BlendedDrawing};
+/// use vga::colors::DEFAULT_PALETTE;
+/// use vga::drawing::Rect;
+/// use vga::writers::{Graphics320x200x256, GraphicsWriter};
+///
+/// let blend_table = BlendTable::new(&DEFAULT_PALETTE);
+/// let mode = Graphics320x200x256::new();
+/// mode.set_mode();
+/// mode.fill_rect_blended(Rect::new(40, 40, 240, 120), 0x01, &blend_table);
+/// # }
+/// ```
+pub struct BlendTable {
+    table: Box<[u8]>,
+}
+
+impl BlendTable {
+    /// Creates a new `BlendTable` for `palette`.
+    pub fn new(palette: &Palette<256>) -> BlendTable {
+        let colors = palette.to_rgb();
+        let mut table = vec![0; COLOR_COUNT * COLOR_COUNT].into_boxed_slice();
+        for source in 0..COLOR_COUNT {
+            for destination in source..COLOR_COUNT {
+                let (a, b) = (colors[source], colors[destination]);
+                let average = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+                let blend = (average(a.0, b.0), average(a.1, b.1), average(a.2, b.2));
+                let nearest = (0..COLOR_COUNT)
+                    .min_by_key(|index| rgb_distance(colors[*index], blend))
+                    .unwrap_or(0) as u8;
+                table[source * COLOR_COUNT + destination] = nearest;
+                table[destination * COLOR_COUNT + source] = nearest;
+            }
+        }
+        BlendTable { table }
+    }
+
+    /// Returns the palette index closest to the 50% blend of `source` and `destination`.
+    pub fn blend(&self, source: u8, destination: u8) -> u8 {
+        self.table[source as usize * COLOR_COUNT + destination as usize]
+    }
+}
+
+impl fmt::Debug for BlendTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlendTable").finish_non_exhaustive()
+    }
+}
+
+/// Implementations of this trait can draw translucent pixels in 256 color modes.
+pub trait BlendedDrawing: GraphicsWriter<u8> + Screen {
+    /// Returns the color of the pixel at `(x, y)`.
+    fn get_pixel(&self, x: usize, y: usize) -> u8;
+
+    /// Blends the pixel at `(x, y)` 50% with `color`.
+    fn set_pixel_blended(&self, x: usize, y: usize, color: u8, blend_table: &BlendTable) {
+        let destination = self.get_pixel(x, y);
+        self.set_pixel(x, y, blend_table.blend(color, destination));
+    }
+
+    /// Blends every pixel of `rect` 50% with `color`, clipped to the screen.
+    fn fill_rect_blended(&self, rect: Rect, color: u8, blend_table: &BlendTable) {
+        let rect = rect.intersection(Rect::new(0, 0, Self::WIDTH, Self::HEIGHT));
+        for y in rect.get_y()..rect.get_y() + rect.get_height() {
+            for x in rect.get_x()..rect.get_x() + rect.get_width() {
+                self.set_pixel_blended(x, y, color, blend_table);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "graphics-modes")]
+impl BlendedDrawing for Graphics320x200x256<'_> {
+    fn get_pixel(&self, x: usize, y: usize) -> u8 {
+        let offset = Self::offset_of(x, y);
+        unsafe { self.get_frame_buffer().add(offset).read_volatile() }
+    }
+}
+
+#[cfg(feature = "modex")]
+impl BlendedDrawing for Graphics320x240x256<'_> {
+    fn get_pixel(&self, x: usize, y: usize) -> u8 {
+        let frame_buffer = self.get_frame_buffer();
+        let offset = Self::offset_of(x, y) / 4;
+        let mut vga = self.lock_vga();
+        vga.graphics_controller_registers
+            .write_read_plane(ReadPlane::try_from((x & 3) as u8).unwrap());
+        unsafe { frame_buffer.add(offset).read_volatile() }
+    }
+}
+
+#[cfg(all(test, feature = "global", feature = "graphics-modes"))]
+mod test {
+    use super::*;
+    use crate::{colors::DEFAULT_PALETTE, mock::lock_for_test};
+
+    #[test]
+    fn test_blend_table() {
+        let blend_table = BlendTable::new(&DEFAULT_PALETTE);
+        let colors = DEFAULT_PALETTE.to_rgb();
+        // The palette has duplicates, so compare the colors rather than the indexes.
+        for color in [0x00, 0x01, 0x0F, 0x28, 0xFF] {
+            let blend = blend_table.blend(color, color);
+            assert_eq!(colors[blend as usize], colors[color as usize]);
+        }
+        assert_eq!(blend_table.blend(0x01, 0x0F), blend_table.blend(0x0F, 0x01));
+    }
+
+    #[test]
+    fn test_fill_rect_blended() {
+        let _guard = lock_for_test();
+        let blend_table = BlendTable::new(&DEFAULT_PALETTE);
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0x0F);
+        mode.fill_rect_blended(Rect::new(310, 190, 20, 20), 0x00, &blend_table);
+
+        assert_eq!(mode.get_pixel(309, 195), 0x0F);
+        assert_eq!(mode.get_pixel(315, 195), blend_table.blend(0x00, 0x0F));
+        assert_eq!(mode.get_pixel(319, 199), blend_table.blend(0x00, 0x0F));
+    }
+}
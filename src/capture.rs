@@ -0,0 +1,198 @@
+//! Encoding captured frames as image files.
+//!
+//! A `Frame` is either true color, such as a picture rendered by the
+//! emulator with the text mode font, or palette indices read from a 256
+//! color mode together with the palette. `encode_ppm` and `encode_bmp`
+//! turn it into the bytes of a file, which can be written to disk or sent
+//! over a serial port for screenshots and regression tests.
+
+use crate::colors::Palette;
+use alloc::vec::Vec;
+
+/// The pixels of a `Frame`, in row-major order.
+#[derive(Debug, Clone, Copy)]
+pub enum FramePixels<'a> {
+    /// `RGBA8888` pixels, such as rendered by `Emulator::render`.
+    Rgba(&'a [u8]),
+    /// One palette index per pixel, and the 6 bit palette they index into.
+    Indexed {
+        /// The palette index of every pixel.
+        pixels: &'a [u8],
+        /// The palette, as loaded into the color palette registers.
+        palette: &'a Palette<256>,
+    },
+}
+
+/// A captured picture to encode.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    width: usize,
+    height: usize,
+    pixels: FramePixels<'a>,
+}
+
+impl<'a> Frame<'a> {
+    /// Creates a new `Frame` of `width` by `height` pixels.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `pixels` holds fewer than `width * height` pixels.
+    pub fn new(
+        width: usize,
+        height: usize,
+        pixels: FramePixels<'a>,
+    ) -> Result<Frame<'a>, &'static str> {
+        let pixel_count = match pixels {
+            FramePixels::Rgba(pixels) => pixels.len() / 4,
+            FramePixels::Indexed { pixels, .. } => pixels.len(),
+        };
+        if pixel_count < width * height {
+            return Err("frame has fewer pixels than its size");
+        }
+        Ok(Frame {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Returns the width of the frame in pixels.
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the frame in pixels.
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Calls `f` with the 8 bit rgb value of every pixel in row `y`.
+    fn for_each_in_row(
+        &self,
+        colors: &[(u8, u8, u8); 256],
+        y: usize,
+        mut f: impl FnMut(u8, u8, u8),
+    ) {
+        let row = y * self.width..(y + 1) * self.width;
+        match self.pixels {
+            FramePixels::Rgba(pixels) => {
+                for pixel in pixels[row.start * 4..row.end * 4].chunks_exact(4) {
+                    f(pixel[0], pixel[1], pixel[2]);
+                }
+            }
+            FramePixels::Indexed { pixels, .. } => {
+                for index in &pixels[row] {
+                    let (red, green, blue) = colors[*index as usize];
+                    f(red, green, blue);
+                }
+            }
+        }
+    }
+
+    /// Returns the 8 bit rgb values of the palette of an indexed frame.
+    fn colors(&self) -> [(u8, u8, u8); 256] {
+        match self.pixels {
+            FramePixels::Rgba(_) => [(0, 0, 0); 256],
+            FramePixels::Indexed { palette, .. } => palette.to_rgb(),
+        }
+    }
+}
+
+/// Encodes `frame` as a binary ppm (`P6`) image.
+pub fn encode_ppm(frame: &Frame<'_>) -> Vec<u8> {
+    let header = alloc::format!("P6\n{} {}\n255\n", frame.width, frame.height);
+    let mut image = Vec::with_capacity(header.len() + frame.width * frame.height * 3);
+    image.extend_from_slice(header.as_bytes());
+
+    let colors = frame.colors();
+    for y in 0..frame.height {
+        frame.for_each_in_row(&colors, y, |red, green, blue| {
+            image.extend_from_slice(&[red, green, blue])
+        });
+    }
+    image
+}
+
+/// Encodes `frame` as an uncompressed 24 bit bmp image.
+pub fn encode_bmp(frame: &Frame<'_>) -> Vec<u8> {
+    const HEADER_SIZE: usize = 14 + 40;
+    // Rows are padded to a multiple of 4 bytes.
+    let row_size = (frame.width * 3 + 3) & !3;
+    let file_size = HEADER_SIZE + row_size * frame.height;
+    let mut image = Vec::with_capacity(file_size);
+
+    // File header.
+    image.extend_from_slice(b"BM");
+    image.extend_from_slice(&(file_size as u32).to_le_bytes());
+    image.extend_from_slice(&[0; 4]);
+    image.extend_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+
+    // BITMAPINFOHEADER, with a negative height for top-down rows.
+    image.extend_from_slice(&40u32.to_le_bytes());
+    image.extend_from_slice(&(frame.width as i32).to_le_bytes());
+    image.extend_from_slice(&(-(frame.height as i32)).to_le_bytes());
+    image.extend_from_slice(&1u16.to_le_bytes());
+    image.extend_from_slice(&24u16.to_le_bytes());
+    image.extend_from_slice(&0u32.to_le_bytes());
+    image.extend_from_slice(&((row_size * frame.height) as u32).to_le_bytes());
+    // 2835 pixels per meter is 72 dpi.
+    image.extend_from_slice(&2835i32.to_le_bytes());
+    image.extend_from_slice(&2835i32.to_le_bytes());
+    image.extend_from_slice(&[0; 8]);
+
+    let colors = frame.colors();
+    let padding = row_size - frame.width * 3;
+    for y in 0..frame.height {
+        frame.for_each_in_row(&colors, y, |red, green, blue| {
+            image.extend_from_slice(&[blue, green, red])
+        });
+        image.extend_from_slice(&[0; 3][..padding]);
+    }
+    image
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::colors::DEFAULT_PALETTE;
+
+    #[test]
+    fn test_encode_ppm() {
+        let pixels = [0x0F, 0x01];
+        let frame = Frame::new(
+            2,
+            1,
+            FramePixels::Indexed {
+                pixels: &pixels,
+                palette: &DEFAULT_PALETTE,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            encode_ppm(&frame),
+            b"P6\n2 1\n255\n\xFF\xFF\xFF\x00\x00\xAA".as_ref()
+        );
+    }
+
+    #[test]
+    fn test_encode_bmp() {
+        let pixels = [
+            0x10, 0x20, 0x30, 0xFF, 0x40, 0x50, 0x60, 0xFF, //
+            0x70, 0x80, 0x90, 0xFF, 0xA0, 0xB0, 0xC0, 0xFF,
+        ];
+        let frame = Frame::new(2, 2, FramePixels::Rgba(&pixels)).unwrap();
+        let image = encode_bmp(&frame);
+        assert_eq!(image.len(), 54 + 8 * 2);
+        assert_eq!(&image[..2], b"BM");
+        assert_eq!(&image[22..26], &(-2i32).to_le_bytes());
+        assert_eq!(
+            &image[54..],
+            &[
+                0x30, 0x20, 0x10, 0x60, 0x50, 0x40, 0, 0, //
+                0x90, 0x80, 0x70, 0xC0, 0xB0, 0xA0, 0, 0,
+            ]
+        );
+
+        assert!(Frame::new(3, 2, FramePixels::Rgba(&pixels)).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! Simple charts for showing kernel diagnostics in graphics modes.
+//!
+//! A `Chart` maps integer values, such as scheduler latencies or memory
+//! usage, onto an area of the screen, and draws labeled axes, line series
+//! and bar series into it with any `PrimitiveDrawing` writer. Series are
+//! clipped to the plot area, so values outside of the chart's range don't
+//! spill over the rest of the screen.
+
+use crate::{
+    drawing::{Point, Rect},
+    writers::PrimitiveDrawing,
+};
+use core::ops::RangeInclusive;
+
+/// The size of the characters drawn by `GraphicsWriter::draw_character`.
+const CHARACTER_SIZE: usize = 8;
+
+/// The length of the tick marks on the value axis.
+const TICK_LENGTH: usize = 3;
+
+/// The most characters an `i64` takes, including its sign.
+const MAX_LABEL_LENGTH: usize = 20;
+
+/// A chart of integer values in a rectangle of the screen, with the value
+/// axis labeled on the left and the series drawn to the right of it.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "graphics-modes"))]
+/// # {
+/// use vga::chart::Chart;
+/// use vga::drawing::Rect;
+/// use vga::writers::{Graphics320x200x256, GraphicsWriter};
+///
+/// let mode = Graphics320x200x256::new();
+/// mode.set_mode();
+/// mode.clear_screen(0x00);
+///
+/// let latencies = [12, 15, 9, 30, 22, 18, 41, 25];
+/// let chart = Chart::new(Rect::new(0, 0, 320, 100), 0..=50);
+/// chart.draw_axes(&mode, 0x0F);
+/// chart.draw_line_series(&mode, &latencies, 0x0A);
+///
+/// let memory_usage = [3, 7, 5];
+/// let chart = Chart::new(Rect::new(0, 110, 320, 90), 0..=8);
+/// chart.draw_axes(&mode, 0x0F);
+/// chart.draw_bar_series(&mode, &memory_usage, 0x09);
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chart {
+    rect: Rect,
+    range: RangeInclusive<i64>,
+}
+
+impl Chart {
+    /// Creates a new `Chart` covering `rect`, with values in `range` spanning
+    /// the height of the plot area.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` holds a single value or none.
+    pub fn new(rect: Rect, range: RangeInclusive<i64>) -> Chart {
+        assert!(
+            range.start() < range.end(),
+            "chart range must hold more than one value"
+        );
+        Chart { rect, range }
+    }
+
+    /// Returns the area covered by the `Chart`, including its axes and labels.
+    pub fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Returns the range of values spanning the height of the plot area.
+    pub fn get_range(&self) -> RangeInclusive<i64> {
+        self.range.clone()
+    }
+
+    /// Returns the area the series are drawn in, to the right of the value
+    /// axis and above the bottom axis.
+    pub fn get_plot_rect(&self) -> Rect {
+        let label_length = label(*self.range.start(), &mut [0; MAX_LABEL_LENGTH])
+            .len()
+            .max(label(*self.range.end(), &mut [0; MAX_LABEL_LENGTH]).len());
+        // Leave room for the labels, the tick marks and the axis itself.
+        let margin = label_length * CHARACTER_SIZE + TICK_LENGTH + 1;
+        Rect::new(
+            self.rect.get_x() + margin,
+            self.rect.get_y(),
+            self.rect.get_width().saturating_sub(margin),
+            self.rect.get_height().saturating_sub(1),
+        )
+    }
+
+    /// Draws the value axis with the bounds of the range as labels, and
+    /// the bottom axis, in `color`.
+    pub fn draw_axes<W, C>(&self, writer: &W, color: C)
+    where
+        W: PrimitiveDrawing<C>,
+        C: Copy,
+    {
+        let plot_rect = self.get_plot_rect();
+        if plot_rect.is_empty() {
+            return;
+        }
+        let axis_x = plot_rect.get_x() as isize - 1;
+        let bottom = (plot_rect.get_y() + plot_rect.get_height()) as isize;
+        let right = (plot_rect.get_x() + plot_rect.get_width()) as isize - 1;
+        writer.draw_line(
+            (axis_x, plot_rect.get_y() as isize),
+            (axis_x, bottom),
+            color,
+        );
+        writer.draw_line((axis_x, bottom), (right, bottom), color);
+
+        for value in [*self.range.start(), *self.range.end()] {
+            let y = self.value_to_y(value, plot_rect);
+            let tick_start = axis_x - TICK_LENGTH as isize;
+            writer.draw_line((tick_start, y), (axis_x, y), color);
+
+            // Center the label on the tick, keeping it within the chart.
+            let mut buffer = [0; MAX_LABEL_LENGTH];
+            let text = label(value, &mut buffer);
+            let label_x = tick_start as usize - text.len() * CHARACTER_SIZE;
+            let label_y = (y as usize)
+                .saturating_sub(CHARACTER_SIZE / 2)
+                .max(self.rect.get_y())
+                .min((bottom as usize + 1).saturating_sub(CHARACTER_SIZE));
+            for (index, character) in text.iter().enumerate() {
+                writer.draw_character(
+                    label_x + index * CHARACTER_SIZE,
+                    label_y,
+                    *character as char,
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Draws `values` as a line through evenly spaced points across the
+    /// plot area, in `color`.
+    pub fn draw_line_series<W, C>(&self, writer: &W, values: &[i64], color: C)
+    where
+        W: PrimitiveDrawing<C>,
+        C: Copy,
+    {
+        let plot_rect = self.get_plot_rect();
+        if plot_rect.is_empty() || values.is_empty() {
+            return;
+        }
+        let point = |index: usize| -> Point<isize> {
+            let steps = (values.len() - 1).max(1);
+            let x = plot_rect.get_x() + index * (plot_rect.get_width() - 1) / steps;
+            (x as isize, self.value_to_y(values[index], plot_rect))
+        };
+        self.with_plot_clip(writer, plot_rect, || {
+            if values.len() == 1 {
+                writer.draw_line(point(0), point(0), color);
+            }
+            for index in 1..values.len() {
+                writer.draw_line(point(index - 1), point(index), color);
+            }
+        });
+    }
+
+    /// Draws `values` as bars of equal width across the plot area, rising
+    /// from the bottom of the range, in `color`.
+    pub fn draw_bar_series<W, C>(&self, writer: &W, values: &[i64], color: C)
+    where
+        W: PrimitiveDrawing<C>,
+        C: Copy,
+    {
+        let plot_rect = self.get_plot_rect();
+        if plot_rect.is_empty() || values.is_empty() {
+            return;
+        }
+        let bottom = plot_rect.get_y() + plot_rect.get_height();
+        self.with_plot_clip(writer, plot_rect, || {
+            for (index, value) in values.iter().enumerate() {
+                let left = plot_rect.get_x() + index * plot_rect.get_width() / values.len();
+                let right = plot_rect.get_x() + (index + 1) * plot_rect.get_width() / values.len();
+                // Keep a gap between bars wider than 2 pixels.
+                let right = if right - left > 2 { right - 1 } else { right };
+                let top = self.value_to_y(*value, plot_rect).max(0) as usize;
+                writer.draw_rect((left, top), (right, bottom), color);
+            }
+        });
+    }
+
+    /// Returns the row of `value` in `plot_rect`, which is outside of it for
+    /// values outside of the range.
+    fn value_to_y(&self, value: i64, plot_rect: Rect) -> isize {
+        let (start, end) = (*self.range.start() as i128, *self.range.end() as i128);
+        let height = plot_rect.get_height() as i128 - 1;
+        let offset = (value as i128 - start) * height / (end - start);
+        let bottom = (plot_rect.get_y() + plot_rect.get_height()) as i128 - 1;
+        (bottom - offset).clamp(isize::MIN as i128, isize::MAX as i128) as isize
+    }
+
+    /// Calls `draw` with the clip rect of `writer` narrowed to `plot_rect`,
+    /// restoring the clip rect afterwards.
+    fn with_plot_clip<W, C>(&self, writer: &W, plot_rect: Rect, draw: impl FnOnce())
+    where
+        W: PrimitiveDrawing<C>,
+        C: Copy,
+    {
+        let clip_rect = writer.get_clip_rect();
+        let plot_clip = match clip_rect {
+            Some(clip_rect) => clip_rect.intersection(plot_rect),
+            None => plot_rect,
+        };
+        writer.set_clip_rect(Some(plot_clip));
+        draw();
+        writer.set_clip_rect(clip_rect);
+    }
+}
+
+/// Writes `value` in decimal to `buffer`, returning the written characters.
+fn label(value: i64, buffer: &mut [u8; MAX_LABEL_LENGTH]) -> &[u8] {
+    let mut magnitude = value.unsigned_abs();
+    let mut start = buffer.len();
+    loop {
+        start -= 1;
+        buffer[start] = b'0' + (magnitude % 10) as u8;
+        magnitude /= 10;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if value < 0 {
+        start -= 1;
+        buffer[start] = b'-';
+    }
+    &buffer[start..]
+}
+
+#[cfg(all(test, feature = "global", feature = "graphics-modes"))]
+mod test {
+    use super::*;
+    use crate::{
+        mock::lock_for_test,
+        writers::{Graphics320x200x256, GraphicsWriter, Screen},
+    };
+
+    #[test]
+    fn test_label() {
+        let mut buffer = [0; MAX_LABEL_LENGTH];
+        assert_eq!(label(0, &mut buffer), b"0");
+        assert_eq!(label(1250, &mut buffer), b"1250");
+        assert_eq!(label(-7, &mut buffer), b"-7");
+        assert_eq!(label(i64::MIN, &mut buffer), b"-9223372036854775808");
+    }
+
+    #[test]
+    fn test_plot_rect() {
+        let chart = Chart::new(Rect::new(10, 20, 100, 50), -5..=100);
+        assert_eq!(chart.get_plot_rect(), Rect::new(38, 20, 72, 49));
+        assert_eq!(chart.value_to_y(-5, chart.get_plot_rect()), 68);
+        assert_eq!(chart.value_to_y(100, chart.get_plot_rect()), 20);
+    }
+
+    #[test]
+    fn test_draw_chart() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+        let frame_buffer = mode.get_frame_buffer();
+        let pixel = |x, y| unsafe {
+            frame_buffer
+                .add(Graphics320x200x256::offset_of(x, y))
+                .read()
+        };
+
+        // Plot area from (12, 0) to (111, 99), with the axes left and below it.
+        let chart = Chart::new(Rect::new(0, 0, 112, 101), 0..=9);
+        assert_eq!(chart.get_plot_rect(), Rect::new(12, 0, 100, 100));
+        chart.draw_axes(&mode, 1);
+        assert_eq!([pixel(11, 0), pixel(11, 100), pixel(111, 100)], [1, 1, 1]);
+        assert_eq!([pixel(8, 99), pixel(7, 99), pixel(12, 99)], [1, 0, 0]);
+
+        chart.draw_line_series(&mode, &[0, 9, 20], 2);
+        assert_eq!([pixel(12, 99), pixel(61, 0), pixel(62, 0)], [2, 2, 0]);
+        assert_eq!(mode.get_clip_rect(), None);
+
+        chart.draw_bar_series(&mode, &[9, 0], 3);
+        assert_eq!([pixel(12, 0), pixel(60, 50), pixel(61, 50)], [3, 3, 0]);
+        assert_eq!([pixel(62, 99), pixel(62, 98), pixel(12, 100)], [3, 0, 1]);
+    }
+}
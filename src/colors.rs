@@ -1,13 +1,134 @@
 //! Common color structures used in vga programming.
 
+use core::convert::TryFrom;
+
 /// Represents the size of the vga palette in bytes.
 pub const PALETTE_SIZE: usize = 768;
 
-/// Represents a 16 bit color used for vga display.
+/// Represents a palette of `N` colors, each made of 6 bit `(red, green,
+/// blue)` values, such as the 16 colors of `EGA_PALETTE` or the 256 colors
+/// of the vga dac, like `DEFAULT_PALETTE`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Palette<const N: usize> {
+    colors: [(u8, u8, u8); N],
+}
+
+impl<const N: usize> Palette<N> {
+    /// Creates a new `Palette` from the given 6 bit `colors`.
+    pub const fn new(colors: [(u8, u8, u8); N]) -> Palette<N> {
+        Palette { colors }
+    }
+
+    /// Creates a new `Palette` from `bytes`, with every 3 bytes
+    /// representing a color, like the palettes of older versions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` doesn't hold exactly `N` colors.
+    pub const fn from_bytes(bytes: &[u8]) -> Palette<N> {
+        assert!(bytes.len() == N * 3, "palette must have 3 bytes per color");
+        let mut colors = [(0, 0, 0); N];
+        let mut index = 0;
+        while index < N {
+            colors[index] = (bytes[index * 3], bytes[index * 3 + 1], bytes[index * 3 + 2]);
+            index += 1;
+        }
+        Palette { colors }
+    }
+
+    /// Returns a `Palette` of `M` colors, with the first colors of `self`
+    /// and black for any colors past the end of `self`.
+    pub const fn resize<const M: usize>(&self) -> Palette<M> {
+        let mut colors = [(0, 0, 0); M];
+        let mut index = 0;
+        while index < N && index < M {
+            colors[index] = self.colors[index];
+            index += 1;
+        }
+        Palette { colors }
+    }
+
+    /// Returns the 6 bit colors of the `Palette`.
+    pub const fn get_colors(&self) -> &[(u8, u8, u8); N] {
+        &self.colors
+    }
+
+    /// Returns the 6 bit color at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` isn't less than `N`.
+    pub const fn get_color(&self, index: usize) -> (u8, u8, u8) {
+        self.colors[index]
+    }
+
+    /// Sets the color at `index` to the given 6 bit `red`, `green` and
+    /// `blue` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` isn't less than `N`.
+    pub fn set_color(&mut self, index: usize, red: u8, green: u8, blue: u8) {
+        self.colors[index] = (red, green, blue);
+    }
+
+    /// Returns the 8 bit rgb value of the color at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` isn't less than `N`.
+    pub fn get_rgb(&self, index: usize) -> (u8, u8, u8) {
+        let scale = |value: u8| (value << 2) | (value >> 4);
+        let (red, green, blue) = self.colors[index];
+        (scale(red & 0x3F), scale(green & 0x3F), scale(blue & 0x3F))
+    }
+
+    /// Returns the 8 bit rgb values of every color.
+    pub fn to_rgb(&self) -> [(u8, u8, u8); N] {
+        let mut colors = [(0, 0, 0); N];
+        for (index, color) in colors.iter_mut().enumerate() {
+            *color = self.get_rgb(index);
+        }
+        colors
+    }
+
+    /// Returns the index of the color closest to the given 8 bit `rgb` value.
+    pub fn nearest(&self, rgb: (u8, u8, u8)) -> u8 {
+        (0..N.min(256))
+            .min_by_key(|index| rgb_distance(self.get_rgb(*index), rgb))
+            .unwrap_or(0) as u8
+    }
+}
+
+impl<const N: usize> Default for Palette<N> {
+    /// Returns a `Palette` of black colors.
+    fn default() -> Palette<N> {
+        Palette::new([(0, 0, 0); N])
+    }
+}
+
+impl From<[u8; PALETTE_SIZE]> for Palette<256> {
+    fn from(value: [u8; PALETTE_SIZE]) -> Palette<256> {
+        Palette::from_bytes(&value)
+    }
+}
+
+impl From<Palette<256>> for [u8; PALETTE_SIZE] {
+    fn from(value: Palette<256>) -> [u8; PALETTE_SIZE] {
+        let mut bytes = [0; PALETTE_SIZE];
+        for (color, (red, green, blue)) in bytes.chunks_exact_mut(3).zip(value.colors.iter()) {
+            color.copy_from_slice(&[*red, *green, *blue]);
+        }
+        bytes
+    }
+}
+
+/// Represents a 16 bit color used for vga display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 #[repr(u8)]
 pub enum Color16 {
     /// Represents the color `Black (0x0)`.
+    #[default]
     Black = 0x0,
     /// Represents the color `Blue (0x1)`.
     Blue = 0x1,
@@ -47,6 +168,152 @@ impl From<Color16> for u8 {
     }
 }
 
+impl TryFrom<u8> for Color16 {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Color16, Self::Error> {
+        match value {
+            0x0..=0xF => Ok(Color16::from_low_bits(value)),
+            _ => Err("value is not a valid Color16"),
+        }
+    }
+}
+
+/// The 8 bit rgb values of the standard colors, indexed by `Color16`.
+const COLOR16_RGB: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00),
+    (0x00, 0x00, 0xAA),
+    (0x00, 0xAA, 0x00),
+    (0x00, 0xAA, 0xAA),
+    (0xAA, 0x00, 0x00),
+    (0xAA, 0x00, 0xAA),
+    (0xAA, 0x55, 0x00),
+    (0xAA, 0xAA, 0xAA),
+    (0x55, 0x55, 0x55),
+    (0x55, 0x55, 0xFF),
+    (0x55, 0xFF, 0x55),
+    (0x55, 0xFF, 0xFF),
+    (0xFF, 0x55, 0x55),
+    (0xFF, 0x55, 0xFF),
+    (0xFF, 0xFF, 0x55),
+    (0xFF, 0xFF, 0xFF),
+];
+
+impl Color16 {
+    /// Returns the color of the lowest 4 bits of `value`, ignoring the rest.
+    pub(crate) const fn from_low_bits(value: u8) -> Color16 {
+        match value & 0x0F {
+            0x0 => Color16::Black,
+            0x1 => Color16::Blue,
+            0x2 => Color16::Green,
+            0x3 => Color16::Cyan,
+            0x4 => Color16::Red,
+            0x5 => Color16::Magenta,
+            0x6 => Color16::Brown,
+            0x7 => Color16::LightGrey,
+            0x8 => Color16::DarkGrey,
+            0x9 => Color16::LightBlue,
+            0xA => Color16::LightGreen,
+            0xB => Color16::LightCyan,
+            0xC => Color16::LightRed,
+            0xD => Color16::Pink,
+            0xE => Color16::Yellow,
+            _ => Color16::White,
+        }
+    }
+
+    /// Returns the standard color closest to the given 8 bit rgb value.
+    pub fn from_rgb(red: u8, green: u8, blue: u8) -> Color16 {
+        let index = (0..COLOR16_RGB.len())
+            .min_by_key(|index| rgb_distance(COLOR16_RGB[*index], (red, green, blue)))
+            .unwrap_or(0);
+        Color16::from_low_bits(index as u8)
+    }
+
+    /// Returns the 8 bit rgb values of every standard color, indexed by `Color16`.
+    pub const fn palette() -> [(u8, u8, u8); 16] {
+        COLOR16_RGB
+    }
+
+    /// Returns the 8 bit rgb value of the standard color.
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        COLOR16_RGB[self as usize]
+    }
+
+    /// Returns the bright variant of a dark color, e.g. `Color16::LightBlue`
+    /// for `Color16::Blue`. Bright colors are returned unchanged.
+    pub fn brighten(self) -> Color16 {
+        Color16::from_low_bits(self as u8 | 0x8)
+    }
+
+    /// Returns the dark variant of a bright color, e.g. `Color16::Blue`
+    /// for `Color16::LightBlue`. Dark colors are returned unchanged.
+    pub fn darken(self) -> Color16 {
+        Color16::from_low_bits(self as u8 & 0x7)
+    }
+
+    /// Returns whether the color is one of the bright variants.
+    pub fn is_bright(self) -> bool {
+        self as u8 & 0x8 != 0
+    }
+}
+
+/// Represents an index into the 256 color palette used by 256 color modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Color256(u8);
+
+impl Color256 {
+    /// Creates a new `Color256` from the given palette `index`.
+    pub const fn new(index: u8) -> Color256 {
+        Color256(index)
+    }
+
+    /// Returns the color of `DEFAULT_PALETTE` closest to the given 8 bit rgb value.
+    ///
+    /// `DEFAULT_PALETTE` is the standard vga palette of 16 colors, a grayscale
+    /// ramp and 24 hues at 9 intensities rather than a 6x6x6 color cube, so
+    /// the closest color is found by searching the palette.
+    pub fn from_rgb(red: u8, green: u8, blue: u8) -> Color256 {
+        Color256(DEFAULT_PALETTE.nearest((red, green, blue)))
+    }
+
+    /// Returns the 8 bit rgb value of the color in `DEFAULT_PALETTE`.
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        DEFAULT_PALETTE.get_rgb(usize::from(self.0))
+    }
+
+    /// Returns the palette index of the color.
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+}
+
+impl From<u8> for Color256 {
+    fn from(value: u8) -> Color256 {
+        Color256(value)
+    }
+}
+
+impl From<Color256> for u8 {
+    fn from(value: Color256) -> u8 {
+        value.0
+    }
+}
+
+impl From<Color16> for Color256 {
+    /// The first 16 colors of `DEFAULT_PALETTE` are the `Color16` colors.
+    fn from(value: Color16) -> Color256 {
+        Color256(value as u8)
+    }
+}
+
+/// Returns the squared euclidean distance between two rgb values.
+pub(crate) fn rgb_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let distance = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    distance(a.0, b.0) + distance(a.1, b.1) + distance(a.2, b.2)
+}
+
 /// Represents a color for vga text modes.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(transparent)]
@@ -59,6 +326,26 @@ impl TextModeColor {
         TextModeColor((background as u8) << 4 | (foreground as u8))
     }
 
+    /// Returns the color with the foreground set to `foreground`.
+    pub const fn with_foreground(self, foreground: Color16) -> TextModeColor {
+        TextModeColor((foreground as u8) | (self.0 & 0xF0))
+    }
+
+    /// Returns the color with the background set to `background`.
+    pub const fn with_background(self, background: Color16) -> TextModeColor {
+        TextModeColor((background as u8) << 4 | (self.0 & 0x0F))
+    }
+
+    /// Returns the foreground color.
+    pub fn get_foreground(self) -> Color16 {
+        Color16::from_low_bits(self.0)
+    }
+
+    /// Returns the background color.
+    pub fn get_background(self) -> Color16 {
+        Color16::from_low_bits(self.0 >> 4)
+    }
+
     /// Sets the background color given the specified `background`;
     pub fn set_background(&mut self, background: Color16) {
         self.0 = (background as u8) << 4 | (self.0 & 0x0F);
@@ -68,10 +355,42 @@ impl TextModeColor {
     pub fn set_foreground(&mut self, foreground: Color16) {
         self.0 = (foreground as u8) | (self.0 & 0xF0);
     }
+
+    /// Returns the color the vga underlines characters in, keeping
+    /// the bright and blink bits of `self`.
+    ///
+    /// The vga underlines characters with a foreground of `Blue` or
+    /// `LightBlue` on a background of `Black` or `DarkGrey`, like the
+    /// underline attribute of monochrome adapters. This is only visible
+    /// when `TextWriter::set_underline_location` puts the underline in the
+    /// character cell, so in color text modes these characters are just
+    /// blue by default.
+    pub const fn underlined(self) -> TextModeColor {
+        TextModeColor((self.0 & 0x88) | 0x01)
+    }
+
+    /// Returns `true` if the vga underlines characters in this color.
+    pub const fn is_underlined(self) -> bool {
+        self.0 & 0x77 == 0x01
+    }
+
+    /// Returns the color with the foreground and background swapped.
+    ///
+    /// **Note:** When blinking is enabled, the top bit of the background
+    /// selects blinking, so a bright foreground becomes a blinking background.
+    pub const fn inverted(self) -> TextModeColor {
+        TextModeColor(self.0.rotate_left(4))
+    }
 }
 
 /// Represents the default vga 256 color palette.
-pub const DEFAULT_PALETTE: [u8; PALETTE_SIZE] = [
+pub const DEFAULT_PALETTE: Palette<256> = Palette::from_bytes(&DEFAULT_PALETTE_BYTES);
+
+/// Represents the 16 color ega palette, the first 16 colors of
+/// `DEFAULT_PALETTE`, which are the `Color16` colors.
+pub const EGA_PALETTE: Palette<16> = DEFAULT_PALETTE.resize();
+
+const DEFAULT_PALETTE_BYTES: [u8; PALETTE_SIZE] = [
     0x00, 0x00, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x2a, 0x00, 0x00, 0x2a, 0x2a, 0x2a, 0x00, 0x00, 0x2a,
     0x00, 0x2a, 0x2a, 0x15, 0x00, 0x2a, 0x2a, 0x2a, 0x15, 0x15, 0x15, 0x15, 0x15, 0x3f, 0x15, 0x3f,
     0x15, 0x15, 0x3f, 0x3f, 0x3f, 0x15, 0x15, 0x3f, 0x15, 0x3f, 0x3f, 0x3f, 0x15, 0x3f, 0x3f, 0x3f,
@@ -147,4 +466,86 @@ mod test {
         color.set_background(Color16::DarkGrey);
         assert_eq!(color.0 >> 4, Color16::DarkGrey as u8);
     }
+
+    #[test]
+    fn test_underlined() {
+        let color = TextModeColor::new(Color16::Yellow, Color16::White).underlined();
+        assert_eq!(color.get_foreground(), Color16::LightBlue);
+        assert_eq!(color.get_background(), Color16::DarkGrey);
+        assert!(color.is_underlined());
+        assert!(!TextModeColor::new(Color16::Blue, Color16::Red).is_underlined());
+    }
+
+    #[test]
+    fn test_inverted() {
+        let color = TextModeColor::new(Color16::Yellow, Color16::Blue).inverted();
+        assert_eq!(color.get_foreground(), Color16::Blue);
+        assert_eq!(color.get_background(), Color16::Yellow);
+        assert_eq!(color.inverted().inverted(), color);
+    }
+
+    #[test]
+    fn test_color16_try_from_u8() {
+        for value in 0..16 {
+            assert_eq!(u8::from(Color16::try_from(value).unwrap()), value);
+        }
+        assert!(Color16::try_from(16).is_err());
+    }
+
+    #[test]
+    fn test_color16_from_rgb() {
+        assert_eq!(Color16::from_rgb(0x00, 0x00, 0x00), Color16::Black);
+        assert_eq!(Color16::from_rgb(0xF0, 0xF0, 0xF0), Color16::White);
+        assert_eq!(Color16::from_rgb(0xA0, 0x60, 0x10), Color16::Brown);
+        assert_eq!(Color16::from_rgb(0x10, 0x10, 0xB0), Color16::Blue);
+    }
+
+    #[test]
+    fn test_color256_from_rgb() {
+        assert_eq!(
+            Color256::from_rgb(0xFF, 0xFF, 0xFF),
+            Color256::from(Color16::White)
+        );
+        assert_eq!(Color256::from_rgb(0x00, 0x00, 0xFF), Color256::new(32));
+        assert_eq!(Color256::new(32).to_rgb(), (0x00, 0x00, 0xFF));
+        assert_eq!(
+            Color256::from(Color16::Blue).to_rgb(),
+            Color16::Blue.to_rgb()
+        );
+    }
+
+    #[test]
+    fn test_palette_nearest() {
+        let mut palette = Palette::<256>::default();
+        palette.set_color(1, 0x3F, 0x00, 0x00);
+        assert_eq!(palette.nearest((0xF0, 0x10, 0x10)), 1);
+        assert_eq!(palette.nearest((0x10, 0x10, 0x10)), 0);
+        assert_eq!(
+            EGA_PALETTE.nearest((0xF0, 0x50, 0x50)),
+            Color16::LightRed as u8
+        );
+    }
+
+    #[test]
+    fn test_palette_conversions() {
+        for (index, color) in Color16::palette().iter().enumerate() {
+            assert_eq!(EGA_PALETTE.get_rgb(index), *color);
+        }
+        let ega: Palette<256> = EGA_PALETTE.resize();
+        assert_eq!(ega.get_color(15), (0x3F, 0x3F, 0x3F));
+        assert_eq!(ega.get_color(16), (0x00, 0x00, 0x00));
+
+        let bytes: [u8; PALETTE_SIZE] = DEFAULT_PALETTE.into();
+        assert_eq!(bytes, DEFAULT_PALETTE_BYTES);
+        assert_eq!(Palette::from(bytes), DEFAULT_PALETTE);
+    }
+
+    #[test]
+    fn test_color16_brighten_darken() {
+        assert_eq!(Color16::Blue.brighten(), Color16::LightBlue);
+        assert_eq!(Color16::LightBlue.darken(), Color16::Blue);
+        assert_eq!(Color16::Black.brighten(), Color16::DarkGrey);
+        assert_eq!(Color16::White.brighten(), Color16::White);
+        assert_eq!(Color16::Red.darken(), Color16::Red);
+    }
 }
@@ -1,8 +1,45 @@
 //! Common video configurations used in vga programming.
 
-use super::registers::{
-    AttributeControllerIndex, CrtcControllerIndex, GraphicsControllerIndex, SequencerIndex,
+use super::{
+    registers::{
+        AttributeControllerIndex, CrtcControllerIndex, GraphicsControllerIndex, SequencerIndex,
+    },
+    vga::ModeInfo,
 };
+use bitflags::bitflags;
+
+/// The highest horizontal sync frequency, in hertz, allowed by `Vga::set_configuration`.
+pub const MAX_HORIZONTAL_SYNC_FREQUENCY: u32 = 32_000;
+
+/// The highest vertical refresh rate, in hertz, allowed by `Vga::set_configuration`.
+pub const MAX_VERTICAL_REFRESH_RATE: u32 = 120;
+
+/// The dot clocks, in hertz, selected by bit 2 of the miscellaneous output register.
+const DOT_CLOCKS: [u32; 2] = [25_175_000, 28_322_000];
+
+bitflags! {
+    /// Represents the values adjusted by `Vga::set_configuration` to make
+    /// a `VgaConfiguration` safe to apply.
+    pub struct ConfigurationAdjustments: u8 {
+        /// Represents no adjustments.
+        const NONE = 0b0000_0000;
+        /// The miscellaneous output register selected an external dot clock,
+        /// which was replaced by the matching standard clock.
+        const DOT_CLOCK = 0b0000_0001;
+        /// The horizontal total or horizontal sync start were adjusted to keep
+        /// the horizontal sync frequency below `MAX_HORIZONTAL_SYNC_FREQUENCY`.
+        const HORIZONTAL_TIMING = 0b0000_0010;
+        /// The vertical total or vertical sync start were adjusted to keep
+        /// the refresh rate below `MAX_VERTICAL_REFRESH_RATE`.
+        const VERTICAL_TIMING = 0b0000_0100;
+        /// The memory map select bits selected the 128K window, which was
+        /// replaced by the 64K window at `0xA0000`.
+        const MEMORY_MAP = 0b0000_1000;
+        /// The sequencer reset register held the sequencer in reset, which
+        /// was replaced by normal operation.
+        const SEQUENCER_RESET = 0b0001_0000;
+    }
+}
 
 /// Represents a set of vga registers for a given mode.
 #[derive(Debug)]
@@ -19,6 +56,310 @@ pub struct VgaConfiguration {
     pub attribute_controller_registers: &'static [(AttributeControllerIndex, u8)],
 }
 
+impl VgaConfiguration {
+    /// Creates a new `VgaConfiguration` from the given register values,
+    /// usable in constants and statics.
+    pub const fn new(
+        miscellaneous_output: u8,
+        sequencer_registers: &'static [(SequencerIndex, u8)],
+        crtc_controller_registers: &'static [(CrtcControllerIndex, u8)],
+        graphics_controller_registers: &'static [(GraphicsControllerIndex, u8)],
+        attribute_controller_registers: &'static [(AttributeControllerIndex, u8)],
+    ) -> VgaConfiguration {
+        VgaConfiguration {
+            miscellaneous_output,
+            sequencer_registers,
+            crtc_controller_registers,
+            graphics_controller_registers,
+            attribute_controller_registers,
+        }
+    }
+
+    /// Returns the configured value for the sequencer register `index`, if any.
+    pub fn sequencer_register(&self, index: SequencerIndex) -> Option<u8> {
+        find_register(self.sequencer_registers, index)
+    }
+
+    /// Returns the configured value for the crtc controller register `index`, if any.
+    pub fn crtc_controller_register(&self, index: CrtcControllerIndex) -> Option<u8> {
+        find_register(self.crtc_controller_registers, index)
+    }
+
+    /// Returns the configured value for the graphics controller register `index`, if any.
+    pub fn graphics_controller_register(&self, index: GraphicsControllerIndex) -> Option<u8> {
+        find_register(self.graphics_controller_registers, index)
+    }
+
+    /// Returns the configured value for the attribute controller register `index`, if any.
+    pub fn attribute_controller_register(&self, index: AttributeControllerIndex) -> Option<u8> {
+        find_register(self.attribute_controller_registers, index)
+    }
+}
+
+/// Represents a custom video mode, as the `VgaConfiguration` setting it
+/// and the `ModeInfo` describing it, set with `Vga::set_mode_definition`.
+///
+/// Mode definitions are usually declared as constants with `vga_mode!`.
+#[derive(Debug)]
+pub struct ModeDefinition {
+    mode_info: ModeInfo,
+    configuration: VgaConfiguration,
+}
+
+impl ModeDefinition {
+    /// Creates a new `ModeDefinition` of `configuration`, described by `mode_info`.
+    pub const fn new(mode_info: ModeInfo, configuration: VgaConfiguration) -> ModeDefinition {
+        ModeDefinition {
+            mode_info,
+            configuration,
+        }
+    }
+
+    /// Returns the `ModeInfo` describing the mode.
+    pub const fn get_mode_info(&self) -> ModeInfo {
+        self.mode_info
+    }
+
+    /// Returns the `VgaConfiguration` setting the mode.
+    pub const fn get_configuration(&self) -> &VgaConfiguration {
+        &self.configuration
+    }
+}
+
+/// Declares a `ModeDefinition` constant for a custom video mode, built
+/// at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use vga::registers::{
+///     AttributeControllerIndex, CrtcControllerIndex, GraphicsControllerIndex, SequencerIndex,
+/// };
+/// use vga::vga::{MemoryLayout, ModeInfo};
+/// use vga::vga_mode;
+///
+/// vga_mode! {
+///     /// Mode 320x200x256 with the miscellaneous output of the standard mode.
+///     pub const MODE_320X200_CUSTOM = {
+///         mode_info: ModeInfo::new(320, 200).with_layout(MemoryLayout::Chained),
+///         miscellaneous_output: 0x63,
+///         sequencer_registers: [
+///             (SequencerIndex::ClockingMode, 0x01),
+///             (SequencerIndex::MemoryMode, 0x0E),
+///         ],
+///         crtc_controller_registers: [(CrtcControllerIndex::Offset, 0x28)],
+///         graphics_controller_registers: [(GraphicsControllerIndex::GraphicsMode, 0x40)],
+///         attribute_controller_registers: [(AttributeControllerIndex::ModeControl, 0x41)],
+///     };
+/// }
+///
+/// assert_eq!(MODE_320X200_CUSTOM.get_mode_info().get_width(), 320);
+/// ```
+#[macro_export]
+macro_rules! vga_mode {
+    (
+        $(#[$attribute:meta])*
+        $visibility:vis const $name:ident = {
+            mode_info: $mode_info:expr,
+            miscellaneous_output: $miscellaneous_output:expr,
+            sequencer_registers: [$($sequencer:expr),* $(,)?],
+            crtc_controller_registers: [$($crtc:expr),* $(,)?],
+            graphics_controller_registers: [$($graphics:expr),* $(,)?],
+            attribute_controller_registers: [$($attribute_controller:expr),* $(,)?] $(,)?
+        };
+    ) => {
+        $(#[$attribute])*
+        $visibility const $name: $crate::configurations::ModeDefinition =
+            $crate::configurations::ModeDefinition::new(
+                $mode_info,
+                $crate::configurations::VgaConfiguration::new(
+                    $miscellaneous_output,
+                    &[$($sequencer),*],
+                    &[$($crtc),*],
+                    &[$($graphics),*],
+                    &[$($attribute_controller),*],
+                ),
+            );
+    };
+}
+
+fn find_register<I: Copy + Into<u8>>(registers: &[(I, u8)], index: I) -> Option<u8> {
+    let index: u8 = index.into();
+    registers
+        .iter()
+        .find(|(register, _)| (*register).into() == index)
+        .map(|(_, value)| *value)
+}
+
+/// Register values replacing the values of a `VgaConfiguration`
+/// that could damage a monitor or leave the vga unusable.
+#[derive(Debug, Default)]
+pub(crate) struct RegisterOverrides {
+    pub(crate) adjustments: ConfigurationAdjustments,
+    pub(crate) miscellaneous_output: Option<u8>,
+    pub(crate) sequencer_reset: Option<u8>,
+    pub(crate) graphics_miscellaneous: Option<u8>,
+    pub(crate) horizontal_total: Option<u8>,
+    pub(crate) horizontal_sync_start: Option<u8>,
+    pub(crate) vertical_total: Option<u8>,
+    pub(crate) vertical_sync_start: Option<u8>,
+    pub(crate) overflow: Option<u8>,
+}
+
+impl Default for ConfigurationAdjustments {
+    fn default() -> ConfigurationAdjustments {
+        ConfigurationAdjustments::NONE
+    }
+}
+
+impl RegisterOverrides {
+    /// Validates `configuration`, using `read_crtc` and `clocking_mode` for
+    /// the current values of registers the configuration doesn't specify.
+    pub(crate) fn new<F>(
+        configuration: &VgaConfiguration,
+        mut read_crtc: F,
+        clocking_mode: u8,
+    ) -> RegisterOverrides
+    where
+        F: FnMut(CrtcControllerIndex) -> u8,
+    {
+        let mut overrides = RegisterOverrides::default();
+        let mut crtc = |index| {
+            configuration
+                .crtc_controller_register(index)
+                .unwrap_or_else(|| read_crtc(index))
+        };
+
+        // Clock selects 2 and 3 are external clocks with unknown frequencies.
+        let mut miscellaneous_output = configuration.miscellaneous_output;
+        if miscellaneous_output & 0x08 != 0 {
+            miscellaneous_output &= !0x08;
+            overrides.miscellaneous_output = Some(miscellaneous_output);
+            overrides.adjustments |= ConfigurationAdjustments::DOT_CLOCK;
+        }
+
+        // Holding the sequencer in reset stops memory refresh.
+        if let Some(value) = configuration.sequencer_register(SequencerIndex::SequencerReset) {
+            if value & 0x03 != 0x03 {
+                overrides.sequencer_reset = Some(0x03);
+                overrides.adjustments |= ConfigurationAdjustments::SEQUENCER_RESET;
+            }
+        }
+
+        // `FrameBuffer` can't represent the 128K window.
+        if let Some(value) =
+            configuration.graphics_controller_register(GraphicsControllerIndex::Miscellaneous)
+        {
+            if value & 0x0C == 0 {
+                overrides.graphics_miscellaneous = Some(value | 0x04);
+                overrides.adjustments |= ConfigurationAdjustments::MEMORY_MAP;
+            }
+        }
+
+        let clocking_mode = configuration
+            .sequencer_register(SequencerIndex::ClockingMode)
+            .unwrap_or(clocking_mode);
+        let mut dot_clock = DOT_CLOCKS[((miscellaneous_output >> 2) & 0x1) as usize];
+        if clocking_mode & 0x08 != 0 {
+            dot_clock /= 2;
+        }
+        let character_width = if clocking_mode & 0x01 != 0 { 8 } else { 9 };
+
+        // The horizontal total is programmed as the number of characters minus 5.
+        let minimum_horizontal_total =
+            dot_clock.div_ceil(character_width * MAX_HORIZONTAL_SYNC_FREQUENCY);
+        let mut horizontal_total = crtc(CrtcControllerIndex::HorizontalTotal) as u32 + 5;
+        if horizontal_total < minimum_horizontal_total {
+            horizontal_total = minimum_horizontal_total.min(0xFF + 5);
+            overrides.horizontal_total = Some((horizontal_total - 5) as u8);
+            overrides.adjustments |= ConfigurationAdjustments::HORIZONTAL_TIMING;
+        }
+        if crtc(CrtcControllerIndex::HorizontalSyncStart) as u32 >= horizontal_total {
+            overrides.horizontal_sync_start = Some((horizontal_total - 1) as u8);
+            overrides.adjustments |= ConfigurationAdjustments::HORIZONTAL_TIMING;
+        }
+
+        // The vertical total is programmed as the number of scan lines minus 2,
+        // with bits 8 and 9 stored in the overflow register.
+        let horizontal_sync_frequency = dot_clock / (character_width * horizontal_total);
+        let minimum_vertical_total = horizontal_sync_frequency.div_ceil(MAX_VERTICAL_REFRESH_RATE);
+        let mut overflow = crtc(CrtcControllerIndex::Overflow);
+        let mut vertical_total = (crtc(CrtcControllerIndex::VeritcalTotal) as u32
+            | (overflow as u32 & 0x01) << 8
+            | (overflow as u32 & 0x20) << 4)
+            + 2;
+        if vertical_total < minimum_vertical_total {
+            vertical_total = minimum_vertical_total.min(0x3FF + 2);
+            let value = vertical_total - 2;
+            overflow =
+                (overflow & !0x21) | ((value >> 8) & 0x1) as u8 | ((value >> 4) & 0x20) as u8;
+            overrides.vertical_total = Some(value as u8);
+            overrides.overflow = Some(overflow);
+            overrides.adjustments |= ConfigurationAdjustments::VERTICAL_TIMING;
+        }
+        let vertical_sync_start = crtc(CrtcControllerIndex::VerticalSyncStart) as u32
+            | (overflow as u32 & 0x04) << 6
+            | (overflow as u32 & 0x80) << 2;
+        if vertical_sync_start >= vertical_total {
+            let value = vertical_total - 1;
+            overflow =
+                (overflow & !0x84) | ((value >> 6) & 0x04) as u8 | ((value >> 2) & 0x80) as u8;
+            overrides.vertical_sync_start = Some(value as u8);
+            overrides.overflow = Some(overflow);
+            overrides.adjustments |= ConfigurationAdjustments::VERTICAL_TIMING;
+        }
+
+        overrides
+    }
+
+    /// Returns the value to write to the sequencer register `index`.
+    pub(crate) fn sequencer(&self, index: SequencerIndex, value: u8) -> u8 {
+        match index {
+            SequencerIndex::SequencerReset => self.sequencer_reset.unwrap_or(value),
+            _ => value,
+        }
+    }
+
+    /// Returns the value to write to the crtc controller register `index`.
+    pub(crate) fn crtc_controller(&self, index: CrtcControllerIndex, value: u8) -> u8 {
+        let value_override = match index {
+            CrtcControllerIndex::HorizontalTotal => self.horizontal_total,
+            CrtcControllerIndex::HorizontalSyncStart => self.horizontal_sync_start,
+            CrtcControllerIndex::VeritcalTotal => self.vertical_total,
+            CrtcControllerIndex::VerticalSyncStart => self.vertical_sync_start,
+            CrtcControllerIndex::Overflow => self.overflow,
+            _ => None,
+        };
+        value_override.unwrap_or(value)
+    }
+
+    /// Returns the value to write to the graphics controller register `index`.
+    pub(crate) fn graphics_controller(&self, index: GraphicsControllerIndex, value: u8) -> u8 {
+        match index {
+            GraphicsControllerIndex::Miscellaneous => self.graphics_miscellaneous.unwrap_or(value),
+            _ => value,
+        }
+    }
+
+    /// Returns every crtc controller register with an override, so they
+    /// can be written even when the configuration doesn't specify them.
+    pub(crate) fn crtc_controller_overrides(&self) -> [(CrtcControllerIndex, Option<u8>); 5] {
+        [
+            (CrtcControllerIndex::HorizontalTotal, self.horizontal_total),
+            (
+                CrtcControllerIndex::HorizontalSyncStart,
+                self.horizontal_sync_start,
+            ),
+            (CrtcControllerIndex::VeritcalTotal, self.vertical_total),
+            (
+                CrtcControllerIndex::VerticalSyncStart,
+                self.vertical_sync_start,
+            ),
+            (CrtcControllerIndex::Overflow, self.overflow),
+        ]
+    }
+}
+
 /// Register values for Vga mode 40x25 Text.
 pub const MODE_40X25_CONFIGURATION: VgaConfiguration = VgaConfiguration {
     // Configuration values acquired from https://www.singlix.com/trdos/archive/vga/Graphics%20in%20pmode.pdf
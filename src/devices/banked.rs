@@ -0,0 +1,270 @@
+use crate::{
+    registers::RegisterPort,
+    vga::Vga,
+    volatile::{volatile_copy, volatile_fill},
+};
+
+pub(crate) const TSENG_SEGMENT_SELECT_ADDRESS: u16 = 0x3CD;
+
+/// The size of a bank, which is the size of the legacy window at `0xA0000`.
+pub const BANK_SIZE: usize = 0x10000;
+
+/// Selects the bank of video memory shown in the legacy window at
+/// `0xA0000`, given the number of the bank in units of `BANK_SIZE`.
+///
+/// The function is called with the `Vga` the frame buffer is drawn
+/// through, so it can reach the chipset's extended registers.
+pub type BankSwitchFn = fn(&mut Vga, usize);
+
+/// Draws to a 256 color svga mode larger than 64K, such as 640x400x256 or
+/// 800x600x256, through the legacy window at `0xA0000`, for cards without
+/// a linear frame buffer.
+///
+/// Video memory is accessed one 64K bank at a time, selected with a
+/// chipset specific `BankSwitchFn`, such as `tseng_et3000_bank_switch`,
+/// `tseng_et4000_bank_switch` or `CirrusDevice::bank_switch`. The current
+/// bank is remembered, so the bank is only switched when drawing crosses
+/// into another one.
+///
+/// Only the Tseng Labs and Cirrus Logic bank switches are provided. Other
+/// chipsets, such as ATI, need their own `BankSwitchFn`.
+///
+/// **Note:** The memory map of the `Vga` must be `MemoryMap::Window64K`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
+/// use vga::devices::{tseng_et4000_bank_switch, BankedFrameBuffer};
+/// use vga::vga::VGA;
+///
+/// // After setting an 800x600x256 mode on a tseng labs et4000.
+/// let mut vga = VGA.lock();
+/// let mut frame_buffer = BankedFrameBuffer::new(800, 600, tseng_et4000_bank_switch);
+/// frame_buffer.clear_screen(&mut vga, 0x01);
+/// frame_buffer.set_pixel(&mut vga, 799, 599, 0x0F);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BankedFrameBuffer {
+    width: usize,
+    height: usize,
+    bytes_per_line: usize,
+    bank_switch: BankSwitchFn,
+    current_bank: Option<usize>,
+}
+
+impl BankedFrameBuffer {
+    /// Creates a new `BankedFrameBuffer` of `width` by `height` pixels,
+    /// switching banks with `bank_switch`.
+    pub const fn new(width: usize, height: usize, bank_switch: BankSwitchFn) -> BankedFrameBuffer {
+        BankedFrameBuffer {
+            width,
+            height,
+            bytes_per_line: width,
+            bank_switch,
+            current_bank: None,
+        }
+    }
+
+    /// Sets the number of bytes of each line, for modes with a line
+    /// longer than their width. This defaults to the width.
+    pub const fn with_bytes_per_line(mut self, bytes_per_line: usize) -> BankedFrameBuffer {
+        self.bytes_per_line = bytes_per_line;
+        self
+    }
+
+    /// Returns the width of the frame buffer in pixels.
+    pub const fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the frame buffer in pixels.
+    pub const fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of bytes of each line of the frame buffer.
+    pub const fn get_bytes_per_line(&self) -> usize {
+        self.bytes_per_line
+    }
+
+    /// Returns the bank shown in the window, or `None` if it isn't known.
+    pub const fn get_current_bank(&self) -> Option<usize> {
+        self.current_bank
+    }
+
+    /// Forgets the current bank, so the next access switches banks again.
+    /// This must be called after anything else switched banks, such as
+    /// a mode change.
+    pub fn invalidate(&mut self) {
+        self.current_bank = None;
+    }
+
+    /// Sets the pixel at `(x, y)` to the palette index `color`.
+    pub fn set_pixel(&mut self, vga: &mut Vga, x: usize, y: usize, color: u8) {
+        let window = self.select(vga, y * self.bytes_per_line + x);
+        unsafe {
+            window.write_volatile(color);
+        }
+    }
+
+    /// Returns the palette index of the pixel at `(x, y)`.
+    pub fn get_pixel(&mut self, vga: &mut Vga, x: usize, y: usize) -> u8 {
+        let window = self.select(vga, y * self.bytes_per_line + x);
+        unsafe { window.read_volatile() }
+    }
+
+    /// Clears the screen by setting all pixels to the palette index `color`.
+    pub fn clear_screen(&mut self, vga: &mut Vga, color: u8) {
+        self.fill(vga, 0, color, self.height * self.bytes_per_line);
+    }
+
+    /// Sets every pixel of the `width` by `height` rectangle with its top
+    /// left corner at `(x, y)` to the palette index `color`.
+    pub fn fill_rect(
+        &mut self,
+        vga: &mut Vga,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        color: u8,
+    ) {
+        for y in y..y + height {
+            self.fill(vga, y * self.bytes_per_line + x, color, width);
+        }
+    }
+
+    /// Copies `bytes` to the frame buffer, starting `offset` bytes into it,
+    /// switching banks where the copy crosses a bank boundary.
+    pub fn write_bytes(&mut self, vga: &mut Vga, offset: usize, bytes: &[u8]) {
+        let mut offset = offset;
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            let count = bytes.len().min(BANK_SIZE - offset % BANK_SIZE);
+            let window = self.select(vga, offset);
+            unsafe {
+                volatile_copy(bytes.as_ptr(), window, count);
+            }
+            offset += count;
+            bytes = &bytes[count..];
+        }
+    }
+
+    fn fill(&mut self, vga: &mut Vga, offset: usize, color: u8, count: usize) {
+        let mut offset = offset;
+        let mut remaining = count;
+        while remaining > 0 {
+            let count = remaining.min(BANK_SIZE - offset % BANK_SIZE);
+            let window = self.select(vga, offset);
+            unsafe {
+                volatile_fill(window, color, count);
+            }
+            offset += count;
+            remaining -= count;
+        }
+    }
+
+    /// Switches to the bank holding `offset`, and returns a pointer to
+    /// where it's shown in the window.
+    fn select(&mut self, vga: &mut Vga, offset: usize) -> *mut u8 {
+        let bank = offset / BANK_SIZE;
+        if self.current_bank != Some(bank) {
+            (self.bank_switch)(vga, bank);
+            self.current_bank = Some(bank);
+        }
+        let window = usize::from(vga.get_frame_buffer()) as *mut u8;
+        unsafe { window.add(offset % BANK_SIZE) }
+    }
+}
+
+/// Switches banks on Tseng Labs ET3000 chipsets, through the read and
+/// write segments in bits 3-5 and 0-2 of the segment select register at
+/// `0x3CD`, with bits 6-7 selecting 64K segments.
+pub fn tseng_et3000_bank_switch(vga: &mut Vga, bank: usize) {
+    let segment = (bank & 0x07) as u8;
+    let mut segment_select =
+        RegisterPort::new(TSENG_SEGMENT_SELECT_ADDRESS, vga.get_register_access());
+    unsafe {
+        segment_select.write(0x40 | segment << 3 | segment);
+    }
+}
+
+/// Switches banks on Tseng Labs ET4000 chipsets, through the read and
+/// write segments in bits 4-7 and 0-3 of the segment select register at
+/// `0x3CD`.
+pub fn tseng_et4000_bank_switch(vga: &mut Vga, bank: usize) {
+    let segment = (bank & 0x0F) as u8;
+    let mut segment_select =
+        RegisterPort::new(TSENG_SEGMENT_SELECT_ADDRESS, vga.get_register_access());
+    unsafe {
+        segment_select.write(segment << 4 | segment);
+    }
+}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::{
+        mock::{lock_for_test, video_memory, MOCK_HARDWARE},
+        vga::{VideoMode, VGA},
+    };
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static BANK_SWITCHES: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_bank_switch(vga: &mut Vga, bank: usize) {
+        BANK_SWITCHES.fetch_add(1, Ordering::Relaxed);
+        tseng_et4000_bank_switch(vga, bank);
+    }
+
+    #[test]
+    fn test_tseng_et3000_bank_switch() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        tseng_et3000_bank_switch(&mut vga, 5);
+        assert_eq!(MOCK_HARDWARE.lock().tseng_segment_select(), 0x6D);
+        tseng_et3000_bank_switch(&mut vga, 0);
+        assert_eq!(MOCK_HARDWARE.lock().tseng_segment_select(), 0x40);
+    }
+
+    #[test]
+    fn test_banked_frame_buffer() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode320x200x256);
+        BANK_SWITCHES.store(0, Ordering::Relaxed);
+        let mut frame_buffer = BankedFrameBuffer::new(800, 600, count_bank_switch);
+
+        frame_buffer.set_pixel(&mut vga, 3, 0, 0x0E);
+        assert_eq!(frame_buffer.get_current_bank(), Some(0));
+        assert_eq!(video_memory()[3], 0x0E);
+        frame_buffer.set_pixel(&mut vga, 4, 0, 0x0E);
+        assert_eq!(BANK_SWITCHES.load(Ordering::Relaxed), 1);
+
+        // Line 82 starts at 0x10040, in the second bank.
+        frame_buffer.set_pixel(&mut vga, 5, 82, 0x0F);
+        assert_eq!(frame_buffer.get_current_bank(), Some(1));
+        assert_eq!(MOCK_HARDWARE.lock().tseng_segment_select(), 0x11);
+        assert_eq!(video_memory()[0x45], 0x0F);
+        assert_eq!(frame_buffer.get_pixel(&mut vga, 5, 82), 0x0F);
+        assert_eq!(BANK_SWITCHES.load(Ordering::Relaxed), 2);
+
+        frame_buffer.write_bytes(&mut vga, 0x2FFFE, &[1, 2, 3, 4]);
+        assert_eq!(frame_buffer.get_current_bank(), Some(3));
+        assert_eq!(MOCK_HARDWARE.lock().tseng_segment_select(), 0x33);
+        assert_eq!(&video_memory()[..2], &[3, 4]);
+        assert_eq!(&video_memory()[0xFFFE..0x10000], &[1, 2]);
+        assert_eq!(BANK_SWITCHES.load(Ordering::Relaxed), 4);
+
+        frame_buffer.invalidate();
+        frame_buffer.fill_rect(&mut vga, 0, 0, 2, 2, 0x09);
+        assert_eq!(frame_buffer.get_current_bank(), Some(0));
+        assert_eq!(&video_memory()[800..802], &[0x09, 0x09]);
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+}
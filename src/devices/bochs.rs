@@ -0,0 +1,684 @@
+use super::{PhysicalAddress, VirtualAddress};
+use crate::{
+    colors::{Palette, DEFAULT_PALETTE},
+    registers::{ColorPaletteRegisters, Port, RegisterAccess},
+    volatile::volatile_fill,
+};
+use core::{
+    convert::TryFrom,
+    ptr::{read_volatile, write_volatile},
+};
+
+pub(crate) const BOCHS_INDEX_ADDRESS: u16 = 0x01CE;
+pub(crate) const BOCHS_DATA_ADDRESS: u16 = 0x01CF;
+#[cfg(any(test, feature = "test-backend"))]
+pub(crate) const BOCHS_REGISTER_COUNT: usize = 0x0B;
+
+const BOCHS_ID_MIN: u16 = 0xB0C0;
+const BOCHS_ID_MAX: u16 = 0xB0C5;
+// The first version supporting an 8 bit dac.
+const BOCHS_ID_8BIT_DAC: u16 = 0xB0C2;
+// Offset of the bochs display interface registers from the vga register
+// window, which starts at `0x400` in the mmio bar.
+const BOCHS_MMIO_OFFSET: usize = 0x100;
+
+const VBE_DISPI_DISABLED: u16 = 0x00;
+const VBE_DISPI_ENABLED: u16 = 0x01;
+const VBE_DISPI_GETCAPS: u16 = 0x02;
+const VBE_DISPI_8BIT_DAC: u16 = 0x20;
+const VBE_DISPI_LFB_ENABLED: u16 = 0x40;
+
+/// Represents an index for the bochs display interface registers.
+#[derive(Debug, Clone, Copy)]
+#[repr(u16)]
+pub enum BochsIndex {
+    /// Represents the `Id` register index.
+    Id = 0x0,
+    /// Represents the `XResolution` register index.
+    XResolution = 0x1,
+    /// Represents the `YResolution` register index.
+    YResolution = 0x2,
+    /// Represents the `BitsPerPixel` register index.
+    BitsPerPixel = 0x3,
+    /// Represents the `Enable` register index.
+    Enable = 0x4,
+    /// Represents the `Bank` register index.
+    Bank = 0x5,
+    /// Represents the `VirtualWidth` register index.
+    VirtualWidth = 0x6,
+    /// Represents the `VirtualHeight` register index.
+    VirtualHeight = 0x7,
+    /// Represents the `XOffset` register index.
+    XOffset = 0x8,
+    /// Represents the `YOffset` register index.
+    YOffset = 0x9,
+    /// Represents the `VideoMemory64K` register index.
+    VideoMemory64K = 0xA,
+}
+
+impl From<BochsIndex> for u16 {
+    fn from(value: BochsIndex) -> u16 {
+        value as u16
+    }
+}
+
+/// Represents the number of bits per pixel of a `BochsDevice`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u16)]
+pub enum Bpp {
+    /// Represents 8 bits per pixel, as indices into the vga dac palette.
+    Bpp8 = 8,
+    /// Represents 15 bits per pixel, as `0bxRRRRRGGGGGBBBBB`.
+    Bpp15 = 15,
+    /// Represents 16 bits per pixel, as `0bRRRRRGGGGGGBBBBB`.
+    Bpp16 = 16,
+    /// Represents 24 bits per pixel, as `0xRRGGBB` stored in 3 bytes.
+    Bpp24 = 24,
+    /// Represents 32 bits per pixel, as `0x00RRGGBB`.
+    Bpp32 = 32,
+}
+
+impl Bpp {
+    /// Returns the number of bytes each pixel takes in the frame buffer.
+    pub const fn get_bytes_per_pixel(self) -> usize {
+        match self {
+            Bpp::Bpp8 => 1,
+            Bpp::Bpp15 | Bpp::Bpp16 => 2,
+            Bpp::Bpp24 => 3,
+            Bpp::Bpp32 => 4,
+        }
+    }
+
+    /// Converts `color`, as `0x00RRGGBB`, to the pixel format of `self`.
+    ///
+    /// **Note:** 8 bits per pixel uses palette indices, so `color` is
+    /// truncated to its low byte instead.
+    pub const fn pack_color(self, color: u32) -> u32 {
+        let red = (color >> 16) & 0xFF;
+        let green = (color >> 8) & 0xFF;
+        let blue = color & 0xFF;
+        match self {
+            Bpp::Bpp8 => color & 0xFF,
+            Bpp::Bpp15 => ((red >> 3) << 10) | ((green >> 3) << 5) | (blue >> 3),
+            Bpp::Bpp16 => ((red >> 3) << 11) | ((green >> 2) << 5) | (blue >> 3),
+            Bpp::Bpp24 | Bpp::Bpp32 => color & 0x00FF_FFFF,
+        }
+    }
+}
+
+impl From<Bpp> for u16 {
+    fn from(value: Bpp) -> u16 {
+        value as u16
+    }
+}
+
+impl TryFrom<u16> for Bpp {
+    type Error = &'static str;
+
+    fn try_from(value: u16) -> Result<Bpp, Self::Error> {
+        match value {
+            8 => Ok(Bpp::Bpp8),
+            15 => Ok(Bpp::Bpp15),
+            16 => Ok(Bpp::Bpp16),
+            24 => Ok(Bpp::Bpp24),
+            32 => Ok(Bpp::Bpp32),
+            _ => Err("unsupported bits per pixel"),
+        }
+    }
+}
+
+/// Represents the resolution of a `BochsDevice` in pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    width: usize,
+    height: usize,
+}
+
+impl Resolution {
+    /// Creates a new `Resolution` of `width` by `height` pixels.
+    pub const fn new(width: usize, height: usize) -> Resolution {
+        Resolution { width, height }
+    }
+
+    /// Returns the width of the `Resolution`.
+    pub const fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the `Resolution`.
+    pub const fn get_height(&self) -> usize {
+        self.height
+    }
+}
+
+/// Drives the bochs display interface, as emulated by bochs and qemu
+/// (`-vga std`), which provides a linear frame buffer at resolutions
+/// beyond what the vga registers support.
+///
+/// The device defaults to 32 bits per pixel, and `set_bits_per_pixel`
+/// selects any other `Bpp`. At 8 bits per pixel, pixels are indices into
+/// the vga dac palette, set with `load_palette`.
+///
+/// Devices without io ports, such as qemu's `secondary-vga` used as a
+/// second display, are driven through their mmio bar with
+/// `BochsDevice::with_register_access`.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// use vga::devices::{BochsDevice, PhysicalAddress, Resolution, VirtualAddress};
+///
+/// // The linear frame buffer is found in bar 0 of the pci device `1234:1111`,
+/// // and must be mapped by the kernel.
+/// let mut device = BochsDevice::new(
+///     PhysicalAddress::new(0xFD00_0000),
+///     VirtualAddress::new(0xFD00_0000),
+/// );
+/// if device.is_present() {
+///     device.set_resolution(Resolution::new(1024, 768)).unwrap();
+///     device.clear_screen(0x0000_00FF);
+///     device.set_pixel(10, 10, 0x00FF_FFFF);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BochsDevice {
+    index_port: Port<u16>,
+    data_port: Port<u16>,
+    register_access: RegisterAccess,
+    physical_address: PhysicalAddress,
+    virtual_address: VirtualAddress,
+    resolution: Resolution,
+    bpp: Bpp,
+    dac_8bit: bool,
+    color_palette_registers: ColorPaletteRegisters,
+}
+
+impl BochsDevice {
+    /// Creates a new `BochsDevice` with its linear frame buffer at
+    /// `physical_address`, mapped at `virtual_address`.
+    pub fn new(physical_address: PhysicalAddress, virtual_address: VirtualAddress) -> BochsDevice {
+        BochsDevice::with_register_access(physical_address, virtual_address, RegisterAccess::Port)
+    }
+
+    /// Creates a new `BochsDevice` like `BochsDevice::new`, accessing its
+    /// registers as specified by `register_access`.
+    ///
+    /// With `RegisterAccess::Mmio`, the address is that of the vga register
+    /// window at offset `0x400` of the mmio bar, the same as for
+    /// `Vga::with_register_access`, and the bochs display interface registers
+    /// are found at offset `0x500`.
+    pub fn with_register_access(
+        physical_address: PhysicalAddress,
+        virtual_address: VirtualAddress,
+        register_access: RegisterAccess,
+    ) -> BochsDevice {
+        let mut device = BochsDevice {
+            index_port: Port::new(BOCHS_INDEX_ADDRESS),
+            data_port: Port::new(BOCHS_DATA_ADDRESS),
+            register_access,
+            physical_address,
+            virtual_address,
+            resolution: Resolution::new(0, 0),
+            bpp: Bpp::Bpp32,
+            dac_8bit: false,
+            color_palette_registers: ColorPaletteRegisters::new(register_access),
+        };
+        device.resolution = Resolution::new(
+            device.read(BochsIndex::XResolution) as usize,
+            device.read(BochsIndex::YResolution) as usize,
+        );
+        if let Ok(bpp) = Bpp::try_from(device.read(BochsIndex::BitsPerPixel)) {
+            device.bpp = bpp;
+        }
+        device.dac_8bit = device.read(BochsIndex::Enable) & VBE_DISPI_8BIT_DAC != 0;
+        device
+    }
+
+    /// Returns the version id reported by the device, between `0xB0C0` and `0xB0C5`.
+    pub fn get_id(&mut self) -> u16 {
+        self.read(BochsIndex::Id)
+    }
+
+    /// Returns `true` if a bochs display interface responds to its ports.
+    pub fn is_present(&mut self) -> bool {
+        (BOCHS_ID_MIN..=BOCHS_ID_MAX).contains(&self.get_id())
+    }
+
+    /// Returns how the device's registers are accessed.
+    pub fn get_register_access(&self) -> RegisterAccess {
+        self.register_access
+    }
+
+    /// Returns the physical address of the linear frame buffer.
+    pub fn get_physical_address(&self) -> PhysicalAddress {
+        self.physical_address
+    }
+
+    /// Returns the virtual address the linear frame buffer is mapped at.
+    pub fn get_virtual_address(&self) -> VirtualAddress {
+        self.virtual_address
+    }
+
+    /// Returns the current `Resolution` of the device.
+    pub fn get_resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Returns the current `Bpp` of the device.
+    pub fn get_bits_per_pixel(&self) -> Bpp {
+        self.bpp
+    }
+
+    /// Sets the device to `bpp` bits per pixel at the current resolution.
+    ///
+    /// Selecting `Bpp::Bpp8` also loads the default 256 color palette
+    /// into the vga dac.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, leaving the mode unchanged, if the current
+    /// resolution at `bpp` doesn't fit the device's capabilities or
+    /// video memory.
+    pub fn set_bits_per_pixel(&mut self, bpp: Bpp) -> Result<(), &'static str> {
+        self.set_mode(self.resolution, bpp)?;
+        if bpp == Bpp::Bpp8 {
+            self.load_palette(&DEFAULT_PALETTE);
+        }
+        Ok(())
+    }
+
+    /// Returns the size of the device's video memory in bytes.
+    ///
+    /// **Note:** This is the size reported by the device, which matches the
+    /// size of pci bar 0. Only the part mapped at the virtual address
+    /// can be drawn to.
+    pub fn video_memory_size(&mut self) -> usize {
+        self.read(BochsIndex::VideoMemory64K) as usize * 0x10000
+    }
+
+    /// Returns the largest `Resolution` the device supports.
+    pub fn get_max_resolution(&mut self) -> Resolution {
+        let (resolution, _) = self.read_capabilities();
+        resolution
+    }
+
+    /// Returns `true` if the dac of the device can be switched to 8 bits
+    /// per color component with `set_8bit_dac`.
+    pub fn supports_8bit_dac(&mut self) -> bool {
+        if self.get_id() < BOCHS_ID_8BIT_DAC {
+            return false;
+        }
+        // Older emulators ignore the bit, so check that it reads back.
+        let enable = self.read(BochsIndex::Enable);
+        self.write(BochsIndex::Enable, enable | VBE_DISPI_8BIT_DAC);
+        let supported = self.read(BochsIndex::Enable) & VBE_DISPI_8BIT_DAC != 0;
+        self.write(BochsIndex::Enable, enable);
+        supported
+    }
+
+    /// Returns `true` if the dac uses 8 bits per color component.
+    pub fn is_8bit_dac(&self) -> bool {
+        self.dac_8bit
+    }
+
+    /// Switches the dac between 8 and 6 bits per color component, which
+    /// stays selected across mode changes.
+    ///
+    /// **Note:** The palette isn't converted, so it should be loaded again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when enabling the 8 bit dac if `supports_8bit_dac`
+    /// is `false`.
+    pub fn set_8bit_dac(&mut self, enabled: bool) -> Result<(), &'static str> {
+        if enabled && !self.supports_8bit_dac() {
+            return Err("the device doesn't support an 8 bit dac");
+        }
+        let enable = self.read(BochsIndex::Enable) & !VBE_DISPI_8BIT_DAC;
+        self.dac_8bit = enabled;
+        self.write(BochsIndex::Enable, enable | self.dac_flag());
+        Ok(())
+    }
+
+    /// Loads the 6 bit `palette` into the vga dac, used for the colors
+    /// of each pixel at 8 bits per pixel. With an 8 bit dac, the colors
+    /// are scaled up to 8 bits.
+    pub fn load_palette<const N: usize>(&mut self, palette: &Palette<N>) {
+        if self.dac_8bit {
+            let scaled = scale_palette(palette, |component| {
+                let component = component & 0x3F;
+                component << 2 | component >> 4
+            });
+            self.color_palette_registers.load_palette(&scaled);
+        } else {
+            self.color_palette_registers.load_palette(palette);
+        }
+    }
+
+    /// Loads the 8 bit `palette` into the vga dac like `load_palette`.
+    /// Without an 8 bit dac, the colors are scaled down to 6 bits.
+    pub fn load_palette_8bit<const N: usize>(&mut self, palette: &Palette<N>) {
+        if self.dac_8bit {
+            self.color_palette_registers.load_palette(palette);
+        } else {
+            let scaled = scale_palette(palette, |component| component >> 2);
+            self.color_palette_registers.load_palette(&scaled);
+        }
+    }
+
+    /// Sets the device to `resolution` with the linear frame buffer enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, leaving the mode unchanged, if `resolution` is
+    /// larger than `get_max_resolution` or doesn't fit in the video memory
+    /// at the current bits per pixel.
+    pub fn set_resolution(&mut self, resolution: Resolution) -> Result<(), &'static str> {
+        self.set_mode(resolution, self.bpp)
+    }
+
+    fn set_mode(&mut self, resolution: Resolution, bpp: Bpp) -> Result<(), &'static str> {
+        let (max_resolution, max_bpp) = self.read_capabilities();
+        if resolution.width > max_resolution.width || resolution.height > max_resolution.height {
+            return Err("resolution exceeds the device's maximum resolution");
+        }
+        if u16::from(bpp) > max_bpp {
+            return Err("bits per pixel exceeds the device's maximum bits per pixel");
+        }
+        let frame_buffer_size = resolution.width * resolution.height * bpp.get_bytes_per_pixel();
+        if frame_buffer_size > self.video_memory_size() {
+            return Err("resolution doesn't fit in video memory");
+        }
+
+        self.bpp = bpp;
+        self.write(BochsIndex::Enable, VBE_DISPI_DISABLED);
+        self.write(BochsIndex::XResolution, resolution.width as u16);
+        self.write(BochsIndex::YResolution, resolution.height as u16);
+        self.set_bpp();
+        self.write(BochsIndex::VirtualWidth, resolution.width as u16);
+        self.write(BochsIndex::XOffset, 0);
+        self.write(BochsIndex::YOffset, 0);
+        self.write(
+            BochsIndex::Enable,
+            VBE_DISPI_ENABLED | VBE_DISPI_LFB_ENABLED | self.dac_flag(),
+        );
+        self.resolution = resolution;
+        Ok(())
+    }
+
+    /// Returns the start of the linear frame buffer.
+    ///
+    /// **Note:** Pixels are only `u32`s at 32 bits per pixel.
+    pub fn get_frame_buffer(&self) -> *mut u32 {
+        self.virtual_address.as_mut_ptr()
+    }
+
+    /// Clears the screen by setting all pixels to `color`, in the
+    /// pixel format of the current `Bpp`.
+    pub fn clear_screen(&self, color: u32) {
+        let pixel_count = self.resolution.width * self.resolution.height;
+        match self.bpp {
+            Bpp::Bpp32 => unsafe {
+                volatile_fill(self.get_frame_buffer(), color, pixel_count);
+            },
+            Bpp::Bpp8 => unsafe {
+                volatile_fill(self.virtual_address.as_mut_ptr(), color as u8, pixel_count);
+            },
+            Bpp::Bpp15 | Bpp::Bpp16 => unsafe {
+                volatile_fill(self.virtual_address.as_mut_ptr(), color as u16, pixel_count);
+            },
+            Bpp::Bpp24 => {
+                for offset in 0..pixel_count {
+                    self.write_pixel(offset, color);
+                }
+            }
+        }
+    }
+
+    /// Sets the pixel at `(x, y)` to `color`, in the pixel format of the
+    /// current `Bpp`, such as `0x00RRGGBB` at 32 bits per pixel or a palette
+    /// index at 8 bits per pixel. `Bpp::pack_color` converts to this format.
+    pub fn set_pixel(&self, x: usize, y: usize, color: u32) {
+        self.write_pixel(y * self.resolution.width + x, color);
+    }
+
+    /// Sets every pixel of the `width` by `height` rectangle with its top
+    /// left corner at `(x, y)` to `color`, in the pixel format of the
+    /// current `Bpp`.
+    pub fn fill_rect(&self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        for y in y..y + height {
+            let line = y * self.resolution.width;
+            for x in x..x + width {
+                self.write_pixel(line + x, color);
+            }
+        }
+    }
+
+    /// Returns the number of bytes of each line of the frame buffer.
+    pub fn get_bytes_per_line(&self) -> usize {
+        self.resolution.width * self.bpp.get_bytes_per_pixel()
+    }
+
+    /// Reads the current value of the register specified by `index`.
+    pub fn read(&mut self, index: BochsIndex) -> u16 {
+        match self.register_access {
+            RegisterAccess::Port => unsafe {
+                self.index_port.write(u16::from(index));
+                self.data_port.read()
+            },
+            RegisterAccess::Mmio(base) => unsafe { read_volatile(Self::mmio_address(base, index)) },
+        }
+    }
+
+    /// Writes `value` to the register specified by `index`.
+    pub fn write(&mut self, index: BochsIndex, value: u16) {
+        match self.register_access {
+            RegisterAccess::Port => unsafe {
+                self.index_port.write(u16::from(index));
+                self.data_port.write(value);
+            },
+            RegisterAccess::Mmio(base) => unsafe {
+                write_volatile(Self::mmio_address(base, index), value);
+            },
+        }
+    }
+
+    fn mmio_address(base: usize, index: BochsIndex) -> *mut u16 {
+        (base + BOCHS_MMIO_OFFSET + usize::from(u16::from(index)) * 2) as *mut u16
+    }
+
+    fn read_capabilities(&mut self) -> (Resolution, u16) {
+        // While the get capabilities bit is set, the resolution and bits
+        // per pixel registers read back the largest supported values.
+        let enable = self.read(BochsIndex::Enable);
+        self.write(BochsIndex::Enable, enable | VBE_DISPI_GETCAPS);
+        let capabilities = (
+            Resolution::new(
+                self.read(BochsIndex::XResolution) as usize,
+                self.read(BochsIndex::YResolution) as usize,
+            ),
+            self.read(BochsIndex::BitsPerPixel),
+        );
+        self.write(BochsIndex::Enable, enable);
+        capabilities
+    }
+
+    fn dac_flag(&self) -> u16 {
+        if self.dac_8bit {
+            VBE_DISPI_8BIT_DAC
+        } else {
+            0
+        }
+    }
+
+    fn set_bpp(&mut self) {
+        self.write(BochsIndex::BitsPerPixel, u16::from(self.bpp));
+    }
+
+    fn write_pixel(&self, offset: usize, color: u32) {
+        let frame_buffer = self.virtual_address.as_mut_ptr::<u8>();
+        let offset = offset * self.bpp.get_bytes_per_pixel();
+        unsafe {
+            match self.bpp {
+                Bpp::Bpp8 => frame_buffer.add(offset).write_volatile(color as u8),
+                Bpp::Bpp15 | Bpp::Bpp16 => frame_buffer
+                    .add(offset)
+                    .cast::<u16>()
+                    .write_volatile(color as u16),
+                Bpp::Bpp24 => {
+                    for (i, byte) in color.to_le_bytes()[..3].iter().enumerate() {
+                        frame_buffer.add(offset + i).write_volatile(*byte);
+                    }
+                }
+                Bpp::Bpp32 => frame_buffer.add(offset).cast::<u32>().write_volatile(color),
+            }
+        }
+    }
+}
+
+/// Returns `palette` with `scale` applied to every component.
+fn scale_palette<const N: usize>(palette: &Palette<N>, scale: impl Fn(u8) -> u8) -> Palette<N> {
+    let mut scaled = *palette;
+    for (index, (red, green, blue)) in palette.get_colors().iter().enumerate() {
+        scaled.set_color(index, scale(*red), scale(*green), scale(*blue));
+    }
+    scaled
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::{lock_for_test, MOCK_HARDWARE, VIDEO_MEMORY_SIZE};
+
+    #[test]
+    fn test_set_resolution() {
+        let _guard = lock_for_test();
+        let mut frame_buffer = [0u32; 16 * 8];
+        let virtual_address = VirtualAddress::new(frame_buffer.as_mut_ptr() as usize);
+        let mut device = BochsDevice::new(PhysicalAddress::new(0xFD00_0000), virtual_address);
+        assert!(device.is_present());
+
+        device.set_resolution(Resolution::new(16, 8)).unwrap();
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            assert_eq!(hardware.bochs(BochsIndex::XResolution), 16);
+            assert_eq!(hardware.bochs(BochsIndex::YResolution), 8);
+            assert_eq!(hardware.bochs(BochsIndex::BitsPerPixel), 32);
+            assert_eq!(hardware.bochs(BochsIndex::Enable), 0x41);
+        }
+        assert_eq!(device.get_resolution(), Resolution::new(16, 8));
+
+        device.clear_screen(0x0000_00FF);
+        device.set_pixel(3, 2, 0x00FF_FFFF);
+        assert_eq!(frame_buffer[2 * 16 + 3], 0x00FF_FFFF);
+        assert_eq!(frame_buffer[2 * 16 + 4], 0x0000_00FF);
+    }
+
+    #[test]
+    fn test_set_bits_per_pixel() {
+        let _guard = lock_for_test();
+        let mut frame_buffer = [0u32; 16 * 8];
+        let virtual_address = VirtualAddress::new(frame_buffer.as_mut_ptr() as usize);
+        let mut device = BochsDevice::new(PhysicalAddress::new(0xFD00_0000), virtual_address);
+        device.set_resolution(Resolution::new(16, 8)).unwrap();
+
+        device.set_bits_per_pixel(Bpp::Bpp8).unwrap();
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            assert_eq!(hardware.bochs(BochsIndex::BitsPerPixel), 8);
+            assert_eq!(hardware.palette(), DEFAULT_PALETTE);
+        }
+        device.clear_screen(0x01);
+        device.set_pixel(5, 0, 0x0F);
+        assert_eq!(frame_buffer[1], 0x0101_0F01);
+
+        device.set_bits_per_pixel(Bpp::Bpp24).unwrap();
+        assert_eq!(device.get_bits_per_pixel(), Bpp::Bpp24);
+        device.set_pixel(1, 0, 0x00AA_BBCC);
+        assert_eq!(frame_buffer[0] & 0xFF00_0000, 0xCC00_0000);
+        assert_eq!(frame_buffer[1] & 0x0000_FFFF, 0x0000_AABB);
+        device.set_bits_per_pixel(Bpp::Bpp32).unwrap();
+    }
+
+    #[test]
+    fn test_8bit_dac() {
+        let _guard = lock_for_test();
+        let mut frame_buffer = [0u32; 16 * 8];
+        let virtual_address = VirtualAddress::new(frame_buffer.as_mut_ptr() as usize);
+        let mut device = BochsDevice::new(PhysicalAddress::new(0xFD00_0000), virtual_address);
+        assert!(device.supports_8bit_dac());
+        assert!(!device.is_8bit_dac());
+
+        device.set_8bit_dac(true).unwrap();
+        device.set_resolution(Resolution::new(16, 8)).unwrap();
+        assert_eq!(MOCK_HARDWARE.lock().bochs(BochsIndex::Enable), 0x61);
+        let mut palette = Palette::new([(0x3F, 0x20, 0x00)]);
+        device.load_palette(&palette);
+        assert_eq!(
+            MOCK_HARDWARE.lock().palette().get_color(0),
+            (0xFF, 0x82, 0x00)
+        );
+
+        device.set_8bit_dac(false).unwrap();
+        assert!(!device.is_8bit_dac());
+        palette.set_color(0, 0xFF, 0x80, 0x00);
+        device.load_palette_8bit(&palette);
+        assert_eq!(
+            MOCK_HARDWARE.lock().palette().get_color(0),
+            (0x3F, 0x20, 0x00)
+        );
+        assert_eq!(MOCK_HARDWARE.lock().bochs(BochsIndex::Enable), 0x41);
+        device.load_palette(&DEFAULT_PALETTE);
+    }
+
+    #[test]
+    fn test_set_resolution_limits() {
+        let _guard = lock_for_test();
+        let mut frame_buffer = [0u32; 16 * 8];
+        let virtual_address = VirtualAddress::new(frame_buffer.as_mut_ptr() as usize);
+        let mut device = BochsDevice::new(PhysicalAddress::new(0xFD00_0000), virtual_address);
+        device.set_resolution(Resolution::new(16, 8)).unwrap();
+        assert_eq!(device.video_memory_size(), VIDEO_MEMORY_SIZE);
+        assert_eq!(device.get_max_resolution(), Resolution::new(2560, 1600));
+
+        assert_eq!(
+            device.set_resolution(Resolution::new(4096, 16)),
+            Err("resolution exceeds the device's maximum resolution")
+        );
+        assert_eq!(
+            device.set_resolution(Resolution::new(2048, 1024)),
+            Err("resolution doesn't fit in video memory")
+        );
+        assert_eq!(device.get_resolution(), Resolution::new(16, 8));
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(hardware.bochs(BochsIndex::XResolution), 16);
+        assert_eq!(hardware.bochs(BochsIndex::Enable), 0x41);
+    }
+
+    #[test]
+    fn test_mmio_access() {
+        let mut window = [0u16; 0x100];
+        let access = RegisterAccess::Mmio(window.as_mut_ptr() as usize);
+        window[0x80] = 0xB0C5;
+        let mut device = BochsDevice::with_register_access(
+            PhysicalAddress::new(0xFD00_0000),
+            VirtualAddress::new(0xFD00_0000),
+            access,
+        );
+        assert!(device.is_present());
+        device.write(BochsIndex::VirtualWidth, 640);
+        assert_eq!(window[0x86], 640);
+        assert_eq!(device.get_register_access(), access);
+    }
+
+    #[test]
+    fn test_pack_color() {
+        assert_eq!(Bpp::Bpp8.pack_color(0x0000_0012), 0x12);
+        assert_eq!(Bpp::Bpp15.pack_color(0x00FF_0000), 0x7C00);
+        assert_eq!(Bpp::Bpp16.pack_color(0x0000_FF00), 0x07E0);
+        assert_eq!(Bpp::Bpp32.pack_color(0x00AA_BBCC), 0x00AA_BBCC);
+        assert_eq!(Bpp::try_from(12), Err("unsupported bits per pixel"));
+    }
+}
@@ -0,0 +1,780 @@
+use super::{BankedFrameBuffer, PhysicalAddress, VirtualAddress};
+use crate::{
+    configurations::VgaConfiguration,
+    registers::{
+        AttributeControllerIndex, CrtcControllerIndex, GraphicsControllerIndex, RegisterPort,
+        SequencerIndex, COLOR_PALETTE_MASK_ADDRESS,
+    },
+    vga::{ModeInfo, Vga, VideoMode},
+    volatile::volatile_fill,
+};
+use core::convert::TryFrom;
+
+#[cfg(any(test, feature = "test-backend"))]
+pub(crate) const CIRRUS_SEQUENCER_REGISTER_COUNT: usize = 0x20;
+#[cfg(any(test, feature = "test-backend"))]
+pub(crate) const CIRRUS_CRTC_CONTROLLER_REGISTER_COUNT: usize = 0x28;
+// The first index of the extended registers, which follow the standard ones.
+#[cfg(any(test, feature = "test-backend"))]
+pub(crate) const CIRRUS_FIRST_SEQUENCER_INDEX: u8 = 0x06;
+#[cfg(any(test, feature = "test-backend"))]
+pub(crate) const CIRRUS_FIRST_CRTC_CONTROLLER_INDEX: u8 = 0x19;
+
+pub(crate) const SR_UNLOCK: u8 = 0x06;
+const SR_EXTENDED_SEQUENCER_MODE: u8 = 0x07;
+const SR_VCLK3_NUMERATOR: u8 = 0x0E;
+pub(crate) const SR_DRAM_CONTROL: u8 = 0x0F;
+const SR_VCLK3_DENOMINATOR: u8 = 0x1E;
+const GR_OFFSET_0: u8 = 0x09;
+const GR_GRAPHICS_CONTROLLER_MODE_EXTENSIONS: u8 = 0x0B;
+const CR_MISCELLANEOUS_CONTROL: u8 = 0x1A;
+const CR_EXTENDED_DISPLAY_CONTROLS: u8 = 0x1B;
+const CR_OVERLAY_EXTENDED_CONTROL: u8 = 0x1D;
+pub(crate) const CR_CHIP_ID: u8 = 0x27;
+
+// Any other value written to the unlock register locks the extensions,
+// which then reads back as `0x0F`.
+pub(crate) const EXTENSIONS_UNLOCKED: u8 = 0x12;
+pub(crate) const EXTENSIONS_LOCKED: u8 = 0x0F;
+// Packed 8 bits per pixel, instead of the standard vga memory layout.
+const EXTENDED_SEQUENCER_MODE_8BPP: u8 = 0x01;
+// Enables the extended blanking and display controls, with the start
+// address and offset overflow bits cleared.
+const EXTENDED_DISPLAY_CONTROLS: u8 = 0x22;
+
+/// Represents a Cirrus Logic GD54xx chipset, as identified by the
+/// device id in bits 7:2 of crtc register `0x27`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CirrusChip {
+    /// Represents the CL-GD5422.
+    Gd5422 = 0x8C,
+    /// Represents the CL-GD5426.
+    Gd5426 = 0x90,
+    /// Represents the CL-GD5424.
+    Gd5424 = 0x94,
+    /// Represents the CL-GD5428.
+    Gd5428 = 0x98,
+    /// Represents the CL-GD5430.
+    Gd5430 = 0xA0,
+    /// Represents the CL-GD5434.
+    Gd5434 = 0xA8,
+    /// Represents the CL-GD5436.
+    Gd5436 = 0xAC,
+    /// Represents the CL-GD5446, as emulated by qemu (`-vga cirrus`).
+    Gd5446 = 0xB8,
+}
+
+impl CirrusChip {
+    /// Returns `true` if the chip is found on the pci bus with its linear
+    /// frame buffer in bar 0. Other chips are drawn to through the legacy
+    /// window, with `CirrusDevice::get_banked_frame_buffer`.
+    pub const fn supports_linear_frame_buffer(self) -> bool {
+        matches!(
+            self,
+            CirrusChip::Gd5430 | CirrusChip::Gd5434 | CirrusChip::Gd5436 | CirrusChip::Gd5446
+        )
+    }
+
+    /// Returns `true` if the chip can have 4MB of video memory, which
+    /// is reported by bit 7 of the dram control register.
+    const fn supports_4mb(self) -> bool {
+        matches!(
+            self,
+            CirrusChip::Gd5434 | CirrusChip::Gd5436 | CirrusChip::Gd5446
+        )
+    }
+}
+
+impl From<CirrusChip> for u8 {
+    fn from(value: CirrusChip) -> u8 {
+        value as u8
+    }
+}
+
+impl TryFrom<u8> for CirrusChip {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<CirrusChip, Self::Error> {
+        // Bits 1:0 are the revision of the chip.
+        match value & 0xFC {
+            0x8C => Ok(CirrusChip::Gd5422),
+            0x90 => Ok(CirrusChip::Gd5426),
+            0x94 => Ok(CirrusChip::Gd5424),
+            0x98 => Ok(CirrusChip::Gd5428),
+            0xA0 => Ok(CirrusChip::Gd5430),
+            0xA8 => Ok(CirrusChip::Gd5434),
+            0xAC => Ok(CirrusChip::Gd5436),
+            0xB8 => Ok(CirrusChip::Gd5446),
+            _ => Err("unsupported cirrus logic chipset"),
+        }
+    }
+}
+
+/// Represents a linear 256 color mode of a `CirrusDevice`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CirrusMode {
+    /// Represents 640x400 pixels at 70Hz.
+    Mode640x400x256,
+    /// Represents 640x480 pixels at 60Hz.
+    Mode640x480x256,
+    /// Represents 800x600 pixels at 60Hz.
+    Mode800x600x256,
+}
+
+impl CirrusMode {
+    /// Returns the width of the mode in pixels.
+    pub const fn get_width(self) -> usize {
+        match self {
+            CirrusMode::Mode640x400x256 | CirrusMode::Mode640x480x256 => 640,
+            CirrusMode::Mode800x600x256 => 800,
+        }
+    }
+
+    /// Returns the height of the mode in pixels.
+    pub const fn get_height(self) -> usize {
+        match self {
+            CirrusMode::Mode640x400x256 => 400,
+            CirrusMode::Mode640x480x256 => 480,
+            CirrusMode::Mode800x600x256 => 600,
+        }
+    }
+
+    /// Returns the `ModeInfo` of the mode.
+    pub const fn mode_info(self) -> ModeInfo {
+        ModeInfo::new(self.get_width(), self.get_height())
+    }
+
+    fn configuration(self) -> &'static VgaConfiguration {
+        match self {
+            CirrusMode::Mode640x400x256 => &MODE_640X400X256_CONFIGURATION,
+            CirrusMode::Mode640x480x256 => &MODE_640X480X256_CONFIGURATION,
+            CirrusMode::Mode800x600x256 => &MODE_800X600X256_CONFIGURATION,
+        }
+    }
+
+    /// Returns the numerator and denominator of the vclk3 synthesizer,
+    /// for modes beyond the standard 25/28 MHz clocks.
+    fn vclk3(self) -> Option<(u8, u8)> {
+        match self {
+            CirrusMode::Mode640x400x256 | CirrusMode::Mode640x480x256 => None,
+            // 14.31818 MHz * 81 / 29 = 39.99 MHz
+            CirrusMode::Mode800x600x256 => Some((0x51, 0x3A)),
+        }
+    }
+}
+
+/// Drives the extensions of Cirrus Logic GD54xx chipsets, as emulated by
+/// qemu (`-vga cirrus`), which provide a linear frame buffer at
+/// resolutions beyond what the vga registers support.
+///
+/// The standard registers are programmed through a `Vga`, so the mode
+/// change callbacks run and `Vga::get_mode_info` describes the
+/// `CirrusMode` while it's set. Standard modes must be set with
+/// `CirrusDevice::set_video_mode`, which turns the extended mode off first.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
+/// use vga::devices::{CirrusDevice, CirrusMode, PhysicalAddress, VirtualAddress};
+/// use vga::vga::VGA;
+///
+/// // The linear frame buffer is found in bar 0 of the pci device `1013:00B8`,
+/// // and must be mapped by the kernel.
+/// let mut vga = VGA.lock();
+/// if let Ok(mut device) = CirrusDevice::detect(
+///     &mut vga,
+///     PhysicalAddress::new(0xFC00_0000),
+///     VirtualAddress::new(0xFC00_0000),
+/// ) {
+///     device.set_mode(&mut vga, CirrusMode::Mode800x600x256).unwrap();
+///     device.clear_screen(0x01);
+///     device.set_pixel(10, 10, 0x0F);
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CirrusDevice {
+    chip: CirrusChip,
+    physical_address: PhysicalAddress,
+    virtual_address: VirtualAddress,
+    video_memory_size: usize,
+    mode: Option<CirrusMode>,
+    color_palette_mask: RegisterPort,
+}
+
+impl CirrusDevice {
+    /// Detects a Cirrus Logic chipset responding to the registers of `vga`,
+    /// with its linear frame buffer at `physical_address`, mapped at
+    /// `virtual_address`, and leaves its extensions unlocked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, leaving the registers unchanged, if the extended
+    /// sequencer registers don't lock and unlock like a Cirrus Logic
+    /// chipset, or the chipset isn't a known GD54xx.
+    pub fn detect(
+        vga: &mut Vga,
+        physical_address: PhysicalAddress,
+        virtual_address: VirtualAddress,
+    ) -> Result<CirrusDevice, &'static str> {
+        let emulation_mode = vga.get_emulation_mode();
+        let sequencer = &mut vga.sequencer_registers;
+        let unlock = unsafe { sequencer.read_raw(SR_UNLOCK) };
+
+        // Standard vgas either don't have the unlock register or read back
+        // any value written to it.
+        let responds = unsafe {
+            sequencer.write_raw(SR_UNLOCK, 0x00);
+            let locked = sequencer.read_raw(SR_UNLOCK);
+            sequencer.write_raw(SR_UNLOCK, EXTENSIONS_UNLOCKED);
+            locked == EXTENSIONS_LOCKED && sequencer.read_raw(SR_UNLOCK) == EXTENSIONS_UNLOCKED
+        };
+        let chip = if responds {
+            let chip_id = unsafe {
+                vga.crtc_controller_registers
+                    .read_raw(emulation_mode, CR_CHIP_ID)
+            };
+            CirrusChip::try_from(chip_id)
+        } else {
+            Err("no cirrus logic chipset responded to the extended registers")
+        };
+        let chip = match chip {
+            Ok(chip) => chip,
+            Err(error) => {
+                unsafe {
+                    vga.sequencer_registers.write_raw(SR_UNLOCK, unlock);
+                }
+                return Err(error);
+            }
+        };
+
+        // Bits 4:3 of the dram control register select 256K to 2MB, which
+        // bit 7 doubles on chips with a 64 bit memory bus.
+        let dram_control = unsafe { vga.sequencer_registers.read_raw(SR_DRAM_CONTROL) };
+        let mut video_memory_size = 0x4_0000 << ((dram_control >> 3) & 0x3);
+        if chip.supports_4mb() && dram_control & 0x80 != 0 {
+            video_memory_size *= 2;
+        }
+
+        Ok(CirrusDevice {
+            chip,
+            physical_address,
+            virtual_address,
+            video_memory_size,
+            mode: None,
+            color_palette_mask: RegisterPort::new(
+                COLOR_PALETTE_MASK_ADDRESS,
+                vga.get_register_access(),
+            ),
+        })
+    }
+
+    /// Returns the detected `CirrusChip`.
+    pub fn get_chip(&self) -> CirrusChip {
+        self.chip
+    }
+
+    /// Returns the physical address of the linear frame buffer.
+    pub fn get_physical_address(&self) -> PhysicalAddress {
+        self.physical_address
+    }
+
+    /// Returns the virtual address the linear frame buffer is mapped at.
+    pub fn get_virtual_address(&self) -> VirtualAddress {
+        self.virtual_address
+    }
+
+    /// Returns the size of the device's video memory in bytes.
+    pub fn video_memory_size(&self) -> usize {
+        self.video_memory_size
+    }
+
+    /// Returns the current `CirrusMode`, or `None` if a standard mode is set.
+    pub fn get_mode(&self) -> Option<CirrusMode> {
+        self.mode
+    }
+
+    /// Unlocks the extended registers, which `detect` already did.
+    pub fn unlock_extensions(&mut self, vga: &mut Vga) {
+        unsafe {
+            vga.sequencer_registers
+                .write_raw(SR_UNLOCK, EXTENSIONS_UNLOCKED);
+        }
+    }
+
+    /// Locks the extended registers again, after which `set_mode` and
+    /// `set_video_mode` don't work until `unlock_extensions` is called.
+    pub fn lock_extensions(&mut self, vga: &mut Vga) {
+        unsafe {
+            vga.sequencer_registers.write_raw(SR_UNLOCK, 0x00);
+        }
+    }
+
+    /// Sets the device to `mode`, with the frame buffer showing one byte
+    /// per pixel as indices into the active palette of `vga`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, leaving the mode unchanged, if the mode doesn't
+    /// fit in video memory.
+    pub fn set_mode(&mut self, vga: &mut Vga, mode: CirrusMode) -> Result<(), &'static str> {
+        if mode.get_width() * mode.get_height() > self.video_memory_size {
+            return Err("mode doesn't fit in video memory");
+        }
+
+        let emulation_mode = vga.get_emulation_mode();
+        unsafe {
+            if let Some((numerator, denominator)) = mode.vclk3() {
+                let sequencer = &mut vga.sequencer_registers;
+                sequencer.write_raw(SR_VCLK3_NUMERATOR, numerator);
+                sequencer.write_raw(SR_VCLK3_DENOMINATOR, denominator);
+            }
+            vga.sequencer_registers
+                .write_raw(SR_EXTENDED_SEQUENCER_MODE, EXTENDED_SEQUENCER_MODE_8BPP);
+            let crtc = &mut vga.crtc_controller_registers;
+            crtc.write_raw(emulation_mode, CR_MISCELLANEOUS_CONTROL, 0x00);
+            crtc.write_raw(
+                emulation_mode,
+                CR_EXTENDED_DISPLAY_CONTROLS,
+                EXTENDED_DISPLAY_CONTROLS,
+            );
+            crtc.write_raw(emulation_mode, CR_OVERLAY_EXTENDED_CONTROL, 0x00);
+            // A single 64K window into video memory, offset in 4K units.
+            let graphics = &mut vga.graphics_controller_registers;
+            graphics.write_raw(GR_GRAPHICS_CONTROLLER_MODE_EXTENSIONS, 0x00);
+            graphics.write_raw(GR_OFFSET_0, 0x00);
+            self.write_hidden_dac(0x00);
+            // The timings are the vesa ones for each mode, and the dot
+            // clock of 800x600 is programmed into vclk3 above, which
+            // `set_configuration` would replace with a standard clock.
+            vga.set_extended_mode(mode.configuration(), mode.mode_info());
+        }
+        vga.load_active_palette();
+        self.mode = Some(mode);
+        Ok(())
+    }
+
+    /// Turns the extended mode off and sets `vga` to the standard `video_mode`.
+    pub fn set_video_mode(&mut self, vga: &mut Vga, video_mode: VideoMode) {
+        let emulation_mode = vga.get_emulation_mode();
+        unsafe {
+            vga.sequencer_registers
+                .write_raw(SR_EXTENDED_SEQUENCER_MODE, 0x00);
+            vga.crtc_controller_registers.write_raw(
+                emulation_mode,
+                CR_EXTENDED_DISPLAY_CONTROLS,
+                0x00,
+            );
+        }
+        vga.set_video_mode(video_mode);
+        self.mode = None;
+    }
+
+    /// Returns a `BankedFrameBuffer` for the current mode, drawing through
+    /// the legacy window, or `None` if a standard mode is set.
+    pub fn get_banked_frame_buffer(&self) -> Option<BankedFrameBuffer> {
+        self.mode.map(|mode| {
+            BankedFrameBuffer::new(
+                mode.get_width(),
+                mode.get_height(),
+                CirrusDevice::bank_switch,
+            )
+        })
+    }
+
+    /// Shows `bank` of video memory in the legacy window, as the
+    /// `BankSwitchFn` of Cirrus Logic chipsets in the modes set by `set_mode`.
+    pub fn bank_switch(vga: &mut Vga, bank: usize) {
+        // The offset is in units of 4K, so it reaches the first 1MB.
+        unsafe {
+            vga.graphics_controller_registers
+                .write_raw(GR_OFFSET_0, (bank << 4) as u8);
+        }
+    }
+
+    /// Returns the start of the linear frame buffer.
+    ///
+    /// **Note:** The linear frame buffer only exists on chips where
+    /// `CirrusChip::supports_linear_frame_buffer` is `true`, and so do the
+    /// drawing methods of the device.
+    pub fn get_frame_buffer(&self) -> *mut u8 {
+        self.virtual_address.as_mut_ptr()
+    }
+
+    /// Returns the number of bytes of each line of the frame buffer.
+    pub fn get_bytes_per_line(&self) -> usize {
+        self.mode.map_or(0, CirrusMode::get_width)
+    }
+
+    /// Clears the screen by setting all pixels to the palette index `color`.
+    pub fn clear_screen(&self, color: u8) {
+        let pixel_count = self
+            .mode
+            .map_or(0, |mode| mode.get_width() * mode.get_height());
+        unsafe {
+            volatile_fill(self.get_frame_buffer(), color, pixel_count);
+        }
+    }
+
+    /// Sets the pixel at `(x, y)` to the palette index `color`.
+    pub fn set_pixel(&self, x: usize, y: usize, color: u8) {
+        let offset = y * self.get_bytes_per_line() + x;
+        unsafe {
+            self.get_frame_buffer().add(offset).write_volatile(color);
+        }
+    }
+
+    /// Sets every pixel of the `width` by `height` rectangle with its top
+    /// left corner at `(x, y)` to the palette index `color`.
+    pub fn fill_rect(&self, x: usize, y: usize, width: usize, height: usize, color: u8) {
+        for y in y..y + height {
+            let line = y * self.get_bytes_per_line() + x;
+            unsafe {
+                volatile_fill(self.get_frame_buffer().add(line), color, width);
+            }
+        }
+    }
+
+    /// Writes `value` to the hidden dac register, which selects the pixel
+    /// format of the dac and is reached by reading the palette mask
+    /// register four times.
+    fn write_hidden_dac(&mut self, value: u8) {
+        unsafe {
+            for _ in 0..4 {
+                self.color_palette_mask.read();
+            }
+            self.color_palette_mask.write(value);
+        }
+    }
+}
+
+/// Register values for Cirrus Logic mode 640x400x256 Graphics, with the
+/// timings of the standard 400 line modes.
+const MODE_640X400X256_CONFIGURATION: VgaConfiguration = VgaConfiguration {
+    miscellaneous_output: 0x63,
+    sequencer_registers: &[
+        (SequencerIndex::SequencerReset, 0x03),
+        (SequencerIndex::ClockingMode, 0x01),
+        (SequencerIndex::PlaneMask, 0x0F),
+        (SequencerIndex::CharacterFont, 0x00),
+        (SequencerIndex::MemoryMode, 0x0E),
+    ],
+    crtc_controller_registers: &[
+        (CrtcControllerIndex::HorizontalTotal, 0x5F),
+        (CrtcControllerIndex::HorizontalDisplayEnableEnd, 0x4F),
+        (CrtcControllerIndex::HorizontalBlankingStart, 0x50),
+        (CrtcControllerIndex::HorizontalBlankingEnd, 0x82),
+        (CrtcControllerIndex::HorizontalSyncStart, 0x54),
+        (CrtcControllerIndex::HorizontalSyncEnd, 0x80),
+        (CrtcControllerIndex::VeritcalTotal, 0xBF),
+        (CrtcControllerIndex::Overflow, 0x1F),
+        (CrtcControllerIndex::PresetRowScan, 0x00),
+        (CrtcControllerIndex::MaximumScanLine, 0x40),
+        (CrtcControllerIndex::TextCursorStart, 0x00),
+        (CrtcControllerIndex::TextCursorEnd, 0x00),
+        (CrtcControllerIndex::StartAddressHigh, 0x00),
+        (CrtcControllerIndex::StartAddressLow, 0x00),
+        (CrtcControllerIndex::TextCursorLocationHigh, 0x00),
+        (CrtcControllerIndex::TextCursorLocationLow, 0x00),
+        (CrtcControllerIndex::VerticalSyncStart, 0x9C),
+        (CrtcControllerIndex::VerticalSyncEnd, 0x0E),
+        (CrtcControllerIndex::VerticalDisplayEnableEnd, 0x8F),
+        (CrtcControllerIndex::Offset, 0x50),
+        (CrtcControllerIndex::UnderlineLocation, 0x00),
+        (CrtcControllerIndex::VerticalBlankingStart, 0x96),
+        (CrtcControllerIndex::VerticalBlankingEnd, 0xB9),
+        (CrtcControllerIndex::ModeControl, 0xE3),
+        (CrtcControllerIndex::LineCompare, 0xFF),
+    ],
+    graphics_controller_registers: GRAPHICS_CONTROLLER_REGISTERS,
+    attribute_controller_registers: ATTRIBUTE_CONTROLLER_REGISTERS,
+};
+
+/// Register values for Cirrus Logic mode 640x480x256 Graphics, with the
+/// timings of the standard 640x480 modes.
+const MODE_640X480X256_CONFIGURATION: VgaConfiguration = VgaConfiguration {
+    miscellaneous_output: 0xE3,
+    sequencer_registers: &[
+        (SequencerIndex::SequencerReset, 0x03),
+        (SequencerIndex::ClockingMode, 0x01),
+        (SequencerIndex::PlaneMask, 0x0F),
+        (SequencerIndex::CharacterFont, 0x00),
+        (SequencerIndex::MemoryMode, 0x0E),
+    ],
+    crtc_controller_registers: &[
+        (CrtcControllerIndex::HorizontalTotal, 0x5F),
+        (CrtcControllerIndex::HorizontalDisplayEnableEnd, 0x4F),
+        (CrtcControllerIndex::HorizontalBlankingStart, 0x50),
+        (CrtcControllerIndex::HorizontalBlankingEnd, 0x82),
+        (CrtcControllerIndex::HorizontalSyncStart, 0x54),
+        (CrtcControllerIndex::HorizontalSyncEnd, 0x80),
+        (CrtcControllerIndex::VeritcalTotal, 0x0B),
+        (CrtcControllerIndex::Overflow, 0x3E),
+        (CrtcControllerIndex::PresetRowScan, 0x00),
+        (CrtcControllerIndex::MaximumScanLine, 0x40),
+        (CrtcControllerIndex::TextCursorStart, 0x00),
+        (CrtcControllerIndex::TextCursorEnd, 0x00),
+        (CrtcControllerIndex::StartAddressHigh, 0x00),
+        (CrtcControllerIndex::StartAddressLow, 0x00),
+        (CrtcControllerIndex::TextCursorLocationHigh, 0x00),
+        (CrtcControllerIndex::TextCursorLocationLow, 0x00),
+        (CrtcControllerIndex::VerticalSyncStart, 0xEA),
+        (CrtcControllerIndex::VerticalSyncEnd, 0x0C),
+        (CrtcControllerIndex::VerticalDisplayEnableEnd, 0xDF),
+        (CrtcControllerIndex::Offset, 0x50),
+        (CrtcControllerIndex::UnderlineLocation, 0x00),
+        (CrtcControllerIndex::VerticalBlankingStart, 0xE7),
+        (CrtcControllerIndex::VerticalBlankingEnd, 0x04),
+        (CrtcControllerIndex::ModeControl, 0xE3),
+        (CrtcControllerIndex::LineCompare, 0xFF),
+    ],
+    graphics_controller_registers: GRAPHICS_CONTROLLER_REGISTERS,
+    attribute_controller_registers: ATTRIBUTE_CONTROLLER_REGISTERS,
+};
+
+/// Register values for Cirrus Logic mode 800x600x256 Graphics, with the
+/// vesa 800x600 at 60Hz timings and a 40 MHz dot clock from vclk3.
+const MODE_800X600X256_CONFIGURATION: VgaConfiguration = VgaConfiguration {
+    miscellaneous_output: 0x2F,
+    sequencer_registers: &[
+        (SequencerIndex::SequencerReset, 0x03),
+        (SequencerIndex::ClockingMode, 0x01),
+        (SequencerIndex::PlaneMask, 0x0F),
+        (SequencerIndex::CharacterFont, 0x00),
+        (SequencerIndex::MemoryMode, 0x0E),
+    ],
+    crtc_controller_registers: &[
+        (CrtcControllerIndex::HorizontalTotal, 0x7F),
+        (CrtcControllerIndex::HorizontalDisplayEnableEnd, 0x63),
+        (CrtcControllerIndex::HorizontalBlankingStart, 0x64),
+        (CrtcControllerIndex::HorizontalBlankingEnd, 0x82),
+        (CrtcControllerIndex::HorizontalSyncStart, 0x69),
+        (CrtcControllerIndex::HorizontalSyncEnd, 0x19),
+        (CrtcControllerIndex::VeritcalTotal, 0x72),
+        (CrtcControllerIndex::Overflow, 0xF0),
+        (CrtcControllerIndex::PresetRowScan, 0x00),
+        (CrtcControllerIndex::MaximumScanLine, 0x60),
+        (CrtcControllerIndex::TextCursorStart, 0x00),
+        (CrtcControllerIndex::TextCursorEnd, 0x00),
+        (CrtcControllerIndex::StartAddressHigh, 0x00),
+        (CrtcControllerIndex::StartAddressLow, 0x00),
+        (CrtcControllerIndex::TextCursorLocationHigh, 0x00),
+        (CrtcControllerIndex::TextCursorLocationLow, 0x00),
+        (CrtcControllerIndex::VerticalSyncStart, 0x59),
+        (CrtcControllerIndex::VerticalSyncEnd, 0x0D),
+        (CrtcControllerIndex::VerticalDisplayEnableEnd, 0x57),
+        (CrtcControllerIndex::Offset, 0x64),
+        (CrtcControllerIndex::UnderlineLocation, 0x00),
+        (CrtcControllerIndex::VerticalBlankingStart, 0x58),
+        (CrtcControllerIndex::VerticalBlankingEnd, 0x72),
+        (CrtcControllerIndex::ModeControl, 0xE3),
+        (CrtcControllerIndex::LineCompare, 0xFF),
+    ],
+    graphics_controller_registers: GRAPHICS_CONTROLLER_REGISTERS,
+    attribute_controller_registers: ATTRIBUTE_CONTROLLER_REGISTERS,
+};
+
+const GRAPHICS_CONTROLLER_REGISTERS: &[(GraphicsControllerIndex, u8)] = &[
+    (GraphicsControllerIndex::SetReset, 0x00),
+    (GraphicsControllerIndex::EnableSetReset, 0x00),
+    (GraphicsControllerIndex::ColorCompare, 0x00),
+    (GraphicsControllerIndex::DataRotate, 0x00),
+    (GraphicsControllerIndex::ReadPlaneSelect, 0x00),
+    (GraphicsControllerIndex::GraphicsMode, 0x40),
+    (GraphicsControllerIndex::Miscellaneous, 0x05),
+    (GraphicsControllerIndex::ColorDontCare, 0x0F),
+    (GraphicsControllerIndex::BitMask, 0xFF),
+];
+
+const ATTRIBUTE_CONTROLLER_REGISTERS: &[(AttributeControllerIndex, u8)] = &[
+    (AttributeControllerIndex::PaletteRegister0, 0x00),
+    (AttributeControllerIndex::PaletteRegister1, 0x01),
+    (AttributeControllerIndex::PaletteRegister2, 0x02),
+    (AttributeControllerIndex::PaletteRegister3, 0x03),
+    (AttributeControllerIndex::PaletteRegister4, 0x04),
+    (AttributeControllerIndex::PaletteRegister5, 0x05),
+    (AttributeControllerIndex::PaletteRegister6, 0x06),
+    (AttributeControllerIndex::PaletteRegister7, 0x07),
+    (AttributeControllerIndex::PaletteRegister8, 0x08),
+    (AttributeControllerIndex::PaletteRegister9, 0x09),
+    (AttributeControllerIndex::PaletteRegisterA, 0x0A),
+    (AttributeControllerIndex::PaletteRegisterB, 0x0B),
+    (AttributeControllerIndex::PaletteRegisterC, 0x0C),
+    (AttributeControllerIndex::PaletteRegisterD, 0x0D),
+    (AttributeControllerIndex::PaletteRegisterE, 0x0E),
+    (AttributeControllerIndex::PaletteRegisterF, 0x0F),
+    (AttributeControllerIndex::ModeControl, 0x41),
+    (AttributeControllerIndex::OverscanColor, 0x00),
+    (AttributeControllerIndex::MemoryPlaneEnable, 0x0F),
+    (AttributeControllerIndex::HorizontalPixelPanning, 0x00),
+    (AttributeControllerIndex::ColorSelect, 0x00),
+];
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::{
+        devices::BANK_SIZE,
+        mock::{lock_for_test, video_memory, MOCK_HARDWARE},
+        vga::VGA,
+    };
+    #[cfg(feature = "bochs-device")]
+    use crate::{
+        devices::{enumerate_display_devices, DisplayDeviceKind},
+        registers::RegisterAccess,
+    };
+    use alloc::vec;
+
+    #[test]
+    fn test_detect() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        let physical_address = PhysicalAddress::new(0xFC00_0000);
+        let virtual_address = VirtualAddress::new(0xFC00_0000);
+        assert_eq!(
+            CirrusDevice::detect(&mut vga, physical_address, virtual_address).unwrap_err(),
+            "no cirrus logic chipset responded to the extended registers"
+        );
+
+        MOCK_HARDWARE
+            .lock()
+            .set_cirrus_chip(Some(CirrusChip::Gd5446));
+        let mut device = CirrusDevice::detect(&mut vga, physical_address, virtual_address).unwrap();
+        assert_eq!(device.get_chip(), CirrusChip::Gd5446);
+        assert_eq!(device.video_memory_size(), 0x40_0000);
+        assert_eq!(device.get_mode(), None);
+        assert_eq!(
+            MOCK_HARDWARE.lock().cirrus_sequencer(SR_UNLOCK),
+            EXTENSIONS_UNLOCKED
+        );
+        device.lock_extensions(&mut vga);
+        assert_eq!(
+            MOCK_HARDWARE.lock().cirrus_sequencer(SR_UNLOCK),
+            EXTENSIONS_LOCKED
+        );
+        drop(vga);
+
+        // A locked cirrus chip is left to the bochs probe.
+        #[cfg(feature = "bochs-device")]
+        assert!(enumerate_display_devices(&[RegisterAccess::Port])
+            .map(|device| device.get_kind())
+            .eq([DisplayDeviceKind::Bochs(0xB0C5)]));
+        MOCK_HARDWARE.lock().set_cirrus_chip(None);
+    }
+
+    #[test]
+    fn test_set_mode() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        let mut frame_buffer = vec![0u8; 800 * 600];
+        let virtual_address = VirtualAddress::new(frame_buffer.as_mut_ptr() as usize);
+        MOCK_HARDWARE
+            .lock()
+            .set_cirrus_chip(Some(CirrusChip::Gd5446));
+        let mut device =
+            CirrusDevice::detect(&mut vga, PhysicalAddress::new(0xFC00_0000), virtual_address)
+                .unwrap();
+
+        device
+            .set_mode(&mut vga, CirrusMode::Mode800x600x256)
+            .unwrap();
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            assert_eq!(hardware.cirrus_sequencer(SR_EXTENDED_SEQUENCER_MODE), 0x01);
+            assert_eq!(hardware.cirrus_sequencer(SR_VCLK3_NUMERATOR), 0x51);
+            assert_eq!(hardware.cirrus_sequencer(SR_VCLK3_DENOMINATOR), 0x3A);
+            assert_eq!(
+                hardware.cirrus_crtc_controller(CR_EXTENDED_DISPLAY_CONTROLS),
+                EXTENDED_DISPLAY_CONTROLS
+            );
+            assert_eq!(hardware.miscellaneous_output(), 0x2F);
+            assert_eq!(hardware.crtc_controller(CrtcControllerIndex::Offset), 0x64);
+        }
+        assert_eq!(device.get_mode(), Some(CirrusMode::Mode800x600x256));
+        assert_eq!(vga.get_mode_info(), Some(ModeInfo::new(800, 600)));
+        assert_eq!(vga.get_most_recent_video_mode(), None);
+
+        device.clear_screen(0x01);
+        device.fill_rect(10, 2, 4, 2, 0x0E);
+        device.set_pixel(799, 599, 0x0F);
+        assert_eq!(frame_buffer[2 * 800 + 9], 0x01);
+        assert_eq!(frame_buffer[3 * 800 + 13], 0x0E);
+        assert_eq!(frame_buffer[599 * 800 + 799], 0x0F);
+
+        device.set_video_mode(&mut vga, VideoMode::Mode80x25);
+        assert_eq!(device.get_mode(), None);
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .cirrus_sequencer(SR_EXTENDED_SEQUENCER_MODE),
+            0x00
+        );
+        assert_eq!(vga.get_most_recent_video_mode(), Some(VideoMode::Mode80x25));
+        MOCK_HARDWARE.lock().set_cirrus_chip(None);
+    }
+
+    #[test]
+    fn test_set_mode_limits() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        let address = VirtualAddress::new(0xFC00_0000);
+        MOCK_HARDWARE
+            .lock()
+            .set_cirrus_chip(Some(CirrusChip::Gd5428));
+        unsafe {
+            vga.sequencer_registers.write_raw(SR_DRAM_CONTROL, 0x80);
+        }
+        let mut device =
+            CirrusDevice::detect(&mut vga, PhysicalAddress::new(0xFC00_0000), address).unwrap();
+        assert!(!device.get_chip().supports_linear_frame_buffer());
+        assert_eq!(device.video_memory_size(), 0x4_0000);
+        assert_eq!(
+            device.set_mode(&mut vga, CirrusMode::Mode640x480x256),
+            Err("mode doesn't fit in video memory")
+        );
+        assert_eq!(device.get_mode(), None);
+        assert!(device.get_banked_frame_buffer().is_none());
+        device.lock_extensions(&mut vga);
+        MOCK_HARDWARE.lock().set_cirrus_chip(None);
+        assert_eq!(CirrusChip::try_from(0xB9), Ok(CirrusChip::Gd5446));
+        assert_eq!(
+            CirrusChip::try_from(0x00),
+            Err("unsupported cirrus logic chipset")
+        );
+    }
+
+    #[test]
+    fn test_banked_frame_buffer() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        let address = VirtualAddress::new(0xFC00_0000);
+        MOCK_HARDWARE
+            .lock()
+            .set_cirrus_chip(Some(CirrusChip::Gd5428));
+        let mut device =
+            CirrusDevice::detect(&mut vga, PhysicalAddress::new(0xFC00_0000), address).unwrap();
+        device
+            .set_mode(&mut vga, CirrusMode::Mode640x400x256)
+            .unwrap();
+        assert_eq!(vga.get_mode_info(), Some(ModeInfo::new(640, 400)));
+
+        let mut frame_buffer = device.get_banked_frame_buffer().unwrap();
+        assert_eq!(frame_buffer.get_width(), 640);
+        assert_eq!(frame_buffer.get_height(), 400);
+        frame_buffer.set_pixel(&mut vga, 639, 399, 0x0F);
+        assert_eq!(frame_buffer.get_current_bank(), Some(3));
+        assert_eq!(
+            MOCK_HARDWARE.lock().graphics_controller_registers[GR_OFFSET_0 as usize],
+            0x30
+        );
+        assert_eq!(video_memory()[(640 * 400 - 1) % BANK_SIZE], 0x0F);
+
+        device.set_video_mode(&mut vga, VideoMode::Mode80x25);
+        MOCK_HARDWARE.lock().set_cirrus_chip(None);
+    }
+}
@@ -0,0 +1,112 @@
+//! Display devices providing more than the standard vga registers.
+
+pub(crate) mod banked;
+#[cfg(feature = "bochs-device")]
+pub(crate) mod bochs;
+#[cfg(feature = "cirrus-device")]
+pub(crate) mod cirrus;
+mod probe;
+
+pub use banked::{
+    tseng_et3000_bank_switch, tseng_et4000_bank_switch, BankSwitchFn, BankedFrameBuffer, BANK_SIZE,
+};
+#[cfg(feature = "bochs-device")]
+pub use bochs::{BochsDevice, BochsIndex, Bpp, Resolution};
+#[cfg(feature = "cirrus-device")]
+pub use cirrus::{CirrusChip, CirrusDevice, CirrusMode};
+pub use probe::{enumerate_display_devices, DisplayDevice, DisplayDeviceKind};
+
+/// Represents a physical memory address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct PhysicalAddress(usize);
+
+impl PhysicalAddress {
+    /// Creates a new `PhysicalAddress` from `address`.
+    pub const fn new(address: usize) -> PhysicalAddress {
+        PhysicalAddress(address)
+    }
+
+    /// Returns the address as a `usize`.
+    pub const fn as_usize(self) -> usize {
+        self.0
+    }
+}
+
+impl From<usize> for PhysicalAddress {
+    fn from(value: usize) -> PhysicalAddress {
+        PhysicalAddress(value)
+    }
+}
+
+impl From<PhysicalAddress> for usize {
+    fn from(value: PhysicalAddress) -> usize {
+        value.0
+    }
+}
+
+/// Represents a virtual memory address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct VirtualAddress(usize);
+
+impl VirtualAddress {
+    /// Creates a new `VirtualAddress` from `address`.
+    pub const fn new(address: usize) -> VirtualAddress {
+        VirtualAddress(address)
+    }
+
+    /// Returns the address as a `usize`.
+    pub const fn as_usize(self) -> usize {
+        self.0
+    }
+
+    /// Returns the address as a raw pointer.
+    pub const fn as_mut_ptr<T>(self) -> *mut T {
+        self.0 as *mut T
+    }
+}
+
+impl From<usize> for VirtualAddress {
+    fn from(value: usize) -> VirtualAddress {
+        VirtualAddress(value)
+    }
+}
+
+impl From<VirtualAddress> for usize {
+    fn from(value: VirtualAddress) -> usize {
+        value.0
+    }
+}
+
+#[cfg(feature = "x86_64")]
+impl From<x86_64::PhysAddr> for PhysicalAddress {
+    fn from(value: x86_64::PhysAddr) -> PhysicalAddress {
+        PhysicalAddress(value.as_u64() as usize)
+    }
+}
+
+#[cfg(feature = "x86_64")]
+impl core::convert::TryFrom<PhysicalAddress> for x86_64::PhysAddr {
+    type Error = &'static str;
+
+    fn try_from(value: PhysicalAddress) -> Result<x86_64::PhysAddr, Self::Error> {
+        x86_64::PhysAddr::try_new(value.0 as u64).map_err(|_| "invalid physical address")
+    }
+}
+
+#[cfg(feature = "x86_64")]
+impl From<x86_64::VirtAddr> for VirtualAddress {
+    fn from(value: x86_64::VirtAddr) -> VirtualAddress {
+        VirtualAddress(value.as_u64() as usize)
+    }
+}
+
+#[cfg(feature = "x86_64")]
+impl core::convert::TryFrom<VirtualAddress> for x86_64::VirtAddr {
+    type Error = &'static str;
+
+    fn try_from(value: VirtualAddress) -> Result<x86_64::VirtAddr, Self::Error> {
+        x86_64::VirtAddr::try_new(value.0 as u64).map_err(|_| "non canonical virtual address")
+    }
+}
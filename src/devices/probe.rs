@@ -0,0 +1,162 @@
+#[cfg(feature = "bochs-device")]
+use super::BochsDevice;
+#[cfg(feature = "cirrus-device")]
+use super::{CirrusChip, CirrusDevice};
+#[cfg(any(feature = "bochs-device", feature = "cirrus-device"))]
+use super::{PhysicalAddress, VirtualAddress};
+use crate::registers::RegisterAccess;
+use crate::vga::Vga;
+
+/// Represents a kind of display device found by `enumerate_display_devices`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DisplayDeviceKind {
+    /// A standard vga, driven with a `Vga`.
+    Vga,
+    /// A bochs display interface reporting the given version id, driven
+    /// with a `BochsDevice`. It's also vga compatible, though the vga
+    /// output is disabled while the display interface is enabled.
+    #[cfg(feature = "bochs-device")]
+    Bochs(u16),
+    /// A Cirrus Logic GD54xx chipset, driven with a `CirrusDevice` and
+    /// a `Vga` for the standard modes.
+    #[cfg(feature = "cirrus-device")]
+    Cirrus(CirrusChip),
+}
+
+/// Represents a display device found by `enumerate_display_devices`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DisplayDevice {
+    kind: DisplayDeviceKind,
+    register_access: RegisterAccess,
+}
+
+impl DisplayDevice {
+    /// Returns the kind of the device.
+    pub fn get_kind(&self) -> DisplayDeviceKind {
+        self.kind
+    }
+
+    /// Returns how the device's registers are accessed.
+    pub fn get_register_access(&self) -> RegisterAccess {
+        self.register_access
+    }
+}
+
+/// Returns the display devices responding at each of `register_accesses`,
+/// such as `RegisterAccess::Port` for the primary display and the mmio bars
+/// of any secondary displays found on the pci bus.
+///
+/// Each device is driven independently, with a `Vga` or `BochsDevice`
+/// created with its register access, and writers created with `with_vga`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "bochs-device")]
+/// # {
+/// use vga::devices::{enumerate_display_devices, DisplayDeviceKind};
+/// use vga::registers::RegisterAccess;
+///
+/// # let secondary_bar_start = 0xFEB0_0000;
+/// let accesses = [
+///     RegisterAccess::Port,
+///     RegisterAccess::Mmio(secondary_bar_start + 0x400),
+/// ];
+/// for device in enumerate_display_devices(&accesses) {
+///     if let DisplayDeviceKind::Bochs(_) = device.get_kind() {
+///         // Create a `BochsDevice` with `device.get_register_access()`.
+///     }
+/// }
+/// # }
+/// ```
+pub fn enumerate_display_devices(
+    register_accesses: &[RegisterAccess],
+) -> impl Iterator<Item = DisplayDevice> + '_ {
+    register_accesses
+        .iter()
+        .filter_map(|&register_access| probe(register_access))
+}
+
+fn probe(register_access: RegisterAccess) -> Option<DisplayDevice> {
+    let kind = if let Some(kind) = probe_bochs(register_access) {
+        kind
+    } else if let Some(kind) = probe_cirrus(register_access) {
+        kind
+    } else if is_vga_present(register_access) {
+        DisplayDeviceKind::Vga
+    } else {
+        return None;
+    };
+    Some(DisplayDevice {
+        kind,
+        register_access,
+    })
+}
+
+#[cfg(feature = "bochs-device")]
+fn probe_bochs(register_access: RegisterAccess) -> Option<DisplayDeviceKind> {
+    // No memory is accessed while probing, so the frame buffer
+    // addresses don't matter.
+    let mut bochs = BochsDevice::with_register_access(
+        PhysicalAddress::new(0),
+        VirtualAddress::new(0),
+        register_access,
+    );
+    if bochs.is_present() {
+        Some(DisplayDeviceKind::Bochs(bochs.get_id()))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(feature = "bochs-device"))]
+fn probe_bochs(_register_access: RegisterAccess) -> Option<DisplayDeviceKind> {
+    None
+}
+
+#[cfg(feature = "cirrus-device")]
+fn probe_cirrus(register_access: RegisterAccess) -> Option<DisplayDeviceKind> {
+    let mut vga = Vga::with_access(register_access);
+    // No memory is accessed while probing, and the extensions are locked
+    // again, as the firmware leaves them.
+    let mut cirrus =
+        CirrusDevice::detect(&mut vga, PhysicalAddress::new(0), VirtualAddress::new(0)).ok()?;
+    cirrus.lock_extensions(&mut vga);
+    Some(DisplayDeviceKind::Cirrus(cirrus.get_chip()))
+}
+
+#[cfg(not(feature = "cirrus-device"))]
+fn probe_cirrus(_register_access: RegisterAccess) -> Option<DisplayDeviceKind> {
+    None
+}
+
+fn is_vga_present(register_access: RegisterAccess) -> bool {
+    Vga::with_access(register_access).probe().is_ok()
+}
+
+#[cfg(all(test, feature = "bochs-device"))]
+mod test {
+    use super::*;
+    use crate::mock::lock_for_test;
+
+    #[test]
+    fn test_enumerate_display_devices() {
+        let _guard = lock_for_test();
+        let mut vga_window = [0u8; 0x200];
+        // The miscellaneous output register selects color emulation.
+        vga_window[0x0C] = 0x01;
+        let accesses = [
+            RegisterAccess::Port,
+            RegisterAccess::Mmio(vga_window.as_mut_ptr() as usize),
+        ];
+        let mut devices = enumerate_display_devices(&accesses);
+
+        let device = devices.next().unwrap();
+        assert_eq!(device.get_kind(), DisplayDeviceKind::Bochs(0xB0C5));
+        assert_eq!(device.get_register_access(), RegisterAccess::Port);
+        let device = devices.next().unwrap();
+        assert_eq!(device.get_kind(), DisplayDeviceKind::Vga);
+        assert_eq!(device.get_register_access(), accesses[1]);
+        assert_eq!(devices.next(), None);
+    }
+}
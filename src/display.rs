@@ -0,0 +1,293 @@
+//! A display manager owning the current text or graphics mode.
+
+#[cfg(feature = "modex")]
+use crate::writers::Graphics320x240x256;
+#[cfg(feature = "graphics-modes")]
+use crate::writers::{Graphics1280x800x256, Graphics320x200x256, Graphics640x480x16};
+#[cfg(feature = "text-modes")]
+use crate::writers::{Text40x25, Text40x50, Text80x25};
+use crate::{
+    colors::{Color16, TextModeColor},
+    vga::VideoMode,
+    writers::{
+        any::{AnyGraphicsWriter, AnyTextWriter},
+        ScreenCharacter,
+    },
+};
+use core::fmt;
+
+const MAX_TEXT_SIZE: usize = 80 * 25;
+const CHARACTER_SIZE: usize = 8;
+
+#[cfg(feature = "text-modes")]
+static TEXT_40X25: Text40x25<'static> = Text40x25::new();
+#[cfg(feature = "text-modes")]
+static TEXT_40X50: Text40x50<'static> = Text40x50::new();
+#[cfg(feature = "text-modes")]
+static TEXT_80X25: Text80x25<'static> = Text80x25::new();
+#[cfg(feature = "graphics-modes")]
+static GRAPHICS_320X200X256: Graphics320x200x256<'static> = Graphics320x200x256::new();
+#[cfg(feature = "modex")]
+static GRAPHICS_320X240X256: Graphics320x240x256<'static> = Graphics320x240x256::new();
+#[cfg(feature = "graphics-modes")]
+static GRAPHICS_640X480X16: Graphics640x480x16<'static> = Graphics640x480x16::new();
+#[cfg(feature = "graphics-modes")]
+static GRAPHICS_1280X800X256: Graphics1280x800x256<'static> = Graphics1280x800x256::new();
+
+/// Returns the `AnyTextWriter` for `video_mode`, or `None` for graphics modes
+/// and text modes compiled out with the `text-modes` feature.
+pub fn text_writer(video_mode: VideoMode) -> Option<&'static dyn AnyTextWriter> {
+    match video_mode {
+        #[cfg(feature = "text-modes")]
+        VideoMode::Mode40x25 => Some(&TEXT_40X25),
+        #[cfg(feature = "text-modes")]
+        VideoMode::Mode40x50 => Some(&TEXT_40X50),
+        #[cfg(feature = "text-modes")]
+        VideoMode::Mode80x25 => Some(&TEXT_80X25),
+        _ => None,
+    }
+}
+
+/// Returns the `AnyGraphicsWriter` for `video_mode`, or `None` for text modes
+/// and graphics modes compiled out with the `graphics-modes` or `modex`
+/// feature.
+pub fn graphics_writer(video_mode: VideoMode) -> Option<&'static dyn AnyGraphicsWriter> {
+    match video_mode {
+        #[cfg(feature = "graphics-modes")]
+        VideoMode::Mode320x200x256 => Some(&GRAPHICS_320X200X256),
+        #[cfg(feature = "modex")]
+        VideoMode::Mode320x240x256 => Some(&GRAPHICS_320X240X256),
+        #[cfg(feature = "graphics-modes")]
+        VideoMode::Mode640x480x16 => Some(&GRAPHICS_640X480X16),
+        #[cfg(feature = "graphics-modes")]
+        VideoMode::Mode1280x800x256 => Some(&GRAPHICS_1280X800X256),
+        _ => None,
+    }
+}
+
+/// The contents of a text mode saved while another mode is active.
+struct SavedText {
+    video_mode: VideoMode,
+    characters: [ScreenCharacter; MAX_TEXT_SIZE],
+    cursor_position: (usize, usize),
+}
+
+/// Owns the current video mode and provides the operations common to
+/// text and graphics modes.
+///
+/// When switching away from a text mode, its contents and cursor position
+/// are saved, and restored when switching back to the same text mode.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// use core::fmt::Write;
+/// use vga::display::Display;
+/// use vga::vga::VideoMode;
+///
+/// let mut display = Display::new();
+/// display.set_mode(VideoMode::Mode80x25);
+/// writeln!(display, "Booting...").unwrap();
+/// display.set_mode(VideoMode::Mode640x480x16);
+/// display.draw_pixel(10, 10, 0xF).unwrap();
+/// // Switching back restores "Booting...".
+/// display.set_mode(VideoMode::Mode80x25);
+/// ```
+pub struct Display {
+    video_mode: Option<VideoMode>,
+    saved_text: Option<SavedText>,
+    text_color: TextModeColor,
+    graphics_color: u32,
+    graphics_cursor: (usize, usize),
+}
+
+impl Display {
+    /// Creates a new `Display`, without changing the current video mode.
+    pub const fn new() -> Display {
+        Display {
+            video_mode: None,
+            saved_text: None,
+            text_color: TextModeColor::new(Color16::Yellow, Color16::Black),
+            graphics_color: 0xF,
+            graphics_cursor: (0, 0),
+        }
+    }
+
+    /// Returns the current video mode, or `None` if `set_mode` wasn't called yet.
+    pub fn get_mode(&self) -> Option<VideoMode> {
+        self.video_mode
+    }
+
+    /// Switches to `video_mode`, saving the contents of the current text
+    /// mode and restoring the saved contents when switching back to it.
+    pub fn set_mode(&mut self, video_mode: VideoMode) {
+        if let Some((current_mode, writer)) = self
+            .video_mode
+            .and_then(|current_mode| Some((current_mode, text_writer(current_mode)?)))
+        {
+            let mut characters = [ScreenCharacter::new(b' ', self.text_color); MAX_TEXT_SIZE];
+            let mode_info = writer.mode_info();
+            let width = mode_info.get_width();
+            for (offset, character) in characters
+                .iter_mut()
+                .take(width * mode_info.get_height())
+                .enumerate()
+            {
+                *character = writer.read_character(offset % width, offset / width);
+            }
+            self.saved_text = Some(SavedText {
+                video_mode: current_mode,
+                characters,
+                cursor_position: writer.get_cursor_position(),
+            });
+        }
+
+        self.video_mode = Some(video_mode);
+        self.graphics_cursor = (0, 0);
+        if let Some(writer) = text_writer(video_mode) {
+            writer.set_mode();
+            match self.saved_text.take() {
+                Some(saved_text) if saved_text.video_mode == video_mode => {
+                    let mode_info = writer.mode_info();
+                    let width = mode_info.get_width();
+                    for (offset, character) in saved_text
+                        .characters
+                        .iter()
+                        .take(width * mode_info.get_height())
+                        .enumerate()
+                    {
+                        writer.write_character(offset % width, offset / width, *character);
+                    }
+                    let (x, y) = saved_text.cursor_position;
+                    writer.set_cursor_position(x, y);
+                }
+                saved_text => {
+                    self.saved_text = saved_text;
+                    writer.clear_screen();
+                    writer.set_cursor_position(0, 0);
+                }
+            }
+        } else if let Some(writer) = graphics_writer(video_mode) {
+            writer.set_mode();
+            writer.clear_screen(0);
+        }
+    }
+
+    /// Returns the writer for the current mode if it's a text mode.
+    pub fn text_writer(&self) -> Option<&'static dyn AnyTextWriter> {
+        self.video_mode.and_then(text_writer)
+    }
+
+    /// Returns the writer for the current mode if it's a graphics mode.
+    pub fn graphics_writer(&self) -> Option<&'static dyn AnyGraphicsWriter> {
+        self.video_mode.and_then(graphics_writer)
+    }
+
+    /// Sets the color used when writing strings in text modes.
+    pub fn set_text_color(&mut self, color: TextModeColor) {
+        self.text_color = color;
+    }
+
+    /// Sets the color used when writing strings in graphics modes.
+    pub fn set_graphics_color(&mut self, color: u32) {
+        self.graphics_color = color;
+    }
+
+    /// Clears the screen and moves the cursor to the top left corner.
+    pub fn clear(&mut self) {
+        if let Some(writer) = self.text_writer() {
+            writer.clear_screen();
+            writer.set_cursor_position(0, 0);
+        } else if let Some(writer) = self.graphics_writer() {
+            writer.clear_screen(0);
+            self.graphics_cursor = (0, 0);
+        }
+    }
+
+    /// Sets the pixel at `(x, y)` to `color`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the current mode isn't a graphics mode.
+    pub fn draw_pixel(&self, x: usize, y: usize, color: u32) -> Result<(), &'static str> {
+        let writer = self
+            .graphics_writer()
+            .ok_or("the current mode isn't a graphics mode")?;
+        writer.set_pixel(x, y, color);
+        Ok(())
+    }
+
+    /// Writes `byte` at the cursor position. In graphics modes, the cursor
+    /// returns to the top of the screen after the last line, since graphics
+    /// modes don't scroll.
+    pub fn write_byte(&mut self, byte: u8) {
+        if let Some(writer) = self.text_writer() {
+            writer.put_char(byte, self.text_color);
+        } else if let Some(writer) = self.graphics_writer() {
+            let mode_info = writer.mode_info();
+            let (x, y) = self.graphics_cursor;
+            let (x, y) = if byte == b'\n' {
+                (0, y + CHARACTER_SIZE)
+            } else {
+                writer.draw_character(x, y, byte as char, self.graphics_color);
+                if x + CHARACTER_SIZE * 2 > mode_info.get_width() {
+                    (0, y + CHARACTER_SIZE)
+                } else {
+                    (x + CHARACTER_SIZE, y)
+                }
+            };
+            self.graphics_cursor = if y + CHARACTER_SIZE > mode_info.get_height() {
+                (x, 0)
+            } else {
+                (x, y)
+            };
+        }
+    }
+}
+
+impl Default for Display {
+    fn default() -> Display {
+        Display::new()
+    }
+}
+
+impl fmt::Write for Display {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            match byte {
+                // Printable ascii or newline
+                0x20..=0x7E | b'\n' => self.write_byte(byte),
+                // Not part of the printable ascii range
+                _ => self.write_byte(0xFE),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "text-modes", feature = "graphics-modes"))]
+mod test {
+    use super::*;
+    use crate::mock::lock_for_test;
+    use core::fmt::Write;
+
+    #[test]
+    fn test_switching_restores_text() {
+        let _guard = lock_for_test();
+        let mut display = Display::new();
+        display.set_mode(VideoMode::Mode80x25);
+        write!(display, "Hi").unwrap();
+        assert!(display.draw_pixel(0, 0, 1).is_err());
+
+        display.set_mode(VideoMode::Mode320x200x256);
+        assert!(display.draw_pixel(0, 0, 1).is_ok());
+
+        display.set_mode(VideoMode::Mode80x25);
+        let writer = display.text_writer().unwrap();
+        assert_eq!(writer.read_character(0, 0).get_character(), b'H');
+        assert_eq!(writer.read_character(1, 0).get_character(), b'i');
+        assert_eq!(writer.get_cursor_position(), (2, 0));
+    }
+}
@@ -0,0 +1,167 @@
+//! Conversion of 24 bit rgb images to indexed colors with dithering.
+//!
+//! Images are given as tightly packed `[red, green, blue]` bytes, and
+//! palettes as a `Palette`, such as `EGA_PALETTE` for 16 color modes or
+//! the palette loaded in 256 color modes.
+
+use crate::colors::Palette;
+
+/// The 4x4 Bayer threshold matrix.
+const BAYER_MATRIX: [[i16; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// The amount the Bayer thresholds are scaled by, roughly the distance
+/// between neighbouring colors of the vga palettes.
+const BAYER_SPREAD: i16 = 64;
+
+fn clamp(value: i16) -> u8 {
+    value.clamp(0, 0xFF) as u8
+}
+
+fn check_sizes<const N: usize>(image: &[u8], output: &[u8]) {
+    assert!(
+        N > 0 && N <= 256,
+        "palette must have between 1 and 256 colors"
+    );
+    assert_eq!(
+        image.len(),
+        output.len() * 3,
+        "image must have 3 bytes for every output pixel"
+    );
+}
+
+/// Converts `image` to indices into `palette` using 4x4 Bayer ordered
+/// dithering, writing one index per pixel to `output`.
+///
+/// Ordered dithering processes every pixel independently, so it's fast
+/// and doesn't show moving artifacts in animations. Nothing is written
+/// if `width` is 0 or `output` is empty.
+///
+/// # Panics
+///
+/// Panics if `image` doesn't hold 3 bytes for every pixel of `output`,
+/// or if `palette` is empty or holds more than 256 colors.
+pub fn ordered_dither<const N: usize>(
+    image: &[u8],
+    width: usize,
+    palette: &Palette<N>,
+    output: &mut [u8],
+) {
+    check_sizes::<N>(image, output);
+    if width == 0 || output.is_empty() {
+        return;
+    }
+    for (offset, (pixel, index)) in image.chunks_exact(3).zip(output.iter_mut()).enumerate() {
+        let (x, y) = (offset % width, offset / width);
+        let threshold = (BAYER_MATRIX[y % 4][x % 4] * 2 - 15) * BAYER_SPREAD / 32;
+        let rgb = (
+            clamp(pixel[0] as i16 + threshold),
+            clamp(pixel[1] as i16 + threshold),
+            clamp(pixel[2] as i16 + threshold),
+        );
+        *index = palette.nearest(rgb);
+    }
+}
+
+/// Converts `image` to indices into `palette` using Floyd-Steinberg error
+/// diffusion, writing one index per pixel to `output`.
+///
+/// Error diffusion gives better results than `ordered_dither` for photos.
+/// Since no allocator is available, the error is diffused into `image`
+/// itself, which is left modified. Nothing is written if `width` is 0 or
+/// `output` is empty.
+///
+/// # Panics
+///
+/// Panics if `image` doesn't hold 3 bytes for every pixel of `output`,
+/// or if `palette` is empty or holds more than 256 colors.
+pub fn floyd_steinberg_dither<const N: usize>(
+    image: &mut [u8],
+    width: usize,
+    palette: &Palette<N>,
+    output: &mut [u8],
+) {
+    check_sizes::<N>(image, output);
+    if width == 0 || output.is_empty() {
+        return;
+    }
+    let height = output.len() / width;
+    for y in 0..height {
+        for x in 0..width {
+            let offset = y * width + x;
+            let pixel = &image[offset * 3..offset * 3 + 3];
+            let index = palette.nearest((pixel[0], pixel[1], pixel[2]));
+            let color = palette.get_rgb(index as usize);
+            let error = [
+                pixel[0] as i16 - color.0 as i16,
+                pixel[1] as i16 - color.1 as i16,
+                pixel[2] as i16 - color.2 as i16,
+            ];
+            output[offset] = index;
+
+            let mut diffuse = |x: usize, y: usize, weight: i16| {
+                if x < width && y < height {
+                    let offset = (y * width + x) * 3;
+                    for (channel, error) in error.iter().enumerate() {
+                        let value = &mut image[offset + channel];
+                        *value = clamp(*value as i16 + error * weight / 16);
+                    }
+                }
+            };
+            diffuse(x + 1, y, 7);
+            if x > 0 {
+                diffuse(x - 1, y + 1, 3);
+            }
+            diffuse(x, y + 1, 5);
+            diffuse(x + 1, y + 1, 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::colors::{Color16, EGA_PALETTE};
+
+    const BLACK_AND_WHITE: Palette<2> = Palette::new([(0x00, 0x00, 0x00), (0x3F, 0x3F, 0x3F)]);
+
+    #[test]
+    fn test_exact_colors_are_kept() {
+        let palette = EGA_PALETTE;
+        let image = [0x00, 0x00, 0xAA, 0xFF, 0xFF, 0x55];
+        let mut output = [0; 2];
+
+        ordered_dither(&image, 2, &palette, &mut output);
+        assert_eq!(output, [Color16::Blue as u8, Color16::Yellow as u8]);
+
+        floyd_steinberg_dither(&mut image.clone(), 2, &palette, &mut output);
+        assert_eq!(output, [Color16::Blue as u8, Color16::Yellow as u8]);
+    }
+
+    #[test]
+    fn test_gray_is_dithered() {
+        let image = [0x80; 4 * 4 * 3];
+        let mut output = [0; 4 * 4];
+
+        ordered_dither(&image, 4, &BLACK_AND_WHITE, &mut output);
+        let white = output.iter().filter(|index| **index == 1).count();
+        assert!((6..=10).contains(&white));
+
+        floyd_steinberg_dither(&mut image.clone(), 4, &BLACK_AND_WHITE, &mut output);
+        let white = output.iter().filter(|index| **index == 1).count();
+        assert!((6..=10).contains(&white));
+    }
+
+    #[test]
+    fn test_zero_dimensions() {
+        let mut image = [0x80; 2 * 3];
+        let mut output = [0xAA; 2];
+
+        ordered_dither(&image, 0, &BLACK_AND_WHITE, &mut output);
+        floyd_steinberg_dither(&mut image, 0, &BLACK_AND_WHITE, &mut output);
+        assert_eq!(output, [0xAA; 2]);
+        assert_eq!(image, [0x80; 2 * 3]);
+
+        ordered_dither(&[], 4, &BLACK_AND_WHITE, &mut []);
+        floyd_steinberg_dither(&mut [], 4, &BLACK_AND_WHITE, &mut []);
+    }
+}
@@ -0,0 +1,140 @@
+use super::{Point, Rect};
+
+const INSIDE: u8 = 0;
+const LEFT: u8 = 1;
+const RIGHT: u8 = 2;
+const TOP: u8 = 4;
+const BOTTOM: u8 = 8;
+
+/// Returns which sides of the inclusive bounds `(left, top, right, bottom)`
+/// `point` lies beyond.
+fn outcode(point: Point<isize>, bounds: (isize, isize, isize, isize)) -> u8 {
+    let (left, top, right, bottom) = bounds;
+    let mut code = INSIDE;
+    if point.0 < left {
+        code |= LEFT;
+    } else if point.0 > right {
+        code |= RIGHT;
+    }
+    if point.1 < top {
+        code |= TOP;
+    } else if point.1 > bottom {
+        code |= BOTTOM;
+    }
+    code
+}
+
+/// Clips the line from `start` to `end` to `rect` with the Cohen-Sutherland
+/// algorithm, returning the part of the line within `rect`, or `None` if
+/// the line doesn't cross `rect`.
+///
+/// The clipped endpoints are rounded to whole pixels, so the clipped line
+/// can differ from the original one by a pixel near the edges of `rect`.
+///
+/// # Examples
+///
+/// ```
+/// use vga::drawing::{clip_line, Rect};
+///
+/// let rect = Rect::new(0, 0, 320, 200);
+/// assert_eq!(clip_line((-10, 0), (10, 0), rect), Some(((0, 0), (10, 0))));
+/// assert_eq!(clip_line((-10, -10), (-1, 300), rect), None);
+/// ```
+pub fn clip_line(
+    start: Point<isize>,
+    end: Point<isize>,
+    rect: Rect,
+) -> Option<(Point<isize>, Point<isize>)> {
+    if rect.is_empty() {
+        return None;
+    }
+    let left = rect.get_x() as isize;
+    let top = rect.get_y() as isize;
+    let right = left + rect.get_width() as isize - 1;
+    let bottom = top + rect.get_height() as isize - 1;
+    let bounds = (left, top, right, bottom);
+
+    let (mut start, mut end) = (start, end);
+    let (mut start_code, mut end_code) = (outcode(start, bounds), outcode(end, bounds));
+    loop {
+        if start_code | end_code == INSIDE {
+            return Some((start, end));
+        }
+        if start_code & end_code != INSIDE {
+            return None;
+        }
+
+        // Move the endpoint outside of the bounds onto the edge it lies beyond.
+        let code = if start_code != INSIDE {
+            start_code
+        } else {
+            end_code
+        };
+        let (point, other) = if code == start_code {
+            (start, end)
+        } else {
+            (end, start)
+        };
+        let (delta_x, delta_y) = (other.0 - point.0, other.1 - point.1);
+        let clipped = if code & TOP != 0 {
+            (point.0 + delta_x * (top - point.1) / delta_y, top)
+        } else if code & BOTTOM != 0 {
+            (point.0 + delta_x * (bottom - point.1) / delta_y, bottom)
+        } else if code & LEFT != 0 {
+            (left, point.1 + delta_y * (left - point.0) / delta_x)
+        } else {
+            (right, point.1 + delta_y * (right - point.0) / delta_x)
+        };
+
+        if code == start_code {
+            start = clipped;
+            start_code = outcode(start, bounds);
+        } else {
+            end = clipped;
+            end_code = outcode(end, bounds);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RECT: Rect = Rect::new(10, 10, 11, 11);
+
+    #[test]
+    fn test_clip_line_inside() {
+        assert_eq!(
+            clip_line((12, 12), (18, 15), RECT),
+            Some(((12, 12), (18, 15)))
+        );
+    }
+
+    #[test]
+    fn test_clip_line_outside() {
+        assert_eq!(clip_line((0, 0), (9, 30), RECT), None);
+        assert_eq!(clip_line((21, 0), (40, 40), RECT), None);
+        assert_eq!(clip_line((0, 19), (19, 0), RECT), None);
+        assert_eq!(clip_line((0, 0), (5, 5), Rect::new(0, 0, 0, 5)), None);
+    }
+
+    #[test]
+    fn test_clip_line_crossing() {
+        assert_eq!(
+            clip_line((0, 15), (30, 15), RECT),
+            Some(((10, 15), (20, 15)))
+        );
+        assert_eq!(
+            clip_line((15, 30), (15, -100), RECT),
+            Some(((15, 20), (15, 10)))
+        );
+        assert_eq!(
+            clip_line((0, 0), (30, 30), RECT),
+            Some(((10, 10), (20, 20)))
+        );
+        assert_eq!(
+            clip_line((isize::MIN / 4, 15), (15, 15), RECT),
+            Some(((10, 15), (15, 15)))
+        );
+    }
+}
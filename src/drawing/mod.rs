@@ -3,18 +3,255 @@
 use num_traits::{NumAssignOps, NumCast, Signed};
 
 mod bresenham;
+mod clipping;
 mod octant;
 
 pub(crate) use bresenham::Bresenham;
+pub use clipping::clip_line;
 use octant::Octant;
 
 /// A point in 2D space.
 pub type Point<T> = (T, T);
 
-pub(crate) trait SignedNum: Signed + Ord + Copy + NumCast + NumAssignOps {
-    fn cast<T: NumCast>(value: T) -> Self {
-        NumCast::from(value).unwrap()
+/// A rectangle with its top left corner at `(x, y)`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Rect {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Rect {
+    /// Creates a new `Rect` of `width` by `height` with its top left corner at `(x, y)`.
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the `x` coordinate of the left edge.
+    pub const fn get_x(&self) -> usize {
+        self.x
+    }
+
+    /// Returns the `y` coordinate of the top edge.
+    pub const fn get_y(&self) -> usize {
+        self.y
+    }
+
+    /// Returns the width of the `Rect`.
+    pub const fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the `Rect`.
+    pub const fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns `true` if the `Rect` has no area.
+    pub const fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns `true` if `(x, y)` lies within the `Rect`.
+    pub const fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+
+    /// Returns the area covered by both `self` and `other`, which is
+    /// empty if they don't overlap.
+    pub fn intersection(&self, other: Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Rect::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+    }
+
+    /// Returns the smallest `Rect` covering both `self` and `other`,
+    /// ignoring either if it's empty.
+    pub fn union(&self, other: Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return other;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Rect::new(x, y, right - x, bottom - y)
     }
 }
 
+/// A borrowed `width` by `height` image, such as a sprite sheet or a back
+/// buffer, with its pixels stored row by row.
+#[derive(Debug, Copy, Clone)]
+pub struct Surface<'a, C> {
+    pixels: &'a [C],
+    width: usize,
+    height: usize,
+}
+
+impl<'a, C: Copy> Surface<'a, C> {
+    /// Creates a new `Surface` of `width` by `height` from `pixels`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` holds fewer than `width * height` pixels.
+    pub fn new(pixels: &'a [C], width: usize, height: usize) -> Surface<'a, C> {
+        assert!(
+            pixels.len() >= width * height,
+            "surface has fewer pixels than its size"
+        );
+        Surface {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the width of the `Surface`.
+    pub const fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the `Surface`.
+    pub const fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the `Rect` covering the whole `Surface`.
+    pub const fn get_rect(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    /// Returns the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is outside of the `Surface`.
+    pub fn get_pixel(&self, x: usize, y: usize) -> C {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel is outside of the surface"
+        );
+        self.pixels[y * self.width + x]
+    }
+
+    /// Returns the pixels of row `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `y` is outside of the `Surface`.
+    pub fn get_row(&self, y: usize) -> &'a [C] {
+        assert!(y < self.height, "row is outside of the surface");
+        &self.pixels[y * self.width..(y + 1) * self.width]
+    }
+}
+
+/// A clockwise rotation in steps of 90 degrees.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Rotation {
+    /// No rotation.
+    #[default]
+    Rotate0,
+    /// Rotated by 90 degrees clockwise.
+    Rotate90,
+    /// Rotated by 180 degrees.
+    Rotate180,
+    /// Rotated by 270 degrees clockwise.
+    Rotate270,
+}
+
+/// Transformations applied by `PrimitiveDrawing::blit_with_options` while
+/// copying, so one sprite can be drawn facing any direction.
+///
+/// The source is flipped before it's rotated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct BlitOptions {
+    flip_horizontal: bool,
+    flip_vertical: bool,
+    rotation: Rotation,
+}
+
+impl BlitOptions {
+    /// Sets whether the source is mirrored left to right.
+    pub const fn with_flip_horizontal(mut self, flip_horizontal: bool) -> BlitOptions {
+        self.flip_horizontal = flip_horizontal;
+        self
+    }
+
+    /// Sets whether the source is mirrored top to bottom.
+    pub const fn with_flip_vertical(mut self, flip_vertical: bool) -> BlitOptions {
+        self.flip_vertical = flip_vertical;
+        self
+    }
+
+    /// Sets the rotation of the source.
+    pub const fn with_rotation(mut self, rotation: Rotation) -> BlitOptions {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Returns whether the source is mirrored left to right.
+    pub const fn get_flip_horizontal(&self) -> bool {
+        self.flip_horizontal
+    }
+
+    /// Returns whether the source is mirrored top to bottom.
+    pub const fn get_flip_vertical(&self) -> bool {
+        self.flip_vertical
+    }
+
+    /// Returns the rotation of the source.
+    pub const fn get_rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Returns the size of a `width` by `height` source once rotated.
+    pub(crate) const fn rotated_size(&self, width: usize, height: usize) -> (usize, usize) {
+        match self.rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => (width, height),
+            Rotation::Rotate90 | Rotation::Rotate270 => (height, width),
+        }
+    }
+
+    /// Maps `(x, y)` of the transformed `width` by `height` source back
+    /// to the point of the source it shows.
+    pub(crate) const fn source_point(
+        &self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> Point<usize> {
+        let (x, y) = match self.rotation {
+            Rotation::Rotate0 => (x, y),
+            Rotation::Rotate90 => (y, height - 1 - x),
+            Rotation::Rotate180 => (width - 1 - x, height - 1 - y),
+            Rotation::Rotate270 => (width - 1 - y, x),
+        };
+        let x = if self.flip_horizontal {
+            width - 1 - x
+        } else {
+            x
+        };
+        let y = if self.flip_vertical {
+            height - 1 - y
+        } else {
+            y
+        };
+        (x, y)
+    }
+}
+
+pub(crate) trait SignedNum: Signed + Ord + Copy + NumCast + NumAssignOps {}
+
 impl<T: Signed + Ord + Copy + NumCast + NumAssignOps> SignedNum for T {}
@@ -0,0 +1,648 @@
+//! A software model of vga hardware that renders to an RGBA buffer.
+//!
+//! `VgaEmulator` interprets register state and the contents of the four
+//! memory planes the same way a vga card would, and renders the resulting
+//! picture into an `RGBA8888` buffer. This allows golden image tests of
+//! text and graphics output, as well as previewing custom modes, without
+//! real hardware.
+//!
+//! Cpu accesses to video memory are modeled by `write_memory` and
+//! `read_memory`, including the memory map select, chain-4, odd/even,
+//! all four write modes, both read modes and the latches.
+//!
+//! # Examples
+//!
+//! Basic usage:
+//!
+//! ```ignore
+//! use vga::colors::{Color16, DEFAULT_PALETTE};
+//! use vga::configurations::MODE_640X480X16_CONFIGURATION;
+//! use vga::emulator::VgaEmulator;
+//! use vga::registers::GraphicsControllerIndex;
+//!
+//! static mut EMULATOR: VgaEmulator = VgaEmulator::new();
+//! static mut BUFFER: [u8; 640 * 480 * 4] = [0; 640 * 480 * 4];
+//!
+//! let emulator = unsafe { &mut EMULATOR };
+//! emulator.set_configuration(&MODE_640X480X16_CONFIGURATION);
+//! emulator.set_palette(&DEFAULT_PALETTE);
+//! emulator.set_graphics_controller(GraphicsControllerIndex::GraphicsMode, 0x02);
+//! emulator.write_memory(0, u8::from(Color16::Red));
+//!
+//! let (width, height) = emulator.render(unsafe { &mut BUFFER });
+//! ```
+
+use super::{
+    colors::Palette,
+    configurations::VgaConfiguration,
+    fonts::VgaFont,
+    registers::{
+        AttributeControllerIndex, CharacterMap, CrtcControllerIndex, GraphicsControllerIndex,
+        MemoryMap, SequencerIndex, ATTRIBUTE_CONTROLLER_REGISTER_COUNT,
+        CRTC_CONTROLLER_REGISTER_COUNT, GRAPHICS_CONTROLLER_REGISTER_COUNT,
+        SEQUENCER_REGISTER_COUNT,
+    },
+};
+use core::convert::TryFrom;
+
+#[cfg(any(test, feature = "test-backend"))]
+use crate::mock::MockHardware;
+
+/// The size of a single vga memory plane in bytes.
+pub const PLANE_SIZE: usize = 0x10000;
+
+/// The number of bytes used per pixel by `VgaEmulator::render`.
+pub const BYTES_PER_PIXEL: usize = 4;
+
+/// Represents an emulated vga graphics card with it's registers and memory planes.
+#[derive(Debug, Clone)]
+pub struct VgaEmulator {
+    miscellaneous_output: u8,
+    sequencer_registers: [u8; SEQUENCER_REGISTER_COUNT],
+    graphics_controller_registers: [u8; GRAPHICS_CONTROLLER_REGISTER_COUNT],
+    crtc_controller_registers: [u8; CRTC_CONTROLLER_REGISTER_COUNT],
+    attribute_controller_registers: [u8; ATTRIBUTE_CONTROLLER_REGISTER_COUNT],
+    palette: Palette<256>,
+    planes: [[u8; PLANE_SIZE]; 4],
+    latches: [u8; 4],
+}
+
+impl Default for VgaEmulator {
+    fn default() -> VgaEmulator {
+        VgaEmulator::new()
+    }
+}
+
+impl VgaEmulator {
+    /// Creates a new `VgaEmulator` with every register and memory plane set to 0.
+    ///
+    /// **Note:** `VgaEmulator` holds 256KiB of plane memory, so it's
+    /// usually best placed in a `static`.
+    pub const fn new() -> VgaEmulator {
+        VgaEmulator {
+            miscellaneous_output: 0x00,
+            sequencer_registers: [0; SEQUENCER_REGISTER_COUNT],
+            graphics_controller_registers: [0; GRAPHICS_CONTROLLER_REGISTER_COUNT],
+            crtc_controller_registers: [0; CRTC_CONTROLLER_REGISTER_COUNT],
+            attribute_controller_registers: [0; ATTRIBUTE_CONTROLLER_REGISTER_COUNT],
+            palette: Palette::new([(0, 0, 0); 256]),
+            planes: [[0; PLANE_SIZE]; 4],
+            latches: [0; 4],
+        }
+    }
+
+    /// Sets every register specified by `configuration`.
+    pub fn set_configuration(&mut self, configuration: &VgaConfiguration) {
+        self.miscellaneous_output = configuration.miscellaneous_output;
+        for (index, value) in configuration.sequencer_registers {
+            self.set_sequencer(*index, *value);
+        }
+        for (index, value) in configuration.crtc_controller_registers {
+            self.set_crtc_controller(*index, *value);
+        }
+        for (index, value) in configuration.graphics_controller_registers {
+            self.set_graphics_controller(*index, *value);
+        }
+        for (index, value) in configuration.attribute_controller_registers {
+            self.set_attribute_controller(*index, *value);
+        }
+    }
+
+    /// Copies the current register state and color palette from `hardware`.
+    #[cfg(any(test, feature = "test-backend"))]
+    pub fn load_registers(&mut self, hardware: &MockHardware) {
+        self.miscellaneous_output = hardware.miscellaneous_output;
+        self.sequencer_registers = hardware.sequencer_registers;
+        self.graphics_controller_registers = hardware.graphics_controller_registers;
+        self.crtc_controller_registers = hardware.crtc_controller_registers;
+        self.attribute_controller_registers = hardware.attribute_controller_registers;
+        self.palette = hardware.palette();
+    }
+
+    /// Copies the registers that affect cpu accesses to video memory from
+    /// `hardware`, which is much cheaper than `load_registers`.
+    #[cfg(any(test, feature = "test-backend"))]
+    pub(crate) fn load_memory_registers(&mut self, hardware: &MockHardware) {
+        self.sequencer_registers = hardware.sequencer_registers;
+        self.graphics_controller_registers = hardware.graphics_controller_registers;
+    }
+
+    /// Sets the miscellaneous output register to `value`.
+    pub fn set_miscellaneous_output(&mut self, value: u8) {
+        self.miscellaneous_output = value;
+    }
+
+    /// Sets the sequencer register specified by `index` to `value`.
+    pub fn set_sequencer(&mut self, index: SequencerIndex, value: u8) {
+        self.sequencer_registers[u8::from(index) as usize] = value;
+    }
+
+    /// Sets the graphics controller register specified by `index` to `value`.
+    pub fn set_graphics_controller(&mut self, index: GraphicsControllerIndex, value: u8) {
+        self.graphics_controller_registers[u8::from(index) as usize] = value;
+    }
+
+    /// Sets the crtc controller register specified by `index` to `value`.
+    pub fn set_crtc_controller(&mut self, index: CrtcControllerIndex, value: u8) {
+        self.crtc_controller_registers[u8::from(index) as usize] = value;
+    }
+
+    /// Sets the attribute controller register specified by `index` to `value`.
+    pub fn set_attribute_controller(&mut self, index: AttributeControllerIndex, value: u8) {
+        self.attribute_controller_registers[u8::from(index) as usize] = value;
+    }
+
+    /// Loads a 256 color palette, as specified by `palette`.
+    pub fn set_palette(&mut self, palette: &Palette<256>) {
+        self.palette = *palette;
+    }
+
+    /// Loads `vga_font` into the character map of plane 2 selected as
+    /// character map A by the `SequencerIndex::CharacterFont` register.
+    ///
+    /// The character map starts at the byte offset into plane 2 given by
+    /// `CharacterMap::get_offset`, with 32 bytes per character. A `VgaFont`
+    /// has at most 256 characters of up to 32 rows, so it always fits.
+    pub fn load_font(&mut self, vga_font: &VgaFont) {
+        let font_offset = self.font_offset(true);
+        let character_height = vga_font.get_character_height() as usize;
+        let font_data = vga_font.get_font_data();
+        for character in 0..vga_font.get_characters() as usize {
+            let start = font_offset + character * 32;
+            let data = &font_data[character * character_height..][..character_height];
+            self.planes[2][start..start + character_height].copy_from_slice(data);
+        }
+    }
+
+    /// Returns the contents of the memory plane specified by `plane`.
+    pub fn plane(&self, plane: usize) -> &[u8; PLANE_SIZE] {
+        &self.planes[plane]
+    }
+
+    /// Performs a cpu write of `value` to video memory, where `address` is
+    /// relative to the start of the vga memory window `(0xA0000)`.
+    ///
+    /// Writes outside of the window selected by the memory map bits of
+    /// `GraphicsControllerIndex::Miscellaneous` are ignored.
+    pub fn write_memory(&mut self, address: usize, value: u8) {
+        let offset = match self.map_address(address) {
+            Some(offset) => offset,
+            None => return,
+        };
+        let (planes, plane_address) = self.select_planes(offset);
+        let planes = planes & self.sequencer(SequencerIndex::PlaneMask);
+        let write_mode = self.graphics_controller(GraphicsControllerIndex::GraphicsMode) & 0x03;
+        let data_rotate = self.graphics_controller(GraphicsControllerIndex::DataRotate);
+        let bit_mask = self.graphics_controller(GraphicsControllerIndex::BitMask);
+        let set_reset = self.graphics_controller(GraphicsControllerIndex::SetReset);
+        let enable_set_reset = self.graphics_controller(GraphicsControllerIndex::EnableSetReset);
+        let rotated = value.rotate_right((data_rotate & 0x07) as u32);
+        let function = (data_rotate >> 3) & 0x03;
+
+        for plane in 0..4 {
+            if planes & (1 << plane) == 0 {
+                continue;
+            }
+            let latch = self.latches[plane];
+            let result = match write_mode {
+                0x0 => {
+                    let data = if enable_set_reset & (1 << plane) != 0 {
+                        expand_bit(set_reset, plane)
+                    } else {
+                        rotated
+                    };
+                    apply_bit_mask(apply_function(function, data, latch), latch, bit_mask)
+                }
+                0x1 => latch,
+                0x2 => apply_bit_mask(
+                    apply_function(function, expand_bit(value, plane), latch),
+                    latch,
+                    bit_mask,
+                ),
+                _ => apply_bit_mask(
+                    apply_function(function, expand_bit(set_reset, plane), latch),
+                    latch,
+                    rotated & bit_mask,
+                ),
+            };
+            self.planes[plane][plane_address] = result;
+        }
+    }
+
+    /// Performs a cpu read of video memory, where `address` is relative to
+    /// the start of the vga memory window `(0xA0000)`. This also loads the
+    /// latches of all 4 planes.
+    ///
+    /// Reads outside of the window selected by the memory map bits of
+    /// `GraphicsControllerIndex::Miscellaneous` return `0xFF`.
+    pub fn read_memory(&mut self, address: usize) -> u8 {
+        let offset = match self.map_address(address) {
+            Some(offset) => offset,
+            None => return 0xFF,
+        };
+        let (_, plane_address) = self.select_planes(offset);
+        for plane in 0..4 {
+            self.latches[plane] = self.planes[plane][plane_address];
+        }
+
+        let graphics_mode = self.graphics_controller(GraphicsControllerIndex::GraphicsMode);
+        if graphics_mode & 0x08 != 0 {
+            // Read mode 1, compare every pixel against the color compare register.
+            let color_compare = self.graphics_controller(GraphicsControllerIndex::ColorCompare);
+            let color_dont_care = self.graphics_controller(GraphicsControllerIndex::ColorDontCare);
+            let mut difference = 0;
+            for plane in 0..4 {
+                if color_dont_care & (1 << plane) != 0 {
+                    difference |= self.latches[plane] ^ expand_bit(color_compare, plane);
+                }
+            }
+            return !difference;
+        }
+
+        let memory_mode = self.sequencer(SequencerIndex::MemoryMode);
+        let read_plane = self.graphics_controller(GraphicsControllerIndex::ReadPlaneSelect) & 0x03;
+        let plane = if memory_mode & 0x08 != 0 {
+            offset & 0x03
+        } else if memory_mode & 0x04 == 0 {
+            (read_plane as usize & 0x02) | (offset & 0x01)
+        } else {
+            read_plane as usize
+        };
+        self.latches[plane]
+    }
+
+    /// Replays every byte of `memory` as a cpu write, where `memory` is a
+    /// copy of the vga memory window starting at `0xA0000`.
+    ///
+    /// This is intended for importing the video memory of `vga::mock`
+    /// in text modes and chain-4 modes, where every byte written by the
+    /// cpu ends up in exactly one plane. Planar modes lose information
+    /// when written through a plain memory buffer, so they should be
+    /// driven through `write_memory`, or read from `vga::mock::video_planes`,
+    /// instead.
+    pub fn import_memory(&mut self, memory: &[u8]) {
+        for (address, value) in memory.iter().enumerate() {
+            if self.map_address(address).is_some() {
+                self.write_memory(address, *value);
+            }
+        }
+    }
+
+    /// Returns the `(width, height)` in pixels of the picture produced by `render`.
+    pub fn resolution(&self) -> (usize, usize) {
+        let characters =
+            self.crtc_controller(CrtcControllerIndex::HorizontalDisplayEnableEnd) as usize + 1;
+        let width = if self.is_text_mode() {
+            characters * self.character_width()
+        } else if self.is_256_color_mode() {
+            characters * 4
+        } else {
+            characters * 8
+        };
+
+        let mut height = self.scan_lines();
+        if self.crtc_controller(CrtcControllerIndex::MaximumScanLine) & 0x80 != 0 {
+            height /= 2;
+        }
+        if !self.is_text_mode() {
+            height /= self.character_height();
+        }
+        (width, height)
+    }
+
+    /// Renders the current picture into `buffer` as `RGBA8888` pixels,
+    /// returning the `(width, height)` of the picture.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is smaller than `width * height * BYTES_PER_PIXEL`.
+    pub fn render(&self, buffer: &mut [u8]) -> (usize, usize) {
+        let (width, height) = self.resolution();
+        assert!(
+            buffer.len() >= width * height * BYTES_PER_PIXEL,
+            "buffer is too small for a {}x{} picture",
+            width,
+            height
+        );
+
+        for y in 0..height {
+            for x in 0..width {
+                let color_index = if self.is_text_mode() {
+                    self.text_pixel(x, y)
+                } else if self.is_256_color_mode() {
+                    self.pixel_256(x, y)
+                } else {
+                    self.pixel_16(x, y)
+                } as usize;
+                let offset = (y * width + x) * BYTES_PER_PIXEL;
+                let (red, green, blue) = self.palette.get_color(color_index);
+                buffer[offset] = scale_dac_value(red);
+                buffer[offset + 1] = scale_dac_value(green);
+                buffer[offset + 2] = scale_dac_value(blue);
+                buffer[offset + 3] = 0xFF;
+            }
+        }
+        (width, height)
+    }
+
+    fn sequencer(&self, index: SequencerIndex) -> u8 {
+        self.sequencer_registers[u8::from(index) as usize]
+    }
+
+    fn graphics_controller(&self, index: GraphicsControllerIndex) -> u8 {
+        self.graphics_controller_registers[u8::from(index) as usize]
+    }
+
+    fn crtc_controller(&self, index: CrtcControllerIndex) -> u8 {
+        self.crtc_controller_registers[u8::from(index) as usize]
+    }
+
+    fn attribute_controller(&self, index: AttributeControllerIndex) -> u8 {
+        self.attribute_controller_registers[u8::from(index) as usize]
+    }
+
+    /// Converts an address relative to `0xA0000` into an offset into the
+    /// window selected by the memory map bits.
+    fn map_address(&self, address: usize) -> Option<usize> {
+        let memory_map = MemoryMap::try_from(
+            (self.graphics_controller(GraphicsControllerIndex::Miscellaneous) >> 0x2) & 0x3,
+        )
+        .unwrap();
+        let (start, size) = (memory_map.get_offset(), memory_map.get_size());
+        if address >= start && address < start + size {
+            Some(address - start)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the planes addressed by `offset` as a bit mask, as well
+    /// as the address within those planes.
+    fn select_planes(&self, offset: usize) -> (u8, usize) {
+        let memory_mode = self.sequencer(SequencerIndex::MemoryMode);
+        let (planes, address) = if memory_mode & 0x08 != 0 {
+            (1 << (offset & 0x03), offset & !0x03)
+        } else if memory_mode & 0x04 == 0 {
+            (0b0101 << (offset & 0x01), offset & !0x01)
+        } else {
+            (0b1111, offset)
+        };
+        (planes, address % PLANE_SIZE)
+    }
+
+    fn is_text_mode(&self) -> bool {
+        self.attribute_controller(AttributeControllerIndex::ModeControl) & 0x01 == 0
+    }
+
+    fn is_256_color_mode(&self) -> bool {
+        self.attribute_controller(AttributeControllerIndex::ModeControl) & 0x40 != 0
+    }
+
+    fn character_width(&self) -> usize {
+        if self.sequencer(SequencerIndex::ClockingMode) & 0x01 != 0 {
+            8
+        } else {
+            9
+        }
+    }
+
+    fn character_height(&self) -> usize {
+        (self.crtc_controller(CrtcControllerIndex::MaximumScanLine) & 0x1F) as usize + 1
+    }
+
+    fn scan_lines(&self) -> usize {
+        let overflow = self.crtc_controller(CrtcControllerIndex::Overflow) as usize;
+        let vertical_display_end =
+            self.crtc_controller(CrtcControllerIndex::VerticalDisplayEnableEnd) as usize;
+        (vertical_display_end | (overflow & 0x02) << 7 | (overflow & 0x40) << 3) + 1
+    }
+
+    /// Returns the value of the crtc address counter at the start of `row`.
+    fn row_counter(&self, row: usize) -> usize {
+        let start_address = (self.crtc_controller(CrtcControllerIndex::StartAddressHigh) as usize)
+            << 8
+            | self.crtc_controller(CrtcControllerIndex::StartAddressLow) as usize;
+        let offset = self.crtc_controller(CrtcControllerIndex::Offset) as usize * 2;
+        start_address + row * offset
+    }
+
+    /// Converts a crtc address counter value into a plane address.
+    fn counter_address(&self, counter: usize) -> usize {
+        let address = if self.crtc_controller(CrtcControllerIndex::UnderlineLocation) & 0x40 != 0 {
+            counter << 2
+        } else if self.crtc_controller(CrtcControllerIndex::ModeControl) & 0x40 == 0 {
+            counter << 1
+        } else {
+            counter
+        };
+        address % PLANE_SIZE
+    }
+
+    /// Returns the byte offset into plane 2 of character map A, or of
+    /// character map B if `map_a` is `false`.
+    fn font_offset(&self, map_a: bool) -> usize {
+        let (primary, secondary) =
+            CharacterMap::from_character_font(self.sequencer(SequencerIndex::CharacterFont));
+        if map_a {
+            secondary.get_offset()
+        } else {
+            primary.get_offset()
+        }
+    }
+
+    fn text_pixel(&self, x: usize, y: usize) -> u8 {
+        let character_width = self.character_width();
+        let character_height = self.character_height();
+        let scan_line = y % character_height;
+        let column = x % character_width;
+        let counter = self.row_counter(y / character_height) + x / character_width;
+        let address = self.counter_address(counter);
+        let character = self.planes[0][address];
+        let attribute = self.planes[1][address];
+
+        let mode_control = self.attribute_controller(AttributeControllerIndex::ModeControl);
+        let font_offset = self.font_offset(attribute & 0x08 != 0);
+        let glyph =
+            self.planes[2][(font_offset + character as usize * 32 + scan_line) % PLANE_SIZE];
+        let set = if column < 8 {
+            glyph & (0x80 >> column) != 0
+        } else {
+            // The ninth column repeats the eighth for line graphics characters.
+            mode_control & 0x04 != 0 && (0xC0..=0xDF).contains(&character) && glyph & 0x01 != 0
+        };
+
+        let cursor_start = self.crtc_controller(CrtcControllerIndex::TextCursorStart);
+        let cursor_end = self.crtc_controller(CrtcControllerIndex::TextCursorEnd) & 0x1F;
+        let cursor_location =
+            (self.crtc_controller(CrtcControllerIndex::TextCursorLocationHigh) as usize) << 8
+                | self.crtc_controller(CrtcControllerIndex::TextCursorLocationLow) as usize;
+        let cursor = cursor_start & 0x20 == 0
+            && counter == cursor_location
+            && scan_line >= (cursor_start & 0x1F) as usize
+            && scan_line <= cursor_end as usize;
+        // Like the monochrome underline attribute, blue on black is underlined.
+        let underline = attribute & 0x77 == 0x01
+            && scan_line
+                == (self.crtc_controller(CrtcControllerIndex::UnderlineLocation) & 0x1F) as usize;
+
+        let foreground = attribute & 0x0F;
+        let mut background = attribute >> 4;
+        if mode_control & 0x08 != 0 {
+            background &= 0x07;
+        }
+        if set || cursor || underline {
+            self.attribute_color(foreground)
+        } else {
+            self.attribute_color(background)
+        }
+    }
+
+    fn pixel_16(&self, x: usize, y: usize) -> u8 {
+        let address = self.counter_address(self.row_counter(y) + x / 8);
+        let bit = 7 - (x % 8);
+        let mut color = 0;
+        for plane in 0..4 {
+            color |= ((self.planes[plane][address] >> bit) & 0x01) << plane;
+        }
+        self.attribute_color(color)
+    }
+
+    fn pixel_256(&self, x: usize, y: usize) -> u8 {
+        let address = self.counter_address(self.row_counter(y) + x / 4);
+        self.planes[x % 4][address]
+    }
+
+    /// Converts a 4 bit color into a dac index using the attribute
+    /// controller palette registers.
+    fn attribute_color(&self, color: u8) -> u8 {
+        let color_plane_enable =
+            self.attribute_controller(AttributeControllerIndex::MemoryPlaneEnable) & 0x0F;
+        let mode_control = self.attribute_controller(AttributeControllerIndex::ModeControl);
+        let color_select = self.attribute_controller(AttributeControllerIndex::ColorSelect);
+        let palette_value =
+            self.attribute_controller_registers[(color & color_plane_enable) as usize];
+        let value = if mode_control & 0x80 != 0 {
+            (palette_value & 0x0F) | (color_select & 0x03) << 4
+        } else {
+            palette_value & 0x3F
+        };
+        value | (color_select & 0x0C) << 4
+    }
+}
+
+fn expand_bit(value: u8, plane: usize) -> u8 {
+    if value & (1 << plane) != 0 {
+        0xFF
+    } else {
+        0x00
+    }
+}
+
+fn apply_function(function: u8, data: u8, latch: u8) -> u8 {
+    match function {
+        0x0 => data,
+        0x1 => data & latch,
+        0x2 => data | latch,
+        _ => data ^ latch,
+    }
+}
+
+fn apply_bit_mask(data: u8, latch: u8, bit_mask: u8) -> u8 {
+    (data & bit_mask) | (latch & !bit_mask)
+}
+
+/// Scales a 6 bit dac value to 8 bits.
+fn scale_dac_value(value: u8) -> u8 {
+    let value = value & 0x3F;
+    value << 2 | value >> 4
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    #[cfg(all(
+        feature = "global",
+        any(feature = "text-modes", feature = "graphics-modes")
+    ))]
+    use crate::mock::{lock_for_test, video_memory, MOCK_HARDWARE};
+    #[cfg(all(feature = "global", feature = "graphics-modes"))]
+    use crate::writers::{Graphics320x200x256, GraphicsWriter};
+    #[cfg(all(feature = "global", feature = "text-modes"))]
+    use crate::{
+        colors::TextModeColor,
+        fonts::TEXT_8X16_FONT,
+        writers::{ScreenCharacter, Text80x25, TextWriter},
+    };
+    use crate::{
+        colors::{Color16, DEFAULT_PALETTE},
+        configurations::MODE_640X480X16_CONFIGURATION,
+    };
+    use std::{boxed::Box, vec};
+
+    fn pixel(buffer: &[u8], width: usize, x: usize, y: usize) -> [u8; 3] {
+        let offset = (y * width + x) * BYTES_PER_PIXEL;
+        [buffer[offset], buffer[offset + 1], buffer[offset + 2]]
+    }
+
+    #[test]
+    #[cfg(all(feature = "global", feature = "text-modes"))]
+    fn test_render_text_mode() {
+        let _guard = lock_for_test();
+        let text_mode = Text80x25::new();
+        let color = TextModeColor::new(Color16::Red, Color16::Blue);
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        text_mode.disable_cursor();
+        text_mode.write_character(1, 0, ScreenCharacter::new(0xDB, color));
+
+        let mut emulator = Box::new(VgaEmulator::new());
+        emulator.load_registers(&MOCK_HARDWARE.lock());
+        emulator.import_memory(video_memory());
+        emulator.load_font(&TEXT_8X16_FONT);
+
+        let mut buffer = vec![0; 720 * 400 * BYTES_PER_PIXEL];
+        assert_eq!(emulator.render(&mut buffer), (720, 400));
+        assert_eq!(pixel(&buffer, 720, 9, 0), [0xAA, 0x00, 0x00]);
+        assert_eq!(pixel(&buffer, 720, 17, 15), [0xAA, 0x00, 0x00]);
+        assert_eq!(pixel(&buffer, 720, 18, 0), [0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    #[cfg(all(feature = "global", feature = "graphics-modes"))]
+    fn test_render_chain_4_mode() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+        mode.set_pixel(10, 5, 0x04);
+
+        let mut emulator = Box::new(VgaEmulator::new());
+        emulator.load_registers(&MOCK_HARDWARE.lock());
+        emulator.import_memory(video_memory());
+
+        let mut buffer = vec![0; 320 * 200 * BYTES_PER_PIXEL];
+        assert_eq!(emulator.render(&mut buffer), (320, 200));
+        assert_eq!(pixel(&buffer, 320, 10, 5), [0xAA, 0x00, 0x00]);
+        assert_eq!(pixel(&buffer, 320, 11, 5), [0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_write_mode_2() {
+        let mut emulator = Box::new(VgaEmulator::new());
+        emulator.set_configuration(&MODE_640X480X16_CONFIGURATION);
+        emulator.set_palette(&DEFAULT_PALETTE);
+        emulator.set_sequencer(SequencerIndex::PlaneMask, 0x0F);
+        emulator.set_graphics_controller(GraphicsControllerIndex::GraphicsMode, 0x02);
+        emulator.set_graphics_controller(GraphicsControllerIndex::BitMask, 0x40);
+        emulator.read_memory(0);
+        emulator.write_memory(0, u8::from(Color16::Red));
+
+        let mut buffer = vec![0; 640 * 480 * BYTES_PER_PIXEL];
+        assert_eq!(emulator.render(&mut buffer), (640, 480));
+        assert_eq!(pixel(&buffer, 640, 0, 0), [0x00, 0x00, 0x00]);
+        assert_eq!(pixel(&buffer, 640, 1, 0), [0xAA, 0x00, 0x00]);
+        assert_eq!(emulator.plane(2)[0], 0x40);
+    }
+}
@@ -1,13 +1,216 @@
 //! Common font structures used in vga programming.
 
+/// The largest number of characters in a character map of plane 2.
+const MAX_CHARACTERS: u16 = 256;
+/// The largest character height, which is the space every character
+/// takes in a character map.
+const MAX_CHARACTER_HEIGHT: u16 = 32;
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_HEADER_SIZE: usize = 4;
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+const PSF2_HEADER_SIZE: usize = 32;
+
+/// Declares a `VgaFont` embedded from a pc screen font (psf) file at
+/// compile time, with the path resolved like `include_bytes!`.
+///
+/// The font is parsed with `VgaFont::from_psf`, and an invalid font
+/// fails the build.
+///
+/// # Examples
+///
+/// ```
+/// use vga::fonts::VgaFont;
+/// use vga::include_font;
+///
+/// static FONT: VgaFont = include_font!("../fonts/example-8x8.psf");
+/// assert_eq!(FONT.get_characters(), 2);
+/// ```
+///
+/// A file that isn't a valid psf font fails the build:
+///
+/// ```compile_fail
+/// use vga::fonts::VgaFont;
+/// use vga::include_font;
+///
+/// static FONT: VgaFont = include_font!("../Cargo.toml");
+/// ```
+#[macro_export]
+macro_rules! include_font {
+    ($path:expr) => {{
+        const FONT: $crate::fonts::VgaFont =
+            match $crate::fonts::VgaFont::from_psf(include_bytes!($path)) {
+                Ok(font) => font,
+                Err(error) => panic!("{}", error),
+            };
+        FONT
+    }};
+}
+
+/// Declares a `VgaFont` from a raw bitmap of `character_height` bytes per
+/// character, with the rows of each character in turn, such as a byte
+/// array or a file embedded with `include_bytes!`.
+///
+/// The font is created with `VgaFont::from_bitmap`, and an invalid font
+/// fails the build.
+///
+/// # Examples
+///
+/// ```
+/// use vga::font_from_bitmap;
+/// use vga::fonts::VgaFont;
+///
+/// static FONT: VgaFont = font_from_bitmap!(2, &[0xFF, 0x81, 0x81, 0xFF]);
+/// assert_eq!(FONT.get_characters(), 2);
+/// ```
+#[macro_export]
+macro_rules! font_from_bitmap {
+    ($character_height:expr, $bitmap:expr) => {{
+        const FONT: $crate::fonts::VgaFont =
+            match $crate::fonts::VgaFont::from_bitmap($character_height, $bitmap) {
+                Ok(font) => font,
+                Err(error) => panic!("{}", error),
+            };
+        FONT
+    }};
+}
+
 /// Represents a font to be used for text mode.
+///
+/// Fonts are created with `VgaFont::new` or `VgaFont::from_psf`, which
+/// check that they fit in a character map and have all of their data.
+#[derive(Debug)]
 pub struct VgaFont {
-    /// Represents the number of characters contained in the font.
-    pub characters: u16,
-    /// Represents the height of the characters in bytes.
-    pub character_height: u16,
-    /// Represents the font data to be loaded in.
-    pub font_data: &'static [u8],
+    characters: u16,
+    character_height: u16,
+    font_data: &'static [u8],
+}
+
+impl VgaFont {
+    /// Creates a new `VgaFont` of `characters` characters, each
+    /// `character_height` bytes of `font_data` long.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the font doesn't fit in a character map, with
+    /// more than 256 characters or a height above 32, or if `font_data` is
+    /// shorter than `characters * character_height` bytes.
+    pub const fn new(
+        characters: u16,
+        character_height: u16,
+        font_data: &'static [u8],
+    ) -> Result<VgaFont, &'static str> {
+        let font = VgaFont {
+            characters,
+            character_height,
+            font_data,
+        };
+        match font.validate() {
+            Ok(()) => Ok(font),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Creates a new `VgaFont` from the contents of a pc screen font (psf)
+    /// file of version 1 or 2, such as the console fonts of linux.
+    ///
+    /// Only the first 256 characters are used, and unicode tables are
+    /// ignored, so characters are in the order of the font.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `data` isn't a psf font, if its glyphs are wider
+    /// than 8 pixels, or if it isn't valid as checked by `VgaFont::new`.
+    pub const fn from_psf(data: &'static [u8]) -> Result<VgaFont, &'static str> {
+        if data.len() >= PSF1_HEADER_SIZE && data[0] == PSF1_MAGIC[0] && data[1] == PSF1_MAGIC[1] {
+            let (_, font_data) = data.split_at(PSF1_HEADER_SIZE);
+            return VgaFont::new(256, data[3] as u16, font_data);
+        }
+
+        if data.len() < PSF2_HEADER_SIZE
+            || data[0] != PSF2_MAGIC[0]
+            || data[1] != PSF2_MAGIC[1]
+            || data[2] != PSF2_MAGIC[2]
+            || data[3] != PSF2_MAGIC[3]
+        {
+            return Err("data isn't a psf font");
+        }
+        let header_size = read_u32(data, 8) as usize;
+        let characters = read_u32(data, 16);
+        let bytes_per_glyph = read_u32(data, 20);
+        let character_height = read_u32(data, 24);
+        let width = read_u32(data, 28);
+        if width > 8 || bytes_per_glyph != character_height {
+            return Err("psf font glyphs are wider than 8 pixels");
+        }
+        if header_size > data.len() || character_height > u16::MAX as u32 {
+            return Err("psf font header is invalid");
+        }
+        let characters = if characters > 256 { 256 } else { characters };
+        let (_, font_data) = data.split_at(header_size);
+        VgaFont::new(characters as u16, character_height as u16, font_data)
+    }
+
+    /// Creates a new `VgaFont` from a raw bitmap of `character_height`
+    /// bytes per character, with as many characters as fit in `bitmap`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the font isn't valid as checked by `VgaFont::new`.
+    pub const fn from_bitmap(
+        character_height: u16,
+        bitmap: &'static [u8],
+    ) -> Result<VgaFont, &'static str> {
+        let characters = match character_height {
+            0 => 0,
+            height => bitmap.len() / height as usize,
+        };
+        let characters = if characters > u16::MAX as usize {
+            u16::MAX
+        } else {
+            characters as u16
+        };
+        VgaFont::new(characters, character_height, bitmap)
+    }
+
+    /// Returns the number of characters contained in the font.
+    pub const fn get_characters(&self) -> u16 {
+        self.characters
+    }
+
+    /// Returns the height of the characters in bytes.
+    pub const fn get_character_height(&self) -> u16 {
+        self.character_height
+    }
+
+    /// Returns the font data, with the rows of each character in turn.
+    pub const fn get_font_data(&self) -> &'static [u8] {
+        self.font_data
+    }
+
+    /// Checks the font as described by `VgaFont::new`.
+    const fn validate(&self) -> Result<(), &'static str> {
+        if self.characters == 0 || self.characters > MAX_CHARACTERS {
+            return Err("font must have between 1 and 256 characters");
+        }
+        if self.character_height == 0 || self.character_height > MAX_CHARACTER_HEIGHT {
+            return Err("font character height must be between 1 and 32");
+        }
+        if self.font_data.len() < self.characters as usize * self.character_height as usize {
+            return Err("font data is shorter than its characters");
+        }
+        Ok(())
+    }
+}
+
+/// Reads the little endian `u32` at `offset` of `data`.
+const fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
 }
 
 /// Standard 8x8 character font.
@@ -157,6 +360,7 @@ pub const TEXT_8X8_FONT: VgaFont = VgaFont {
 };
 
 /// Standard 8x16 character font.
+#[cfg(feature = "fonts-8x16")]
 pub const TEXT_8X16_FONT: VgaFont = VgaFont {
     characters: 256,
     character_height: 16,
@@ -438,3 +642,72 @@ pub const TEXT_8X16_FONT: VgaFont = VgaFont {
         0x00,
     ],
 };
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "fonts-8x16")]
+    fn test_new() {
+        assert!(VgaFont::new(256, 16, TEXT_8X16_FONT.font_data).is_ok());
+        assert!(VgaFont::new(256, 8, TEXT_8X16_FONT.font_data).is_ok());
+        assert_eq!(
+            VgaFont::new(256, 16, TEXT_8X8_FONT.font_data).unwrap_err(),
+            "font data is shorter than its characters"
+        );
+        assert!(VgaFont::new(257, 8, TEXT_8X16_FONT.font_data).is_err());
+        assert!(VgaFont::new(16, 33, TEXT_8X16_FONT.font_data).is_err());
+        assert!(VgaFont::new(0, 8, &[]).is_err());
+    }
+
+    #[test]
+    fn test_from_psf() {
+        static PSF1: [u8; 4 + 256 * 2] = {
+            let mut data = [0; 4 + 256 * 2];
+            data[0] = 0x36;
+            data[1] = 0x04;
+            data[3] = 2;
+            data[4 + 0x41 * 2] = 0x7E;
+            data
+        };
+        let font = VgaFont::from_psf(&PSF1).unwrap();
+        assert_eq!((font.characters, font.character_height), (256, 2));
+        assert_eq!(&font.font_data[0x41 * 2..0x41 * 2 + 2], &[0x7E, 0x00]);
+
+        static PSF2: [u8; 32 + 2 * 4] = [
+            0x72, 0xB5, 0x4A, 0x86, 0, 0, 0, 0, 32, 0, 0, 0, 0, 0, 0, 0, //
+            2, 0, 0, 0, 4, 0, 0, 0, 4, 0, 0, 0, 8, 0, 0, 0, //
+            1, 2, 3, 4, 5, 6, 7, 8,
+        ];
+        let font = VgaFont::from_psf(&PSF2).unwrap();
+        assert_eq!((font.characters, font.character_height), (2, 4));
+        assert_eq!(font.font_data, &[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        assert_eq!(
+            VgaFont::from_psf(&PSF2[..16]).unwrap_err(),
+            "data isn't a psf font"
+        );
+        assert!(VgaFont::from_psf(&PSF1[..100]).is_err());
+    }
+
+    #[test]
+    fn test_include_font() {
+        static FONT: VgaFont = include_font!("../fonts/example-8x8.psf");
+        assert_eq!((FONT.characters, FONT.character_height), (2, 8));
+        assert_eq!(
+            FONT.font_data,
+            &[
+                0xFF, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0xFF, //
+                0x81, 0x42, 0x24, 0x18, 0x18, 0x24, 0x42, 0x81,
+            ]
+        );
+
+        static BITMAP: VgaFont = font_from_bitmap!(4, &[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!((BITMAP.characters, BITMAP.character_height), (2, 4));
+        assert_eq!(
+            VgaFont::from_bitmap(0, &[]).unwrap_err(),
+            "font must have between 1 and 256 characters"
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Frame pacing based on counting vertical retraces, for timing animations
+//! without a pit or apic timer.
+//!
+//! A `FrameCounter` counts frames either by polling input status register 1
+//! with `FrameCounter::poll`, or by calling `FrameCounter::tick` from an
+//! interrupt handler, such as one for the vertical retrace interrupt
+//! with the help of a `VblankInterrupt`.
+
+use crate::vga::Vga;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Counts the frames displayed by the vga, one per vertical retrace.
+///
+/// The counter can be shared with an interrupt handler, since all of its
+/// methods take `&self`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
+/// use vga::frame_counter::FrameCounter;
+/// use vga::vga::VGA;
+///
+/// static FRAMES: FrameCounter = FrameCounter::new();
+///
+/// // Called from the vertical retrace interrupt handler.
+/// FRAMES.tick();
+///
+/// // Or counted by polling, for example to wait one second at 70hz.
+/// FRAMES.wait_frames(&mut VGA.lock(), 70);
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct FrameCounter {
+    frames: AtomicUsize,
+    in_retrace: AtomicBool,
+}
+
+impl FrameCounter {
+    /// Creates a new `FrameCounter` with no frames counted.
+    pub const fn new() -> FrameCounter {
+        FrameCounter {
+            frames: AtomicUsize::new(0),
+            in_retrace: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the number of frames counted so far, wrapping on overflow.
+    pub fn get_frame_count(&self) -> usize {
+        self.frames.load(Ordering::Acquire)
+    }
+
+    /// Counts one frame, meant to be called from an interrupt handler
+    /// that runs once per vertical retrace.
+    pub fn tick(&self) {
+        self.frames.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Reads input status register 1 once and counts a frame if the
+    /// vertical retrace started since the last poll, returning whether
+    /// a frame was counted.
+    ///
+    /// Frames are missed if the counter isn't polled at least once
+    /// during each vertical retrace.
+    pub fn poll(&self, vga: &mut Vga) -> bool {
+        let emulation_mode = vga.get_emulation_mode();
+        let in_retrace = vga.general_registers.is_vertical_retrace(emulation_mode);
+        let was_in_retrace = self.in_retrace.swap(in_retrace, Ordering::AcqRel);
+        if in_retrace && !was_in_retrace {
+            self.tick();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Busy waits until `frames` more frames have been counted by polling `vga`.
+    pub fn wait_frames(&self, vga: &mut Vga, frames: usize) {
+        let start = self.get_frame_count();
+        while self.get_frame_count().wrapping_sub(start) < frames {
+            self.poll(vga);
+        }
+    }
+
+    /// Waits until `frames` more frames have been counted by `tick`,
+    /// calling `idle` in between, such as to halt until the next interrupt.
+    ///
+    /// **Note:** This never returns unless `tick` is called from an
+    /// interrupt handler or another thread.
+    pub fn wait_ticks(&self, frames: usize, mut idle: impl FnMut()) {
+        let start = self.get_frame_count();
+        while self.get_frame_count().wrapping_sub(start) < frames {
+            idle();
+        }
+    }
+}
+
+/// Counts the vertical retrace interrupts raised by the vga, for calling
+/// from a kernel's irq 2 handler.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
+/// use vga::frame_counter::VblankInterrupt;
+/// use vga::vga::VGA;
+///
+/// static VBLANK: VblankInterrupt = VblankInterrupt::new();
+///
+/// VBLANK.enable(&mut VGA.lock());
+///
+/// // In the irq handler, before signaling the end of interrupt. If the
+/// // interrupted code holds the lock, the frame is skipped.
+/// if let Some(mut vga) = VGA.try_lock() {
+///     if VBLANK.handle(&mut vga) {
+///         // Flip pages here.
+///     }
+/// }
+///
+/// // Waits for the next interrupt, rather than polling the vga.
+/// VBLANK.get_frame_counter().wait_ticks(1, || {});
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct VblankInterrupt {
+    frame_counter: FrameCounter,
+}
+
+impl VblankInterrupt {
+    /// Creates a new `VblankInterrupt` with no frames counted.
+    pub const fn new() -> VblankInterrupt {
+        VblankInterrupt {
+            frame_counter: FrameCounter::new(),
+        }
+    }
+
+    /// Returns the `FrameCounter` ticked by `handle`.
+    pub fn get_frame_counter(&self) -> &FrameCounter {
+        &self.frame_counter
+    }
+
+    /// Enables the vertical retrace interrupt on `vga`.
+    pub fn enable(&self, vga: &mut Vga) {
+        vga.set_vertical_interrupt(true);
+    }
+
+    /// Disables the vertical retrace interrupt on `vga`.
+    pub fn disable(&self, vga: &mut Vga) {
+        vga.set_vertical_interrupt(false);
+    }
+
+    /// Acknowledges and counts a pending vertical retrace interrupt on `vga`,
+    /// returning `false` if `vga` didn't raise the interrupt.
+    ///
+    /// **Note:** The interrupt handler calling this must not block, since
+    /// the code it interrupted may hold the lock on the `Vga`, which would
+    /// then never be released. Lock it with `try_lock` and skip the frame if
+    /// it's held.
+    pub fn handle(&self, vga: &mut Vga) -> bool {
+        if !vga.is_vertical_interrupt_pending() {
+            return false;
+        }
+        vga.clear_vertical_interrupt();
+        self.frame_counter.tick();
+        true
+    }
+}
+
+/// Busy waits for `frames` vertical retraces on `vga`.
+pub fn wait_frames(vga: &mut Vga, frames: usize) {
+    FrameCounter::new().wait_frames(vga, frames);
+}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::{mock::lock_for_test, vga::VGA};
+
+    #[test]
+    fn test_poll() {
+        let _guard = lock_for_test();
+        let counter = FrameCounter::new();
+        let mut vga = VGA.lock();
+        let counted = (0..10).filter(|_| counter.poll(&mut vga)).count();
+        assert_eq!(counted, 5);
+        assert_eq!(counter.get_frame_count(), 5);
+    }
+
+    #[test]
+    fn test_wait_frames() {
+        let _guard = lock_for_test();
+        let counter = FrameCounter::new();
+        counter.tick();
+        counter.wait_frames(&mut VGA.lock(), 3);
+        assert_eq!(counter.get_frame_count(), 4);
+    }
+
+    #[test]
+    fn test_wait_ticks() {
+        let counter = FrameCounter::new();
+        let mut idles = 0;
+        counter.wait_ticks(2, || {
+            idles += 1;
+            counter.tick();
+        });
+        assert_eq!(idles, 2);
+        assert_eq!(counter.get_frame_count(), 2);
+    }
+
+    #[test]
+    fn test_vblank_interrupt() {
+        let _guard = lock_for_test();
+        let vblank = VblankInterrupt::new();
+        let mut vga = VGA.lock();
+        let emulation_mode = vga.get_emulation_mode();
+        assert!(!vblank.handle(&mut vga));
+
+        // Leave the retrace, so the next read starts one.
+        while vga.general_registers.is_vertical_retrace(emulation_mode) {}
+        vblank.enable(&mut vga);
+        assert!(vga.get_vertical_interrupt());
+        vga.general_registers.read_st01(emulation_mode);
+        assert!(vblank.handle(&mut vga));
+        assert!(!vblank.handle(&mut vga));
+        assert_eq!(vblank.get_frame_counter().get_frame_count(), 1);
+
+        vblank.disable(&mut vga);
+        assert!(!vga.get_vertical_interrupt());
+        vga.general_registers.read_st01(emulation_mode);
+        vga.general_registers.read_st01(emulation_mode);
+        assert!(!vblank.handle(&mut vga));
+    }
+}
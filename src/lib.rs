@@ -3,14 +3,65 @@
 //!
 //! Memory addresses `0xA0000 -> 0xBFFFF` must be readable and writeable
 //! this crate to work properly.
+//!
+//! The writers use the global `vga::VGA` static, provided by the default
+//! `global` feature. Every writer can also be created with `with_vga` to use
+//! a `Spinlock<Vga>` of its own, created with `Vga::new()` when the `instances`
+//! feature is enabled or the `global` feature is disabled.
+//!
+//! The writers can be compiled out to save space in small kernels. The
+//! default `text-modes` feature provides the text writers and `PanicScreen`,
+//! `graphics-modes` the 320x200, 640x480 and 1280x800 writers, `modex` the
+//! 320x240 writer and `VirtualDesktop`, `bochs-device` the `BochsDevice`,
+//! `cirrus-device` the `CirrusDevice` and `fonts-8x16` the `TEXT_8X16_FONT`,
+//! which the text modes require.
+//!
+//! Io ports only exist on x86. On other architectures, such as aarch64
+//! and riscv64, create a `Vga` with `Vga::with_register_access` and
+//! `RegisterAccess::Mmio` to drive a card exposing its registers in a bar.
 
 #![no_std]
 #![warn(missing_docs)]
 
+#[cfg(any(test, feature = "alloc"))]
+extern crate alloc;
+
+pub mod antialiasing;
+#[cfg(any(test, feature = "alloc"))]
+pub mod blending;
+#[cfg(any(test, feature = "alloc"))]
+pub mod capture;
+pub mod chart;
 pub mod colors;
 pub mod configurations;
+pub mod devices;
+#[cfg(feature = "global")]
+pub mod display;
+pub mod dithering;
 pub mod drawing;
+#[cfg(any(test, feature = "emulator"))]
+pub mod emulator;
 pub mod fonts;
+pub mod frame_counter;
+#[cfg(any(test, feature = "log"))]
+pub mod logger;
+pub mod mirrored_console;
+#[cfg(any(test, feature = "test-backend"))]
+pub mod mock;
+pub mod palette_queue;
+#[cfg(feature = "text-modes")]
+pub mod panic_screen;
+pub mod planar;
+pub mod presenter;
+pub mod raster_effects;
+#[cfg(feature = "global")]
+pub mod reflow_console;
 pub mod registers;
+pub mod splash;
+pub mod stroke_font;
+#[cfg(any(test, feature = "tui"))]
+pub mod tui;
 pub mod vga;
+pub mod volatile;
+pub mod vt;
 pub mod writers;
@@ -0,0 +1,209 @@
+//! A `log` crate backend writing to a text mode.
+//!
+//! `TextLogger` writes every record as a line with its level, target and
+//! message, colored by level, and optionally preceded by a timestamp
+//! from a user provided function. Records are dropped while the `Vga` is
+//! locked, so logging from code holding the lock doesn't deadlock.
+
+use crate::{
+    colors::{Color16, TextModeColor},
+    writers::{TextLine, TextWriter},
+};
+use core::fmt::{self, Write};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// Writes a timestamp to the given `fmt::Write`, for example the number of
+/// ticks since boot.
+pub type TimestampFn = fn(&mut dyn fmt::Write) -> fmt::Result;
+
+/// A `log::Log` implementation writing records to a `TextWriter`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "text-modes"))]
+/// # {
+/// use core::fmt::Write;
+/// use vga::colors::{Color16, TextModeColor};
+/// use vga::logger::TextLogger;
+/// use vga::writers::{Text80x25, TextWriter};
+///
+/// static LOGGER: TextLogger<Text80x25> = TextLogger::new(Text80x25::new())
+///     .with_color(
+///         log::Level::Debug,
+///         TextModeColor::new(Color16::Cyan, Color16::Black),
+///     )
+///     .with_timestamp(|f| write!(f, "[{:>8}]", 0));
+///
+/// LOGGER.get_writer().set_mode();
+/// LOGGER.init().unwrap();
+/// log::info!("Booting...");
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TextLogger<T> {
+    writer: T,
+    level: LevelFilter,
+    colors: [TextModeColor; 5],
+    timestamp: Option<TimestampFn>,
+}
+
+impl<T: TextWriter + Send + Sync> TextLogger<T> {
+    /// Creates a new `TextLogger` writing records up to `Level::Info` to
+    /// `writer`, starting at its current cursor position.
+    pub const fn new(writer: T) -> TextLogger<T> {
+        TextLogger {
+            writer,
+            level: LevelFilter::Info,
+            colors: [
+                TextModeColor::new(Color16::LightRed, Color16::Black),
+                TextModeColor::new(Color16::Yellow, Color16::Black),
+                TextModeColor::new(Color16::White, Color16::Black),
+                TextModeColor::new(Color16::LightGrey, Color16::Black),
+                TextModeColor::new(Color16::DarkGrey, Color16::Black),
+            ],
+            timestamp: None,
+        }
+    }
+
+    /// Sets the most verbose level written.
+    pub const fn with_level(mut self, level: LevelFilter) -> TextLogger<T> {
+        self.level = level;
+        self
+    }
+
+    /// Sets the color records of `level` are written with.
+    pub const fn with_color(mut self, level: Level, color: TextModeColor) -> TextLogger<T> {
+        self.colors[level as usize - 1] = color;
+        self
+    }
+
+    /// Sets the function writing the timestamp each record starts with,
+    /// which is called with the `Vga` locked.
+    pub const fn with_timestamp(mut self, timestamp: TimestampFn) -> TextLogger<T> {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Returns the `TextWriter` records are written to.
+    pub fn get_writer(&self) -> &T {
+        &self.writer
+    }
+
+    /// Returns the most verbose level written.
+    pub fn get_level(&self) -> LevelFilter {
+        self.level
+    }
+
+    /// Returns the color records of `level` are written with.
+    pub fn get_color(&self, level: Level) -> TextModeColor {
+        self.colors[level as usize - 1]
+    }
+
+    /// Sets this logger as the logger of the `log` crate, and its level as
+    /// the maximum level.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a logger was already set.
+    pub fn init(&'static self) -> Result<(), SetLoggerError> {
+        log::set_logger(self)?;
+        log::set_max_level(self.level);
+        Ok(())
+    }
+
+    fn write_record(&self, line: &mut TextLine<'_, T>, record: &Record<'_>) -> fmt::Result {
+        if let Some(timestamp) = self.timestamp {
+            timestamp(line)?;
+            line.write_char(' ')?;
+        }
+        writeln!(
+            line,
+            "{:<5} {}: {}",
+            record.level(),
+            record.target(),
+            record.args()
+        )
+    }
+}
+
+impl<T: TextWriter + Send + Sync> Log for TextLogger<T> {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // The `Vga` stays locked until the whole record is written.
+        let color = self.get_color(record.level());
+        if let Some(mut line) = TextLine::try_new(&self.writer, color) {
+            // There's nowhere to report a failure to log to.
+            let _ = self.write_record(&mut line, record);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(all(test, feature = "global", feature = "text-modes"))]
+mod test {
+    use super::*;
+    use crate::{
+        mock::lock_for_test,
+        writers::{ScreenCharacter, Text80x25},
+    };
+
+    fn read_line(writer: &Text80x25<'_>, y: usize, buffer: &mut [u8]) {
+        for (x, byte) in buffer.iter_mut().enumerate() {
+            *byte = writer.read_character(x, y).get_character();
+        }
+    }
+
+    #[test]
+    fn test_text_logger() {
+        let _guard = lock_for_test();
+        let warn_color = TextModeColor::new(Color16::Red, Color16::White);
+        let logger = TextLogger::new(Text80x25::new())
+            .with_level(LevelFilter::Warn)
+            .with_color(Level::Warn, warn_color)
+            .with_timestamp(|f| f.write_str("[42]"));
+        let writer = *logger.get_writer();
+        writer.set_mode();
+        writer.clear_screen();
+        writer.set_cursor_position(0, 0);
+
+        logger.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("kernel::memory")
+                .args(format_args!("low on {}", "memory"))
+                .build(),
+        );
+        logger.log(&Record::builder().level(Level::Info).build());
+        let mut line = [0; 32];
+        read_line(&writer, 0, &mut line);
+        assert_eq!(&line, b"[42] WARN  kernel::memory: low o");
+        assert_eq!(
+            writer.read_character(0, 0),
+            ScreenCharacter::new(b'[', warn_color)
+        );
+        assert_eq!(writer.get_cursor_position(), (0, 1));
+
+        // Records are dropped while the vga is locked.
+        let vga = writer.lock_vga();
+        logger.log(&Record::builder().level(Level::Error).build());
+        drop(vga);
+        assert_eq!(writer.get_cursor_position(), (0, 1));
+
+        logger.log(&Record::builder().level(Level::Error).target("é").build());
+        read_line(&writer, 1, &mut line[..14]);
+        assert_eq!(&line[..14], b"[42] ERROR \xFE\xFE:");
+        assert_eq!(
+            writer.read_character(0, 1).get_color(),
+            logger.get_color(Level::Error)
+        );
+        writer.set_mode();
+    }
+}
@@ -0,0 +1,181 @@
+//! A text console mirrored to a second `fmt::Write`, such as a serial port.
+//!
+//! Kernels commonly print their log to both the screen and a serial port,
+//! so it can be read after a crash or captured by an emulator. Output is
+//! written to the serial port first, and only to the screen while the
+//! `Vga` can be locked, so printing from a panic that happened while the
+//! `Vga` was locked doesn't deadlock.
+
+use crate::{
+    colors::TextModeColor,
+    writers::{TextLine, TextWriter},
+};
+use core::fmt::{self, Write};
+
+/// Writes text to both a `TextWriter` and a serial `fmt::Write`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "text-modes"))]
+/// # {
+/// use core::fmt::{self, Write};
+/// use vga::colors::{Color16, TextModeColor};
+/// use vga::mirrored_console::MirroredConsole;
+/// use vga::writers::{Text80x25, TextWriter};
+///
+/// # struct SerialPort;
+/// # impl Write for SerialPort {
+/// #     fn write_str(&mut self, _s: &str) -> fmt::Result { Ok(()) }
+/// # }
+/// # let serial_port = SerialPort;
+/// let text_mode = Text80x25::new();
+/// text_mode.set_mode();
+/// let color = TextModeColor::new(Color16::LightGrey, Color16::Black);
+/// let mut console = MirroredConsole::new(text_mode, serial_port, color);
+/// writeln!(console, "Booting...").unwrap();
+///
+/// // In the panic handler, the screen may be in any state.
+/// console.set_serial_only(true);
+/// writeln!(console, "Kernel panic").unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MirroredConsole<T, W> {
+    writer: T,
+    serial: W,
+    color: TextModeColor,
+    serial_only: bool,
+}
+
+impl<T: TextWriter, W: fmt::Write> MirroredConsole<T, W> {
+    /// Creates a new `MirroredConsole` writing to `writer` with `color`, as
+    /// well as to `serial`. The mode of `writer` isn't changed, and text is
+    /// written from its current cursor position.
+    pub const fn new(writer: T, serial: W, color: TextModeColor) -> MirroredConsole<T, W> {
+        MirroredConsole {
+            writer,
+            serial,
+            color,
+            serial_only: false,
+        }
+    }
+
+    /// Returns the `TextWriter` of the console.
+    pub fn get_writer(&self) -> &T {
+        &self.writer
+    }
+
+    /// Returns the serial `fmt::Write` of the console.
+    pub fn get_serial_mut(&mut self) -> &mut W {
+        &mut self.serial
+    }
+
+    /// Returns the color text is written to the screen with.
+    pub fn get_color(&self) -> TextModeColor {
+        self.color
+    }
+
+    /// Sets the color text is written to the screen with.
+    pub fn set_color(&mut self, color: TextModeColor) {
+        self.color = color;
+    }
+
+    /// Returns whether text is only written to the serial `fmt::Write`.
+    pub fn is_serial_only(&self) -> bool {
+        self.serial_only
+    }
+
+    /// Sets whether text is only written to the serial `fmt::Write`, for
+    /// example in a panic handler, when the screen may not be usable.
+    pub fn set_serial_only(&mut self, serial_only: bool) {
+        self.serial_only = serial_only;
+    }
+
+    /// Writes `s` to the screen, unless the console is serial only or the
+    /// `Vga` is locked. Bytes outside of the printable ascii range are
+    /// written as `0xFE`.
+    fn write_to_screen(&self, s: &str) {
+        if self.serial_only {
+            return;
+        }
+        // The `Vga` stays locked until all of `s` is written.
+        if let Some(mut line) = TextLine::try_new(&self.writer, self.color) {
+            let _ = line.write_str(s);
+        }
+    }
+}
+
+impl<T: TextWriter, W: fmt::Write> fmt::Write for MirroredConsole<T, W> {
+    /// Writes `s` to the serial `fmt::Write`, and then to the screen if the
+    /// console isn't serial only and the `Vga` isn't locked.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.serial.write_str(s)?;
+        self.write_to_screen(s);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "global", feature = "text-modes"))]
+mod test {
+    use super::*;
+    use crate::{
+        colors::Color16,
+        mock::lock_for_test,
+        writers::{ScreenCharacter, Text80x25},
+    };
+    use core::fmt::Write;
+
+    struct Buffer {
+        bytes: [u8; 32],
+        len: usize,
+    }
+
+    impl Write for Buffer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.len + s.len();
+            self.bytes
+                .get_mut(self.len..end)
+                .ok_or(fmt::Error)?
+                .copy_from_slice(s.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_mirrored_console() {
+        let _guard = lock_for_test();
+        let text_mode = Text80x25::new();
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        text_mode.set_cursor_position(0, 0);
+        let color = TextModeColor::new(Color16::White, Color16::Blue);
+        let serial = Buffer {
+            bytes: [0; 32],
+            len: 0,
+        };
+        let mut console = MirroredConsole::new(text_mode, serial, color);
+
+        writeln!(console, "ok {}", 1).unwrap();
+        assert_eq!(&console.get_serial_mut().bytes[..5], b"ok 1\n");
+        assert_eq!(
+            text_mode.read_character(3, 0),
+            ScreenCharacter::new(b'1', color)
+        );
+        assert_eq!(text_mode.get_cursor_position(), (0, 1));
+
+        // Only the serial port is written to while the vga is locked.
+        let vga = text_mode.lock_vga();
+        write!(console, "locked").unwrap();
+        drop(vga);
+        console.set_serial_only(true);
+        write!(console, "!").unwrap();
+        assert_eq!(&console.get_serial_mut().bytes[..12], b"ok 1\nlocked!");
+        assert_eq!(text_mode.get_cursor_position(), (0, 1));
+        assert_eq!(text_mode.read_character(0, 1).get_character(), b' ');
+
+        assert_eq!(write!(console, "{:40}", ""), Err(fmt::Error));
+        text_mode.set_mode();
+    }
+}
@@ -0,0 +1,1568 @@
+//! An in-memory vga device used in place of real hardware.
+//!
+//! When the `test-backend` feature is enabled (or when running this crate's
+//! own unit tests), all port io performed by the register types is routed to
+//! `MOCK_HARDWARE` instead of the real io ports, and `Vga` uses a static
+//! buffer as its video memory. This allows drawing algorithms and mode
+//! configurations to be tested with `cargo test` on the host.
+//!
+//! Writers that access memory through the plane registers, such as the
+//! planar and unchained modes, go through the four planes and latches of
+//! `video_planes` as well, so their pixels can be checked after all write
+//! modes, set/reset and read mode 1.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use vga::mock::MOCK_HARDWARE;
+//! use vga::registers::SequencerIndex;
+//! use vga::vga::{VideoMode, VGA};
+//!
+//! VGA.lock().set_video_mode(VideoMode::Mode80x25);
+//! assert_eq!(MOCK_HARDWARE.lock().sequencer(SequencerIndex::MemoryMode), 0x02);
+//! ```
+
+#[cfg(feature = "bochs-device")]
+use super::devices::{
+    bochs::{BOCHS_DATA_ADDRESS, BOCHS_INDEX_ADDRESS, BOCHS_REGISTER_COUNT},
+    BochsIndex,
+};
+#[cfg(feature = "cirrus-device")]
+use super::devices::{
+    cirrus::{
+        CIRRUS_CRTC_CONTROLLER_REGISTER_COUNT, CIRRUS_FIRST_CRTC_CONTROLLER_INDEX,
+        CIRRUS_FIRST_SEQUENCER_INDEX, CIRRUS_SEQUENCER_REGISTER_COUNT, CR_CHIP_ID,
+        EXTENSIONS_LOCKED, EXTENSIONS_UNLOCKED, SR_DRAM_CONTROL, SR_UNLOCK,
+    },
+    CirrusChip,
+};
+use super::{
+    colors::{Palette, PALETTE_SIZE},
+    devices::banked::TSENG_SEGMENT_SELECT_ADDRESS,
+    emulator::VgaEmulator,
+    registers::{
+        AttributeControllerIndex, CrtcControllerIndex, GraphicsControllerIndex, SequencerIndex,
+        ARX_DATA_ADDRESS, ARX_INDEX_ADDRESS, ATTRIBUTE_CONTROLLER_REGISTER_COUNT,
+        COLOR_PALETTE_DATA_ADDRESS, COLOR_PALETTE_INDEX_READ_ADDRESS,
+        COLOR_PALETTE_INDEX_WRITE_ADDRESSS, CRTC_CONTROLLER_REGISTER_COUNT, CRX_DATA_CGA_ADDRESS,
+        CRX_DATA_MDA_ADDRESS, CRX_INDEX_CGA_ADDRESS, CRX_INDEX_MDA_ADDRESS, FCR_CGA_WRITE_ADDRESS,
+        FCR_MDA_WRITE_ADDRESS, FCR_READ_ADDRESS, GRAPHICS_CONTROLLER_REGISTER_COUNT,
+        GRX_DATA_ADDRESS, GRX_INDEX_ADDRESS, MSR_READ_ADDRESS, MSR_WRITE_ADDRESS,
+        SEQUENCER_REGISTER_COUNT, SRX_DATA_ADDRESS, SRX_INDEX_ADDRESS, ST00_READ_ADDRESS,
+        ST01_READ_CGA_ADDRESS, ST01_READ_MDA_ADDRESS,
+    },
+    vga::MonitorType,
+};
+use core::{cell::UnsafeCell, marker::PhantomData, mem};
+use spinning_top::{Spinlock, SpinlockGuard};
+
+/// The size of the simulated video memory in bytes.
+///
+/// This is large enough to hold the frame buffer of every supported mode,
+/// including `Graphics1280x800x256`.
+pub const VIDEO_MEMORY_SIZE: usize = 0x40_0000;
+
+/// Provides access to the simulated vga registers.
+pub static MOCK_HARDWARE: Spinlock<MockHardware> = Spinlock::new(MockHardware::new());
+
+static VIDEO_MEMORY: VideoMemory = VideoMemory(UnsafeCell::new([0; VIDEO_MEMORY_SIZE]));
+
+static VIDEO_PLANES: Spinlock<VgaEmulator> = Spinlock::new(VgaEmulator::new());
+
+/// The size of the vga memory window at the start of the video memory,
+/// whose planar accesses are modeled by `video_planes`.
+const VGA_WINDOW_SIZE: usize = 0x20000;
+
+// Real video memory is page aligned, which writers may rely on.
+#[repr(align(4096))]
+struct VideoMemory(UnsafeCell<[u8; VIDEO_MEMORY_SIZE]>);
+
+// Safety: The video memory is only accessed through raw pointers, the same
+// way real video memory is.
+unsafe impl Sync for VideoMemory {}
+
+#[cfg(feature = "bochs-device")]
+const BOCHS_MAX_X_RESOLUTION: u16 = 2560;
+#[cfg(feature = "bochs-device")]
+const BOCHS_MAX_Y_RESOLUTION: u16 = 1600;
+#[cfg(feature = "bochs-device")]
+const BOCHS_MAX_BITS_PER_PIXEL: u16 = 32;
+
+/// Returns the address of the simulated video memory. `Vga` uses this
+/// as its memory start when the mock backend is active.
+pub fn video_memory_start() -> usize {
+    VIDEO_MEMORY.0.get() as usize
+}
+
+/// Returns the simulated video memory, starting at the address
+/// returned by `video_memory_start`.
+pub fn video_memory() -> &'static [u8] {
+    unsafe { &*VIDEO_MEMORY.0.get() }
+}
+
+/// Returns the simulated memory planes of the vga memory window.
+///
+/// Accesses made with `read_planar`, `write_planar` and `fill_planar` go
+/// through these planes with the current register state, the same way
+/// they would on real hardware, while `video_memory` only holds the bytes
+/// the cpu wrote.
+pub fn video_planes() -> SpinlockGuard<'static, VgaEmulator> {
+    VIDEO_PLANES.lock()
+}
+
+/// Returns the offset of `address` into the vga memory window, or `None`
+/// if `count` elements at `address` don't fit in the window.
+fn window_offset<T>(address: *const T, count: usize) -> Option<usize> {
+    let offset = (address as usize).checked_sub(video_memory_start())?;
+    if offset + count * mem::size_of::<T>() <= VGA_WINDOW_SIZE {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+/// Returns the simulated planes, with the registers they use for memory
+/// accesses loaded from `MOCK_HARDWARE`.
+fn planes_with_registers() -> SpinlockGuard<'static, VgaEmulator> {
+    let mut planes = VIDEO_PLANES.lock();
+    planes.load_memory_registers(&MOCK_HARDWARE.lock());
+    planes
+}
+
+/// Reads the byte at `src` through the simulated planes, which also loads
+/// the latches. Reads outside of the vga memory window are plain volatile
+/// reads.
+pub(crate) unsafe fn read_planar(src: *const u8) -> u8 {
+    match window_offset(src, 1) {
+        Some(offset) => planes_with_registers().read_memory(offset),
+        None => src.read_volatile(),
+    }
+}
+
+/// Writes `value` to `dst` in the video memory, and through the simulated
+/// planes if `dst` is in the vga memory window.
+pub(crate) unsafe fn write_planar<T: Copy>(dst: *mut T, value: T) {
+    fill_planar(dst, value, 1);
+}
+
+/// Writes `value` to `count` elements starting at `dst` in the video
+/// memory, and through the simulated planes if they're in the vga memory
+/// window.
+pub(crate) unsafe fn fill_planar<T: Copy>(dst: *mut T, value: T, count: usize) {
+    for offset in 0..count {
+        dst.add(offset).write_volatile(value);
+    }
+    if let Some(start) = window_offset(dst, count) {
+        let mut planes = planes_with_registers();
+        let bytes =
+            core::slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>());
+        for element in 0..count {
+            for (byte_offset, byte) in bytes.iter().enumerate() {
+                planes.write_memory(start + element * bytes.len() + byte_offset, *byte);
+            }
+        }
+    }
+}
+
+/// Represents the state of a simulated vga device.
+#[derive(Debug)]
+pub struct MockHardware {
+    pub(crate) miscellaneous_output: u8,
+    feature_control: u8,
+    input_status_1: u8,
+    vertical_interrupt_pending: bool,
+    monitor_type: MonitorType,
+    sequencer_index: u8,
+    pub(crate) sequencer_registers: [u8; SEQUENCER_REGISTER_COUNT],
+    graphics_controller_index: u8,
+    pub(crate) graphics_controller_registers: [u8; GRAPHICS_CONTROLLER_REGISTER_COUNT],
+    crtc_controller_index: u8,
+    pub(crate) crtc_controller_registers: [u8; CRTC_CONTROLLER_REGISTER_COUNT],
+    attribute_controller_index: u8,
+    attribute_controller_flip_flop: bool,
+    pub(crate) attribute_controller_registers: [u8; ATTRIBUTE_CONTROLLER_REGISTER_COUNT],
+    pub(crate) palette: [u8; PALETTE_SIZE],
+    palette_read_index: u8,
+    palette_write_index: u8,
+    palette_component: usize,
+    palette_reading: bool,
+    #[cfg(feature = "bochs-device")]
+    bochs_index: u16,
+    #[cfg(feature = "bochs-device")]
+    pub(crate) bochs_registers: [u16; BOCHS_REGISTER_COUNT],
+    tseng_segment_select: u8,
+    present: bool,
+    #[cfg(feature = "cirrus-device")]
+    cirrus_chip: Option<CirrusChip>,
+    #[cfg(feature = "cirrus-device")]
+    cirrus_sequencer_registers: [u8; CIRRUS_SEQUENCER_REGISTER_COUNT],
+    #[cfg(feature = "cirrus-device")]
+    cirrus_crtc_controller_registers: [u8; CIRRUS_CRTC_CONTROLLER_REGISTER_COUNT],
+}
+
+impl MockHardware {
+    const fn new() -> MockHardware {
+        MockHardware {
+            miscellaneous_output: 0x00,
+            feature_control: 0x00,
+            input_status_1: 0x00,
+            vertical_interrupt_pending: false,
+            monitor_type: MonitorType::Color,
+            sequencer_index: 0x00,
+            sequencer_registers: [0; SEQUENCER_REGISTER_COUNT],
+            graphics_controller_index: 0x00,
+            graphics_controller_registers: [0; GRAPHICS_CONTROLLER_REGISTER_COUNT],
+            crtc_controller_index: 0x00,
+            crtc_controller_registers: [0; CRTC_CONTROLLER_REGISTER_COUNT],
+            attribute_controller_index: 0x00,
+            attribute_controller_flip_flop: false,
+            attribute_controller_registers: [0; ATTRIBUTE_CONTROLLER_REGISTER_COUNT],
+            palette: [0; PALETTE_SIZE],
+            palette_read_index: 0x00,
+            palette_write_index: 0x00,
+            palette_component: 0,
+            palette_reading: false,
+            #[cfg(feature = "bochs-device")]
+            bochs_index: 0x00,
+            #[cfg(feature = "bochs-device")]
+            bochs_registers: MockHardware::bochs_registers(),
+            tseng_segment_select: 0x00,
+            present: true,
+            #[cfg(feature = "cirrus-device")]
+            cirrus_chip: None,
+            #[cfg(feature = "cirrus-device")]
+            cirrus_sequencer_registers: [0; CIRRUS_SEQUENCER_REGISTER_COUNT],
+            #[cfg(feature = "cirrus-device")]
+            cirrus_crtc_controller_registers: [0; CIRRUS_CRTC_CONTROLLER_REGISTER_COUNT],
+        }
+    }
+
+    #[cfg(feature = "bochs-device")]
+    const fn bochs_registers() -> [u16; BOCHS_REGISTER_COUNT] {
+        let mut registers = [0; BOCHS_REGISTER_COUNT];
+        registers[BochsIndex::Id as usize] = 0xB0C5;
+        registers[BochsIndex::VideoMemory64K as usize] = (VIDEO_MEMORY_SIZE / 0x10000) as u16;
+        registers
+    }
+
+    /// Resets every register to 0 and clears the simulated video memory.
+    pub fn reset(&mut self) {
+        *self = MockHardware::new();
+        unsafe {
+            (*VIDEO_MEMORY.0.get()).fill(0);
+        }
+        *VIDEO_PLANES.lock() = VgaEmulator::new();
+    }
+
+    /// Sets the kind of monitor the switch sense bit responds as, which
+    /// is `MonitorType::Color` by default.
+    pub fn set_monitor_type(&mut self, monitor_type: MonitorType) {
+        self.monitor_type = monitor_type;
+    }
+
+    /// Sets whether the registers respond, or read `0xFF` and ignore
+    /// writes like a system without a vga. They respond by default.
+    pub fn set_present(&mut self, present: bool) {
+        self.present = present;
+    }
+
+    /// Makes the sequencer and crtc controller respond with the extended
+    /// registers of `chip`, with its extensions locked and 4MB of video
+    /// memory, or as a standard vga for `None`, which is the default.
+    #[cfg(feature = "cirrus-device")]
+    pub fn set_cirrus_chip(&mut self, chip: Option<CirrusChip>) {
+        self.cirrus_chip = chip;
+        self.cirrus_sequencer_registers = [0; CIRRUS_SEQUENCER_REGISTER_COUNT];
+        self.cirrus_crtc_controller_registers = [0; CIRRUS_CRTC_CONTROLLER_REGISTER_COUNT];
+        if let Some(chip) = chip {
+            self.cirrus_sequencer_registers[SR_UNLOCK as usize] = EXTENSIONS_LOCKED;
+            self.cirrus_sequencer_registers[SR_DRAM_CONTROL as usize] = 0x98;
+            self.cirrus_crtc_controller_registers[CR_CHIP_ID as usize] = u8::from(chip);
+        }
+    }
+
+    /// Returns the current value of the cirrus logic extended sequencer
+    /// register specified by `index`.
+    #[cfg(feature = "cirrus-device")]
+    pub fn cirrus_sequencer(&self, index: u8) -> u8 {
+        self.cirrus_sequencer_registers[index as usize]
+    }
+
+    /// Returns the current value of the cirrus logic extended crtc
+    /// controller register specified by `index`.
+    #[cfg(feature = "cirrus-device")]
+    pub fn cirrus_crtc_controller(&self, index: u8) -> u8 {
+        self.cirrus_crtc_controller_registers[index as usize]
+    }
+
+    /// Returns the current value of the tseng labs segment select register.
+    pub fn tseng_segment_select(&self) -> u8 {
+        self.tseng_segment_select
+    }
+
+    /// Returns the current value of the miscellaneous output register.
+    pub fn miscellaneous_output(&self) -> u8 {
+        self.miscellaneous_output
+    }
+
+    /// Returns the current value of the sequencer register specified by `index`.
+    pub fn sequencer(&self, index: SequencerIndex) -> u8 {
+        self.sequencer_registers[u8::from(index) as usize]
+    }
+
+    /// Returns the current value of the graphics controller register specified by `index`.
+    pub fn graphics_controller(&self, index: GraphicsControllerIndex) -> u8 {
+        self.graphics_controller_registers[u8::from(index) as usize]
+    }
+
+    /// Returns the current value of the crtc controller register specified by `index`.
+    pub fn crtc_controller(&self, index: CrtcControllerIndex) -> u8 {
+        self.crtc_controller_registers[u8::from(index) as usize]
+    }
+
+    /// Returns the current value of the attribute controller register specified by `index`.
+    pub fn attribute_controller(&self, index: AttributeControllerIndex) -> u8 {
+        self.attribute_controller_registers[u8::from(index) as usize]
+    }
+
+    /// Returns the current contents of the color palette.
+    pub fn palette(&self) -> Palette<256> {
+        Palette::from_bytes(&self.palette)
+    }
+
+    /// Returns the current value of the bochs display interface register
+    /// specified by `index`.
+    #[cfg(feature = "bochs-device")]
+    pub fn bochs(&self, index: BochsIndex) -> u16 {
+        self.bochs_registers[u16::from(index) as usize]
+    }
+
+    /// Returns the switch sense bit for the color of palette entry 0, which
+    /// is set if any dac output driving a level isn't loaded by the monitor.
+    fn switch_sense(&self) -> u8 {
+        let (red, green, blue) = (self.palette[0], self.palette[1], self.palette[2]);
+        let sensed = match self.monitor_type {
+            MonitorType::Color => false,
+            MonitorType::Monochrome => red | blue != 0,
+            MonitorType::None => red | green | blue != 0,
+        };
+        if sensed {
+            0x10
+        } else {
+            0x00
+        }
+    }
+
+    #[cfg(feature = "bochs-device")]
+    fn read_u16(&mut self, port: u16) -> u16 {
+        match port {
+            BOCHS_INDEX_ADDRESS => self.bochs_index,
+            BOCHS_DATA_ADDRESS => {
+                // The get capabilities bit of the enable register makes the
+                // mode registers read back the largest supported values.
+                let capabilities = self.bochs(BochsIndex::Enable) & 0x02 != 0;
+                match self.bochs_index {
+                    index if capabilities && index == u16::from(BochsIndex::XResolution) => {
+                        BOCHS_MAX_X_RESOLUTION
+                    }
+                    index if capabilities && index == u16::from(BochsIndex::YResolution) => {
+                        BOCHS_MAX_Y_RESOLUTION
+                    }
+                    index if capabilities && index == u16::from(BochsIndex::BitsPerPixel) => {
+                        BOCHS_MAX_BITS_PER_PIXEL
+                    }
+                    index => self
+                        .bochs_registers
+                        .get(index as usize)
+                        .copied()
+                        .unwrap_or(0xFFFF),
+                }
+            }
+            _ => 0xFFFF,
+        }
+    }
+
+    #[cfg(feature = "bochs-device")]
+    fn write_u16(&mut self, port: u16, value: u16) {
+        match port {
+            BOCHS_INDEX_ADDRESS => self.bochs_index = value,
+            // The id register reports the version of the device.
+            BOCHS_DATA_ADDRESS if self.bochs_index == u16::from(BochsIndex::Id) => (),
+            BOCHS_DATA_ADDRESS => {
+                if let Some(register) = self.bochs_registers.get_mut(self.bochs_index as usize) {
+                    *register = value;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn read(&mut self, port: u16) -> u8 {
+        if !self.present {
+            return 0xFF;
+        }
+        match port {
+            ST00_READ_ADDRESS => {
+                let interrupt_pending = if self.vertical_interrupt_pending {
+                    0x80
+                } else {
+                    0x00
+                };
+                interrupt_pending | self.switch_sense()
+            }
+            ST01_READ_CGA_ADDRESS | ST01_READ_MDA_ADDRESS => {
+                // Reading input status #1 resets the attribute controller flip-flop.
+                // Toggle the display enable and vertical retrace bits so code
+                // waiting on them makes progress.
+                self.attribute_controller_flip_flop = false;
+                self.input_status_1 ^= 0x09;
+                // Raise the vertical interrupt at the start of vertical retrace,
+                // when enabled and not waiting to be cleared.
+                let vertical_sync_end = self.crtc_controller_registers
+                    [u8::from(CrtcControllerIndex::VerticalSyncEnd) as usize];
+                if self.input_status_1 & 0x08 != 0 && vertical_sync_end & 0x30 == 0x10 {
+                    self.vertical_interrupt_pending = true;
+                }
+                self.input_status_1
+            }
+            FCR_READ_ADDRESS => self.feature_control,
+            MSR_READ_ADDRESS => self.miscellaneous_output,
+            TSENG_SEGMENT_SELECT_ADDRESS => self.tseng_segment_select,
+            SRX_INDEX_ADDRESS => self.sequencer_index,
+            #[cfg(feature = "cirrus-device")]
+            SRX_DATA_ADDRESS if self.is_cirrus_sequencer_index() => {
+                read_indexed(&self.cirrus_sequencer_registers, self.sequencer_index)
+            }
+            SRX_DATA_ADDRESS => read_indexed(&self.sequencer_registers, self.sequencer_index),
+            GRX_INDEX_ADDRESS => self.graphics_controller_index,
+            GRX_DATA_ADDRESS => read_indexed(
+                &self.graphics_controller_registers,
+                self.graphics_controller_index,
+            ),
+            CRX_INDEX_CGA_ADDRESS | CRX_INDEX_MDA_ADDRESS => self.crtc_controller_index,
+            #[cfg(feature = "cirrus-device")]
+            CRX_DATA_CGA_ADDRESS | CRX_DATA_MDA_ADDRESS
+                if self.is_cirrus_crtc_controller_index() =>
+            {
+                read_indexed(
+                    &self.cirrus_crtc_controller_registers,
+                    self.crtc_controller_index,
+                )
+            }
+            CRX_DATA_CGA_ADDRESS | CRX_DATA_MDA_ADDRESS => {
+                read_indexed(&self.crtc_controller_registers, self.crtc_controller_index)
+            }
+            ARX_INDEX_ADDRESS => self.attribute_controller_index,
+            ARX_DATA_ADDRESS => read_indexed(
+                &self.attribute_controller_registers,
+                self.attribute_controller_index & 0x1F,
+            ),
+            COLOR_PALETTE_INDEX_READ_ADDRESS => {
+                if self.palette_reading {
+                    0x00
+                } else {
+                    0x03
+                }
+            }
+            COLOR_PALETTE_INDEX_WRITE_ADDRESSS => self.palette_write_index,
+            COLOR_PALETTE_DATA_ADDRESS => {
+                let value =
+                    self.palette[self.palette_read_index as usize * 3 + self.palette_component];
+                self.palette_read_index = self.next_palette_index(self.palette_read_index);
+                value
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write(&mut self, port: u16, value: u8) {
+        if !self.present {
+            return;
+        }
+        match port {
+            MSR_WRITE_ADDRESS => self.miscellaneous_output = value,
+            TSENG_SEGMENT_SELECT_ADDRESS => self.tseng_segment_select = value,
+            FCR_CGA_WRITE_ADDRESS | FCR_MDA_WRITE_ADDRESS => self.feature_control = value,
+            SRX_INDEX_ADDRESS => self.sequencer_index = value,
+            // Only the unlock value unlocks the extensions, and the
+            // register reads back as locked for any other value.
+            #[cfg(feature = "cirrus-device")]
+            SRX_DATA_ADDRESS
+                if self.is_cirrus_sequencer_index() && self.sequencer_index == SR_UNLOCK =>
+            {
+                self.cirrus_sequencer_registers[SR_UNLOCK as usize] =
+                    if value & 0x17 == EXTENSIONS_UNLOCKED {
+                        EXTENSIONS_UNLOCKED
+                    } else {
+                        EXTENSIONS_LOCKED
+                    };
+            }
+            #[cfg(feature = "cirrus-device")]
+            SRX_DATA_ADDRESS if self.is_cirrus_sequencer_index() => write_indexed(
+                &mut self.cirrus_sequencer_registers,
+                self.sequencer_index,
+                value,
+            ),
+            SRX_DATA_ADDRESS => {
+                write_indexed(&mut self.sequencer_registers, self.sequencer_index, value)
+            }
+            GRX_INDEX_ADDRESS => self.graphics_controller_index = value,
+            GRX_DATA_ADDRESS => write_indexed(
+                &mut self.graphics_controller_registers,
+                self.graphics_controller_index,
+                value,
+            ),
+            CRX_INDEX_CGA_ADDRESS | CRX_INDEX_MDA_ADDRESS => self.crtc_controller_index = value,
+            // The chip id register is read only.
+            #[cfg(feature = "cirrus-device")]
+            CRX_DATA_CGA_ADDRESS | CRX_DATA_MDA_ADDRESS
+                if self.is_cirrus_crtc_controller_index() =>
+            {
+                if self.crtc_controller_index != CR_CHIP_ID {
+                    write_indexed(
+                        &mut self.cirrus_crtc_controller_registers,
+                        self.crtc_controller_index,
+                        value,
+                    );
+                }
+            }
+            CRX_DATA_CGA_ADDRESS | CRX_DATA_MDA_ADDRESS => {
+                // Clearing bit 4 of the vertical sync end register clears
+                // a pending vertical interrupt.
+                if self.crtc_controller_index == u8::from(CrtcControllerIndex::VerticalSyncEnd)
+                    && value & 0x10 == 0
+                {
+                    self.vertical_interrupt_pending = false;
+                }
+                // Bit 7 of the vertical sync end register protects CR00-CR07,
+                // except for the line compare bit 4 of the overflow register.
+                let protected = self.crtc_controller_registers
+                    [u8::from(CrtcControllerIndex::VerticalSyncEnd) as usize]
+                    & 0x80
+                    != 0;
+                let value = match self.crtc_controller_index {
+                    0x07 if protected => {
+                        (self.crtc_controller_registers[0x07] & !0x10) | (value & 0x10)
+                    }
+                    0x00..=0x06 if protected => return,
+                    _ => value,
+                };
+                write_indexed(
+                    &mut self.crtc_controller_registers,
+                    self.crtc_controller_index,
+                    value,
+                )
+            }
+            ARX_INDEX_ADDRESS => {
+                // The attribute controller alternates between index and data writes.
+                if self.attribute_controller_flip_flop {
+                    write_indexed(
+                        &mut self.attribute_controller_registers,
+                        self.attribute_controller_index & 0x1F,
+                        value,
+                    );
+                } else {
+                    self.attribute_controller_index = value;
+                }
+                self.attribute_controller_flip_flop = !self.attribute_controller_flip_flop;
+            }
+            COLOR_PALETTE_INDEX_READ_ADDRESS => {
+                self.palette_read_index = value;
+                self.palette_component = 0;
+                self.palette_reading = true;
+            }
+            COLOR_PALETTE_INDEX_WRITE_ADDRESSS => {
+                self.palette_write_index = value;
+                self.palette_component = 0;
+                self.palette_reading = false;
+            }
+            COLOR_PALETTE_DATA_ADDRESS => {
+                self.palette[self.palette_write_index as usize * 3 + self.palette_component] =
+                    value & self.dac_mask();
+                self.palette_write_index = self.next_palette_index(self.palette_write_index);
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns the bits of each palette component the dac keeps.
+    fn dac_mask(&self) -> u8 {
+        // The 8 bit dac bit of the bochs enable register keeps all bits
+        // of each component.
+        #[cfg(feature = "bochs-device")]
+        if self.bochs(BochsIndex::Enable) & 0x20 != 0 {
+            return 0xFF;
+        }
+        0x3F
+    }
+
+    #[cfg(feature = "cirrus-device")]
+    fn is_cirrus_sequencer_index(&self) -> bool {
+        self.cirrus_chip.is_some() && self.sequencer_index >= CIRRUS_FIRST_SEQUENCER_INDEX
+    }
+
+    #[cfg(feature = "cirrus-device")]
+    fn is_cirrus_crtc_controller_index(&self) -> bool {
+        self.cirrus_chip.is_some()
+            && self.crtc_controller_index >= CIRRUS_FIRST_CRTC_CONTROLLER_INDEX
+    }
+
+    fn next_palette_index(&mut self, index: u8) -> u8 {
+        self.palette_component += 1;
+        if self.palette_component == 3 {
+            self.palette_component = 0;
+            index.wrapping_add(1)
+        } else {
+            index
+        }
+    }
+}
+
+fn read_indexed(registers: &[u8], index: u8) -> u8 {
+    registers.get(index as usize).copied().unwrap_or(0xFF)
+}
+
+fn write_indexed(registers: &mut [u8], index: u8, value: u8) {
+    if let Some(register) = registers.get_mut(index as usize) {
+        *register = value;
+    }
+}
+
+/// A simulated io port, mirroring `x86_64::instructions::port::Port`.
+#[derive(Debug)]
+pub(crate) struct Port<T> {
+    port: u16,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Port<T> {
+    pub(crate) const fn new(port: u16) -> Port<T> {
+        Port {
+            port,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl Port<u8> {
+    pub(crate) unsafe fn read(&mut self) -> u8 {
+        MOCK_HARDWARE.lock().read(self.port)
+    }
+
+    pub(crate) unsafe fn write(&mut self, value: u8) {
+        MOCK_HARDWARE.lock().write(self.port, value);
+    }
+}
+
+#[cfg(feature = "bochs-device")]
+impl Port<u16> {
+    pub(crate) unsafe fn read(&mut self) -> u16 {
+        MOCK_HARDWARE.lock().read_u16(self.port)
+    }
+
+    pub(crate) unsafe fn write(&mut self, value: u16) {
+        MOCK_HARDWARE.lock().write_u16(self.port, value);
+    }
+}
+
+/// Serializes tests that share the global mock hardware.
+#[cfg(test)]
+pub(crate) fn lock_for_test() -> spinning_top::SpinlockGuard<'static, ()> {
+    static TEST_LOCK: Spinlock<()> = Spinlock::new(());
+    TEST_LOCK.lock()
+}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    #[cfg(any(feature = "text-modes", feature = "graphics-modes"))]
+    use crate::writers::ModeOptions;
+    #[cfg(feature = "graphics-modes")]
+    use crate::writers::{Graphics320x200x256, GraphicsWriter};
+    #[cfg(feature = "text-modes")]
+    use crate::{
+        colors::TextModeColor,
+        vga::VgaError,
+        writers::{Screen, ScreenCharacter, Text80x25, TextWriter},
+    };
+    use crate::{
+        colors::{Color16, DEFAULT_PALETTE},
+        configurations::{
+            ConfigurationAdjustments, VgaConfiguration, MAX_HORIZONTAL_SYNC_FREQUENCY,
+            MODE_320X200X256_CONFIGURATION, MODE_640X480X16_CONFIGURATION,
+            MODE_80X25_CONFIGURATION,
+        },
+        fonts::{VgaFont, TEXT_8X8_FONT},
+        registers::{
+            CharacterMap, ColorPlaneEnable, CrtcControllerIndex, EmulationMode,
+            GraphicsControllerIndex, GraphicsMode, MemoryMap, PlaneMask, ReadMode, SequencerIndex,
+            ShiftRegisterMode, WriteMode,
+        },
+        vga::{
+            frame_buffer_ptr, DotClock, MemoryLayout, ModeInfo, SyncPolarity, TextRefreshRate,
+            VideoMode, MAX_MODE_CHANGE_CALLBACKS, VGA,
+        },
+        vga_mode,
+    };
+
+    #[test]
+    fn test_set_video_mode_registers() {
+        let _guard = lock_for_test();
+        VGA.lock().set_video_mode(VideoMode::Mode80x25);
+
+        let hardware = MOCK_HARDWARE.lock();
+        let configuration = &MODE_80X25_CONFIGURATION;
+        assert_eq!(
+            hardware.miscellaneous_output(),
+            configuration.miscellaneous_output
+        );
+        for (index, value) in configuration.sequencer_registers {
+            assert_eq!(hardware.sequencer(*index), *value);
+        }
+        for (index, value) in configuration.graphics_controller_registers {
+            assert_eq!(hardware.graphics_controller(*index), *value);
+        }
+        for (index, value) in configuration.attribute_controller_registers {
+            assert_eq!(hardware.attribute_controller(*index), *value);
+        }
+    }
+
+    #[test]
+    fn test_set_configuration_adjusts_unsafe_values() {
+        static CONFIGURATION: VgaConfiguration = VgaConfiguration {
+            miscellaneous_output: 0x6F,
+            sequencer_registers: &[
+                (SequencerIndex::SequencerReset, 0x01),
+                (SequencerIndex::ClockingMode, 0x01),
+            ],
+            crtc_controller_registers: &[
+                (CrtcControllerIndex::HorizontalTotal, 0x00),
+                (CrtcControllerIndex::HorizontalSyncStart, 0xF0),
+                (CrtcControllerIndex::VeritcalTotal, 0x00),
+                (CrtcControllerIndex::Overflow, 0x00),
+                (CrtcControllerIndex::VerticalSyncStart, 0xF0),
+            ],
+            graphics_controller_registers: &[(GraphicsControllerIndex::Miscellaneous, 0x01)],
+            attribute_controller_registers: &[],
+        };
+        let _guard = lock_for_test();
+        let adjustments = VGA.lock().set_configuration(&CONFIGURATION);
+
+        assert_eq!(adjustments, ConfigurationAdjustments::all());
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(hardware.miscellaneous_output(), 0x67);
+        assert_eq!(hardware.sequencer(SequencerIndex::SequencerReset), 0x03);
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::Miscellaneous),
+            0x05
+        );
+        let horizontal_total = hardware.crtc_controller(CrtcControllerIndex::HorizontalTotal);
+        assert!(25_175_000 / (8 * (horizontal_total as u32 + 5)) <= MAX_HORIZONTAL_SYNC_FREQUENCY);
+        assert!(
+            hardware.crtc_controller(CrtcControllerIndex::HorizontalSyncStart)
+                < horizontal_total + 5
+        );
+        assert_ne!(
+            hardware.crtc_controller(CrtcControllerIndex::VeritcalTotal),
+            0x00
+        );
+    }
+
+    #[test]
+    fn test_set_mode_definition() {
+        vga_mode! {
+            const MODE_360X480X256 = {
+                mode_info: ModeInfo::new(360, 480).with_layout(MemoryLayout::Unchained),
+                miscellaneous_output: 0xE7,
+                sequencer_registers: [(SequencerIndex::MemoryMode, 0x06)],
+                crtc_controller_registers: [(CrtcControllerIndex::Offset, 0x2D)],
+                graphics_controller_registers: [],
+                attribute_controller_registers: [],
+            };
+        }
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+        vga.set_mode_definition(&MODE_360X480X256);
+
+        assert_eq!(vga.get_most_recent_video_mode(), None);
+        assert_eq!(vga.get_mode_info(), Some(MODE_360X480X256.get_mode_info()));
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(hardware.miscellaneous_output(), 0xE7);
+        assert_eq!(hardware.sequencer(SequencerIndex::MemoryMode), 0x06);
+        assert_eq!(hardware.crtc_controller(CrtcControllerIndex::Offset), 0x2D);
+    }
+
+    #[test]
+    fn test_memory_map() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+        assert_eq!(vga.get_memory_map(), MemoryMap::Color);
+        assert_eq!(MemoryMap::Color.get_physical_address(), 0xB8000);
+
+        vga.set_memory_map(MemoryMap::Window64K).unwrap();
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .graphics_controller(GraphicsControllerIndex::Miscellaneous),
+            MODE_80X25_CONFIGURATION
+                .graphics_controller_register(GraphicsControllerIndex::Miscellaneous)
+                .unwrap()
+                & 0xF3
+                | 0x04
+        );
+        assert_eq!(
+            vga.set_memory_map(MemoryMap::Window128K),
+            Err("the 128K memory map isn't supported by the writers")
+        );
+        assert_eq!(vga.get_memory_map(), MemoryMap::Window64K);
+    }
+
+    #[test]
+    fn test_frame_buffer_ptr() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        for &mode in &[
+            VideoMode::Mode40x25,
+            VideoMode::Mode40x50,
+            VideoMode::Mode80x25,
+            VideoMode::Mode320x200x256,
+            VideoMode::Mode320x240x256,
+            VideoMode::Mode640x480x16,
+            VideoMode::Mode1280x800x256,
+        ] {
+            vga.set_video_mode(mode);
+            assert_eq!(vga.get_memory_map(), mode.memory_map());
+            let frame_buffer = unsafe { frame_buffer_ptr(mode, video_memory_start()) };
+            assert_eq!(frame_buffer as usize, usize::from(vga.get_frame_buffer()));
+        }
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+
+    #[test]
+    fn test_chain4_and_odd_even() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode320x200x256);
+        assert!(vga.sequencer_registers.get_chain4());
+        assert!(!vga.sequencer_registers.get_odd_even());
+
+        vga.sequencer_registers.set_chain4(false);
+        assert_eq!(
+            MOCK_HARDWARE.lock().sequencer(SequencerIndex::MemoryMode),
+            0x06
+        );
+
+        vga.sequencer_registers.set_odd_even(true);
+        assert!(vga.sequencer_registers.get_odd_even());
+        assert_eq!(
+            MOCK_HARDWARE.lock().sequencer(SequencerIndex::MemoryMode),
+            0x02
+        );
+    }
+
+    #[test]
+    fn test_graphics_mode() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+        let graphics_mode = vga.graphics_controller_registers.get_graphics_mode();
+        assert_eq!(graphics_mode, GraphicsMode::new().with_host_odd_even(true));
+
+        vga.graphics_controller_registers
+            .set_write_mode(WriteMode::Mode2);
+        vga.graphics_controller_registers
+            .set_read_mode(ReadMode::Mode1);
+        assert_eq!(
+            vga.graphics_controller_registers.get_write_mode(),
+            WriteMode::Mode2
+        );
+        assert_eq!(
+            vga.graphics_controller_registers.get_read_mode(),
+            ReadMode::Mode1
+        );
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .graphics_controller(GraphicsControllerIndex::GraphicsMode),
+            0x1A
+        );
+
+        vga.graphics_controller_registers
+            .set_graphics_mode(graphics_mode);
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .graphics_controller(GraphicsControllerIndex::GraphicsMode),
+            0x10
+        );
+
+        vga.set_video_mode(VideoMode::Mode320x200x256);
+        assert_eq!(
+            vga.graphics_controller_registers
+                .get_graphics_mode()
+                .get_shift_register_mode(),
+            ShiftRegisterMode::Color256
+        );
+        let graphics_mode = GraphicsMode::new()
+            .with_write_mode(WriteMode::Mode3)
+            .with_shift_register_mode(ShiftRegisterMode::Interleaved);
+        assert_eq!(u8::from(graphics_mode), 0x23);
+        assert_eq!(GraphicsMode::from(0x23), graphics_mode);
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+
+    #[test]
+    fn test_planar_memory() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        vga.sequencer_registers
+            .set_plane_mask(PlaneMask::ALL_PLANES);
+        let frame_buffer = usize::from(vga.get_frame_buffer()) as *mut u8;
+        let read = || unsafe { read_planar(frame_buffer) };
+        let write =
+            |offset: usize, value: u8| unsafe { write_planar(frame_buffer.add(offset), value) };
+        let plane_bytes = |offset: usize| {
+            let planes = video_planes();
+            [0, 1, 2, 3].map(|plane| planes.plane(plane)[offset])
+        };
+        unsafe { fill_planar(frame_buffer, 0x00, 2) };
+
+        // Write mode 0 with set/reset writes the set/reset color to the
+        // pixels selected by the bit mask.
+        let graphics = &mut vga.graphics_controller_registers;
+        graphics.set_write_mode(WriteMode::Mode0);
+        graphics.write_set_reset(Color16::Red);
+        graphics.write_enable_set_reset(0x0F);
+        graphics.set_bit_mask(0xF0);
+        write(0, 0x00);
+        assert_eq!(plane_bytes(0), [0x00, 0x00, 0xF0, 0x00]);
+
+        // Write mode 2 keeps the pixels outside of the bit mask from the
+        // latches.
+        graphics.write_enable_set_reset(0x00);
+        graphics.set_write_mode(WriteMode::Mode2);
+        graphics.set_bit_mask(0x0F);
+        read();
+        write(0, u8::from(Color16::Blue));
+        assert_eq!(plane_bytes(0), [0x0F, 0x00, 0xF0, 0x00]);
+
+        // Write mode 3 uses the rotated data as the bit mask for the
+        // set/reset color.
+        graphics.set_write_mode(WriteMode::Mode3);
+        graphics.write_set_reset(Color16::Green);
+        graphics.set_bit_mask(0xFF);
+        read();
+        write(0, 0x81);
+        assert_eq!(plane_bytes(0), [0x0E, 0x81, 0x70, 0x00]);
+
+        // Write mode 1 copies the latches.
+        graphics.set_write_mode(WriteMode::Mode1);
+        read();
+        write(1, 0x00);
+        assert_eq!(plane_bytes(1), plane_bytes(0));
+
+        // Read mode 1 returns the pixels matching the color compare register.
+        graphics.set_read_mode(ReadMode::Mode1);
+        graphics.set_color_compare(Color16::Red);
+        graphics.set_color_dont_care(PlaneMask::ALL_PLANES);
+        assert_eq!(read(), 0x70);
+        graphics.set_color_dont_care(PlaneMask::PLANE2);
+        assert_eq!(read(), 0x70);
+        graphics.set_color_dont_care(PlaneMask::PLANE1);
+        assert_eq!(read(), 0x7E);
+
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        unsafe { fill_planar(frame_buffer, 0x00, 2) };
+    }
+
+    #[test]
+    fn test_mode_change_callbacks() {
+        use alloc::{sync::Arc, vec::Vec};
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static LAST_MODE: AtomicUsize = AtomicUsize::new(0);
+        fn record_mode(video_mode: Option<VideoMode>) {
+            let mode = video_mode.map_or(usize::MAX, |video_mode| video_mode as usize);
+            LAST_MODE.store(mode, Ordering::SeqCst);
+        }
+        fn ignore_mode(_video_mode: Option<VideoMode>) {}
+
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        let mode_generation = vga.get_mode_generation();
+        let id = vga.on_mode_change(record_mode).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let closure_calls = calls.clone();
+        let closure_id = vga.on_mode_change_closure(move |_| {
+            closure_calls.fetch_add(1, Ordering::SeqCst);
+        });
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        assert_eq!(
+            LAST_MODE.load(Ordering::SeqCst),
+            VideoMode::Mode640x480x16 as usize
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(vga.get_mode_generation(), mode_generation.wrapping_add(1));
+
+        vga.set_configuration(&MODE_80X25_CONFIGURATION);
+        assert_eq!(LAST_MODE.load(Ordering::SeqCst), usize::MAX);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(vga.get_mode_generation(), mode_generation.wrapping_add(2));
+
+        // Only a fixed number of functions can be registered.
+        let ids: Vec<_> = (1..MAX_MODE_CHANGE_CALLBACKS)
+            .map(|_| vga.on_mode_change(ignore_mode).unwrap())
+            .collect();
+        assert!(vga.on_mode_change(ignore_mode).is_err());
+        for id in ids {
+            assert!(vga.remove_mode_change_callback(id));
+        }
+
+        assert!(vga.remove_mode_change_callback(id));
+        assert!(vga.remove_mode_change_callback(closure_id));
+        assert!(!vga.remove_mode_change_callback(closure_id));
+        vga.set_video_mode(VideoMode::Mode80x25);
+        assert_eq!(LAST_MODE.load(Ordering::SeqCst), usize::MAX);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(vga.get_mode_generation(), mode_generation.wrapping_add(3));
+    }
+
+    #[test]
+    fn test_color_plane_enable() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        let emulation_mode = vga.get_emulation_mode();
+        assert_eq!(
+            vga.attribute_controller_registers
+                .get_color_plane_enable(emulation_mode),
+            ColorPlaneEnable::ALL_PLANES
+        );
+
+        MOCK_HARDWARE.lock().attribute_controller_registers
+            [u8::from(AttributeControllerIndex::MemoryPlaneEnable) as usize] = 0x3F;
+        vga.attribute_controller_registers
+            .set_color_plane_enable(emulation_mode, ColorPlaneEnable::PLANE0);
+        assert_eq!(
+            vga.attribute_controller_registers
+                .get_color_plane_enable(emulation_mode),
+            ColorPlaneEnable::PLANE0
+        );
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(
+            hardware.attribute_controller(AttributeControllerIndex::MemoryPlaneEnable),
+            0x31
+        );
+        assert_eq!(hardware.attribute_controller_index & 0x20, 0x20);
+        drop(hardware);
+
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+
+    #[test]
+    fn test_input_status_registers() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        let emulation_mode = vga.get_emulation_mode();
+        let in_retrace = vga.general_registers.is_vertical_retrace(emulation_mode);
+        assert_eq!(
+            vga.general_registers.is_display_disabled(emulation_mode),
+            !in_retrace
+        );
+        assert_eq!(
+            vga.general_registers.is_vertical_retrace(emulation_mode),
+            in_retrace
+        );
+        assert!(!vga.general_registers.get_switch_sense());
+        assert!(!vga.general_registers.is_vertical_interrupt_pending());
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_probe() {
+        let _guard = lock_for_test();
+        let cursor_location_low = MOCK_HARDWARE
+            .lock()
+            .crtc_controller(CrtcControllerIndex::TextCursorLocationLow);
+        assert_eq!(VGA.lock().probe(), Ok(()));
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .crtc_controller(CrtcControllerIndex::TextCursorLocationLow),
+            cursor_location_low
+        );
+        assert!(Text80x25::try_new().is_ok());
+
+        MOCK_HARDWARE.lock().set_present(false);
+        assert_eq!(VGA.lock().probe(), Err(VgaError::NotPresent));
+        assert_eq!(Text80x25::try_new().err(), Some(VgaError::NotPresent));
+        MOCK_HARDWARE.lock().set_present(true);
+    }
+
+    #[test]
+    fn test_detect_monitor() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+        vga.color_palette_registers.load_palette(&DEFAULT_PALETTE);
+
+        MOCK_HARDWARE
+            .lock()
+            .set_monitor_type(MonitorType::Monochrome);
+        assert_eq!(vga.detect_monitor(), MonitorType::Monochrome);
+        assert_eq!(vga.get_emulation_mode() as u8, EmulationMode::Mda as u8);
+        assert_eq!(MOCK_HARDWARE.lock().palette(), DEFAULT_PALETTE);
+
+        MOCK_HARDWARE.lock().set_monitor_type(MonitorType::None);
+        assert_eq!(vga.detect_monitor(), MonitorType::None);
+        assert_eq!(vga.get_emulation_mode() as u8, EmulationMode::Mda as u8);
+
+        MOCK_HARDWARE.lock().set_monitor_type(MonitorType::Color);
+        assert_eq!(vga.detect_monitor(), MonitorType::Color);
+        assert_eq!(vga.get_emulation_mode() as u8, EmulationMode::Cga as u8);
+    }
+
+    #[test]
+    fn test_set_scan_doubling() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode320x200x256);
+        assert!(vga.get_scan_doubling());
+
+        vga.set_scan_doubling(false).unwrap();
+        assert!(!vga.get_scan_doubling());
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::VerticalDisplayEnableEnd),
+                199
+            );
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::Overflow) & 0x42,
+                0x00
+            );
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::VerticalBlankingStart),
+                206
+            );
+        }
+
+        vga.set_scan_doubling(true).unwrap();
+        assert!(vga.get_scan_doubling());
+        let hardware = MOCK_HARDWARE.lock();
+        for (index, value) in MODE_320X200X256_CONFIGURATION.crtc_controller_registers {
+            // The crtc registers are locked again afterwards.
+            let value = match index {
+                CrtcControllerIndex::VerticalSyncEnd => value | 0x80,
+                _ => *value,
+            };
+            assert_eq!(hardware.crtc_controller(*index), value);
+        }
+        drop(hardware);
+
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        assert!(vga.set_scan_doubling(true).is_err());
+        assert!(!vga.get_scan_doubling());
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .crtc_controller(CrtcControllerIndex::VerticalDisplayEnableEnd),
+            0xDF
+        );
+
+        vga.set_video_mode(VideoMode::Mode80x25);
+        assert!(vga.set_scan_doubling(false).is_err());
+    }
+
+    #[test]
+    fn test_relock_crtc() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        let vertical_sync_end = |value: u8| {
+            MOCK_HARDWARE
+                .lock()
+                .crtc_controller(CrtcControllerIndex::VerticalSyncEnd)
+                & 0x80
+                == value
+        };
+        assert!(vga.get_relock_crtc());
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        assert!(vertical_sync_end(0x80));
+
+        vga.unlock_crtc_registers();
+        assert!(vertical_sync_end(0x00));
+        vga.lock_crtc_registers();
+        assert!(vertical_sync_end(0x80));
+
+        vga.set_relock_crtc(false);
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        assert!(vertical_sync_end(0x00));
+        vga.set_relock_crtc(true);
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+
+    #[test]
+    fn test_crtc_write_protect() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        let emulation_mode = vga.get_emulation_mode();
+        {
+            // The protected registers were written while unlocked, and the
+            // protect bit is restored afterwards.
+            let hardware = MOCK_HARDWARE.lock();
+            for (index, value) in MODE_640X480X16_CONFIGURATION.crtc_controller_registers {
+                let value = match index {
+                    CrtcControllerIndex::VerticalSyncEnd => value | 0x80,
+                    _ => *value,
+                };
+                assert_eq!(hardware.crtc_controller(*index), value);
+            }
+        }
+
+        let overflow = vga
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::Overflow);
+        vga.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::HorizontalTotal,
+            0x00,
+        );
+        vga.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::Overflow,
+            !overflow,
+        );
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::HorizontalTotal),
+                0x5F
+            );
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::Overflow),
+                overflow ^ 0x10
+            );
+        }
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+
+    #[test]
+    fn test_raw_register_access() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+        let emulation_mode = vga.get_emulation_mode();
+
+        unsafe {
+            vga.sequencer_registers.write_raw(0x02, 0x03);
+            assert_eq!(vga.sequencer_registers.read_raw(0x02), 0x03);
+            vga.graphics_controller_registers.write_raw(0x05, 0x10);
+            assert_eq!(vga.graphics_controller_registers.read_raw(0x05), 0x10);
+            vga.crtc_controller_registers
+                .write_raw(emulation_mode, 0x0A, 0x20);
+            assert_eq!(
+                vga.crtc_controller_registers.read_raw(emulation_mode, 0x0A),
+                0x20
+            );
+            vga.attribute_controller_registers
+                .write_raw(emulation_mode, 0x33, 0x08);
+            assert_eq!(
+                vga.attribute_controller_registers
+                    .read_raw(emulation_mode, 0x33),
+                0x08
+            );
+        }
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(hardware.sequencer(SequencerIndex::PlaneMask), 0x03);
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::GraphicsMode),
+            0x10
+        );
+        assert_eq!(
+            hardware.crtc_controller(CrtcControllerIndex::TextCursorStart),
+            0x20
+        );
+        assert_eq!(
+            hardware.attribute_controller(AttributeControllerIndex::HorizontalPixelPanning),
+            0x08
+        );
+        drop(hardware);
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+
+    #[test]
+    fn test_set_text_refresh_rate() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+
+        vga.set_text_refresh_rate(TextRefreshRate::Hz60).unwrap();
+        assert_eq!(
+            vga.get_sync_polarity(),
+            (SyncPolarity::Negative, SyncPolarity::Negative)
+        );
+        assert_eq!(vga.get_dot_clock(), Some(DotClock::Clock28Mhz));
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::VeritcalTotal),
+                0x0B
+            );
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::Overflow),
+                0x3E
+            );
+        }
+
+        vga.set_text_refresh_rate(TextRefreshRate::Hz70).unwrap();
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(
+            hardware.miscellaneous_output(),
+            MODE_80X25_CONFIGURATION.miscellaneous_output
+        );
+        for (index, value) in MODE_80X25_CONFIGURATION.crtc_controller_registers {
+            // The crtc registers are locked again afterwards.
+            let value = match index {
+                CrtcControllerIndex::VerticalSyncEnd => value | 0x80,
+                _ => *value,
+            };
+            assert_eq!(hardware.crtc_controller(*index), value);
+        }
+        drop(hardware);
+
+        // The previous protect bit is kept when relocking is disabled.
+        vga.set_relock_crtc(false);
+        vga.set_text_refresh_rate(TextRefreshRate::Hz60).unwrap();
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .crtc_controller(CrtcControllerIndex::VerticalSyncEnd)
+                & 0x80,
+            0x80
+        );
+        vga.unlock_crtc_registers();
+        vga.set_text_refresh_rate(TextRefreshRate::Hz70).unwrap();
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .crtc_controller(CrtcControllerIndex::VerticalSyncEnd)
+                & 0x80,
+            0x00
+        );
+        vga.set_relock_crtc(true);
+
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        assert!(vga.set_text_refresh_rate(TextRefreshRate::Hz60).is_err());
+    }
+
+    #[test]
+    fn test_load_palette() {
+        let _guard = lock_for_test();
+        let mut palette = Palette::default();
+        let mut vga = VGA.lock();
+        vga.color_palette_registers.load_palette(&DEFAULT_PALETTE);
+        vga.color_palette_registers.read_palette(&mut palette);
+
+        assert_eq!(palette, DEFAULT_PALETTE);
+        assert_eq!(MOCK_HARDWARE.lock().palette(), DEFAULT_PALETTE);
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_active_palette_survives_mode_switch() {
+        let _guard = lock_for_test();
+        let mut palette = DEFAULT_PALETTE;
+        palette.set_color(0, 0x3F, 0x00, 0x3F);
+        VGA.lock().set_active_palette(&palette);
+
+        let graphics_mode = Graphics320x200x256::new();
+        graphics_mode.set_mode();
+        assert_eq!(MOCK_HARDWARE.lock().palette(), palette);
+
+        VGA.lock().set_persist_palette(false);
+        graphics_mode.set_mode();
+        assert_eq!(MOCK_HARDWARE.lock().palette(), DEFAULT_PALETTE);
+
+        let mut vga = VGA.lock();
+        vga.set_persist_palette(true);
+        vga.set_active_palette(&DEFAULT_PALETTE);
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_set_mode_with_options() {
+        let _guard = lock_for_test();
+        let color = TextModeColor::new(Color16::White, Color16::Blue);
+        let mut palette = DEFAULT_PALETTE;
+        palette.set_color(0, 0x3F, 0x3F, 0x00);
+
+        let text_mode = Text80x25::new();
+        text_mode.set_mode();
+        text_mode.write_character(0, 0, ScreenCharacter::new(b'T', color));
+        VGA.lock().color_palette_registers.load_palette(&palette);
+
+        let options = ModeOptions::default()
+            .with_load_palette(false)
+            .with_load_font(false)
+            .with_clear(true);
+        text_mode.set_mode_with_options(options);
+        assert_eq!(MOCK_HARDWARE.lock().palette(), palette);
+        assert_eq!(text_mode.read_character(0, 0).get_character(), b' ');
+
+        text_mode.set_mode();
+        assert_eq!(MOCK_HARDWARE.lock().palette(), DEFAULT_PALETTE);
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_clear_vram_on_set_mode() {
+        let _guard = lock_for_test();
+        let graphics_mode = Graphics320x200x256::new();
+        graphics_mode.set_mode();
+        graphics_mode.set_pixel(1, 1, 0x0F);
+
+        graphics_mode.set_mode();
+        assert_eq!(video_memory()[321], 0x0F);
+
+        graphics_mode.set_mode_with_options(ModeOptions::default().with_clear_vram(true));
+        assert_eq!(video_memory()[321], 0x00);
+        let hardware = MOCK_HARDWARE.lock();
+        for (index, value) in MODE_320X200X256_CONFIGURATION.sequencer_registers {
+            assert_eq!(hardware.sequencer(*index), *value);
+        }
+        for (index, value) in MODE_320X200X256_CONFIGURATION.graphics_controller_registers {
+            assert_eq!(hardware.graphics_controller(*index), *value);
+        }
+    }
+
+    #[test]
+    fn test_load_font_sets_character_height() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+        vga.load_font(&TEXT_8X8_FONT).unwrap();
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .crtc_controller(CrtcControllerIndex::MaximumScanLine)
+                & 0x1F,
+            7
+        );
+        assert_eq!(vga.get_mode_info().map(|info| info.get_height()), Some(50));
+
+        assert!(VgaFont::new(256, 16, TEXT_8X8_FONT.get_font_data()).is_err());
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+
+    #[test]
+    fn test_set_text_font() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+        vga.set_text_font(&TEXT_8X8_FONT).unwrap();
+        assert_eq!(
+            vga.get_mode_info(),
+            Some(
+                ModeInfo::new(80, 50)
+                    .with_bits_per_pixel(16)
+                    .with_layout(MemoryLayout::Text)
+            )
+        );
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::MaximumScanLine) & 0x1F,
+                7
+            );
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::TextCursorStart) & 0x1F,
+                6
+            );
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::TextCursorEnd) & 0x1F,
+                7
+            );
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::UnderlineLocation) & 0x1F,
+                0x1F
+            );
+        }
+
+        vga.set_video_mode(VideoMode::Mode320x200x256);
+        assert!(vga.set_text_font(&TEXT_8X8_FONT).is_err());
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+
+    #[test]
+    fn test_load_font_into() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+        vga.load_font_into(&TEXT_8X8_FONT, CharacterMap::Map7)
+            .unwrap();
+        // Loading a map that isn't displayed keeps the character height.
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .crtc_controller(CrtcControllerIndex::MaximumScanLine)
+                & 0x1F,
+            15
+        );
+        assert_eq!(vga.get_mode_info().map(|info| info.get_height()), Some(25));
+        vga.sequencer_registers
+            .set_character_maps(CharacterMap::Map0, CharacterMap::Map7);
+        assert_eq!(
+            vga.sequencer_registers.get_character_maps(),
+            (CharacterMap::Map0, CharacterMap::Map7)
+        );
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .sequencer(SequencerIndex::CharacterFont),
+            0x2C
+        );
+        assert!(vga
+            .load_font_into(&TEXT_8X8_FONT, CharacterMap::Map7)
+            .is_err());
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .graphics_controller(GraphicsControllerIndex::Miscellaneous),
+            MODE_80X25_CONFIGURATION
+                .graphics_controller_registers
+                .iter()
+                .find(|(index, _)| *index as u8 == GraphicsControllerIndex::Miscellaneous as u8)
+                .unwrap()
+                .1
+        );
+
+        let offset = 0xE000 + usize::from(b'A') * 32;
+        let glyph = usize::from(b'A') * 8;
+        assert_eq!(
+            &video_memory()[offset..offset + 8],
+            &TEXT_8X8_FONT.get_font_data()[glyph..glyph + 8]
+        );
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_write_character() {
+        let _guard = lock_for_test();
+        let text_mode = Text80x25::new();
+        let color = TextModeColor::new(Color16::White, Color16::Blue);
+        let screen_character = ScreenCharacter::new(b'T', color);
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        text_mode.write_character(1, 1, screen_character);
+
+        let offset = 0x18000 + (Text80x25::WIDTH + 1) * 2;
+        assert_eq!(video_memory()[offset], b'T');
+        assert_eq!(text_mode.read_character(1, 1), screen_character);
+    }
+}
@@ -0,0 +1,159 @@
+//! Palette changes deferred to the vertical retrace.
+//!
+//! Changing the palette while a frame is displayed shows the old colors
+//! above the beam and the new ones below it, and on some cards causes
+//! "snow". A `PaletteQueue` collects the changes made during a frame and
+//! writes them all at once during the next vertical retrace.
+
+use crate::{colors::Palette, raster_effects::wait_for_vertical_retrace, vga::Vga};
+
+const COLOR_COUNT: usize = 256;
+
+/// A queue of palette changes, written to the vga by `flush`.
+///
+/// Only the most recent value of each color is kept, and consecutive
+/// changed colors are written with a single index write.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
+/// use vga::palette_queue::PaletteQueue;
+/// use vga::vga::VGA;
+///
+/// let mut queue = PaletteQueue::new();
+/// queue.set_color(1, 0x3F, 0x00, 0x00);
+/// queue.set_color(2, 0x00, 0x3F, 0x00);
+/// queue.flush_in_vertical_retrace(&mut VGA.lock());
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PaletteQueue {
+    colors: Palette<COLOR_COUNT>,
+    pending: [u32; COLOR_COUNT / 32],
+}
+
+impl PaletteQueue {
+    /// Creates a new `PaletteQueue` without pending changes.
+    pub const fn new() -> PaletteQueue {
+        PaletteQueue {
+            colors: Palette::new([(0, 0, 0); COLOR_COUNT]),
+            pending: [0; COLOR_COUNT / 32],
+        }
+    }
+
+    /// Queues setting the color at `index` to the given 6 bit `red`,
+    /// `green` and `blue` values.
+    pub fn set_color(&mut self, index: u8, red: u8, green: u8, blue: u8) {
+        let index = usize::from(index);
+        self.colors.set_color(index, red, green, blue);
+        self.pending[index / 32] |= 1 << (index % 32);
+    }
+
+    /// Queues loading the whole `palette`.
+    pub fn set_palette(&mut self, palette: &Palette<COLOR_COUNT>) {
+        self.colors = *palette;
+        self.pending = [u32::MAX; COLOR_COUNT / 32];
+    }
+
+    /// Returns the number of colors with pending changes.
+    pub fn len(&self) -> usize {
+        self.pending
+            .iter()
+            .map(|pending| pending.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns `true` if no changes are pending.
+    pub fn is_empty(&self) -> bool {
+        self.pending.iter().all(|pending| *pending == 0)
+    }
+
+    /// Discards the pending changes.
+    pub fn clear(&mut self) {
+        self.pending = [0; COLOR_COUNT / 32];
+    }
+
+    /// Writes the pending changes to `vga` right away, such as from a
+    /// vertical retrace interrupt handler.
+    pub fn flush(&mut self, vga: &mut Vga) {
+        let mut index = 0;
+        while index < COLOR_COUNT {
+            if !self.is_pending(index) {
+                index += 1;
+                continue;
+            }
+            let start = index;
+            while index < COLOR_COUNT && self.is_pending(index) {
+                index += 1;
+            }
+            vga.color_palette_registers
+                .write_colors(start as u8, &self.colors.get_colors()[start..index]);
+        }
+        self.clear();
+    }
+
+    /// Waits for the start of the next vertical retrace on `vga` and
+    /// writes the pending changes during it. Nothing is waited for if no
+    /// changes are pending.
+    pub fn flush_in_vertical_retrace(&mut self, vga: &mut Vga) {
+        if self.is_empty() {
+            return;
+        }
+        wait_for_vertical_retrace(vga);
+        self.flush(vga);
+    }
+
+    fn is_pending(&self, index: usize) -> bool {
+        self.pending[index / 32] & (1 << (index % 32)) != 0
+    }
+}
+
+impl Default for PaletteQueue {
+    fn default() -> PaletteQueue {
+        PaletteQueue::new()
+    }
+}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::{
+        colors::DEFAULT_PALETTE,
+        mock::{lock_for_test, MOCK_HARDWARE},
+        vga::VGA,
+    };
+
+    #[test]
+    fn test_flush() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.color_palette_registers.load_palette(&DEFAULT_PALETTE);
+
+        let mut queue = PaletteQueue::new();
+        queue.set_color(1, 0x01, 0x02, 0x03);
+        queue.set_color(2, 0x04, 0x05, 0x06);
+        queue.set_color(1, 0x07, 0x08, 0x09);
+        queue.set_color(255, 0x3F, 0x3F, 0x00);
+        assert_eq!(queue.len(), 3);
+        assert_eq!(MOCK_HARDWARE.lock().palette(), DEFAULT_PALETTE);
+
+        queue.flush_in_vertical_retrace(&mut vga);
+        assert!(queue.is_empty());
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            let palette = hardware.palette();
+            assert_eq!(palette.get_color(0), DEFAULT_PALETTE.get_color(0));
+            assert_eq!(palette.get_color(1), (0x07, 0x08, 0x09));
+            assert_eq!(palette.get_color(2), (0x04, 0x05, 0x06));
+            assert_eq!(palette.get_color(3), DEFAULT_PALETTE.get_color(3));
+            assert_eq!(palette.get_color(255), (0x3F, 0x3F, 0x00));
+        }
+
+        queue.set_palette(&DEFAULT_PALETTE);
+        assert_eq!(queue.len(), COLOR_COUNT);
+        queue.flush(&mut vga);
+        assert_eq!(MOCK_HARDWARE.lock().palette(), DEFAULT_PALETTE);
+    }
+}
@@ -0,0 +1,328 @@
+//! A screen for reporting panics from a `#[panic_handler]`.
+//!
+//! The panic may have happened in any video mode, with the registers in
+//! any state and the `Vga` locked, so `PanicScreen` sets every register of
+//! the 80x25 text mode, reloads its font and the default palette, and
+//! forcibly unlocks the `Vga` it's given if it's locked.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # #[cfg(feature = "global")]
+//! # {
+//! use core::panic::PanicInfo;
+//! use vga::panic_screen::PanicScreen;
+//!
+//! // Called from the `#[panic_handler]`.
+//! fn panic(info: &PanicInfo) -> ! {
+//!     PanicScreen::new().show(info);
+//!     loop {}
+//! }
+//! # }
+//! ```
+
+use crate::{
+    colors::{Color16, TextModeColor, DEFAULT_PALETTE},
+    fonts::TEXT_8X16_FONT,
+    registers::CrtcControllerIndex,
+    vga::{Vga, VideoMode},
+    writers::ScreenCharacter,
+};
+use core::fmt::{self, Write};
+use spinning_top::Spinlock;
+
+#[cfg(feature = "global")]
+use crate::vga::VGA;
+
+const WIDTH: usize = 80;
+const HEIGHT: usize = 25;
+/// The row the title is centered on.
+const TITLE_ROW: usize = 1;
+/// The first row of the message.
+const MESSAGE_ROW: usize = 3;
+/// The number of columns left blank on either side of the message.
+const MARGIN: usize = 2;
+const MESSAGE_WIDTH: usize = WIDTH - 2 * MARGIN;
+
+/// Renders a panic message on a colored background in the 80x25 text mode.
+#[derive(Debug, Clone, Copy)]
+pub struct PanicScreen {
+    title: &'static str,
+    color: TextModeColor,
+}
+
+impl PanicScreen {
+    /// Creates a new `PanicScreen` titled "KERNEL PANIC", with white text
+    /// on a blue background.
+    pub const fn new() -> PanicScreen {
+        PanicScreen {
+            title: "KERNEL PANIC",
+            color: TextModeColor::new(Color16::White, Color16::Blue),
+        }
+    }
+
+    /// Sets the title shown above the message.
+    pub const fn with_title(mut self, title: &'static str) -> PanicScreen {
+        self.title = title;
+        self
+    }
+
+    /// Sets the color of the message and background. The title is shown
+    /// with the foreground and background swapped.
+    pub const fn with_color(mut self, color: TextModeColor) -> PanicScreen {
+        self.color = color;
+        self
+    }
+
+    /// Returns the title shown above the message.
+    pub fn get_title(&self) -> &'static str {
+        self.title
+    }
+
+    /// Returns the color of the message and background.
+    pub fn get_color(&self) -> TextModeColor {
+        self.color
+    }
+
+    /// Shows `message` using the global `VGA`, see `show_with_vga`.
+    #[cfg(feature = "global")]
+    pub fn show(&self, message: impl fmt::Display) {
+        self.show_with_vga(&VGA, message);
+    }
+
+    /// Switches `vga` to the 80x25 text mode and shows `message`, word
+    /// wrapped to the width of the screen. Whatever doesn't fit on the
+    /// screen is left out.
+    ///
+    /// If `vga` is locked, for example because the panic happened while it
+    /// was locked, it's forcibly unlocked, so the screen is shown with the
+    /// video memory start and `RegisterAccess` of `vga`, and the state it
+    /// keeps track of, like the most recent video mode, stays up to date.
+    /// Whoever held the lock shouldn't touch the `Vga` after the panic.
+    pub fn show_with_vga(&self, vga: &Spinlock<Vga>, message: impl fmt::Display) {
+        let mut vga = match vga.try_lock() {
+            Some(vga) => vga,
+            None => {
+                // Waiting for the lock could deadlock if it's held by the
+                // code that panicked, which will never release it.
+                unsafe { vga.force_unlock() };
+                vga.lock()
+            }
+        };
+        self.render(&mut vga, message);
+    }
+
+    fn render(&self, vga: &mut Vga, message: impl fmt::Display) {
+        vga.set_video_mode(VideoMode::Mode80x25);
+        vga.load_font(&TEXT_8X16_FONT).unwrap();
+        vga.color_palette_registers.load_palette(&DEFAULT_PALETTE);
+
+        let emulation_mode = vga.get_emulation_mode();
+        let cursor_start = vga
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::TextCursorStart);
+        vga.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::TextCursorStart,
+            cursor_start | 0x20,
+        );
+
+        let frame_buffer = usize::from(vga.get_frame_buffer()) as *mut ScreenCharacter;
+        let blank = ScreenCharacter::new(b' ', self.color);
+        for offset in 0..WIDTH * HEIGHT {
+            unsafe { frame_buffer.add(offset).write_volatile(blank) };
+        }
+
+        let title_color =
+            TextModeColor::new(self.color.get_background(), self.color.get_foreground());
+        let title_len = (self.title.len() + 2).min(WIDTH);
+        let title_start = TITLE_ROW * WIDTH + (WIDTH - title_len) / 2;
+        let title = b" ".iter().chain(self.title.as_bytes()).chain(b" ");
+        for (offset, byte) in title.take(title_len).enumerate() {
+            let character = ScreenCharacter::new(printable(*byte), title_color);
+            unsafe {
+                frame_buffer
+                    .add(title_start + offset)
+                    .write_volatile(character)
+            };
+        }
+
+        let mut writer = WrappingWriter {
+            frame_buffer,
+            color: self.color,
+            x: 0,
+            y: MESSAGE_ROW,
+            word: [0; MESSAGE_WIDTH],
+            word_len: 0,
+        };
+        // The writer never fails, only formatting `message` can.
+        let _ = write!(writer, "{}", message);
+        writer.flush_word();
+    }
+}
+
+impl Default for PanicScreen {
+    fn default() -> PanicScreen {
+        PanicScreen::new()
+    }
+}
+
+/// Returns `byte` if it's printable ascii, or `0xFE` otherwise.
+fn printable(byte: u8) -> u8 {
+    match byte {
+        0x20..=0x7E => byte,
+        _ => 0xFE,
+    }
+}
+
+/// Writes text to the message area, wrapping lines between words.
+struct WrappingWriter {
+    frame_buffer: *mut ScreenCharacter,
+    color: TextModeColor,
+    x: usize,
+    y: usize,
+    word: [u8; MESSAGE_WIDTH],
+    word_len: usize,
+}
+
+impl WrappingWriter {
+    fn put(&mut self, byte: u8) {
+        if self.x == MESSAGE_WIDTH {
+            self.new_line();
+        }
+        // Leave the last row blank, like the first.
+        if self.y < HEIGHT - 1 {
+            let offset = self.y * WIDTH + MARGIN + self.x;
+            let character = ScreenCharacter::new(byte, self.color);
+            unsafe { self.frame_buffer.add(offset).write_volatile(character) };
+        }
+        self.x += 1;
+    }
+
+    fn new_line(&mut self) {
+        self.x = 0;
+        self.y += 1;
+    }
+
+    /// Writes the buffered word, on the next line if it doesn't fit on
+    /// the current one.
+    fn flush_word(&mut self) {
+        if self.x > 0 && self.x + self.word_len > MESSAGE_WIDTH {
+            self.new_line();
+        }
+        for i in 0..self.word_len {
+            self.put(self.word[i]);
+        }
+        self.word_len = 0;
+    }
+}
+
+impl fmt::Write for WrappingWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            match byte {
+                b'\n' => {
+                    self.flush_word();
+                    self.new_line();
+                }
+                b' ' | b'\t' => {
+                    self.flush_word();
+                    // Spaces at the end of a line are dropped.
+                    if self.x > 0 && self.x < MESSAGE_WIDTH {
+                        self.put(b' ');
+                    }
+                }
+                byte => {
+                    // Words longer than a line are broken up.
+                    if self.word_len == MESSAGE_WIDTH {
+                        self.flush_word();
+                    }
+                    self.word[self.word_len] = printable(byte);
+                    self.word_len += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::{
+        configurations::MODE_80X25_CONFIGURATION,
+        mock::lock_for_test,
+        registers::{Register, RegisterSnapshot},
+        vga::VGA,
+        writers::{Text80x25, TextWriter},
+    };
+
+    fn read_row(y: usize, buffer: &mut [u8]) {
+        let text_mode = Text80x25::new();
+        for (x, byte) in buffer.iter_mut().enumerate() {
+            *byte = text_mode.read_character(x, y).get_character();
+        }
+    }
+
+    #[test]
+    fn test_panic_screen() {
+        let _guard = lock_for_test();
+        VGA.lock().set_video_mode(VideoMode::Mode320x200x256);
+
+        // The screen is shown even while the vga is locked.
+        let vga = VGA.lock();
+        let screen = PanicScreen::new().with_title("OOPS");
+        screen.show_with_vga(&VGA, format_args!("{} {}\nnext", "x".repeat(70), "wrapped"));
+        drop(vga);
+
+        let mut vga = VGA.lock();
+        // Only the cursor, which is disabled, differs from the text mode.
+        let snapshot = RegisterSnapshot::capture(&mut vga);
+        let mut changes = snapshot.diff_configuration(&MODE_80X25_CONFIGURATION);
+        assert_eq!(
+            changes.next().map(|change| change.get_register()),
+            Some(Register::CrtcController(
+                CrtcControllerIndex::TextCursorStart
+            ))
+        );
+        assert_eq!(changes.next(), None);
+        vga.set_video_mode(VideoMode::Mode80x25);
+        drop(vga);
+
+        let mut row = [0; WIDTH];
+        read_row(TITLE_ROW, &mut row);
+        assert_eq!(&row[37..43], b" OOPS ");
+        assert_eq!(
+            Text80x25::new().read_character(38, TITLE_ROW).get_color(),
+            TextModeColor::new(Color16::Blue, Color16::White)
+        );
+        read_row(MESSAGE_ROW, &mut row);
+        assert_eq!(&row[..4], b"  xx");
+        assert_eq!(&row[72..], b"        ");
+        read_row(MESSAGE_ROW + 1, &mut row);
+        assert_eq!(&row[..10], b"  wrapped ");
+        read_row(MESSAGE_ROW + 2, &mut row);
+        assert_eq!(&row[..6], b"  next");
+        assert_eq!(
+            Text80x25::new().read_character(0, HEIGHT - 1),
+            ScreenCharacter::new(b' ', screen.get_color())
+        );
+    }
+
+    #[test]
+    fn test_panic_screen_locked() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode320x200x256);
+
+        // The locked vga is used rather than a new one, so it keeps track
+        // of the mode the panic screen set.
+        PanicScreen::new().show_with_vga(&VGA, "locked");
+        assert_eq!(vga.get_most_recent_video_mode(), Some(VideoMode::Mode80x25));
+        drop(vga);
+
+        let mut row = [0; WIDTH];
+        read_row(MESSAGE_ROW, &mut row);
+        assert_eq!(&row[..8], b"  locked");
+    }
+}
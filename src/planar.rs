@@ -0,0 +1,438 @@
+//! Access to planar video memory.
+//!
+//! In the planar and unchained modes every address of the frame buffer
+//! refers to four bytes, one in each plane. Which planes a write lands in
+//! is selected by the sequencer's plane mask, and which plane a read comes
+//! from by the graphics controller's read plane, so plane access always
+//! involves register state. `PlanarFrameBuffer` keeps that state in one
+//! place, and only writes a register when a different plane or mask than
+//! the current one is needed.
+
+use crate::{
+    registers::{GraphicsControllerIndex, PlaneMask, ReadPlane, SequencerIndex},
+    vga::Vga,
+    volatile::{read_planar, write_planar},
+};
+use core::{fmt, ops::Range};
+
+/// A handle for reading and writing the individual planes of video memory.
+///
+/// Creating a `PlanarFrameBuffer` saves the registers it uses and sets up
+/// write mode 0 with set/reset, the logical operation and rotation
+/// disabled, so bytes are written to the selected planes as is. The saved
+/// registers are restored when it's dropped.
+///
+/// Offsets are checked against the size of the memory window the frame
+/// buffer is mapped with, and out of range offsets panic like an out of
+/// bounds slice index.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
+/// use vga::planar::PlanarFrameBuffer;
+/// use vga::registers::{PlaneMask, ReadPlane};
+/// use vga::vga::VGA;
+///
+/// let mut vga = VGA.lock();
+/// let mut planes = PlanarFrameBuffer::new(&mut vga);
+/// planes.write_byte(PlaneMask::PLANE0 | PlaneMask::PLANE1, 0, 0xFF);
+/// assert_eq!(planes.read_byte(ReadPlane::Plane0, 0), 0xFF);
+/// # }
+/// ```
+pub struct PlanarFrameBuffer<'a> {
+    vga: &'a mut Vga,
+    frame_buffer: *mut u8,
+    plane_size: usize,
+    plane_mask: Option<PlaneMask>,
+    read_plane: Option<u8>,
+    bit_mask: Option<u8>,
+    saved_registers: SavedRegisters,
+}
+
+/// The registers changed by `PlanarFrameBuffer`.
+#[derive(Debug, Clone, Copy)]
+struct SavedRegisters {
+    plane_mask: u8,
+    read_plane: u8,
+    graphics_mode: u8,
+    enable_set_reset: u8,
+    data_rotate: u8,
+    bit_mask: u8,
+}
+
+impl<'a> PlanarFrameBuffer<'a> {
+    /// Creates a new `PlanarFrameBuffer` for the frame buffer of `vga`.
+    pub fn new(vga: &'a mut Vga) -> PlanarFrameBuffer<'a> {
+        let saved_registers = SavedRegisters {
+            plane_mask: vga.sequencer_registers.read(SequencerIndex::PlaneMask),
+            read_plane: vga
+                .graphics_controller_registers
+                .read(GraphicsControllerIndex::ReadPlaneSelect),
+            graphics_mode: vga
+                .graphics_controller_registers
+                .read(GraphicsControllerIndex::GraphicsMode),
+            enable_set_reset: vga
+                .graphics_controller_registers
+                .read(GraphicsControllerIndex::EnableSetReset),
+            data_rotate: vga
+                .graphics_controller_registers
+                .read(GraphicsControllerIndex::DataRotate),
+            bit_mask: vga
+                .graphics_controller_registers
+                .read(GraphicsControllerIndex::BitMask),
+        };
+
+        // Write mode 0 and read mode 0, keeping the odd/even and shift bits.
+        vga.graphics_controller_registers.write(
+            GraphicsControllerIndex::GraphicsMode,
+            saved_registers.graphics_mode & !0x0B,
+        );
+        vga.graphics_controller_registers.write(
+            GraphicsControllerIndex::EnableSetReset,
+            saved_registers.enable_set_reset & 0xF0,
+        );
+        vga.graphics_controller_registers.write(
+            GraphicsControllerIndex::DataRotate,
+            saved_registers.data_rotate & 0xE0,
+        );
+
+        let frame_buffer = usize::from(vga.get_frame_buffer()) as *mut u8;
+        let plane_size = vga.get_memory_map().get_size();
+        PlanarFrameBuffer {
+            vga,
+            frame_buffer,
+            plane_size,
+            plane_mask: None,
+            read_plane: None,
+            bit_mask: None,
+            saved_registers,
+        }
+    }
+
+    /// Returns the number of bytes of each plane that can be addressed,
+    /// which is the size of the memory window.
+    pub fn get_plane_size(&self) -> usize {
+        self.plane_size
+    }
+
+    /// Reads the byte at `offset` of the given `plane`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is outside of the plane.
+    pub fn read_byte(&mut self, plane: ReadPlane, offset: usize) -> u8 {
+        self.check_range(offset, 1);
+        self.set_read_plane(plane);
+        unsafe { read_planar(self.frame_buffer.add(offset)) }
+    }
+
+    /// Reads the byte at `offset` of every plane, indexed by plane.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is outside of the plane.
+    pub fn read_planes(&mut self, offset: usize) -> [u8; 4] {
+        [
+            self.read_byte(ReadPlane::Plane0, offset),
+            self.read_byte(ReadPlane::Plane1, offset),
+            self.read_byte(ReadPlane::Plane2, offset),
+            self.read_byte(ReadPlane::Plane3, offset),
+        ]
+    }
+
+    /// Returns an iterator over the bytes of `plane` in the given range
+    /// of offsets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offsets` isn't within the plane.
+    pub fn plane_bytes(&mut self, plane: ReadPlane, offsets: Range<usize>) -> PlaneBytes<'_, 'a> {
+        self.check_range(offsets.start, offsets.len());
+        self.set_read_plane(plane);
+        PlaneBytes {
+            frame_buffer: self,
+            offsets,
+        }
+    }
+
+    /// Writes `value` to the byte at `offset` of every plane in `planes`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is outside of the plane.
+    pub fn write_byte(&mut self, planes: PlaneMask, offset: usize, value: u8) {
+        self.check_range(offset, 1);
+        self.set_plane_mask(planes);
+        self.set_bit_mask(0xFF);
+        unsafe {
+            write_planar(self.frame_buffer.add(offset), value);
+        }
+    }
+
+    /// Writes the bits of `value` selected by `bit_mask` to the byte at
+    /// `offset` of every plane in `planes`. The other bits keep their
+    /// current value in each plane.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is outside of the plane.
+    pub fn write_byte_masked(&mut self, planes: PlaneMask, offset: usize, value: u8, bit_mask: u8) {
+        self.check_range(offset, 1);
+        self.set_plane_mask(planes);
+        self.set_bit_mask(bit_mask);
+        unsafe {
+            let byte = self.frame_buffer.add(offset);
+            // Load the latches, so the bits outside of the mask are kept.
+            read_planar(byte);
+            write_planar(byte, value);
+        }
+    }
+
+    /// Writes `value` to the bytes at `offsets` of every plane in `planes`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offsets` isn't within the plane.
+    pub fn fill(&mut self, planes: PlaneMask, offsets: Range<usize>, value: u8) {
+        self.check_range(offsets.start, offsets.len());
+        self.set_plane_mask(planes);
+        self.set_bit_mask(0xFF);
+        for offset in offsets {
+            unsafe {
+                write_planar(self.frame_buffer.add(offset), value);
+            }
+        }
+    }
+
+    /// Copies `count` bytes of every plane from offset `src` to offset `dst`
+    /// through the latches, using write mode 1. Each byte copied moves all
+    /// four planes at once, and like `ptr::copy` the ranges may overlap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either range isn't within the plane.
+    pub fn copy_latched(&mut self, src: usize, dst: usize, count: usize) {
+        self.check_range(src, count);
+        self.check_range(dst, count);
+        let graphics_mode = self.saved_registers.graphics_mode & !0x0B;
+        self.set_plane_mask(PlaneMask::ALL_PLANES);
+        self.vga
+            .graphics_controller_registers
+            .write(GraphicsControllerIndex::GraphicsMode, graphics_mode | 0x01);
+        let copy_byte = |offset: usize| unsafe {
+            read_planar(self.frame_buffer.add(src + offset));
+            write_planar(self.frame_buffer.add(dst + offset), 0);
+        };
+        if dst > src && dst < src + count {
+            (0..count).rev().for_each(copy_byte);
+        } else {
+            (0..count).for_each(copy_byte);
+        }
+        self.vga
+            .graphics_controller_registers
+            .write(GraphicsControllerIndex::GraphicsMode, graphics_mode);
+    }
+
+    /// Returns the `Vga` this frame buffer was created with.
+    ///
+    /// **Note:** The frame buffer assumes the registers it set up stay
+    /// unchanged.
+    pub fn get_vga(&mut self) -> &mut Vga {
+        self.vga
+    }
+
+    fn check_range(&self, start: usize, count: usize) {
+        assert!(
+            start
+                .checked_add(count)
+                .is_some_and(|end| end <= self.plane_size),
+            "offset is outside of the plane"
+        );
+    }
+
+    fn set_plane_mask(&mut self, plane_mask: PlaneMask) {
+        if self.plane_mask != Some(plane_mask) {
+            self.plane_mask = Some(plane_mask);
+            self.vga.sequencer_registers.set_plane_mask(plane_mask);
+        }
+    }
+
+    fn set_read_plane(&mut self, read_plane: ReadPlane) {
+        let plane = u8::from(read_plane);
+        if self.read_plane != Some(plane) {
+            self.read_plane = Some(plane);
+            self.vga
+                .graphics_controller_registers
+                .write_read_plane(read_plane);
+        }
+    }
+
+    fn set_bit_mask(&mut self, bit_mask: u8) {
+        if self.bit_mask != Some(bit_mask) {
+            self.bit_mask = Some(bit_mask);
+            self.vga
+                .graphics_controller_registers
+                .set_bit_mask(bit_mask);
+        }
+    }
+}
+
+impl fmt::Debug for PlanarFrameBuffer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PlanarFrameBuffer")
+            .field("frame_buffer", &self.frame_buffer)
+            .field("plane_size", &self.plane_size)
+            .field("plane_mask", &self.plane_mask)
+            .field("read_plane", &self.read_plane)
+            .field("bit_mask", &self.bit_mask)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for PlanarFrameBuffer<'_> {
+    fn drop(&mut self) {
+        let saved = self.saved_registers;
+        let graphics = &mut self.vga.graphics_controller_registers;
+        graphics.write(GraphicsControllerIndex::BitMask, saved.bit_mask);
+        graphics.write(GraphicsControllerIndex::DataRotate, saved.data_rotate);
+        graphics.write(
+            GraphicsControllerIndex::EnableSetReset,
+            saved.enable_set_reset,
+        );
+        graphics.write(GraphicsControllerIndex::GraphicsMode, saved.graphics_mode);
+        graphics.write(GraphicsControllerIndex::ReadPlaneSelect, saved.read_plane);
+        self.vga
+            .sequencer_registers
+            .write(SequencerIndex::PlaneMask, saved.plane_mask);
+    }
+}
+
+/// An iterator over the bytes of a single plane, created with
+/// `PlanarFrameBuffer::plane_bytes`.
+#[derive(Debug)]
+pub struct PlaneBytes<'b, 'a> {
+    frame_buffer: &'b mut PlanarFrameBuffer<'a>,
+    offsets: Range<usize>,
+}
+
+impl Iterator for PlaneBytes<'_, '_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let offset = self.offsets.next()?;
+        unsafe { Some(read_planar(self.frame_buffer.frame_buffer.add(offset))) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.offsets.size_hint()
+    }
+}
+
+impl ExactSizeIterator for PlaneBytes<'_, '_> {}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::{
+        mock::{lock_for_test, video_planes, MOCK_HARDWARE},
+        vga::{VideoMode, VGA},
+    };
+
+    #[test]
+    fn test_planar_frame_buffer() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        vga.graphics_controller_registers
+            .write(GraphicsControllerIndex::GraphicsMode, 0x02);
+        vga.graphics_controller_registers.set_bit_mask(0x80);
+        vga.sequencer_registers.set_plane_mask(PlaneMask::PLANE0);
+        vga.graphics_controller_registers
+            .write_read_plane(ReadPlane::Plane1);
+        {
+            let mut planes = PlanarFrameBuffer::new(&mut vga);
+            {
+                let hardware = MOCK_HARDWARE.lock();
+                assert_eq!(
+                    hardware.graphics_controller(GraphicsControllerIndex::GraphicsMode),
+                    0x00
+                );
+                assert_eq!(
+                    hardware.graphics_controller(GraphicsControllerIndex::EnableSetReset),
+                    0x00
+                );
+            }
+
+            planes.write_byte(PlaneMask::PLANE1 | PlaneMask::PLANE3, 3, 0x5A);
+            {
+                let hardware = MOCK_HARDWARE.lock();
+                assert_eq!(hardware.sequencer(SequencerIndex::PlaneMask), 0x0A);
+                assert_eq!(
+                    hardware.graphics_controller(GraphicsControllerIndex::BitMask),
+                    0xFF
+                );
+            }
+            assert_eq!(planes.read_byte(ReadPlane::Plane3, 3), 0x5A);
+            assert_eq!(
+                MOCK_HARDWARE
+                    .lock()
+                    .graphics_controller(GraphicsControllerIndex::ReadPlaneSelect),
+                0x03
+            );
+
+            planes.fill(PlaneMask::ALL_PLANES, 4..5, 0xF0);
+            planes.write_byte_masked(PlaneMask::PLANE2, 4, 0xC3, 0x0F);
+            assert_eq!(
+                MOCK_HARDWARE
+                    .lock()
+                    .graphics_controller(GraphicsControllerIndex::BitMask),
+                0x0F
+            );
+            assert!(planes
+                .plane_bytes(ReadPlane::Plane2, 3..5)
+                .eq([0x00, 0xF3].iter().copied()));
+            assert_eq!(planes.read_planes(3), [0x00, 0x5A, 0x00, 0x5A]);
+
+            planes.copy_latched(3, 10, 2);
+            assert_eq!(planes.read_planes(10), [0x00, 0x5A, 0x00, 0x5A]);
+            assert_eq!(planes.read_planes(11), [0xF0, 0xF0, 0xF3, 0xF0]);
+        }
+        {
+            let video_planes = video_planes();
+            assert_eq!(&video_planes.plane(1)[3..5], &[0x5A, 0xF0]);
+            assert_eq!(&video_planes.plane(2)[3..5], &[0x00, 0xF3]);
+        }
+        {
+            let mut planes = PlanarFrameBuffer::new(&mut vga);
+            assert_eq!(planes.get_plane_size(), 0x10000);
+            planes.fill(PlaneMask::PLANE0, 0xFFFF..0x10000, 0x0F);
+            assert_eq!(planes.read_byte(ReadPlane::Plane0, 0xFFFF), 0x0F);
+        }
+
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(hardware.sequencer(SequencerIndex::PlaneMask), 0x01);
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::GraphicsMode),
+            0x02
+        );
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::BitMask),
+            0x80
+        );
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::ReadPlaneSelect),
+            0x01
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "offset is outside of the plane")]
+    fn test_planar_frame_buffer_out_of_range() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        PlanarFrameBuffer::new(&mut vga).copy_latched(0, 0xFFFF, 2);
+    }
+}
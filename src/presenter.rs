@@ -0,0 +1,197 @@
+//! Once per frame presentation of double buffered animations.
+//!
+//! A `Presenter` ties together the pieces needed to show a new frame
+//! without tearing: it waits for the vertical retrace, writes the palette
+//! changes queued during the frame, then lets the caller flip pages or copy
+//! the dirty rectangles of a back buffer while the beam is off screen.
+
+use crate::{drawing::Rect, frame_counter::FrameCounter, palette_queue::PaletteQueue, vga::Vga};
+use spinning_top::Spinlock;
+
+/// Presents one frame per call to `present`, tracking up to `N` dirty
+/// rectangles and the number of frames presented per second.
+///
+/// # Examples
+///
+/// Flipping between two pages of a 320x240 mode:
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "modex"))]
+/// # {
+/// use vga::presenter::Presenter;
+/// use vga::vga::VGA;
+/// use vga::writers::VirtualDesktop;
+///
+/// let desktop = VirtualDesktop::new(320, 480);
+/// let mut presenter = Presenter::<1>::new(60);
+/// let mut back_page = 1;
+/// loop {
+///     // Draw the next frame to rows `back_page * 240..`.
+///     presenter.get_palette_queue_mut().set_color(1, 0x3F, 0x00, 0x00);
+///     presenter.present(&VGA, |_| desktop.set_viewport(0, back_page * 240));
+///     back_page ^= 1;
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Presenter<const N: usize> {
+    palette_queue: PaletteQueue,
+    frame_counter: FrameCounter,
+    dirty_rects: [Rect; N],
+    dirty_rect_count: usize,
+    refresh_rate: usize,
+    window_start: usize,
+    window_frames: usize,
+    frames_per_second: usize,
+}
+
+impl<const N: usize> Presenter<N> {
+    /// Creates a new `Presenter` for a mode refreshing `refresh_rate` times
+    /// per second, such as 70 for 320x200 and 60 for 640x480.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `refresh_rate` or `N` is 0.
+    pub const fn new(refresh_rate: usize) -> Presenter<N> {
+        assert!(refresh_rate > 0, "refresh rate must be at least 1");
+        assert!(N > 0, "at least 1 dirty rect must be tracked");
+        Presenter {
+            palette_queue: PaletteQueue::new(),
+            frame_counter: FrameCounter::new(),
+            dirty_rects: [Rect::new(0, 0, 0, 0); N],
+            dirty_rect_count: 0,
+            refresh_rate,
+            window_start: 0,
+            window_frames: 0,
+            frames_per_second: 0,
+        }
+    }
+
+    /// Returns the `PaletteQueue` written by the next `present`.
+    pub fn get_palette_queue_mut(&mut self) -> &mut PaletteQueue {
+        &mut self.palette_queue
+    }
+
+    /// Returns the `FrameCounter` counting the vertical retraces waited for.
+    pub fn get_frame_counter(&self) -> &FrameCounter {
+        &self.frame_counter
+    }
+
+    /// Returns the refresh rate given to `new`.
+    pub fn get_refresh_rate(&self) -> usize {
+        self.refresh_rate
+    }
+
+    /// Returns the number of frames presented during the last second of
+    /// vertical retraces, or 0 until a second has passed.
+    pub fn get_frames_per_second(&self) -> usize {
+        self.frames_per_second
+    }
+
+    /// Returns the rectangles marked dirty since the last `present`.
+    pub fn get_dirty_rects(&self) -> &[Rect] {
+        &self.dirty_rects[..self.dirty_rect_count]
+    }
+
+    /// Marks `rect` as changed in the back buffer, to be passed to the next
+    /// `present`. Once `N` rectangles are tracked, they're all merged into
+    /// the single rectangle covering them.
+    pub fn mark_dirty(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+        if self.dirty_rect_count == N {
+            let bounds = self
+                .get_dirty_rects()
+                .iter()
+                .fold(rect, |bounds, dirty_rect| bounds.union(*dirty_rect));
+            self.dirty_rects[0] = bounds;
+            self.dirty_rect_count = 1;
+        } else {
+            self.dirty_rects[self.dirty_rect_count] = rect;
+            self.dirty_rect_count += 1;
+        }
+    }
+
+    /// Waits for the next vertical retrace on `vga`, writes the queued
+    /// palette changes and calls `present` with the dirty rectangles, which
+    /// should flip pages or copy the rectangles to the frame buffer.
+    ///
+    /// `vga` is unlocked before calling `present`, so it may use writers
+    /// that lock `vga` themselves. Only the time spent in the vertical
+    /// retrace is tear free, so `present` should finish quickly.
+    pub fn present<R>(&mut self, vga: &Spinlock<Vga>, present: impl FnOnce(&[Rect]) -> R) -> R {
+        {
+            let mut vga = vga.lock();
+            self.frame_counter.wait_frames(&mut vga, 1);
+            self.palette_queue.flush(&mut vga);
+        }
+        let result = present(self.get_dirty_rects());
+        self.dirty_rect_count = 0;
+        self.count_frame();
+        result
+    }
+
+    fn count_frame(&mut self) {
+        self.window_frames += 1;
+        let frame_count = self.frame_counter.get_frame_count();
+        let elapsed = frame_count.wrapping_sub(self.window_start);
+        if elapsed >= self.refresh_rate {
+            self.frames_per_second = self.window_frames * self.refresh_rate / elapsed;
+            self.window_start = frame_count;
+            self.window_frames = 0;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::{colors::DEFAULT_PALETTE, mock::lock_for_test, vga::VGA};
+
+    #[test]
+    fn test_mark_dirty() {
+        let mut presenter = Presenter::<2>::new(70);
+        presenter.mark_dirty(Rect::new(0, 0, 0, 10));
+        assert!(presenter.get_dirty_rects().is_empty());
+
+        presenter.mark_dirty(Rect::new(10, 10, 10, 10));
+        presenter.mark_dirty(Rect::new(30, 5, 5, 5));
+        assert_eq!(presenter.get_dirty_rects().len(), 2);
+        presenter.mark_dirty(Rect::new(0, 40, 5, 5));
+        assert_eq!(presenter.get_dirty_rects(), &[Rect::new(0, 5, 35, 40)]);
+    }
+
+    #[test]
+    fn test_present() {
+        let _guard = lock_for_test();
+        VGA.lock()
+            .color_palette_registers
+            .load_palette(&DEFAULT_PALETTE);
+        let mut presenter = Presenter::<4>::new(4);
+        presenter
+            .get_palette_queue_mut()
+            .set_color(1, 0x01, 0x02, 0x03);
+        presenter.mark_dirty(Rect::new(1, 2, 3, 4));
+
+        let rects = presenter.present(&VGA, |rects| {
+            let color = VGA.lock().color_palette_registers.read_color(1);
+            assert_eq!(color, (0x01, 0x02, 0x03));
+            rects.len()
+        });
+        assert_eq!(rects, 1);
+        assert!(presenter.get_dirty_rects().is_empty());
+        assert!(presenter.get_palette_queue_mut().is_empty());
+        assert_eq!(presenter.get_frames_per_second(), 0);
+
+        for _ in 0..3 {
+            presenter.present(&VGA, |_| {});
+        }
+        assert_eq!(presenter.get_frame_counter().get_frame_count(), 4);
+        assert_eq!(presenter.get_frames_per_second(), 4);
+
+        VGA.lock()
+            .color_palette_registers
+            .load_palette(&DEFAULT_PALETTE);
+    }
+}
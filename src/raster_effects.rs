@@ -0,0 +1,172 @@
+//! Register changes synchronized to the scan line being displayed,
+//! such as the classic "copper bars".
+//!
+//! The beam position is found by polling input status register 1, so
+//! `run_frame` busy waits for a whole frame. Interrupts should be disabled
+//! while it runs, since any delay shifts the effects down the screen.
+//!
+//! **Note:** Most vga hardware latches the crtc start address at vertical
+//! retrace, so it can't be changed mid-frame. Palette colors and the
+//! horizontal pixel panning take effect immediately.
+
+use crate::{registers::AttributeControllerIndex, vga::Vga};
+
+/// Represents a register change applied by a `RasterEffect`.
+#[derive(Debug, Copy, Clone)]
+pub enum RasterChange {
+    /// Sets the palette entry `index` to the 6 bit `red`, `green` and `blue` values.
+    PaletteColor {
+        /// The palette entry to change.
+        index: u8,
+        /// The 6 bit red value.
+        red: u8,
+        /// The 6 bit green value.
+        green: u8,
+        /// The 6 bit blue value.
+        blue: u8,
+    },
+    /// Sets the horizontal pixel panning.
+    PixelPanning(u8),
+    /// Calls the function with the locked `Vga`, for any other change.
+    Custom(fn(&mut Vga)),
+}
+
+/// Represents a `RasterChange` applied when the display reaches `scan_line`.
+#[derive(Debug, Copy, Clone)]
+pub struct RasterEffect {
+    scan_line: usize,
+    change: RasterChange,
+}
+
+impl RasterEffect {
+    /// Creates a new `RasterEffect` applying `change` at `scan_line`.
+    ///
+    /// Scan lines are counted as displayed, so double scanned modes such as
+    /// 320x200x256 have two scan lines per pixel row.
+    pub const fn new(scan_line: usize, change: RasterChange) -> RasterEffect {
+        RasterEffect { scan_line, change }
+    }
+
+    /// Returns the scan line the effect is applied at.
+    pub const fn get_scan_line(&self) -> usize {
+        self.scan_line
+    }
+
+    fn apply(&self, vga: &mut Vga) {
+        match self.change {
+            RasterChange::PaletteColor {
+                index,
+                red,
+                green,
+                blue,
+            } => vga
+                .color_palette_registers
+                .write_color(index, red, green, blue),
+            RasterChange::PixelPanning(panning) => {
+                let emulation_mode = vga.get_emulation_mode();
+                vga.attribute_controller_registers.write(
+                    emulation_mode,
+                    AttributeControllerIndex::HorizontalPixelPanning,
+                    panning,
+                );
+                vga.attribute_controller_registers
+                    .unblank_screen(emulation_mode);
+            }
+            RasterChange::Custom(change) => change(vga),
+        }
+    }
+}
+
+/// Waits for the start of the next vertical retrace on `vga`.
+pub fn wait_for_vertical_retrace(vga: &mut Vga) {
+    let emulation_mode = vga.get_emulation_mode();
+    while vga.general_registers.is_vertical_retrace(emulation_mode) {}
+    while !vga.general_registers.is_vertical_retrace(emulation_mode) {}
+}
+
+/// Waits for the next frame of `vga` and applies each of `effects` as the display
+/// reaches its scan line, returning after the last effect is applied.
+///
+/// Effects at the same scan line are applied in order.
+///
+/// # Panics
+///
+/// Panics if `effects` aren't sorted by scan line.
+pub fn run_frame(vga: &mut Vga, effects: &[RasterEffect]) {
+    assert!(
+        effects
+            .windows(2)
+            .all(|effects| effects[0].scan_line <= effects[1].scan_line),
+        "effects must be sorted by scan line"
+    );
+
+    let emulation_mode = vga.get_emulation_mode();
+
+    // Wait for the end of the next vertical retrace, so the next
+    // displayed scan line is the first one of the frame.
+    while vga.general_registers.is_vertical_retrace(emulation_mode) {}
+    while !vga.general_registers.is_vertical_retrace(emulation_mode) {}
+    while vga.general_registers.is_vertical_retrace(emulation_mode) {}
+
+    let mut effects = effects.iter().peekable();
+    let mut scan_line = 0;
+    while effects.peek().is_some() {
+        // Wait for the display of `scan_line` to start.
+        while vga.general_registers.is_display_disabled(emulation_mode) {}
+        while let Some(effect) = effects.next_if(|effect| effect.scan_line <= scan_line) {
+            effect.apply(vga);
+        }
+        // Wait for the horizontal blanking at the end of `scan_line`.
+        while !vga.general_registers.is_display_disabled(emulation_mode) {}
+        scan_line += 1;
+    }
+}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::{
+        mock::{lock_for_test, MOCK_HARDWARE},
+        vga::VGA,
+    };
+
+    #[test]
+    fn test_run_frame() {
+        let _guard = lock_for_test();
+        let effects = [
+            RasterEffect::new(
+                10,
+                RasterChange::PaletteColor {
+                    index: 0,
+                    red: 0x3F,
+                    green: 0x00,
+                    blue: 0x00,
+                },
+            ),
+            RasterEffect::new(
+                20,
+                RasterChange::PaletteColor {
+                    index: 1,
+                    red: 0x00,
+                    green: 0x3F,
+                    blue: 0x00,
+                },
+            ),
+        ];
+        run_frame(&mut VGA.lock(), &effects);
+
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(hardware.palette().get_color(0), (0x3F, 0x00, 0x00));
+        assert_eq!(hardware.palette().get_color(1), (0x00, 0x3F, 0x00));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_run_frame_unsorted() {
+        let change = RasterChange::PixelPanning(0);
+        run_frame(
+            &mut VGA.lock(),
+            &[RasterEffect::new(2, change), RasterEffect::new(1, change)],
+        );
+    }
+}
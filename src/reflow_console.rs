@@ -0,0 +1,550 @@
+//! A console keeping its text in a scrollback buffer, so it can be laid
+//! out again for the new size whenever the video mode changes.
+//!
+//! The consoles of the writers only know about the screen they draw to,
+//! so switching from the 80x25 text mode to a graphics mode loses what
+//! was written. `ReflowConsole` instead writes every character to a
+//! `Scrollback`, and when `Vga::get_mode_generation` shows the mode was
+//! set since it last drew, wraps the scrollback to the columns of the new
+//! mode and redraws the rows that fit on the screen.
+
+use crate::{
+    colors::{Color16, TextModeColor},
+    display::{graphics_writer, text_writer},
+    vga::{VideoMode, VGA},
+    writers::{
+        any::{AnyGraphicsWriter, AnyTextWriter},
+        ScreenCharacter,
+    },
+};
+use core::fmt;
+
+/// The size of a character cell in graphics modes.
+const CHARACTER_SIZE: usize = 8;
+
+/// A ring buffer of the last `N` characters written to a console, with
+/// lines separated by `b'\n'` characters.
+///
+/// Lines aren't wrapped in the scrollback, so it can be laid out for any
+/// number of columns with `rows`.
+#[derive(Debug, Clone)]
+pub struct Scrollback<const N: usize> {
+    characters: [ScreenCharacter; N],
+    start: usize,
+    len: usize,
+}
+
+impl<const N: usize> Scrollback<N> {
+    /// Creates a new, empty `Scrollback`.
+    pub const fn new() -> Scrollback<N> {
+        Scrollback {
+            characters: [ScreenCharacter::new(
+                b' ',
+                TextModeColor::new(Color16::Black, Color16::Black),
+            ); N],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of characters the scrollback holds.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of characters in the scrollback.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the scrollback is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every character from the scrollback.
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+
+    /// Appends `character`, dropping the oldest character when the
+    /// scrollback is full.
+    pub fn push(&mut self, character: ScreenCharacter) {
+        if N == 0 {
+            return;
+        }
+        if self.len == N {
+            self.characters[self.start] = character;
+            self.start = (self.start + 1) % N;
+        } else {
+            self.characters[(self.start + self.len) % N] = character;
+            self.len += 1;
+        }
+    }
+
+    /// Returns the character at `index`, counting from the oldest one.
+    pub fn get(&self, index: usize) -> Option<ScreenCharacter> {
+        if index < self.len {
+            Some(self.characters[(self.start + index) % N])
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the characters, from the oldest one.
+    pub fn iter(&self) -> ScrollbackIter<'_, N> {
+        ScrollbackIter {
+            scrollback: self,
+            index: 0,
+        }
+    }
+
+    /// Returns an iterator laying out the characters in `columns` wide
+    /// rows, which yields the `(x, y)` position of every character other
+    /// than `b'\n'`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `columns` is 0.
+    pub fn rows(&self, columns: usize) -> ScrollbackRows<'_, N> {
+        assert!(columns > 0, "a scrollback can't be laid out in 0 columns");
+        ScrollbackRows {
+            characters: self.iter(),
+            columns,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    /// Returns the number of rows the characters take up when laid out in
+    /// `columns` wide rows, including the row text is written to next.
+    pub fn row_count(&self, columns: usize) -> usize {
+        let mut rows = self.rows(columns);
+        rows.by_ref().for_each(drop);
+        rows.get_position().1 + 1
+    }
+}
+
+impl<const N: usize> Default for Scrollback<N> {
+    fn default() -> Scrollback<N> {
+        Scrollback::new()
+    }
+}
+
+/// An iterator over the characters of a `Scrollback`, created by
+/// `Scrollback::iter`.
+#[derive(Debug, Clone)]
+pub struct ScrollbackIter<'a, const N: usize> {
+    scrollback: &'a Scrollback<N>,
+    index: usize,
+}
+
+impl<const N: usize> Iterator for ScrollbackIter<'_, N> {
+    type Item = ScreenCharacter;
+
+    fn next(&mut self) -> Option<ScreenCharacter> {
+        let character = self.scrollback.get(self.index)?;
+        self.index += 1;
+        Some(character)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.scrollback.len() - self.index;
+        (len, Some(len))
+    }
+}
+
+impl<const N: usize> ExactSizeIterator for ScrollbackIter<'_, N> {}
+
+/// An iterator laying out the characters of a `Scrollback` in rows,
+/// created by `Scrollback::rows`.
+///
+/// A line filling a whole row only moves on to the next row when another
+/// character follows it, so lines as wide as the screen don't leave a
+/// blank row behind.
+#[derive(Debug, Clone)]
+pub struct ScrollbackRows<'a, const N: usize> {
+    characters: ScrollbackIter<'a, N>,
+    columns: usize,
+    x: usize,
+    y: usize,
+}
+
+impl<const N: usize> ScrollbackRows<'_, N> {
+    /// Returns the position the next character is laid out at as `(x, y)`,
+    /// where `x` equals the number of columns if the current row is full.
+    pub fn get_position(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+}
+
+impl<const N: usize> Iterator for ScrollbackRows<'_, N> {
+    type Item = (usize, usize, ScreenCharacter);
+
+    fn next(&mut self) -> Option<(usize, usize, ScreenCharacter)> {
+        loop {
+            let character = self.characters.next()?;
+            if let Some(position) = advance(&mut self.x, &mut self.y, self.columns, character) {
+                return Some((position.0, position.1, character));
+            }
+        }
+    }
+}
+
+/// Lays out `character` at the position `(x, y)` of rows `columns` wide,
+/// returning where it goes unless it's a `b'\n'`.
+fn advance(
+    x: &mut usize,
+    y: &mut usize,
+    columns: usize,
+    character: ScreenCharacter,
+) -> Option<(usize, usize)> {
+    if character.get_character() == b'\n' {
+        *x = 0;
+        *y += 1;
+        return None;
+    }
+    if *x == columns {
+        *x = 0;
+        *y += 1;
+    }
+    *x += 1;
+    Some((*x - 1, *y))
+}
+
+/// A text console for the text modes and graphics modes of the global
+/// `VGA`, which keeps the last `N` characters written to it and lays
+/// them out again when the video mode changes.
+///
+/// In text modes, characters are written to the cells of the mode, and
+/// the text cursor follows the console. In graphics modes, characters
+/// are drawn in 8x8 cells in the colors of the `Color16` palette, or in
+/// their rgb values in modes with 32 bits per pixel. Modes set with
+/// `Vga::set_configuration`, and modes compiled out by their feature,
+/// only write to the scrollback until a supported mode is set.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// # #[cfg(all(feature = "text-modes", feature = "graphics-modes"))]
+/// # {
+/// use core::fmt::Write;
+/// use vga::colors::{Color16, TextModeColor};
+/// use vga::reflow_console::ReflowConsole;
+/// use vga::writers::{Graphics640x480x16, GraphicsWriter, Text80x25, TextWriter};
+///
+/// let mut console: ReflowConsole<4096> =
+///     ReflowConsole::new(TextModeColor::new(Color16::LightGrey, Color16::Black));
+/// Text80x25::new().set_mode();
+/// writeln!(console, "Booting...").unwrap();
+///
+/// // "Booting..." is drawn again at the top of the 80x60 graphics console.
+/// Graphics640x480x16::new().set_mode();
+/// writeln!(console, "Switched to 640x480").unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReflowConsole<const N: usize> {
+    scrollback: Scrollback<N>,
+    color: TextModeColor,
+    mode_generation: Option<usize>,
+    video_mode: Option<VideoMode>,
+    columns: usize,
+    rows: usize,
+    top_row: usize,
+    x: usize,
+    y: usize,
+}
+
+impl<const N: usize> ReflowConsole<N> {
+    /// Creates a new `ReflowConsole` writing in `color`. Nothing is drawn
+    /// until something is written, or `clear` or `reflow` is called.
+    pub const fn new(color: TextModeColor) -> ReflowConsole<N> {
+        ReflowConsole {
+            scrollback: Scrollback::new(),
+            color,
+            mode_generation: None,
+            video_mode: None,
+            columns: 0,
+            rows: 0,
+            top_row: 0,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    /// Returns the color characters are written in.
+    pub fn get_color(&self) -> TextModeColor {
+        self.color
+    }
+
+    /// Sets the color characters are written in from now on.
+    pub fn set_color(&mut self, color: TextModeColor) {
+        self.color = color;
+    }
+
+    /// Returns the `Scrollback` holding the characters written so far.
+    pub fn get_scrollback(&self) -> &Scrollback<N> {
+        &self.scrollback
+    }
+
+    /// Returns the size of the console as `(columns, rows)`, which is
+    /// `(0, 0)` while the current mode isn't supported.
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    /// Removes every character from the scrollback and clears the screen.
+    pub fn clear(&mut self) {
+        self.scrollback.clear();
+        self.reflow();
+    }
+
+    /// Lays out the scrollback for the current video mode and redraws
+    /// the screen, showing the last rows of the scrollback.
+    ///
+    /// This happens automatically when writing after the mode changed,
+    /// but can be called to redraw the console right away.
+    pub fn reflow(&mut self) {
+        let (mode_generation, video_mode) = {
+            let vga = VGA.lock();
+            (vga.get_mode_generation(), vga.get_most_recent_video_mode())
+        };
+        self.mode_generation = Some(mode_generation);
+        self.video_mode = video_mode;
+        let (columns, rows) = match (self.text_writer(), self.graphics_writer()) {
+            (Some(writer), _) => {
+                let mode_info = writer.mode_info();
+                (mode_info.get_width(), mode_info.get_height())
+            }
+            (None, Some(writer)) => {
+                let mode_info = writer.mode_info();
+                (
+                    mode_info.get_width() / CHARACTER_SIZE,
+                    mode_info.get_height() / CHARACTER_SIZE,
+                )
+            }
+            (None, None) => (0, 0),
+        };
+        self.columns = columns;
+        self.rows = rows;
+        self.redraw();
+    }
+
+    /// Writes `byte` at the end of the console, moving to the next line on
+    /// `b'\n'` and scrolling when needed. The console is laid out again
+    /// first if the video mode changed since it was last drawn.
+    pub fn write_byte(&mut self, byte: u8) {
+        let mode_generation = VGA.lock().get_mode_generation();
+        if self.mode_generation != Some(mode_generation) {
+            self.reflow();
+        }
+
+        let character = ScreenCharacter::new(byte, self.color);
+        self.scrollback.push(character);
+        if self.columns == 0 {
+            return;
+        }
+        let position = advance(&mut self.x, &mut self.y, self.columns, character);
+        if self.y >= self.top_row + self.rows {
+            self.redraw();
+        } else {
+            if let Some((x, y)) = position {
+                self.draw_cell(x, y - self.top_row, character);
+            }
+            self.update_cursor();
+        }
+    }
+
+    /// Lays out the scrollback in the current number of columns, and
+    /// draws the rows that fit on the screen.
+    fn redraw(&mut self) {
+        if self.columns == 0 {
+            return;
+        }
+        let row_count = self.scrollback.row_count(self.columns);
+        self.top_row = row_count.saturating_sub(self.rows);
+
+        if let Some(writer) = self.text_writer() {
+            let blank = writer.get_blank_character().get_character();
+            writer.fill_screen(ScreenCharacter::new(blank, self.color));
+        } else if let Some(writer) = self.graphics_writer() {
+            writer.clear_screen(self.graphics_color(self.color.get_background()));
+        }
+
+        let mut rows = self.scrollback.rows(self.columns);
+        for (x, y, character) in rows.by_ref() {
+            if y >= self.top_row {
+                self.draw_cell(x, y - self.top_row, character);
+            }
+        }
+        let (x, y) = rows.get_position();
+        self.x = x;
+        self.y = y;
+        self.update_cursor();
+    }
+
+    /// Draws `character` in the cell at `(x, y)` of the screen.
+    fn draw_cell(&self, x: usize, y: usize, character: ScreenCharacter) {
+        if let Some(writer) = self.text_writer() {
+            writer.write_character(x, y, character);
+        } else if let Some(writer) = self.graphics_writer() {
+            let (x, y) = (x * CHARACTER_SIZE, y * CHARACTER_SIZE);
+            let color = character.get_color();
+            writer.draw_rect(
+                (x, y),
+                (x + CHARACTER_SIZE, y + CHARACTER_SIZE),
+                self.graphics_color(color.get_background()),
+            );
+            writer.draw_character(
+                x,
+                y,
+                character.get_character() as char,
+                self.graphics_color(color.get_foreground()),
+            );
+        }
+    }
+
+    /// Moves the text cursor to where the next character is written.
+    fn update_cursor(&self) {
+        if let Some(writer) = self.text_writer() {
+            let x = self.x.min(self.columns - 1);
+            writer.set_cursor_position(x, self.y - self.top_row);
+        }
+    }
+
+    /// Returns the color `color` is drawn in by the graphics writer.
+    fn graphics_color(&self, color: Color16) -> u32 {
+        match self.graphics_writer() {
+            Some(writer) if writer.mode_info().get_bits_per_pixel() == 32 => {
+                let (red, green, blue) = color.to_rgb();
+                u32::from(red) << 16 | u32::from(green) << 8 | u32::from(blue)
+            }
+            _ => u32::from(u8::from(color)),
+        }
+    }
+
+    fn text_writer(&self) -> Option<&'static dyn AnyTextWriter> {
+        self.video_mode.and_then(text_writer)
+    }
+
+    fn graphics_writer(&self) -> Option<&'static dyn AnyGraphicsWriter> {
+        self.video_mode.and_then(graphics_writer)
+    }
+}
+
+impl<const N: usize> fmt::Write for ReflowConsole<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            match byte {
+                // Printable ascii or newline
+                0x20..=0x7E | b'\n' => self.write_byte(byte),
+                // Not part of the printable ascii range
+                _ => self.write_byte(0xFE),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Scrollback;
+    #[cfg(all(feature = "text-modes", feature = "graphics-modes"))]
+    use super::{ReflowConsole, CHARACTER_SIZE};
+    use crate::{
+        colors::{Color16, TextModeColor},
+        writers::ScreenCharacter,
+    };
+    #[cfg(all(feature = "text-modes", feature = "graphics-modes"))]
+    use crate::{
+        mock::{lock_for_test, video_memory},
+        writers::{Graphics320x200x256, GraphicsWriter, Text40x25, Text80x25, TextWriter},
+    };
+    #[cfg(all(feature = "text-modes", feature = "graphics-modes"))]
+    use core::fmt::Write;
+
+    #[test]
+    fn test_scrollback_rows() {
+        let color = TextModeColor::new(Color16::White, Color16::Black);
+        let mut scrollback: Scrollback<8> = Scrollback::new();
+        for byte in b"abcd\nefghij" {
+            scrollback.push(ScreenCharacter::new(*byte, color));
+        }
+        assert_eq!(scrollback.len(), 8);
+        let characters: [u8; 8] =
+            core::array::from_fn(|i| scrollback.get(i).unwrap().get_character());
+        assert_eq!(&characters, b"d\nefghij");
+
+        let mut rows = scrollback.rows(3);
+        let positions: [(usize, usize); 7] = core::array::from_fn(|_| {
+            let (x, y, _) = rows.next().unwrap();
+            (x, y)
+        });
+        assert_eq!(
+            positions,
+            [(0, 0), (0, 1), (1, 1), (2, 1), (0, 2), (1, 2), (2, 2)]
+        );
+        assert_eq!(rows.next(), None);
+        assert_eq!(rows.get_position(), (3, 2));
+        assert_eq!(scrollback.row_count(3), 3);
+        assert_eq!(scrollback.row_count(80), 2);
+    }
+
+    #[test]
+    #[cfg(all(feature = "text-modes", feature = "graphics-modes"))]
+    fn test_reflow_on_mode_change() {
+        let _guard = lock_for_test();
+        let color = TextModeColor::new(Color16::White, Color16::Blue);
+        let mut console: ReflowConsole<256> = ReflowConsole::new(color);
+
+        let text_80x25 = Text80x25::new();
+        text_80x25.set_mode();
+        write!(console, "{:a<50}\nend", "").unwrap();
+        assert_eq!(console.get_size(), (80, 25));
+        assert_eq!(text_80x25.read_character(49, 0).get_character(), b'a');
+        assert_eq!(text_80x25.read_character(50, 0).get_character(), b' ');
+        assert_eq!(text_80x25.read_character(0, 1).get_character(), b'e');
+
+        // The 50 character line wraps onto a second row in 40 columns.
+        let text_40x25 = Text40x25::new();
+        text_40x25.set_mode();
+        write!(console, "!").unwrap();
+        assert_eq!(console.get_size(), (40, 25));
+        assert_eq!(text_40x25.read_character(39, 0).get_character(), b'a');
+        assert_eq!(text_40x25.read_character(9, 1).get_character(), b'a');
+        assert_eq!(text_40x25.read_character(10, 1).get_character(), b' ');
+        assert_eq!(
+            text_40x25.read_character(3, 2),
+            ScreenCharacter::new(b'!', color)
+        );
+        assert_eq!(text_40x25.get_cursor_position(), (4, 2));
+
+        // Only the last rows are shown once they don't fit on the screen.
+        write!(console, "{:\n<23}x", "").unwrap();
+        assert_eq!(text_40x25.read_character(0, 0).get_character(), b'a');
+        assert_eq!(text_40x25.read_character(10, 0).get_character(), b' ');
+        assert_eq!(text_40x25.read_character(0, 24).get_character(), b'x');
+
+        // Graphics modes draw the characters in 8x8 cells.
+        let graphics_mode = Graphics320x200x256::new();
+        graphics_mode.set_mode();
+        console.clear();
+        write!(console, "\n\u{7f}").unwrap();
+        assert_eq!(console.get_size(), (40, 25));
+        let cell = |x: usize, y: usize| {
+            (0..CHARACTER_SIZE).flat_map(move |row| {
+                let offset = (y * CHARACTER_SIZE + row) * 320 + x * CHARACTER_SIZE;
+                video_memory()[offset..offset + CHARACTER_SIZE].to_vec()
+            })
+        };
+        assert!(cell(0, 0).all(|pixel| pixel == Color16::Blue as u8));
+        assert!(cell(0, 1).any(|pixel| pixel == Color16::White as u8));
+        assert!(cell(1, 1).all(|pixel| pixel == Color16::Blue as u8));
+
+        text_80x25.set_mode();
+    }
+}
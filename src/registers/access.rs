@@ -0,0 +1,102 @@
+use super::Port;
+use core::ptr::{read_volatile, write_volatile};
+
+const MMIO_WINDOW_START: u16 = 0x3C0;
+const MMIO_WINDOW_END: u16 = 0x3DF;
+
+/// Represents how the vga registers are accessed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum RegisterAccess {
+    /// Represents the legacy io ports `0x3B4` through `0x3DA`.
+    ///
+    /// **Note:** Io ports only exist on x86. On other architectures,
+    /// such as aarch64 and riscv64, accessing a register this way panics,
+    /// so `RegisterAccess::Mmio` must be used instead.
+    #[default]
+    Port,
+    /// Represents a memory mapped window of the io ports `0x3C0` through
+    /// `0x3DF`, starting at the given virtual address, as exposed in a bar
+    /// by some pci vga compatible cards.
+    ///
+    /// **Note:** The monochrome ports at `0x3B4` through `0x3BA` aren't part
+    /// of the window, so only color emulation is supported. Reads from them
+    /// return `0xFF` and writes are ignored.
+    Mmio(usize),
+}
+
+/// A single vga register port, accessed as specified by a `RegisterAccess`.
+#[derive(Debug)]
+pub(crate) struct RegisterPort {
+    port: Port<u8>,
+    address: u16,
+    access: RegisterAccess,
+}
+
+impl RegisterPort {
+    pub(crate) const fn new(address: u16, access: RegisterAccess) -> RegisterPort {
+        RegisterPort {
+            port: Port::new(address),
+            address,
+            access,
+        }
+    }
+
+    pub(crate) unsafe fn read(&mut self) -> u8 {
+        match self.access {
+            RegisterAccess::Port => self.port.read(),
+            RegisterAccess::Mmio(base) => match self.mmio_address(base) {
+                Some(address) => read_volatile(address as *const u8),
+                None => 0xFF,
+            },
+        }
+    }
+
+    pub(crate) unsafe fn write(&mut self, value: u8) {
+        match self.access {
+            RegisterAccess::Port => self.port.write(value),
+            RegisterAccess::Mmio(base) => {
+                if let Some(address) = self.mmio_address(base) {
+                    write_volatile(address as *mut u8, value);
+                }
+            }
+        }
+    }
+
+    fn mmio_address(&self, base: usize) -> Option<usize> {
+        match self.address {
+            MMIO_WINDOW_START..=MMIO_WINDOW_END => {
+                Some(base + usize::from(self.address - MMIO_WINDOW_START))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::registers::{
+        CrtcControllerIndex, CrtcControllerRegisters, EmulationMode, SequencerIndex,
+        SequencerRegisters,
+    };
+
+    #[test]
+    fn test_mmio_access() {
+        let mut window = [0u8; 0x20];
+        let access = RegisterAccess::Mmio(window.as_mut_ptr() as usize);
+
+        let mut sequencer = SequencerRegisters::new(access);
+        sequencer.write(SequencerIndex::MemoryMode, 0x06);
+        assert_eq!(window[0x04], u8::from(SequencerIndex::MemoryMode));
+        assert_eq!(window[0x05], 0x06);
+
+        let mut crtc = CrtcControllerRegisters::new(access);
+        crtc.write(EmulationMode::Cga, CrtcControllerIndex::Offset, 0x28);
+        assert_eq!(window[0x14], u8::from(CrtcControllerIndex::Offset));
+        assert_eq!(window[0x15], 0x28);
+        assert_eq!(
+            crtc.read(EmulationMode::Mda, CrtcControllerIndex::Offset),
+            0xFF
+        );
+    }
+}
@@ -2,10 +2,34 @@ use super::{
     EmulationMode, ARX_DATA_ADDRESS, ARX_INDEX_ADDRESS, ST01_READ_CGA_ADDRESS,
     ST01_READ_MDA_ADDRESS,
 };
-use x86_64::instructions::port::Port;
+use super::{RegisterAccess, RegisterPort};
+use bitflags::bitflags;
+
+bitflags! {
+    /// Represents the planes enabled by the
+    /// `AttributeControllerIndex::MemoryPlaneEnable` register, which are
+    /// the bits of each 4 bit pixel that are displayed.
+    ///
+    /// Disabled planes are still written to, so drawing into a disabled
+    /// plane and then enabling it reveals the whole drawing at once.
+    pub struct ColorPlaneEnable: u8 {
+        /// Represents none of the planes.
+        const NONE = 0b0000_0000;
+        /// Represents `Plane0` of vga memory.
+        const PLANE0 = 0b0000_0001;
+        /// Represents `Plane1` of vga memory.
+        const PLANE1 = 0b0000_0010;
+        /// Represents `Plane2` of vga memory.
+        const PLANE2 = 0b0000_0100;
+        /// Represents `Plane3` of vga memory.
+        const PLANE3 = 0b0000_1000;
+        /// Represents all of the planes.
+        const ALL_PLANES = Self::PLANE0.bits() | Self::PLANE1.bits() | Self::PLANE2.bits() | Self::PLANE3.bits();
+    }
+}
 
 /// Represents an index for the attribute controller registers.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum AttributeControllerIndex {
     /// Represents the `Palette 0` register index.
@@ -61,28 +85,26 @@ impl From<AttributeControllerIndex> for u8 {
 /// Represents the attribute controller registers on vga hardware.
 #[derive(Debug)]
 pub struct AttributeControllerRegisters {
-    arx_index: Port<u8>,
-    arx_data: Port<u8>,
-    st01_read_cga: Port<u8>,
-    st01_read_mda: Port<u8>,
+    arx_index: RegisterPort,
+    arx_data: RegisterPort,
+    st01_read_cga: RegisterPort,
+    st01_read_mda: RegisterPort,
 }
 
 impl AttributeControllerRegisters {
-    pub(crate) fn new() -> AttributeControllerRegisters {
+    pub(crate) fn new(access: RegisterAccess) -> AttributeControllerRegisters {
         AttributeControllerRegisters {
-            arx_index: Port::new(ARX_INDEX_ADDRESS),
-            arx_data: Port::new(ARX_DATA_ADDRESS),
-            st01_read_cga: Port::new(ST01_READ_CGA_ADDRESS),
-            st01_read_mda: Port::new(ST01_READ_MDA_ADDRESS),
+            arx_index: RegisterPort::new(ARX_INDEX_ADDRESS, access),
+            arx_data: RegisterPort::new(ARX_DATA_ADDRESS, access),
+            st01_read_cga: RegisterPort::new(ST01_READ_CGA_ADDRESS, access),
+            st01_read_mda: RegisterPort::new(ST01_READ_MDA_ADDRESS, access),
         }
     }
 
     /// Reads the current value of the attribute controller, as specified
     /// by `emulation_mode` and `index`.
     pub fn read(&mut self, emulation_mode: EmulationMode, index: AttributeControllerIndex) -> u8 {
-        self.toggle_index(emulation_mode);
-        self.set_index(index);
-        unsafe { self.arx_data.read() }
+        unsafe { self.read_raw(emulation_mode, u8::from(index)) }
     }
 
     /// Writes the `value` to the attribute controller, as specified
@@ -93,13 +115,71 @@ impl AttributeControllerRegisters {
         index: AttributeControllerIndex,
         value: u8,
     ) {
-        self.toggle_index(emulation_mode);
-        self.set_index(index);
         unsafe {
-            self.arx_index.write(value);
+            self.write_raw(emulation_mode, u8::from(index), value);
         }
     }
 
+    /// Reads the current value of the attribute controller at the raw
+    /// `index`, as specified by `emulation_mode`, including extended
+    /// registers that `AttributeControllerIndex` doesn't cover.
+    ///
+    /// # Safety
+    ///
+    /// `index` is written to the index port as is, and bit 5 is the palette
+    /// address source, so the screen stays blank until it's set or
+    /// `unblank_screen` is called. The caller must ensure the register at
+    /// `index` exists on the current hardware and is safe to read.
+    pub unsafe fn read_raw(&mut self, emulation_mode: EmulationMode, index: u8) -> u8 {
+        self.toggle_index(emulation_mode);
+        self.arx_index.write(index);
+        self.arx_data.read()
+    }
+
+    /// Writes the `value` to the attribute controller at the raw `index`,
+    /// as specified by `emulation_mode`, including extended registers that
+    /// `AttributeControllerIndex` doesn't cover.
+    ///
+    /// # Safety
+    ///
+    /// `index` is written to the index port as is, and bit 5 is the palette
+    /// address source, so the screen stays blank until it's set or
+    /// `unblank_screen` is called. The caller must ensure the register at
+    /// `index` exists on the current hardware, and that nothing else
+    /// accesses the attribute controller between the index and data writes.
+    pub unsafe fn write_raw(&mut self, emulation_mode: EmulationMode, index: u8, value: u8) {
+        self.toggle_index(emulation_mode);
+        self.arx_index.write(index);
+        self.arx_index.write(value);
+    }
+
+    /// Returns the planes that are displayed, as specified by `emulation_mode`.
+    pub fn get_color_plane_enable(&mut self, emulation_mode: EmulationMode) -> ColorPlaneEnable {
+        let value = self.read(emulation_mode, AttributeControllerIndex::MemoryPlaneEnable);
+        self.unblank_screen(emulation_mode);
+        ColorPlaneEnable::from_bits_truncate(value)
+    }
+
+    /// Sets the planes that are displayed to `color_plane_enable`, as
+    /// specified by `emulation_mode`, keeping the video status mux bits.
+    ///
+    /// The pixels of a 16 color mode are displayed with the bits of the
+    /// disabled planes cleared.
+    pub fn set_color_plane_enable(
+        &mut self,
+        emulation_mode: EmulationMode,
+        color_plane_enable: ColorPlaneEnable,
+    ) {
+        let original_value =
+            self.read(emulation_mode, AttributeControllerIndex::MemoryPlaneEnable) & 0xF0;
+        self.write(
+            emulation_mode,
+            AttributeControllerIndex::MemoryPlaneEnable,
+            original_value | color_plane_enable.bits(),
+        );
+        self.unblank_screen(emulation_mode);
+    }
+
     /// Video Enable. Note that In the VGA standard, this is called the "Palette Address Source" bit.
     /// Clearing this bit will cause the VGA display data to become all 00 index values. For the default
     /// palette, this will cause a black screen. The video timing signals continue. Another control bit will
@@ -132,12 +212,6 @@ impl AttributeControllerRegisters {
         }
     }
 
-    fn set_index(&mut self, index: AttributeControllerIndex) {
-        unsafe {
-            self.arx_index.write(u8::from(index));
-        }
-    }
-
     fn toggle_index(&mut self, emulation_mode: EmulationMode) {
         let st01_read = match emulation_mode {
             EmulationMode::Cga => &mut self.st01_read_cga,
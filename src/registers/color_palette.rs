@@ -1,49 +1,198 @@
+use super::{RegisterAccess, RegisterPort};
 use super::{
     COLOR_PALETTE_DATA_ADDRESS, COLOR_PALETTE_INDEX_READ_ADDRESS,
-    COLOR_PALETTE_INDEX_WRITE_ADDRESSS, PALETTE_SIZE,
+    COLOR_PALETTE_INDEX_WRITE_ADDRESSS,
 };
-use x86_64::instructions::port::Port;
+use crate::colors::Palette;
 
 /// Represents the color palette registers on vga hardware.
 #[derive(Debug)]
 pub struct ColorPaletteRegisters {
-    data_port: Port<u8>,
-    index_read_port: Port<u8>,
-    index_write_port: Port<u8>,
+    data_port: RegisterPort,
+    index_read_port: RegisterPort,
+    index_write_port: RegisterPort,
 }
 
 impl ColorPaletteRegisters {
-    pub(crate) fn new() -> ColorPaletteRegisters {
+    pub(crate) fn new(access: RegisterAccess) -> ColorPaletteRegisters {
         ColorPaletteRegisters {
-            data_port: Port::new(COLOR_PALETTE_DATA_ADDRESS),
-            index_read_port: Port::new(COLOR_PALETTE_INDEX_READ_ADDRESS),
-            index_write_port: Port::new(COLOR_PALETTE_INDEX_WRITE_ADDRESSS),
+            data_port: RegisterPort::new(COLOR_PALETTE_DATA_ADDRESS, access),
+            index_read_port: RegisterPort::new(COLOR_PALETTE_INDEX_READ_ADDRESS, access),
+            index_write_port: RegisterPort::new(COLOR_PALETTE_INDEX_WRITE_ADDRESSS, access),
         }
     }
 
-    /// Loads a 256 color palette, as specified by `palette`, with every 3
-    /// bytes representing a color.
-    pub fn load_palette(&mut self, palette: &[u8; PALETTE_SIZE]) {
+    /// Loads `palette` into the first `N` colors of the dac, such as a
+    /// 16 color palette for 16 color modes or a 256 color palette for
+    /// 256 color modes.
+    pub fn load_palette<const N: usize>(&mut self, palette: &Palette<N>) {
+        self.write_colors(0, palette.get_colors());
+    }
+
+    /// Loads `palette` like `load_palette`, with each 6 bit component `c`
+    /// corrected to `63 * (c / 63) ^ (1 / gamma)`.
+    ///
+    /// A `gamma` above 1.0 brightens the mid tones, and a `gamma` below
+    /// 1.0 darkens them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gamma` isn't a positive finite number.
+    pub fn load_palette_gamma<const N: usize>(&mut self, palette: &Palette<N>, gamma: f32) {
+        assert!(
+            gamma.is_finite() && gamma > 0.0,
+            "gamma must be a positive finite number"
+        );
+        let mut gamma_table = [0u8; 64];
+        for (value, corrected) in gamma_table.iter_mut().enumerate() {
+            *corrected = (pow(value as f32 / 63.0, 1.0 / gamma) * 63.0 + 0.5) as u8;
+        }
+
         unsafe {
             self.index_write_port.write(0);
         }
-        for i in palette.iter() {
+        for (red, green, blue) in palette.get_colors().iter() {
+            for component in [red, green, blue] {
+                unsafe {
+                    self.data_port
+                        .write(gamma_table[(component & 0x3F) as usize]);
+                }
+            }
+        }
+    }
+
+    /// Sets the color at `index` to the given 6 bit `red`, `green` and `blue` values.
+    pub fn write_color(&mut self, index: u8, red: u8, green: u8, blue: u8) {
+        unsafe {
+            self.index_write_port.write(index);
+            self.data_port.write(red);
+            self.data_port.write(green);
+            self.data_port.write(blue);
+        }
+    }
+
+    /// Sets the colors starting at `start_index` to the given 6 bit
+    /// `colors`, wrapping back to color 0 after color 255.
+    pub fn write_colors(&mut self, start_index: u8, colors: &[(u8, u8, u8)]) {
+        unsafe {
+            self.index_write_port.write(start_index);
+        }
+        for (red, green, blue) in colors.iter() {
             unsafe {
-                self.data_port.write(*i);
+                self.data_port.write(*red);
+                self.data_port.write(*green);
+                self.data_port.write(*blue);
             }
         }
     }
 
-    /// Reads the current 256 color palette into `palette`, with every 3
-    /// bytes representing a color.
-    pub fn read_palette(&mut self, palette: &mut [u8; PALETTE_SIZE]) {
+    /// Returns the 6 bit `(red, green, blue)` values of the color at `index`.
+    pub fn read_color(&mut self, index: u8) -> (u8, u8, u8) {
+        unsafe {
+            self.index_read_port.write(index);
+            (
+                self.data_port.read(),
+                self.data_port.read(),
+                self.data_port.read(),
+            )
+        }
+    }
+
+    /// Reads the first `N` colors of the dac into `palette`.
+    pub fn read_palette<const N: usize>(&mut self, palette: &mut Palette<N>) {
         unsafe {
             self.index_read_port.write(0);
         }
-        for byte in palette.iter_mut().take(PALETTE_SIZE) {
+        for index in 0..N {
             unsafe {
-                *byte = self.data_port.read();
+                palette.set_color(
+                    index,
+                    self.data_port.read(),
+                    self.data_port.read(),
+                    self.data_port.read(),
+                );
             }
         }
     }
 }
+
+/// Returns `base` raised to `exponent`, for `base` between 0.0 and 1.0.
+///
+/// `core` doesn't provide `powf`, so this computes `exp(exponent * ln(base))`
+/// with short series, which is accurate enough for 6 bit colors.
+fn pow(base: f32, exponent: f32) -> f32 {
+    if base <= 0.0 {
+        return 0.0;
+    }
+
+    // ln(base) = e * ln(2) + ln(m), with base = m * 2^e and m in [1, 2).
+    let bits = base.to_bits();
+    let e = ((bits >> 23) & 0xFF) as i32 - 127;
+    let m = f32::from_bits((bits & 0x007F_FFFF) | (127 << 23));
+    let z = (m - 1.0) / (m + 1.0);
+    let mut ln_m = 0.0;
+    let mut term = z;
+    for n in 0..8 {
+        ln_m += term / (2 * n + 1) as f32;
+        term *= z * z;
+    }
+    let y = exponent * (e as f32 * core::f32::consts::LN_2 + 2.0 * ln_m);
+
+    // exp(y) = 2^k * exp(r), with y = k * ln(2) + r.
+    let k = (y / core::f32::consts::LN_2) as i32 - 1;
+    if k < -126 {
+        return 0.0;
+    }
+    let r = y - k as f32 * core::f32::consts::LN_2;
+    let mut exp_r = 1.0;
+    let mut term = 1.0;
+    for n in 1..12 {
+        term *= r / n as f32;
+        exp_r += term;
+    }
+    exp_r * f32::from_bits(((k + 127) as u32) << 23)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::{lock_for_test, MOCK_HARDWARE};
+
+    #[test]
+    fn test_pow() {
+        assert!((pow(0.25, 0.5) - 0.5).abs() < 1e-5);
+        assert!((pow(0.5, 2.2) - 0.217_637_64).abs() < 1e-5);
+        assert!((pow(1.0, 3.0) - 1.0).abs() < 1e-5);
+        assert_eq!(pow(0.0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn test_read_and_write_color() {
+        let _guard = lock_for_test();
+        let mut registers = ColorPaletteRegisters::new(RegisterAccess::Port);
+        let color = registers.read_color(0x10);
+
+        registers.write_color(0x10, 0x3F, 0x20, 0x01);
+        assert_eq!(registers.read_color(0x10), (0x3F, 0x20, 0x01));
+        assert_eq!(
+            MOCK_HARDWARE.lock().palette().get_color(0x10),
+            (0x3F, 0x20, 0x01)
+        );
+        registers.write_color(0x10, color.0, color.1, color.2);
+    }
+
+    #[test]
+    fn test_load_palette_gamma() {
+        let _guard = lock_for_test();
+        let palette = Palette::new([(0, 16, 32), (63, 0, 0)]);
+        let mut registers = ColorPaletteRegisters::new(RegisterAccess::Port);
+
+        registers.load_palette_gamma(&palette, 1.0);
+        assert_eq!(MOCK_HARDWARE.lock().palette().get_color(0), (0, 16, 32));
+        assert_eq!(MOCK_HARDWARE.lock().palette().get_color(1), (63, 0, 0));
+
+        registers.load_palette_gamma(&palette, 2.2);
+        assert_eq!(MOCK_HARDWARE.lock().palette().get_color(0), (0, 34, 46));
+        assert_eq!(MOCK_HARDWARE.lock().palette().get_color(1), (63, 0, 0));
+    }
+}
@@ -2,10 +2,10 @@ use super::{
     EmulationMode, CRX_DATA_CGA_ADDRESS, CRX_DATA_MDA_ADDRESS, CRX_INDEX_CGA_ADDRESS,
     CRX_INDEX_MDA_ADDRESS,
 };
-use x86_64::instructions::port::Port;
+use super::{RegisterAccess, RegisterPort};
 
 /// Represents an index for the crtc controller registers.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum CrtcControllerIndex {
     /// Represents the `Horizontal Total` register index.
@@ -73,52 +73,75 @@ impl From<CrtcControllerIndex> for u8 {
 /// Represents the crtc controller registers on vga hardware.
 #[derive(Debug)]
 pub struct CrtcControllerRegisters {
-    crx_index_cga: Port<u8>,
-    crx_index_mda: Port<u8>,
-    crx_data_cga: Port<u8>,
-    crx_data_mda: Port<u8>,
+    crx_index_cga: RegisterPort,
+    crx_index_mda: RegisterPort,
+    crx_data_cga: RegisterPort,
+    crx_data_mda: RegisterPort,
 }
 
 impl CrtcControllerRegisters {
-    pub(crate) fn new() -> CrtcControllerRegisters {
+    pub(crate) fn new(access: RegisterAccess) -> CrtcControllerRegisters {
         CrtcControllerRegisters {
-            crx_index_cga: Port::new(CRX_INDEX_CGA_ADDRESS),
-            crx_index_mda: Port::new(CRX_INDEX_MDA_ADDRESS),
-            crx_data_cga: Port::new(CRX_DATA_CGA_ADDRESS),
-            crx_data_mda: Port::new(CRX_DATA_MDA_ADDRESS),
+            crx_index_cga: RegisterPort::new(CRX_INDEX_CGA_ADDRESS, access),
+            crx_index_mda: RegisterPort::new(CRX_INDEX_MDA_ADDRESS, access),
+            crx_data_cga: RegisterPort::new(CRX_DATA_CGA_ADDRESS, access),
+            crx_data_mda: RegisterPort::new(CRX_DATA_MDA_ADDRESS, access),
         }
     }
 
     /// Reads the current value from the crtc controller, as specified
     /// by `emulation_mode` and `index`.
     pub fn read(&mut self, emulation_mode: EmulationMode, index: CrtcControllerIndex) -> u8 {
-        self.set_index(emulation_mode, index);
-        unsafe { self.get_data_port(emulation_mode).read() }
+        unsafe { self.read_raw(emulation_mode, u8::from(index)) }
     }
 
     /// Writes the `value` to the crtc_controller, as specified
     /// by `emulation_mode` and `index`.
     pub fn write(&mut self, emulation_mode: EmulationMode, index: CrtcControllerIndex, value: u8) {
-        self.set_index(emulation_mode, index);
         unsafe {
-            self.get_data_port(emulation_mode).write(value);
+            self.write_raw(emulation_mode, u8::from(index), value);
         }
     }
 
-    fn set_index(&mut self, emulation_mode: EmulationMode, index: CrtcControllerIndex) {
-        unsafe {
-            self.get_index_port(emulation_mode).write(u8::from(index));
-        }
+    /// Reads the current value from the crtc controller at the raw `index`,
+    /// as specified by `emulation_mode`, including extended registers that
+    /// `CrtcControllerIndex` doesn't cover.
+    ///
+    /// # Safety
+    ///
+    /// `index` may address vendor specific extension registers, such as the
+    /// ones starting at `0x25` on tseng labs and s3 cards, and reading some
+    /// of them has side effects. The caller must ensure the register at
+    /// `index` exists on the current hardware and is safe to read.
+    pub unsafe fn read_raw(&mut self, emulation_mode: EmulationMode, index: u8) -> u8 {
+        self.get_index_port(emulation_mode).write(index);
+        self.get_data_port(emulation_mode).read()
+    }
+
+    /// Writes the `value` to the crtc controller at the raw `index`, as
+    /// specified by `emulation_mode`, including extended registers that
+    /// `CrtcControllerIndex` doesn't cover.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure the register at `index` exists on the current
+    /// hardware, and that `value` doesn't produce timings the attached
+    /// monitor can't handle. Registers `0x00` through `0x07` are ignored
+    /// while bit 7 of `CrtcControllerIndex::VerticalSyncEnd` is set, and
+    /// `Vga` sets that bit again after changing the timings.
+    pub unsafe fn write_raw(&mut self, emulation_mode: EmulationMode, index: u8, value: u8) {
+        self.get_index_port(emulation_mode).write(index);
+        self.get_data_port(emulation_mode).write(value);
     }
 
-    fn get_data_port(&mut self, emulation_mode: EmulationMode) -> &mut Port<u8> {
+    fn get_data_port(&mut self, emulation_mode: EmulationMode) -> &mut RegisterPort {
         match emulation_mode {
             EmulationMode::Cga => &mut self.crx_data_cga,
             EmulationMode::Mda => &mut self.crx_data_mda,
         }
     }
 
-    fn get_index_port(&mut self, emulation_mode: EmulationMode) -> &mut Port<u8> {
+    fn get_index_port(&mut self, emulation_mode: EmulationMode) -> &mut RegisterPort {
         match emulation_mode {
             EmulationMode::Cga => &mut self.crx_index_cga,
             EmulationMode::Mda => &mut self.crx_index_mda,
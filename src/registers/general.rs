@@ -1,34 +1,101 @@
+use super::{EmulationMode, RegisterAccess, RegisterPort};
 use super::{
     FCR_CGA_WRITE_ADDRESS, FCR_MDA_WRITE_ADDRESS, FCR_READ_ADDRESS, MSR_READ_ADDRESS,
     MSR_WRITE_ADDRESS, ST00_READ_ADDRESS, ST01_READ_CGA_ADDRESS, ST01_READ_MDA_ADDRESS,
 };
-use x86_64::instructions::port::{PortReadOnly, PortWriteOnly};
 
 /// Represents the general registers on vga hardware.
-#[allow(dead_code)]
 #[derive(Debug)]
 pub struct GeneralRegisters {
-    st00_read: PortReadOnly<u8>,
-    st01_read_cga: PortReadOnly<u8>,
-    st01_read_mda: PortReadOnly<u8>,
-    fcr_read: PortReadOnly<u8>,
-    fcr_write_cga: PortWriteOnly<u8>,
-    fcr_write_mda: PortWriteOnly<u8>,
-    msr_read: PortReadOnly<u8>,
-    msr_write: PortWriteOnly<u8>,
+    st00_read: RegisterPort,
+    st01_read_cga: RegisterPort,
+    st01_read_mda: RegisterPort,
+    fcr_read: RegisterPort,
+    fcr_write_cga: RegisterPort,
+    fcr_write_mda: RegisterPort,
+    msr_read: RegisterPort,
+    msr_write: RegisterPort,
 }
 
 impl GeneralRegisters {
-    pub(crate) fn new() -> GeneralRegisters {
+    pub(crate) fn new(access: RegisterAccess) -> GeneralRegisters {
         GeneralRegisters {
-            st00_read: PortReadOnly::new(ST00_READ_ADDRESS),
-            st01_read_cga: PortReadOnly::new(ST01_READ_CGA_ADDRESS),
-            st01_read_mda: PortReadOnly::new(ST01_READ_MDA_ADDRESS),
-            fcr_read: PortReadOnly::new(FCR_READ_ADDRESS),
-            fcr_write_cga: PortWriteOnly::new(FCR_CGA_WRITE_ADDRESS),
-            fcr_write_mda: PortWriteOnly::new(FCR_MDA_WRITE_ADDRESS),
-            msr_read: PortReadOnly::new(MSR_READ_ADDRESS),
-            msr_write: PortWriteOnly::new(MSR_WRITE_ADDRESS),
+            st00_read: RegisterPort::new(ST00_READ_ADDRESS, access),
+            st01_read_cga: RegisterPort::new(ST01_READ_CGA_ADDRESS, access),
+            st01_read_mda: RegisterPort::new(ST01_READ_MDA_ADDRESS, access),
+            fcr_read: RegisterPort::new(FCR_READ_ADDRESS, access),
+            fcr_write_cga: RegisterPort::new(FCR_CGA_WRITE_ADDRESS, access),
+            fcr_write_mda: RegisterPort::new(FCR_MDA_WRITE_ADDRESS, access),
+            msr_read: RegisterPort::new(MSR_READ_ADDRESS, access),
+            msr_write: RegisterPort::new(MSR_WRITE_ADDRESS, access),
+        }
+    }
+
+    /// Reads the current value from input status register 0.
+    ///
+    /// Bit 7 is set while a vertical retrace interrupt is pending.
+    pub fn read_st00(&mut self) -> u8 {
+        unsafe { self.st00_read.read() }
+    }
+
+    /// Reads the current value from input status register 1, as specified
+    /// by `emulation_mode`.
+    ///
+    /// Bit 0 is set while the display is disabled for horizontal or vertical
+    /// blanking, and bit 3 is set during vertical retrace. Reading this register
+    /// also resets the attribute controller to expect an index.
+    pub fn read_st01(&mut self, emulation_mode: EmulationMode) -> u8 {
+        let st01_read = match emulation_mode {
+            EmulationMode::Cga => &mut self.st01_read_cga,
+            EmulationMode::Mda => &mut self.st01_read_mda,
+        };
+        unsafe { st01_read.read() }
+    }
+
+    /// Returns whether a vertical retrace interrupt is pending, as reported
+    /// by bit 7 of input status register 0.
+    pub fn is_vertical_interrupt_pending(&mut self) -> bool {
+        self.read_st00() & 0x80 != 0
+    }
+
+    /// Returns the switch sense bit of input status register 0, which reads
+    /// the monitor sense line compared against the dac output. It's set when
+    /// the dac output is above the sense threshold, such as when no monitor
+    /// is loading the outputs.
+    pub fn get_switch_sense(&mut self) -> bool {
+        self.read_st00() & 0x10 != 0
+    }
+
+    /// Returns whether the display is disabled for horizontal or vertical
+    /// blanking, as reported by bit 0 of input status register 1.
+    ///
+    /// Like `read_st01`, this resets the attribute controller to expect an index.
+    pub fn is_display_disabled(&mut self, emulation_mode: EmulationMode) -> bool {
+        self.read_st01(emulation_mode) & 0x01 != 0
+    }
+
+    /// Returns whether the display is in vertical retrace, as reported by
+    /// bit 3 of input status register 1.
+    ///
+    /// Like `read_st01`, this resets the attribute controller to expect an index.
+    pub fn is_vertical_retrace(&mut self, emulation_mode: EmulationMode) -> bool {
+        self.read_st01(emulation_mode) & 0x08 != 0
+    }
+
+    /// Reads the current value from the feature control register.
+    pub fn read_fcr(&mut self) -> u8 {
+        unsafe { self.fcr_read.read() }
+    }
+
+    /// Writes the `value` to the feature control register, as specified
+    /// by `emulation_mode`.
+    pub fn write_fcr(&mut self, emulation_mode: EmulationMode, value: u8) {
+        let fcr_write = match emulation_mode {
+            EmulationMode::Cga => &mut self.fcr_write_cga,
+            EmulationMode::Mda => &mut self.fcr_write_mda,
+        };
+        unsafe {
+            fcr_write.write(value);
         }
     }
 
@@ -1,6 +1,6 @@
-use super::{Color16, GRX_DATA_ADDRESS, GRX_INDEX_ADDRESS};
+use super::{Color16, PlaneMask, GRX_DATA_ADDRESS, GRX_INDEX_ADDRESS};
+use super::{RegisterAccess, RegisterPort};
 use core::convert::TryFrom;
-use x86_64::instructions::port::Port;
 
 /// Represents a plane for the `GraphicsControllerIndex::ReadPlaneSelect` register.
 #[allow(dead_code)]
@@ -37,8 +37,72 @@ impl From<ReadPlane> for u8 {
     }
 }
 
+/// Represents the part of the legacy window at `0xA0000` through `0xBFFFF`
+/// the vga responds to, as selected by the memory map bits of the
+/// `GraphicsControllerIndex::Miscellaneous` register.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MemoryMap {
+    /// Represents the 128K window from `0xA0000` through `0xBFFFF`.
+    Window128K = 0x0,
+    /// Represents the 64K window from `0xA0000` through `0xAFFFF`,
+    /// used by graphics modes.
+    Window64K = 0x1,
+    /// Represents the 32K window from `0xB0000` through `0xB7FFF`,
+    /// used by monochrome text modes.
+    Monochrome = 0x2,
+    /// Represents the 32K window from `0xB8000` through `0xBFFFF`,
+    /// used by color text modes.
+    Color = 0x3,
+}
+
+impl MemoryMap {
+    /// Returns the offset of the window from `0xA0000`.
+    pub const fn get_offset(self) -> usize {
+        match self {
+            MemoryMap::Window128K | MemoryMap::Window64K => 0x00000,
+            MemoryMap::Monochrome => 0x10000,
+            MemoryMap::Color => 0x18000,
+        }
+    }
+
+    /// Returns the size of the window in bytes.
+    pub const fn get_size(self) -> usize {
+        match self {
+            MemoryMap::Window128K => 0x20000,
+            MemoryMap::Window64K => 0x10000,
+            MemoryMap::Monochrome | MemoryMap::Color => 0x8000,
+        }
+    }
+
+    /// Returns the physical address the window starts at.
+    pub const fn get_physical_address(self) -> usize {
+        0xA0000 + self.get_offset()
+    }
+}
+
+impl TryFrom<u8> for MemoryMap {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(MemoryMap::Window128K),
+            1 => Ok(MemoryMap::Window64K),
+            2 => Ok(MemoryMap::Monochrome),
+            3 => Ok(MemoryMap::Color),
+            _ => Err("MemoryMap only accepts values between 0-3!"),
+        }
+    }
+}
+
+impl From<MemoryMap> for u8 {
+    fn from(value: MemoryMap) -> u8 {
+        value as u8
+    }
+}
+
 /// Represents an index for the graphics controller registers.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum GraphicsControllerIndex {
     /// Represents the `Set/Reset` register index.
@@ -74,7 +138,7 @@ impl From<GraphicsControllerIndex> for u8 {
 }
 
 /// Represents a write mode for vga hardware.
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum WriteMode {
     /// Represents `WriteMode` 0.
@@ -124,43 +188,247 @@ pub enum WriteMode {
     Mode3 = 0x3,
 }
 
+impl TryFrom<u8> for WriteMode {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(WriteMode::Mode0),
+            1 => Ok(WriteMode::Mode1),
+            2 => Ok(WriteMode::Mode2),
+            3 => Ok(WriteMode::Mode3),
+            _ => Err("WriteMode only accepts values between 0-3!"),
+        }
+    }
+}
+
 impl From<WriteMode> for u8 {
     fn from(value: WriteMode) -> u8 {
         value as u8
     }
 }
 
+/// Represents a read mode for vga hardware.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ReadMode {
+    /// Represents `ReadMode` 0.
+    ///
+    /// During a CPU read from the frame buffer, the addressed byte of the
+    /// plane selected by the `GraphicsControllerIndex::ReadPlaneSelect`
+    /// register is returned.
+    Mode0 = 0x0,
+    /// Represents `ReadMode` 1.
+    ///
+    /// During a CPU read from the frame buffer, each bit of the returned
+    /// byte is set if the color of the corresponding pixel matches the
+    /// `GraphicsControllerIndex::ColorCompare` register, ignoring the planes
+    /// cleared in the `GraphicsControllerIndex::ColorDontCare` register.
+    Mode1 = 0x1,
+}
+
+impl TryFrom<u8> for ReadMode {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ReadMode::Mode0),
+            1 => Ok(ReadMode::Mode1),
+            _ => Err("ReadMode only accepts values between 0-1!"),
+        }
+    }
+}
+
+impl From<ReadMode> for u8 {
+    fn from(value: ReadMode) -> u8 {
+        value as u8
+    }
+}
+
+/// Represents how the shift registers serialize video memory into pixels,
+/// as selected by the `GraphicsControllerIndex::GraphicsMode` register.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShiftRegisterMode {
+    /// Each plane supplies one bit of every pixel, as in 16 color modes.
+    Planar,
+    /// Even and odd bits of planes 0 and 2, then 1 and 3, are interleaved
+    /// into 2 bit pixels, as in cga compatible 4 color modes.
+    Interleaved,
+    /// Each byte supplies the upper and lower 4 bits of a 256 color pixel.
+    Color256,
+}
+
+/// A typed view of the `GraphicsControllerIndex::GraphicsMode` register,
+/// for saving and restoring the state around custom operations.
+///
+/// The reserved bits aren't represented, and are kept as they are when
+/// written with `GraphicsControllerRegisters::set_graphics_mode`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GraphicsMode {
+    write_mode: WriteMode,
+    read_mode: ReadMode,
+    host_odd_even: bool,
+    shift_register_mode: ShiftRegisterMode,
+}
+
+impl GraphicsMode {
+    /// Creates a new `GraphicsMode` with write mode 0, read mode 0, host
+    /// odd/even disabled and planar shift registers.
+    pub const fn new() -> GraphicsMode {
+        GraphicsMode {
+            write_mode: WriteMode::Mode0,
+            read_mode: ReadMode::Mode0,
+            host_odd_even: false,
+            shift_register_mode: ShiftRegisterMode::Planar,
+        }
+    }
+
+    /// Sets the `WriteMode`.
+    pub const fn with_write_mode(mut self, write_mode: WriteMode) -> GraphicsMode {
+        self.write_mode = write_mode;
+        self
+    }
+
+    /// Sets the `ReadMode`.
+    pub const fn with_read_mode(mut self, read_mode: ReadMode) -> GraphicsMode {
+        self.read_mode = read_mode;
+        self
+    }
+
+    /// Sets whether host odd/even addressing is enabled, which text modes
+    /// use to keep characters and attributes in planes 0 and 1.
+    pub const fn with_host_odd_even(mut self, host_odd_even: bool) -> GraphicsMode {
+        self.host_odd_even = host_odd_even;
+        self
+    }
+
+    /// Sets the `ShiftRegisterMode`.
+    pub const fn with_shift_register_mode(
+        mut self,
+        shift_register_mode: ShiftRegisterMode,
+    ) -> GraphicsMode {
+        self.shift_register_mode = shift_register_mode;
+        self
+    }
+
+    /// Returns the `WriteMode`.
+    pub const fn get_write_mode(&self) -> WriteMode {
+        self.write_mode
+    }
+
+    /// Returns the `ReadMode`.
+    pub const fn get_read_mode(&self) -> ReadMode {
+        self.read_mode
+    }
+
+    /// Returns whether host odd/even addressing is enabled.
+    pub const fn get_host_odd_even(&self) -> bool {
+        self.host_odd_even
+    }
+
+    /// Returns the `ShiftRegisterMode`.
+    pub const fn get_shift_register_mode(&self) -> ShiftRegisterMode {
+        self.shift_register_mode
+    }
+}
+
+impl Default for GraphicsMode {
+    fn default() -> GraphicsMode {
+        GraphicsMode::new()
+    }
+}
+
+impl From<u8> for GraphicsMode {
+    fn from(value: u8) -> GraphicsMode {
+        // The 256 color bit takes precedence over the interleaved bit.
+        let shift_register_mode = if value & 0x40 != 0 {
+            ShiftRegisterMode::Color256
+        } else if value & 0x20 != 0 {
+            ShiftRegisterMode::Interleaved
+        } else {
+            ShiftRegisterMode::Planar
+        };
+        GraphicsMode {
+            write_mode: WriteMode::try_from(value & 0x03).unwrap(),
+            read_mode: ReadMode::try_from((value >> 3) & 0x01).unwrap(),
+            host_odd_even: value & 0x10 != 0,
+            shift_register_mode,
+        }
+    }
+}
+
+impl From<GraphicsMode> for u8 {
+    fn from(value: GraphicsMode) -> u8 {
+        let shift_register_mode = match value.shift_register_mode {
+            ShiftRegisterMode::Planar => 0x00,
+            ShiftRegisterMode::Interleaved => 0x20,
+            ShiftRegisterMode::Color256 => 0x40,
+        };
+        u8::from(value.write_mode)
+            | u8::from(value.read_mode) << 3
+            | if value.host_odd_even { 0x10 } else { 0x00 }
+            | shift_register_mode
+    }
+}
+
 /// Represents the graphics controller registers on vga hardware.
 #[derive(Debug)]
 pub struct GraphicsControllerRegisters {
-    grx_index: Port<u8>,
-    grx_data: Port<u8>,
+    grx_index: RegisterPort,
+    grx_data: RegisterPort,
 }
 
 impl GraphicsControllerRegisters {
-    pub(crate) fn new() -> GraphicsControllerRegisters {
+    pub(crate) fn new(access: RegisterAccess) -> GraphicsControllerRegisters {
         GraphicsControllerRegisters {
-            grx_index: Port::new(GRX_INDEX_ADDRESS),
-            grx_data: Port::new(GRX_DATA_ADDRESS),
+            grx_index: RegisterPort::new(GRX_INDEX_ADDRESS, access),
+            grx_data: RegisterPort::new(GRX_DATA_ADDRESS, access),
         }
     }
 
     /// Reads the current value from the graphics controller, as specified
     /// by `index`.
     pub fn read(&mut self, index: GraphicsControllerIndex) -> u8 {
-        self.set_index(index);
-        unsafe { self.grx_data.read() }
+        unsafe { self.read_raw(u8::from(index)) }
     }
 
     /// Writes the `value` to the graphics controller, as specified
     /// by `index.
     pub fn write(&mut self, index: GraphicsControllerIndex, value: u8) {
-        self.set_index(index);
         unsafe {
-            self.grx_data.write(value);
+            self.write_raw(u8::from(index), value);
         }
     }
 
+    /// Reads the current value from the graphics controller at the raw
+    /// `index`, including extended registers that `GraphicsControllerIndex`
+    /// doesn't cover.
+    ///
+    /// # Safety
+    ///
+    /// `index` may address vendor specific extension registers, such as the
+    /// bank select registers at `0x09` and `0x0A` on cirrus logic cards. The
+    /// caller must ensure the register at `index` exists on the current
+    /// hardware and is safe to read.
+    pub unsafe fn read_raw(&mut self, index: u8) -> u8 {
+        self.grx_index.write(index);
+        self.grx_data.read()
+    }
+
+    /// Writes the `value` to the graphics controller at the raw `index`,
+    /// including extended registers that `GraphicsControllerIndex` doesn't
+    /// cover.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure the register at `index` exists on the current
+    /// hardware, and that `value` doesn't move the memory map or change the
+    /// read and write modes in a way the writers of this crate don't expect.
+    pub unsafe fn write_raw(&mut self, index: u8, value: u8) {
+        self.grx_index.write(index);
+        self.grx_data.write(value);
+    }
+
     /// Sets the read plane of the graphics controller, as specified by `read_plane`.
     pub fn write_read_plane(&mut self, read_plane: ReadPlane) {
         let read_plane = u8::from(read_plane) & 0x3;
@@ -196,15 +464,107 @@ impl GraphicsControllerRegisters {
         );
     }
 
+    /// Returns the color read mode 1 compares pixels against.
+    pub fn get_color_compare(&mut self) -> Color16 {
+        Color16::from_low_bits(self.read(GraphicsControllerIndex::ColorCompare))
+    }
+
+    /// Sets the color read mode 1 compares pixels against, as specified
+    /// by `color`.
+    pub fn set_color_compare(&mut self, color: Color16) {
+        let original_value = self.read(GraphicsControllerIndex::ColorCompare) & 0xF0;
+        self.write(
+            GraphicsControllerIndex::ColorCompare,
+            original_value | u8::from(color),
+        );
+    }
+
+    /// Returns the planes read mode 1 compares, which are the set bits of
+    /// the `GraphicsControllerIndex::ColorDontCare` register.
+    pub fn get_color_dont_care(&mut self) -> PlaneMask {
+        PlaneMask::from_bits_truncate(self.read(GraphicsControllerIndex::ColorDontCare))
+    }
+
+    /// Sets the planes read mode 1 compares, as specified by `planes`. The
+    /// bits of the other planes are ignored, so they match any color.
+    pub fn set_color_dont_care(&mut self, planes: PlaneMask) {
+        let original_value = self.read(GraphicsControllerIndex::ColorDontCare) & 0xF0;
+        self.write(
+            GraphicsControllerIndex::ColorDontCare,
+            original_value | planes.bits(),
+        );
+    }
+
+    /// Returns which mode the vga writes in.
+    pub fn get_write_mode(&mut self) -> WriteMode {
+        self.get_graphics_mode().get_write_mode()
+    }
+
+    /// Returns which mode the vga reads in.
+    pub fn get_read_mode(&mut self) -> ReadMode {
+        self.get_graphics_mode().get_read_mode()
+    }
+
+    /// Sets which mode the vga reads in, as specified by `read_mode`.
+    pub fn set_read_mode(&mut self, read_mode: ReadMode) {
+        let original_value = self.read(GraphicsControllerIndex::GraphicsMode) & 0xF7;
+        self.write(
+            GraphicsControllerIndex::GraphicsMode,
+            original_value | u8::from(read_mode) << 3,
+        );
+    }
+
+    /// Returns the `GraphicsMode` register as a `GraphicsMode`.
+    pub fn get_graphics_mode(&mut self) -> GraphicsMode {
+        GraphicsMode::from(self.read(GraphicsControllerIndex::GraphicsMode))
+    }
+
+    /// Sets the `GraphicsMode` register to `graphics_mode`, keeping its
+    /// reserved bits.
+    ///
+    /// # Examples
+    ///
+    /// Reading a plane in read mode 1 and restoring the previous state:
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "global")]
+    /// # {
+    /// use vga::registers::ReadMode;
+    /// use vga::vga::VGA;
+    ///
+    /// let mut vga = VGA.lock();
+    /// let graphics_mode = vga.graphics_controller_registers.get_graphics_mode();
+    /// vga.graphics_controller_registers.set_read_mode(ReadMode::Mode1);
+    /// // ...
+    /// vga.graphics_controller_registers.set_graphics_mode(graphics_mode);
+    /// # }
+    /// ```
+    pub fn set_graphics_mode(&mut self, graphics_mode: GraphicsMode) {
+        let original_value = self.read(GraphicsControllerIndex::GraphicsMode) & 0x84;
+        self.write(
+            GraphicsControllerIndex::GraphicsMode,
+            original_value | u8::from(graphics_mode),
+        );
+    }
+
+    /// Returns the `MemoryMap` selected by the `Miscellaneous` register.
+    pub fn get_memory_map(&mut self) -> MemoryMap {
+        let miscellaneous = self.read(GraphicsControllerIndex::Miscellaneous);
+        MemoryMap::try_from((miscellaneous >> 0x2) & 0x3).unwrap()
+    }
+
+    /// Selects `memory_map` in the `Miscellaneous` register, keeping its other bits.
+    pub fn set_memory_map(&mut self, memory_map: MemoryMap) {
+        let original_value = self.read(GraphicsControllerIndex::Miscellaneous) & 0xF3;
+        self.write(
+            GraphicsControllerIndex::Miscellaneous,
+            original_value | (u8::from(memory_map) << 0x2),
+        );
+    }
+
     /// Sets which bits are effected by certain operations, as specified
     /// by `bit_mask`.
     pub fn set_bit_mask(&mut self, bit_mask: u8) {
         self.write(GraphicsControllerIndex::BitMask, bit_mask);
     }
-
-    fn set_index(&mut self, index: GraphicsControllerIndex) {
-        unsafe {
-            self.grx_index.write(u8::from(index));
-        }
-    }
 }
@@ -1,47 +1,75 @@
 //! Common registers used in vga programming.
 
+mod access;
 mod attribute_controller;
 mod color_palette;
 mod crtc_controller;
 mod general;
 mod graphics_controller;
+#[cfg(not(any(test, feature = "test-backend")))]
+mod port;
 mod sequencer;
+mod snapshot;
 
-use crate::colors::{Color16, PALETTE_SIZE};
+use crate::colors::Color16;
 
-pub use attribute_controller::{AttributeControllerIndex, AttributeControllerRegisters};
+#[cfg(any(test, feature = "test-backend"))]
+pub(crate) use crate::mock::Port;
+#[cfg(not(any(test, feature = "test-backend")))]
+pub(crate) use port::Port;
+
+pub use access::RegisterAccess;
+pub(crate) use access::RegisterPort;
+pub use attribute_controller::{
+    AttributeControllerIndex, AttributeControllerRegisters, ColorPlaneEnable,
+};
 pub use color_palette::ColorPaletteRegisters;
 pub use crtc_controller::{CrtcControllerIndex, CrtcControllerRegisters};
 pub use general::GeneralRegisters;
-pub use graphics_controller::{GraphicsControllerIndex, GraphicsControllerRegisters, WriteMode};
-pub use sequencer::{PlaneMask, SequencerIndex, SequencerRegisters};
+pub use graphics_controller::{
+    GraphicsControllerIndex, GraphicsControllerRegisters, GraphicsMode, MemoryMap, ReadMode,
+    ReadPlane, ShiftRegisterMode, WriteMode,
+};
+pub use sequencer::{CharacterMap, PlaneMask, SequencerIndex, SequencerRegisters};
+pub use snapshot::{Register, RegisterChange, RegisterSnapshot};
+
+pub(crate) const ST00_READ_ADDRESS: u16 = 0x3C2;
+pub(crate) const ST01_READ_CGA_ADDRESS: u16 = 0x3DA;
+pub(crate) const ST01_READ_MDA_ADDRESS: u16 = 0x3BA;
+pub(crate) const FCR_READ_ADDRESS: u16 = 0x3CA;
+pub(crate) const FCR_CGA_WRITE_ADDRESS: u16 = 0x3DA;
+pub(crate) const FCR_MDA_WRITE_ADDRESS: u16 = 0x3BA;
+pub(crate) const MSR_READ_ADDRESS: u16 = 0x3CC;
+pub(crate) const MSR_WRITE_ADDRESS: u16 = 0x3C2;
 
-const ST00_READ_ADDRESS: u16 = 0x3C2;
-const ST01_READ_CGA_ADDRESS: u16 = 0x3DA;
-const ST01_READ_MDA_ADDRESS: u16 = 0x3BA;
-const FCR_READ_ADDRESS: u16 = 0x3CA;
-const FCR_CGA_WRITE_ADDRESS: u16 = 0x3DA;
-const FCR_MDA_WRITE_ADDRESS: u16 = 0x3BA;
-const MSR_READ_ADDRESS: u16 = 0x3CC;
-const MSR_WRITE_ADDRESS: u16 = 0x3C2;
+pub(crate) const SRX_INDEX_ADDRESS: u16 = 0x3C4;
+pub(crate) const SRX_DATA_ADDRESS: u16 = 0x3C5;
 
-const SRX_INDEX_ADDRESS: u16 = 0x3C4;
-const SRX_DATA_ADDRESS: u16 = 0x3C5;
+pub(crate) const GRX_INDEX_ADDRESS: u16 = 0x3CE;
+pub(crate) const GRX_DATA_ADDRESS: u16 = 0x3CF;
 
-const GRX_INDEX_ADDRESS: u16 = 0x3CE;
-const GRX_DATA_ADDRESS: u16 = 0x3CF;
+pub(crate) const ARX_INDEX_ADDRESS: u16 = 0x3C0;
+pub(crate) const ARX_DATA_ADDRESS: u16 = 0x3C1;
 
-const ARX_INDEX_ADDRESS: u16 = 0x3C0;
-const ARX_DATA_ADDRESS: u16 = 0x3C1;
+pub(crate) const CRX_INDEX_CGA_ADDRESS: u16 = 0x3D4;
+pub(crate) const CRX_INDEX_MDA_ADDRESS: u16 = 0x3B4;
+pub(crate) const CRX_DATA_CGA_ADDRESS: u16 = 0x3D5;
+pub(crate) const CRX_DATA_MDA_ADDRESS: u16 = 0x3B5;
 
-const CRX_INDEX_CGA_ADDRESS: u16 = 0x3D4;
-const CRX_INDEX_MDA_ADDRESS: u16 = 0x3B4;
-const CRX_DATA_CGA_ADDRESS: u16 = 0x3D5;
-const CRX_DATA_MDA_ADDRESS: u16 = 0x3B5;
+#[cfg(any(test, feature = "test-backend", feature = "emulator"))]
+pub(crate) const SEQUENCER_REGISTER_COUNT: usize = 0x08;
+#[cfg(any(test, feature = "test-backend", feature = "emulator"))]
+pub(crate) const GRAPHICS_CONTROLLER_REGISTER_COUNT: usize = 0x19;
+#[cfg(any(test, feature = "test-backend", feature = "emulator"))]
+pub(crate) const CRTC_CONTROLLER_REGISTER_COUNT: usize = 0x25;
+#[cfg(any(test, feature = "test-backend", feature = "emulator"))]
+pub(crate) const ATTRIBUTE_CONTROLLER_REGISTER_COUNT: usize = 0x15;
 
-const COLOR_PALETTE_DATA_ADDRESS: u16 = 0x3C9;
-const COLOR_PALETTE_INDEX_READ_ADDRESS: u16 = 0x3C7;
-const COLOR_PALETTE_INDEX_WRITE_ADDRESSS: u16 = 0x3C8;
+#[cfg(feature = "cirrus-device")]
+pub(crate) const COLOR_PALETTE_MASK_ADDRESS: u16 = 0x3C6;
+pub(crate) const COLOR_PALETTE_DATA_ADDRESS: u16 = 0x3C9;
+pub(crate) const COLOR_PALETTE_INDEX_READ_ADDRESS: u16 = 0x3C7;
+pub(crate) const COLOR_PALETTE_INDEX_WRITE_ADDRESSS: u16 = 0x3C8;
 
 /// Represents a vga emulation mode.
 #[derive(Debug, Copy, Clone)]
@@ -0,0 +1,63 @@
+use core::marker::PhantomData;
+
+/// An io port, read and written with the `in` and `out` instructions.
+///
+/// On architectures without io ports, accessing a `Port` panics.
+#[derive(Debug)]
+pub(crate) struct Port<T> {
+    #[cfg_attr(
+        not(any(target_arch = "x86", target_arch = "x86_64")),
+        allow(dead_code)
+    )]
+    port: u16,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Port<T> {
+    pub(crate) const fn new(port: u16) -> Port<T> {
+        Port {
+            port,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Port<u8> {
+    pub(crate) unsafe fn read(&mut self) -> u8 {
+        let value: u8;
+        core::arch::asm!("in al, dx", out("al") value, in("dx") self.port, options(nomem, nostack, preserves_flags));
+        value
+    }
+
+    pub(crate) unsafe fn write(&mut self, value: u8) {
+        core::arch::asm!("out dx, al", in("dx") self.port, in("al") value, options(nomem, nostack, preserves_flags));
+    }
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    feature = "bochs-device"
+))]
+impl Port<u16> {
+    pub(crate) unsafe fn read(&mut self) -> u16 {
+        let value: u16;
+        core::arch::asm!("in ax, dx", out("ax") value, in("dx") self.port, options(nomem, nostack, preserves_flags));
+        value
+    }
+
+    pub(crate) unsafe fn write(&mut self, value: u16) {
+        core::arch::asm!("out dx, ax", in("dx") self.port, in("ax") value, options(nomem, nostack, preserves_flags));
+    }
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+impl<T> Port<T> {
+    pub(crate) unsafe fn read(&mut self) -> T {
+        panic!("io ports are only available on x86, use RegisterAccess::Mmio instead")
+    }
+
+    pub(crate) unsafe fn write(&mut self, _value: T) {
+        panic!("io ports are only available on x86, use RegisterAccess::Mmio instead")
+    }
+}
@@ -1,7 +1,7 @@
+use super::{RegisterAccess, RegisterPort};
 use super::{SRX_DATA_ADDRESS, SRX_INDEX_ADDRESS};
 use bitflags::bitflags;
 use core::convert::TryFrom;
-use x86_64::instructions::port::Port;
 
 bitflags! {
     /// Represents the plane masks of the `SequencerIndex::PlaneMask` register.
@@ -41,8 +41,88 @@ impl From<PlaneMask> for u8 {
     }
 }
 
+/// Represents one of the 8 character maps in plane 2 that text modes
+/// read glyphs from, as selected by the `SequencerIndex::CharacterFont`
+/// register.
+///
+/// Each map holds 256 characters of 32 bytes, but maps 4 through 7
+/// are placed between maps 0 through 3.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CharacterMap {
+    /// Represents the character map at offset `0x0000` of plane 2.
+    Map0 = 0x0,
+    /// Represents the character map at offset `0x4000` of plane 2.
+    Map1 = 0x1,
+    /// Represents the character map at offset `0x8000` of plane 2.
+    Map2 = 0x2,
+    /// Represents the character map at offset `0xC000` of plane 2.
+    Map3 = 0x3,
+    /// Represents the character map at offset `0x2000` of plane 2.
+    Map4 = 0x4,
+    /// Represents the character map at offset `0x6000` of plane 2.
+    Map5 = 0x5,
+    /// Represents the character map at offset `0xA000` of plane 2.
+    Map6 = 0x6,
+    /// Represents the character map at offset `0xE000` of plane 2.
+    Map7 = 0x7,
+}
+
+impl CharacterMap {
+    /// Returns the offset of the character map in plane 2.
+    pub const fn get_offset(self) -> usize {
+        let map = self as usize;
+        (map & 0x03) * 0x4000 + (map >> 2) * 0x2000
+    }
+
+    /// Returns the character maps selected by a `SequencerIndex::CharacterFont`
+    /// register `value` as `(primary, secondary)`.
+    pub(crate) fn from_character_font(value: u8) -> (CharacterMap, CharacterMap) {
+        // Map B, used for attributes with bit 3 clear, is stored in bits
+        // 4, 1 and 0, and map A in bits 5, 3 and 2.
+        let primary = (value >> 2) & 0x04 | value & 0x03;
+        let secondary = (value >> 3) & 0x04 | (value >> 2) & 0x03;
+        (
+            CharacterMap::try_from(primary).unwrap(),
+            CharacterMap::try_from(secondary).unwrap(),
+        )
+    }
+
+    /// Returns the `SequencerIndex::CharacterFont` register value selecting
+    /// the `primary` and `secondary` character maps.
+    pub(crate) fn to_character_font(primary: CharacterMap, secondary: CharacterMap) -> u8 {
+        let primary = primary as u8;
+        let secondary = secondary as u8;
+        (secondary & 0x04) << 3 | (primary & 0x04) << 2 | (secondary & 0x03) << 2 | primary & 0x03
+    }
+}
+
+impl TryFrom<u8> for CharacterMap {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(CharacterMap::Map0),
+            1 => Ok(CharacterMap::Map1),
+            2 => Ok(CharacterMap::Map2),
+            3 => Ok(CharacterMap::Map3),
+            4 => Ok(CharacterMap::Map4),
+            5 => Ok(CharacterMap::Map5),
+            6 => Ok(CharacterMap::Map6),
+            7 => Ok(CharacterMap::Map7),
+            _ => Err("CharacterMap only accepts values between 0-7!"),
+        }
+    }
+}
+
+impl From<CharacterMap> for u8 {
+    fn from(value: CharacterMap) -> u8 {
+        value as u8
+    }
+}
+
 /// Represents an index for the seqeuncer registers.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum SequencerIndex {
     /// Represents the `Sequencer Reset` register index.
@@ -68,32 +148,58 @@ impl From<SequencerIndex> for u8 {
 /// Represents the sequencer registers on vga hardware.
 #[derive(Debug)]
 pub struct SequencerRegisters {
-    srx_index: Port<u8>,
-    srx_data: Port<u8>,
+    srx_index: RegisterPort,
+    srx_data: RegisterPort,
 }
 
 impl SequencerRegisters {
-    pub(crate) fn new() -> SequencerRegisters {
+    pub(crate) fn new(access: RegisterAccess) -> SequencerRegisters {
         SequencerRegisters {
-            srx_index: Port::new(SRX_INDEX_ADDRESS),
-            srx_data: Port::new(SRX_DATA_ADDRESS),
+            srx_index: RegisterPort::new(SRX_INDEX_ADDRESS, access),
+            srx_data: RegisterPort::new(SRX_DATA_ADDRESS, access),
         }
     }
 
     /// Reads the current value from the sequencer, as specified by `index`.
     pub fn read(&mut self, index: SequencerIndex) -> u8 {
-        self.set_index(index);
-        unsafe { self.srx_data.read() }
+        unsafe { self.read_raw(u8::from(index)) }
     }
 
     /// Writes the `value` to the sequencer, as specified by `index`.
     pub fn write(&mut self, index: SequencerIndex, value: u8) {
-        self.set_index(index);
         unsafe {
-            self.srx_data.write(value);
+            self.write_raw(u8::from(index), value);
         }
     }
 
+    /// Reads the current value from the sequencer at the raw `index`,
+    /// including extended registers that `SequencerIndex` doesn't cover.
+    ///
+    /// # Safety
+    ///
+    /// `index` may address vendor specific extension registers, such as the
+    /// unlock register at `0x06` on cirrus logic cards, and reading some of
+    /// them has side effects. The caller must ensure the register at `index`
+    /// exists on the current hardware and is safe to read.
+    pub unsafe fn read_raw(&mut self, index: u8) -> u8 {
+        self.srx_index.write(index);
+        self.srx_data.read()
+    }
+
+    /// Writes the `value` to the sequencer at the raw `index`, including
+    /// extended registers that `SequencerIndex` doesn't cover.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure the register at `index` exists on the current
+    /// hardware, and that `value` doesn't leave the sequencer in a state the
+    /// rest of this crate can't handle, such as holding it in reset or
+    /// changing the memory mode behind `Vga`'s back.
+    pub unsafe fn write_raw(&mut self, index: u8, value: u8) {
+        self.srx_index.write(index);
+        self.srx_data.write(value);
+    }
+
     /// Sets the plane mask of the sequencer controller, as specified by `plane_mask`.
     pub fn set_plane_mask(&mut self, plane_mask: PlaneMask) {
         let original_value = self.read(SequencerIndex::PlaneMask) & 0xF0;
@@ -103,9 +209,81 @@ impl SequencerRegisters {
         );
     }
 
-    fn set_index(&mut self, index: SequencerIndex) {
-        unsafe {
-            self.srx_index.write(u8::from(index));
+    /// Returns the character maps text modes read glyphs from as
+    /// `(primary, secondary)`.
+    ///
+    /// The primary map is used for characters whose attribute has bit 3,
+    /// the bright foreground bit, clear, and the secondary map for the
+    /// others. When both are the same, 256 characters can be displayed,
+    /// and when they differ, 512.
+    pub fn get_character_maps(&mut self) -> (CharacterMap, CharacterMap) {
+        CharacterMap::from_character_font(self.read(SequencerIndex::CharacterFont))
+    }
+
+    /// Selects the `primary` and `secondary` character maps, as described
+    /// by `get_character_maps`.
+    pub fn set_character_maps(&mut self, primary: CharacterMap, secondary: CharacterMap) {
+        let original_value = self.read(SequencerIndex::CharacterFont) & 0xC0;
+        self.write(
+            SequencerIndex::CharacterFont,
+            original_value | CharacterMap::to_character_font(primary, secondary),
+        );
+    }
+
+    /// Returns whether chain-4 addressing is enabled, in which case the low
+    /// two bits of the host address select the plane.
+    pub fn get_chain4(&mut self) -> bool {
+        self.read(SequencerIndex::MemoryMode) & 0x08 != 0
+    }
+
+    /// Enables or disables chain-4 addressing, as specified by `enabled`.
+    ///
+    /// Disabling chain-4 in a 256 color mode gives the unchained
+    /// (Mode X) layout, where each plane is written through the plane mask.
+    pub fn set_chain4(&mut self, enabled: bool) {
+        let original_value = self.read(SequencerIndex::MemoryMode) & !0x08;
+        let chain4 = if enabled { 0x08 } else { 0x00 };
+        self.write(SequencerIndex::MemoryMode, original_value | chain4);
+    }
+
+    /// Returns whether odd/even host memory addressing is enabled.
+    pub fn get_odd_even(&mut self) -> bool {
+        self.read(SequencerIndex::MemoryMode) & 0x04 == 0
+    }
+
+    /// Enables or disables odd/even host memory addressing, as specified
+    /// by `enabled`.
+    ///
+    /// The hardware bit is an odd/even *disable* bit, so enabling odd/even
+    /// clears it.
+    pub fn set_odd_even(&mut self, enabled: bool) {
+        let original_value = self.read(SequencerIndex::MemoryMode) & !0x04;
+        let odd_even_disable = if enabled { 0x00 } else { 0x04 };
+        self.write(
+            SequencerIndex::MemoryMode,
+            original_value | odd_even_disable,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_character_font_encoding() {
+        assert_eq!(CharacterMap::Map5.get_offset(), 0x6000);
+        assert_eq!(
+            CharacterMap::to_character_font(CharacterMap::Map0, CharacterMap::Map1),
+            0x04
+        );
+        assert_eq!(
+            CharacterMap::to_character_font(CharacterMap::Map6, CharacterMap::Map3),
+            0x1E
+        );
+        for value in 0..0x40 {
+            let (primary, secondary) = CharacterMap::from_character_font(value);
+            assert_eq!(CharacterMap::to_character_font(primary, secondary), value);
         }
     }
 }
@@ -0,0 +1,448 @@
+use super::{
+    AttributeControllerIndex, CrtcControllerIndex, GraphicsControllerIndex, SequencerIndex,
+};
+use crate::{configurations::VgaConfiguration, vga::Vga};
+
+const SEQUENCER_INDICES: [SequencerIndex; 5] = [
+    SequencerIndex::SequencerReset,
+    SequencerIndex::ClockingMode,
+    SequencerIndex::PlaneMask,
+    SequencerIndex::CharacterFont,
+    SequencerIndex::MemoryMode,
+];
+
+const CRTC_CONTROLLER_INDICES: [CrtcControllerIndex; 25] = [
+    CrtcControllerIndex::HorizontalTotal,
+    CrtcControllerIndex::HorizontalDisplayEnableEnd,
+    CrtcControllerIndex::HorizontalBlankingStart,
+    CrtcControllerIndex::HorizontalBlankingEnd,
+    CrtcControllerIndex::HorizontalSyncStart,
+    CrtcControllerIndex::HorizontalSyncEnd,
+    CrtcControllerIndex::VeritcalTotal,
+    CrtcControllerIndex::Overflow,
+    CrtcControllerIndex::PresetRowScan,
+    CrtcControllerIndex::MaximumScanLine,
+    CrtcControllerIndex::TextCursorStart,
+    CrtcControllerIndex::TextCursorEnd,
+    CrtcControllerIndex::StartAddressHigh,
+    CrtcControllerIndex::StartAddressLow,
+    CrtcControllerIndex::TextCursorLocationHigh,
+    CrtcControllerIndex::TextCursorLocationLow,
+    CrtcControllerIndex::VerticalSyncStart,
+    CrtcControllerIndex::VerticalSyncEnd,
+    CrtcControllerIndex::VerticalDisplayEnableEnd,
+    CrtcControllerIndex::Offset,
+    CrtcControllerIndex::UnderlineLocation,
+    CrtcControllerIndex::VerticalBlankingStart,
+    CrtcControllerIndex::VerticalBlankingEnd,
+    CrtcControllerIndex::ModeControl,
+    CrtcControllerIndex::LineCompare,
+];
+
+const GRAPHICS_CONTROLLER_INDICES: [GraphicsControllerIndex; 9] = [
+    GraphicsControllerIndex::SetReset,
+    GraphicsControllerIndex::EnableSetReset,
+    GraphicsControllerIndex::ColorCompare,
+    GraphicsControllerIndex::DataRotate,
+    GraphicsControllerIndex::ReadPlaneSelect,
+    GraphicsControllerIndex::GraphicsMode,
+    GraphicsControllerIndex::Miscellaneous,
+    GraphicsControllerIndex::ColorDontCare,
+    GraphicsControllerIndex::BitMask,
+];
+
+const ATTRIBUTE_CONTROLLER_INDICES: [AttributeControllerIndex; 21] = [
+    AttributeControllerIndex::PaletteRegister0,
+    AttributeControllerIndex::PaletteRegister1,
+    AttributeControllerIndex::PaletteRegister2,
+    AttributeControllerIndex::PaletteRegister3,
+    AttributeControllerIndex::PaletteRegister4,
+    AttributeControllerIndex::PaletteRegister5,
+    AttributeControllerIndex::PaletteRegister6,
+    AttributeControllerIndex::PaletteRegister7,
+    AttributeControllerIndex::PaletteRegister8,
+    AttributeControllerIndex::PaletteRegister9,
+    AttributeControllerIndex::PaletteRegisterA,
+    AttributeControllerIndex::PaletteRegisterB,
+    AttributeControllerIndex::PaletteRegisterC,
+    AttributeControllerIndex::PaletteRegisterD,
+    AttributeControllerIndex::PaletteRegisterE,
+    AttributeControllerIndex::PaletteRegisterF,
+    AttributeControllerIndex::ModeControl,
+    AttributeControllerIndex::OverscanColor,
+    AttributeControllerIndex::MemoryPlaneEnable,
+    AttributeControllerIndex::HorizontalPixelPanning,
+    AttributeControllerIndex::ColorSelect,
+];
+
+/// Identifies a single vga register.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Register {
+    /// The miscellaneous output register.
+    MiscellaneousOutput,
+    /// A sequencer register.
+    Sequencer(SequencerIndex),
+    /// A crtc controller register.
+    CrtcController(CrtcControllerIndex),
+    /// A graphics controller register.
+    GraphicsController(GraphicsControllerIndex),
+    /// An attribute controller register.
+    AttributeController(AttributeControllerIndex),
+}
+
+/// A register with different values in two snapshots, or in a snapshot
+/// and a configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RegisterChange {
+    register: Register,
+    old_value: u8,
+    new_value: u8,
+}
+
+impl RegisterChange {
+    /// Returns the register that changed.
+    pub fn get_register(&self) -> Register {
+        self.register
+    }
+
+    /// Returns the value of the register in the snapshot or configuration
+    /// compared against.
+    pub fn get_old_value(&self) -> u8 {
+        self.old_value
+    }
+
+    /// Returns the value of the register in the snapshot compared.
+    pub fn get_new_value(&self) -> u8 {
+        self.new_value
+    }
+}
+
+/// The values of the standard vga registers at one point in time.
+///
+/// Only the registers set by a `VgaConfiguration` are captured, leaving out
+/// extended and read only registers.
+///
+/// # Examples
+///
+/// Checking which registers deviate from a mode's configuration:
+///
+/// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
+/// use vga::configurations::MODE_80X25_CONFIGURATION;
+/// use vga::registers::RegisterSnapshot;
+/// use vga::vga::{VideoMode, VGA};
+///
+/// let mut vga = VGA.lock();
+/// vga.set_video_mode(VideoMode::Mode80x25);
+/// let snapshot = RegisterSnapshot::capture(&mut vga);
+/// for change in snapshot.diff_configuration(&MODE_80X25_CONFIGURATION) {
+///     // Log `change.get_register()` and its values.
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterSnapshot {
+    miscellaneous_output: u8,
+    sequencer_registers: [u8; SEQUENCER_INDICES.len()],
+    crtc_controller_registers: [u8; CRTC_CONTROLLER_INDICES.len()],
+    graphics_controller_registers: [u8; GRAPHICS_CONTROLLER_INDICES.len()],
+    attribute_controller_registers: [u8; ATTRIBUTE_CONTROLLER_INDICES.len()],
+}
+
+impl RegisterSnapshot {
+    /// Reads the registers of `vga`.
+    ///
+    /// Reading the attribute controller registers blanks the screen, so
+    /// the screen is unblanked afterwards.
+    pub fn capture(vga: &mut Vga) -> RegisterSnapshot {
+        let emulation_mode = vga.get_emulation_mode();
+        let mut snapshot = RegisterSnapshot {
+            miscellaneous_output: vga.general_registers.read_msr(),
+            sequencer_registers: [0; SEQUENCER_INDICES.len()],
+            crtc_controller_registers: [0; CRTC_CONTROLLER_INDICES.len()],
+            graphics_controller_registers: [0; GRAPHICS_CONTROLLER_INDICES.len()],
+            attribute_controller_registers: [0; ATTRIBUTE_CONTROLLER_INDICES.len()],
+        };
+        for (value, index) in snapshot
+            .sequencer_registers
+            .iter_mut()
+            .zip(SEQUENCER_INDICES)
+        {
+            *value = vga.sequencer_registers.read(index);
+        }
+        for (value, index) in snapshot
+            .crtc_controller_registers
+            .iter_mut()
+            .zip(CRTC_CONTROLLER_INDICES)
+        {
+            *value = vga.crtc_controller_registers.read(emulation_mode, index);
+        }
+        for (value, index) in snapshot
+            .graphics_controller_registers
+            .iter_mut()
+            .zip(GRAPHICS_CONTROLLER_INDICES)
+        {
+            *value = vga.graphics_controller_registers.read(index);
+        }
+        for (value, index) in snapshot
+            .attribute_controller_registers
+            .iter_mut()
+            .zip(ATTRIBUTE_CONTROLLER_INDICES)
+        {
+            *value = vga
+                .attribute_controller_registers
+                .read(emulation_mode, index);
+        }
+        vga.attribute_controller_registers
+            .unblank_screen(emulation_mode);
+        snapshot
+    }
+
+    /// Returns the captured value of `register`, or `None` if it isn't
+    /// one of the captured registers.
+    pub fn get(&self, register: Register) -> Option<u8> {
+        match register {
+            Register::MiscellaneousOutput => Some(self.miscellaneous_output),
+            Register::Sequencer(index) => {
+                lookup(&SEQUENCER_INDICES, &self.sequencer_registers, index)
+            }
+            Register::CrtcController(index) => lookup(
+                &CRTC_CONTROLLER_INDICES,
+                &self.crtc_controller_registers,
+                index,
+            ),
+            Register::GraphicsController(index) => lookup(
+                &GRAPHICS_CONTROLLER_INDICES,
+                &self.graphics_controller_registers,
+                index,
+            ),
+            Register::AttributeController(index) => lookup(
+                &ATTRIBUTE_CONTROLLER_INDICES,
+                &self.attribute_controller_registers,
+                index,
+            ),
+        }
+    }
+
+    /// Returns the registers with different values in `other` and `self`,
+    /// with the values of `other` as the old values.
+    pub fn diff<'a>(
+        &'a self,
+        other: &'a RegisterSnapshot,
+    ) -> impl Iterator<Item = RegisterChange> + 'a {
+        registers()
+            .filter_map(move |register| change(register, other.get(register)?, self.get(register)?))
+    }
+
+    /// Returns the registers set by `configuration` with different values
+    /// in `self`, with the values of `configuration` as the old values.
+    ///
+    /// The protect bit of the crtc `VerticalSyncEnd` register is ignored,
+    /// since `Vga` locks the crtc registers again after setting a mode.
+    pub fn diff_configuration<'a>(
+        &'a self,
+        configuration: &'a VgaConfiguration,
+    ) -> impl Iterator<Item = RegisterChange> + 'a {
+        let sequencer = configuration
+            .sequencer_registers
+            .iter()
+            .map(|(index, value)| (Register::Sequencer(*index), *value));
+        let crtc_controller = configuration
+            .crtc_controller_registers
+            .iter()
+            .map(|(index, value)| (Register::CrtcController(*index), *value));
+        let graphics_controller = configuration
+            .graphics_controller_registers
+            .iter()
+            .map(|(index, value)| (Register::GraphicsController(*index), *value));
+        let attribute_controller = configuration
+            .attribute_controller_registers
+            .iter()
+            .map(|(index, value)| (Register::AttributeController(*index), *value));
+        core::iter::once((
+            Register::MiscellaneousOutput,
+            configuration.miscellaneous_output,
+        ))
+        .chain(sequencer)
+        .chain(crtc_controller)
+        .chain(graphics_controller)
+        .chain(attribute_controller)
+        .filter_map(move |(register, value)| {
+            let new_value = self.get(register)?;
+            if register == Register::CrtcController(CrtcControllerIndex::VerticalSyncEnd)
+                && value ^ new_value == 0x80
+            {
+                return None;
+            }
+            change(register, value, new_value)
+        })
+    }
+}
+
+/// Returns every captured register, in the order they're set in.
+fn registers() -> impl Iterator<Item = Register> {
+    core::iter::once(Register::MiscellaneousOutput)
+        .chain(SEQUENCER_INDICES.iter().copied().map(Register::Sequencer))
+        .chain(
+            CRTC_CONTROLLER_INDICES
+                .iter()
+                .copied()
+                .map(Register::CrtcController),
+        )
+        .chain(
+            GRAPHICS_CONTROLLER_INDICES
+                .iter()
+                .copied()
+                .map(Register::GraphicsController),
+        )
+        .chain(
+            ATTRIBUTE_CONTROLLER_INDICES
+                .iter()
+                .copied()
+                .map(Register::AttributeController),
+        )
+}
+
+fn lookup<I: PartialEq>(indices: &[I], values: &[u8], index: I) -> Option<u8> {
+    indices
+        .iter()
+        .position(|captured| *captured == index)
+        .map(|position| values[position])
+}
+
+fn change(register: Register, old_value: u8, new_value: u8) -> Option<RegisterChange> {
+    if old_value == new_value {
+        None
+    } else {
+        Some(RegisterChange {
+            register,
+            old_value,
+            new_value,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::{
+        configurations::{MODE_640X480X16_CONFIGURATION, MODE_80X25_CONFIGURATION},
+        mock::lock_for_test,
+        vga::{VideoMode, VGA},
+    };
+
+    #[test]
+    fn test_diff() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode80x25);
+        let emulation_mode = vga.get_emulation_mode();
+        let snapshot = RegisterSnapshot::capture(&mut vga);
+        assert_eq!(
+            snapshot
+                .diff_configuration(&MODE_80X25_CONFIGURATION)
+                .count(),
+            0
+        );
+        assert_eq!(
+            snapshot.get(Register::CrtcController(CrtcControllerIndex::Offset)),
+            Some(0x28)
+        );
+        assert_eq!(
+            snapshot.get(Register::Sequencer(SequencerIndex::CounterReset)),
+            None
+        );
+
+        vga.crtc_controller_registers
+            .write(emulation_mode, CrtcControllerIndex::Offset, 0x50);
+        vga.graphics_controller_registers
+            .write(GraphicsControllerIndex::BitMask, 0x0F);
+        let changed = RegisterSnapshot::capture(&mut vga);
+        let mut changes = changed.diff(&snapshot);
+        assert_eq!(
+            changes.next(),
+            Some(RegisterChange {
+                register: Register::CrtcController(CrtcControllerIndex::Offset),
+                old_value: 0x28,
+                new_value: 0x50,
+            })
+        );
+        let change = changes.next().unwrap();
+        assert_eq!(
+            change.get_register(),
+            Register::GraphicsController(GraphicsControllerIndex::BitMask)
+        );
+        assert_eq!(
+            (change.get_old_value(), change.get_new_value()),
+            (0xFF, 0x0F)
+        );
+        assert_eq!(changes.next(), None);
+        assert_eq!(
+            changed
+                .diff_configuration(&MODE_80X25_CONFIGURATION)
+                .count(),
+            2
+        );
+
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+
+    #[test]
+    fn test_diff_configuration() {
+        let _guard = lock_for_test();
+        let mut vga = VGA.lock();
+        vga.set_video_mode(VideoMode::Mode640x480x16);
+        let emulation_mode = vga.get_emulation_mode();
+        let snapshot = RegisterSnapshot::capture(&mut vga);
+        // Only the protect bit differs after the crtc registers are relocked.
+        assert_eq!(
+            snapshot.get(Register::CrtcController(
+                CrtcControllerIndex::VerticalSyncEnd
+            )),
+            Some(0x8C)
+        );
+        assert_eq!(
+            snapshot
+                .diff_configuration(&MODE_640X480X16_CONFIGURATION)
+                .next(),
+            None
+        );
+        assert!(
+            snapshot
+                .diff_configuration(&MODE_80X25_CONFIGURATION)
+                .count()
+                > 0
+        );
+
+        vga.unlock_crtc_registers();
+        vga.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::VerticalSyncEnd,
+            0x0D,
+        );
+        vga.sequencer_registers
+            .write(SequencerIndex::PlaneMask, 0x01);
+        let changed = RegisterSnapshot::capture(&mut vga);
+        let mut changes = changed.diff_configuration(&MODE_640X480X16_CONFIGURATION);
+        assert_eq!(
+            changes.next(),
+            Some(RegisterChange {
+                register: Register::Sequencer(SequencerIndex::PlaneMask),
+                old_value: 0x08,
+                new_value: 0x01,
+            })
+        );
+        assert_eq!(
+            changes.next(),
+            Some(RegisterChange {
+                register: Register::CrtcController(CrtcControllerIndex::VerticalSyncEnd),
+                old_value: 0x0C,
+                new_value: 0x0D,
+            })
+        );
+        assert_eq!(changes.next(), None);
+
+        vga.set_video_mode(VideoMode::Mode80x25);
+    }
+}
@@ -0,0 +1,219 @@
+//! A boot splash screen with a progress bar.
+//!
+//! `Splash` sets a 256 color graphics mode, loads the palette of an
+//! indexed color image and shows the image centered above a progress bar,
+//! which is updated with `set_progress`. When booting is done, `finish`
+//! switches to a text mode and restores the palette.
+
+use crate::{
+    colors::Palette,
+    drawing::{Rect, Surface},
+    writers::{ModeOptions, PrimitiveDrawing, TextWriter},
+};
+
+/// The space between the image and the progress bar.
+const BAR_GAP: usize = 8;
+const BAR_HEIGHT: usize = 8;
+
+/// A splash screen showing an image and a progress bar.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "text-modes", feature = "graphics-modes"))]
+/// # {
+/// use vga::colors::DEFAULT_PALETTE;
+/// use vga::drawing::Surface;
+/// use vga::splash::Splash;
+/// use vga::writers::{Graphics320x200x256, Text80x25};
+///
+/// static LOGO: [u8; 64 * 32] = [0x2C; 64 * 32];
+///
+/// let image = Surface::new(&LOGO, 64, 32);
+/// let mut splash = Splash::new(Graphics320x200x256::new(), image, &DEFAULT_PALETTE);
+/// splash.show();
+/// for percent in (0..=100).step_by(10) {
+///     // Load a driver.
+///     splash.set_progress(percent);
+/// }
+/// splash.finish(&Text80x25::new());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Splash<'a, W> {
+    writer: W,
+    image: Surface<'a, u8>,
+    palette: &'a Palette<256>,
+    background: u8,
+    bar_color: u8,
+    progress: u8,
+}
+
+impl<'a, W: PrimitiveDrawing<u8>> Splash<'a, W> {
+    /// Creates a new `Splash` showing `image` with `palette` on `writer`,
+    /// with the background in color 0 and the progress bar in color 15.
+    pub fn new(writer: W, image: Surface<'a, u8>, palette: &'a Palette<256>) -> Splash<'a, W> {
+        Splash {
+            writer,
+            image,
+            palette,
+            background: 0,
+            bar_color: 15,
+            progress: 0,
+        }
+    }
+
+    /// Sets the palette indexes of the background and the progress bar.
+    pub fn with_colors(mut self, background: u8, bar_color: u8) -> Splash<'a, W> {
+        self.background = background;
+        self.bar_color = bar_color;
+        self
+    }
+
+    /// Returns the writer the splash is shown on.
+    pub fn get_writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns the progress shown, in percent.
+    pub fn get_progress(&self) -> u8 {
+        self.progress
+    }
+
+    /// Sets the mode of the writer, loads the palette and draws the image
+    /// and the progress bar. The image is cropped to its center if it
+    /// doesn't fit on the screen.
+    pub fn show(&mut self) {
+        self.writer
+            .set_mode_with_options(ModeOptions::default().with_load_palette(false));
+        self.writer
+            .lock_vga()
+            .color_palette_registers
+            .load_palette(self.palette);
+        self.writer.clear_screen(self.background);
+
+        let (src_rect, dst_rect, bar) = self.layout();
+        self.writer.blit_scaled(&self.image, src_rect, dst_rect);
+
+        // A one pixel border, with a one pixel gap to the bar inside it.
+        let (left, top) = (bar.get_x(), bar.get_y());
+        let (right, bottom) = (left + bar.get_width(), top + bar.get_height());
+        let color = self.bar_color;
+        self.writer.draw_rect((left, top), (right, top + 1), color);
+        self.writer
+            .draw_rect((left, bottom - 1), (right, bottom), color);
+        self.writer
+            .draw_rect((left, top), (left + 1, bottom), color);
+        self.writer
+            .draw_rect((right - 1, top), (right, bottom), color);
+        self.draw_progress();
+    }
+
+    /// Sets the progress shown to `percent`, clamped to 100, and redraws
+    /// the progress bar.
+    pub fn set_progress(&mut self, percent: u8) {
+        self.progress = percent.min(100);
+        self.draw_progress();
+    }
+
+    /// Switches to the mode of `text_writer` and loads its palette, which
+    /// ends the splash.
+    pub fn finish<T: TextWriter>(self, text_writer: &T) {
+        text_writer.set_mode_with_options(ModeOptions::default().with_clear(true));
+        // Setting the mode only reloads the palette for some hardware.
+        text_writer.lock_vga().load_active_palette();
+    }
+
+    /// Returns the part of the image shown, where it's shown, and the
+    /// outline of the progress bar.
+    fn layout(&self) -> (Rect, Rect, Rect) {
+        let width = self.image.get_width().min(W::WIDTH);
+        let height = self
+            .image
+            .get_height()
+            .min(W::HEIGHT - BAR_GAP - BAR_HEIGHT);
+        let src_rect = Rect::new(
+            (self.image.get_width() - width) / 2,
+            (self.image.get_height() - height) / 2,
+            width,
+            height,
+        );
+
+        let top = (W::HEIGHT - height - BAR_GAP - BAR_HEIGHT) / 2;
+        let dst_rect = Rect::new((W::WIDTH - width) / 2, top, width, height);
+        let bar = Rect::new(
+            W::WIDTH / 4,
+            top + height + BAR_GAP,
+            W::WIDTH / 2,
+            BAR_HEIGHT,
+        );
+        (src_rect, dst_rect, bar)
+    }
+
+    fn draw_progress(&self) {
+        let (_, _, bar) = self.layout();
+        let (left, top) = (bar.get_x() + 2, bar.get_y() + 2);
+        let (right, bottom) = (
+            bar.get_x() + bar.get_width() - 2,
+            bar.get_y() + bar.get_height() - 2,
+        );
+        let filled = left + (right - left) * usize::from(self.progress) / 100;
+        self.writer
+            .draw_rect((left, top), (filled, bottom), self.bar_color);
+        self.writer
+            .draw_rect((filled, top), (right, bottom), self.background);
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "global",
+    feature = "text-modes",
+    feature = "graphics-modes"
+))]
+mod test {
+    use super::*;
+    use crate::{
+        colors::DEFAULT_PALETTE,
+        mock::{lock_for_test, video_memory},
+        vga::{VideoMode, VGA},
+        writers::{Graphics320x200x256, Screen, Text80x25},
+    };
+
+    #[test]
+    fn test_splash() {
+        let _guard = lock_for_test();
+        let mut palette = DEFAULT_PALETTE;
+        palette.set_color(1, 0x3F, 0x00, 0x00);
+        let pixels = [0x01; 100 * 300];
+        let image = Surface::new(&pixels, 100, 300);
+        let mut splash =
+            Splash::new(Graphics320x200x256::new(), image, &palette).with_colors(0x02, 0x03);
+        splash.show();
+        assert_eq!(
+            VGA.lock().color_palette_registers.read_color(1),
+            (0x3F, 0, 0)
+        );
+
+        // The image is cropped to leave room for the bar.
+        let frame_buffer = &video_memory()[..Graphics320x200x256::SIZE];
+        let pixel = |x, y| frame_buffer[Graphics320x200x256::offset_of(x, y)];
+        assert_eq!((pixel(110, 0), pixel(109, 0)), (0x01, 0x02));
+        assert_eq!((pixel(209, 183), pixel(210, 183)), (0x01, 0x02));
+        // The bar's outline, with nothing filled yet.
+        assert_eq!((pixel(80, 192), pixel(239, 199)), (0x03, 0x03));
+        assert_eq!((pixel(82, 194), pixel(237, 197)), (0x02, 0x02));
+
+        splash.set_progress(50);
+        assert_eq!((pixel(82, 194), pixel(159, 197)), (0x03, 0x03));
+        assert_eq!(pixel(160, 194), 0x02);
+        splash.set_progress(200);
+        assert_eq!(splash.get_progress(), 100);
+        assert_eq!(pixel(237, 197), 0x03);
+
+        splash.finish(&Text80x25::new());
+        let mut vga = VGA.lock();
+        assert_eq!(vga.get_most_recent_video_mode(), Some(VideoMode::Mode80x25));
+        assert_eq!(vga.color_palette_registers.read_color(1), (0, 0, 0x2A));
+    }
+}
@@ -0,0 +1,374 @@
+//! A compact stroke font for drawing scalable text in graphics modes.
+//!
+//! Every glyph is a few polylines on a grid 5 units wide and 9 units tall,
+//! drawn with `PrimitiveDrawing::draw_line`, so text can be drawn at any
+//! integer scale without storing a bitmap font per size. Capitals span rows
+//! 0 to 6, lowercase letters start at row 2 and descenders reach row 8.
+
+use crate::{drawing::Point, writers::PrimitiveDrawing};
+
+/// Separates the polylines of a glyph.
+const PEN_UP: u8 = 0xFF;
+
+/// The horizontal distance between glyphs, in grid units.
+const ADVANCE: usize = 6;
+
+/// The vertical distance between lines of text, in grid units.
+const LINE_HEIGHT: usize = 10;
+
+/// The glyphs of the printable ascii characters, from `' '` to `'~'`, with
+/// every point stored as `x << 4 | y`.
+const GLYPHS: [&[u8]; 95] = [
+    // ' '
+    &[],
+    // '!'
+    &[0x20, 0x24, PEN_UP, 0x25, 0x26],
+    // '"'
+    &[0x10, 0x11, PEN_UP, 0x30, 0x31],
+    // '#'
+    &[
+        0x11, 0x15, PEN_UP, 0x31, 0x35, PEN_UP, 0x02, 0x42, PEN_UP, 0x04, 0x44,
+    ],
+    // '$'
+    &[
+        0x41, 0x11, 0x02, 0x13, 0x33, 0x44, 0x35, 0x05, PEN_UP, 0x20, 0x26,
+    ],
+    // '%'
+    &[
+        0x06, 0x40, PEN_UP, 0x00, 0x10, 0x11, 0x01, 0x00, PEN_UP, 0x35, 0x45, 0x46, 0x36, 0x35,
+    ],
+    // '&'
+    &[
+        0x46, 0x12, 0x11, 0x20, 0x31, 0x32, 0x04, 0x05, 0x16, 0x26, 0x44,
+    ],
+    // '\''
+    &[0x20, 0x21],
+    // '('
+    &[0x30, 0x21, 0x25, 0x36],
+    // ')'
+    &[0x10, 0x21, 0x25, 0x16],
+    // '*'
+    &[0x21, 0x25, PEN_UP, 0x02, 0x44, PEN_UP, 0x04, 0x42],
+    // '+'
+    &[0x21, 0x25, PEN_UP, 0x03, 0x43],
+    // ','
+    &[0x25, 0x26, 0x17],
+    // '-'
+    &[0x03, 0x43],
+    // '.'
+    &[0x25, 0x26],
+    // '/'
+    &[0x06, 0x40],
+    // '0'
+    &[
+        0x10, 0x30, 0x41, 0x45, 0x36, 0x16, 0x05, 0x01, 0x10, PEN_UP, 0x05, 0x41,
+    ],
+    // '1'
+    &[0x11, 0x20, 0x26, PEN_UP, 0x16, 0x36],
+    // '2'
+    &[0x01, 0x10, 0x30, 0x41, 0x42, 0x06, 0x46],
+    // '3'
+    &[0x00, 0x40, 0x22, 0x32, 0x43, 0x45, 0x36, 0x16, 0x05],
+    // '4'
+    &[0x36, 0x30, 0x04, 0x44],
+    // '5'
+    &[0x40, 0x00, 0x02, 0x32, 0x43, 0x45, 0x36, 0x06],
+    // '6'
+    &[0x30, 0x10, 0x01, 0x05, 0x16, 0x36, 0x45, 0x44, 0x33, 0x03],
+    // '7'
+    &[0x00, 0x40, 0x16],
+    // '8'
+    &[
+        0x10, 0x30, 0x41, 0x42, 0x33, 0x13, 0x04, 0x05, 0x16, 0x36, 0x45, 0x44, 0x33, PEN_UP, 0x13,
+        0x02, 0x01, 0x10,
+    ],
+    // '9'
+    &[0x43, 0x13, 0x02, 0x01, 0x10, 0x30, 0x41, 0x45, 0x36, 0x16],
+    // ':'
+    &[0x21, 0x22, PEN_UP, 0x25, 0x26],
+    // ';'
+    &[0x21, 0x22, PEN_UP, 0x25, 0x26, 0x17],
+    // '<'
+    &[0x40, 0x03, 0x46],
+    // '='
+    &[0x02, 0x42, PEN_UP, 0x04, 0x44],
+    // '>'
+    &[0x00, 0x43, 0x06],
+    // '?'
+    &[0x01, 0x10, 0x30, 0x41, 0x42, 0x23, 0x24, PEN_UP, 0x25, 0x26],
+    // '@'
+    &[
+        0x34, 0x32, 0x22, 0x13, 0x24, 0x44, 0x41, 0x30, 0x10, 0x01, 0x05, 0x16, 0x46,
+    ],
+    // 'A'
+    &[0x06, 0x02, 0x20, 0x42, 0x46, PEN_UP, 0x04, 0x44],
+    // 'B'
+    &[
+        0x00, 0x06, 0x36, 0x45, 0x44, 0x33, 0x03, PEN_UP, 0x00, 0x30, 0x41, 0x42, 0x33,
+    ],
+    // 'C'
+    &[0x41, 0x30, 0x10, 0x01, 0x05, 0x16, 0x36, 0x45],
+    // 'D'
+    &[0x00, 0x06, 0x26, 0x44, 0x42, 0x20, 0x00],
+    // 'E'
+    &[0x40, 0x00, 0x06, 0x46, PEN_UP, 0x03, 0x33],
+    // 'F'
+    &[0x40, 0x00, 0x06, PEN_UP, 0x03, 0x33],
+    // 'G'
+    &[0x41, 0x30, 0x10, 0x01, 0x05, 0x16, 0x36, 0x45, 0x43, 0x23],
+    // 'H'
+    &[0x00, 0x06, PEN_UP, 0x40, 0x46, PEN_UP, 0x03, 0x43],
+    // 'I'
+    &[0x10, 0x30, PEN_UP, 0x20, 0x26, PEN_UP, 0x16, 0x36],
+    // 'J'
+    &[0x40, 0x45, 0x36, 0x16, 0x05],
+    // 'K'
+    &[0x00, 0x06, PEN_UP, 0x40, 0x04, PEN_UP, 0x13, 0x46],
+    // 'L'
+    &[0x00, 0x06, 0x46],
+    // 'M'
+    &[0x06, 0x00, 0x23, 0x40, 0x46],
+    // 'N'
+    &[0x06, 0x00, 0x46, 0x40],
+    // 'O'
+    &[0x10, 0x30, 0x41, 0x45, 0x36, 0x16, 0x05, 0x01, 0x10],
+    // 'P'
+    &[0x06, 0x00, 0x30, 0x41, 0x42, 0x33, 0x03],
+    // 'Q'
+    &[
+        0x10, 0x30, 0x41, 0x45, 0x36, 0x16, 0x05, 0x01, 0x10, PEN_UP, 0x24, 0x46,
+    ],
+    // 'R'
+    &[0x06, 0x00, 0x30, 0x41, 0x42, 0x33, 0x03, PEN_UP, 0x23, 0x46],
+    // 'S'
+    &[
+        0x41, 0x30, 0x10, 0x01, 0x02, 0x13, 0x33, 0x44, 0x45, 0x36, 0x16, 0x05,
+    ],
+    // 'T'
+    &[0x00, 0x40, PEN_UP, 0x20, 0x26],
+    // 'U'
+    &[0x00, 0x05, 0x16, 0x36, 0x45, 0x40],
+    // 'V'
+    &[0x00, 0x26, 0x40],
+    // 'W'
+    &[0x00, 0x16, 0x23, 0x36, 0x40],
+    // 'X'
+    &[0x00, 0x46, PEN_UP, 0x40, 0x06],
+    // 'Y'
+    &[0x00, 0x23, 0x40, PEN_UP, 0x23, 0x26],
+    // 'Z'
+    &[0x00, 0x40, 0x06, 0x46],
+    // '['
+    &[0x30, 0x10, 0x16, 0x36],
+    // '\\'
+    &[0x00, 0x46],
+    // ']'
+    &[0x10, 0x30, 0x36, 0x16],
+    // '^'
+    &[0x02, 0x20, 0x42],
+    // '_'
+    &[0x07, 0x47],
+    // '`'
+    &[0x10, 0x21],
+    // 'a'
+    &[
+        0x12, 0x32, 0x43, 0x46, PEN_UP, 0x44, 0x14, 0x05, 0x16, 0x36, 0x45,
+    ],
+    // 'b'
+    &[0x00, 0x06, 0x36, 0x45, 0x43, 0x32, 0x02],
+    // 'c'
+    &[0x42, 0x12, 0x03, 0x05, 0x16, 0x46],
+    // 'd'
+    &[0x40, 0x46, 0x16, 0x05, 0x03, 0x12, 0x42],
+    // 'e'
+    &[0x04, 0x44, 0x43, 0x32, 0x12, 0x03, 0x05, 0x16, 0x46],
+    // 'f'
+    &[0x40, 0x30, 0x21, 0x26, PEN_UP, 0x12, 0x32],
+    // 'g'
+    &[
+        0x42, 0x47, 0x38, 0x18, PEN_UP, 0x42, 0x12, 0x03, 0x04, 0x15, 0x45,
+    ],
+    // 'h'
+    &[0x00, 0x06, PEN_UP, 0x02, 0x32, 0x43, 0x46],
+    // 'i'
+    &[0x22, 0x26, PEN_UP, 0x20, 0x21],
+    // 'j'
+    &[0x32, 0x37, 0x28, 0x18, PEN_UP, 0x30, 0x31],
+    // 'k'
+    &[0x00, 0x06, PEN_UP, 0x42, 0x05, PEN_UP, 0x14, 0x46],
+    // 'l'
+    &[0x10, 0x20, 0x26, PEN_UP, 0x16, 0x36],
+    // 'm'
+    &[
+        0x06, 0x02, PEN_UP, 0x03, 0x12, 0x23, 0x26, PEN_UP, 0x23, 0x32, 0x43, 0x46,
+    ],
+    // 'n'
+    &[0x02, 0x06, PEN_UP, 0x03, 0x12, 0x32, 0x43, 0x46],
+    // 'o'
+    &[0x12, 0x32, 0x43, 0x45, 0x36, 0x16, 0x05, 0x03, 0x12],
+    // 'p'
+    &[0x02, 0x08, PEN_UP, 0x02, 0x32, 0x43, 0x45, 0x36, 0x06],
+    // 'q'
+    &[0x42, 0x48, PEN_UP, 0x42, 0x12, 0x03, 0x05, 0x16, 0x46],
+    // 'r'
+    &[0x02, 0x06, PEN_UP, 0x04, 0x22, 0x42],
+    // 's'
+    &[0x42, 0x12, 0x03, 0x14, 0x34, 0x45, 0x36, 0x06],
+    // 't'
+    &[0x20, 0x25, 0x36, 0x46, PEN_UP, 0x12, 0x42],
+    // 'u'
+    &[0x02, 0x05, 0x16, 0x36, 0x45, PEN_UP, 0x42, 0x46],
+    // 'v'
+    &[0x02, 0x26, 0x42],
+    // 'w'
+    &[0x02, 0x16, 0x24, 0x36, 0x42],
+    // 'x'
+    &[0x02, 0x46, PEN_UP, 0x42, 0x06],
+    // 'y'
+    &[0x02, 0x04, 0x15, 0x45, PEN_UP, 0x42, 0x47, 0x38, 0x08],
+    // 'z'
+    &[0x02, 0x42, 0x06, 0x46],
+    // '{'
+    &[0x30, 0x21, 0x22, 0x13, 0x24, 0x25, 0x36],
+    // '|'
+    &[0x20, 0x26],
+    // '}'
+    &[0x10, 0x21, 0x22, 0x33, 0x24, 0x25, 0x16],
+    // '~'
+    &[0x03, 0x12, 0x34, 0x43],
+];
+
+/// The glyph drawn for characters outside of the printable ascii range.
+const REPLACEMENT_GLYPH: &[u8] = GLYPHS[(b'?' - b' ') as usize];
+
+/// Returns the width in pixels of the widest line of `s` drawn at `scale`.
+pub fn str_width(s: &str, scale: usize) -> usize {
+    s.lines()
+        .map(|line| line.chars().count() * ADVANCE * scale)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns the height in pixels of a line of text drawn at `scale`,
+/// including the space below it.
+pub const fn line_height(scale: usize) -> usize {
+    LINE_HEIGHT * scale
+}
+
+/// Draws `s` with its top left corner at `position`, with every grid unit
+/// of the font covering `scale` pixels, and returns the position just past
+/// the last character drawn. A newline starts a new line below `position`.
+///
+/// Characters outside of the printable ascii range are drawn as `'?'`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "graphics-modes"))]
+/// # {
+/// use vga::colors::Color16;
+/// use vga::stroke_font;
+/// use vga::writers::{Graphics640x480x16, GraphicsWriter};
+///
+/// let mode = Graphics640x480x16::new();
+/// mode.set_mode();
+/// mode.clear_screen(Color16::Black);
+/// stroke_font::draw_str(&mode, (20, 20), "Hello, World!", 8, Color16::Yellow);
+/// # }
+/// ```
+pub fn draw_str<W, C>(
+    writer: &W,
+    position: Point<isize>,
+    s: &str,
+    scale: usize,
+    color: C,
+) -> Point<isize>
+where
+    W: PrimitiveDrawing<C>,
+    C: Copy,
+{
+    let scale = scale as isize;
+    let (mut x, mut y) = position;
+    for character in s.chars() {
+        if character == '\n' {
+            x = position.0;
+            y += LINE_HEIGHT as isize * scale;
+            continue;
+        }
+        let glyph = match character {
+            ' '..='~' => GLYPHS[(character as u8 - b' ') as usize],
+            _ => REPLACEMENT_GLYPH,
+        };
+        let point = |byte: u8| {
+            (
+                x + (byte >> 4) as isize * scale,
+                y + (byte & 0x0F) as isize * scale,
+            )
+        };
+        for stroke in glyph.split(|byte| *byte == PEN_UP) {
+            for line in stroke.windows(2) {
+                writer.draw_line(point(line[0]), point(line[1]), color);
+            }
+        }
+        x += ADVANCE as isize * scale;
+    }
+    (x, y)
+}
+
+#[cfg(all(test, feature = "global", feature = "graphics-modes"))]
+mod test {
+    use super::*;
+    use crate::{
+        mock::lock_for_test,
+        writers::{Graphics320x200x256, GraphicsWriter, Screen},
+    };
+
+    #[test]
+    fn test_glyphs_fit_the_grid() {
+        for glyph in GLYPHS.iter() {
+            for stroke in glyph.split(|byte| *byte == PEN_UP) {
+                assert_ne!(stroke.len(), 1);
+                for point in stroke {
+                    assert!(point >> 4 < 5 && point & 0x0F < 9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_str_width() {
+        assert_eq!(str_width("", 2), 0);
+        assert_eq!(str_width("Hi", 2), 24);
+        assert_eq!(str_width("A\nlonger", 1), 36);
+        assert_eq!(line_height(3), 30);
+    }
+
+    #[test]
+    fn test_draw_str() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+        let frame_buffer = mode.get_frame_buffer();
+        let pixel = |x, y| unsafe {
+            frame_buffer
+                .add(Graphics320x200x256::offset_of(x, y))
+                .read()
+        };
+
+        let end = draw_str(&mode, (10, 10), "-T", 2, 5);
+        assert_eq!(end, (34, 10));
+        // The bar of '-' from (0, 3) to (4, 3) in grid units.
+        assert_eq!(
+            [pixel(9, 16), pixel(10, 16), pixel(18, 16), pixel(19, 16)],
+            [0, 5, 5, 0]
+        );
+        // The stem of 'T' from (2, 0) to (2, 6), 12 pixels to the right.
+        assert_eq!([pixel(26, 10), pixel(26, 22), pixel(26, 23)], [5, 5, 0]);
+
+        let end = draw_str(&mode, (10, 10), "a\n\u{e9}", 1, 5);
+        assert_eq!(end, (16, 20));
+        // The bottom of the '?' drawn for the accented letter.
+        assert_eq!(pixel(12, 26), 5);
+    }
+}
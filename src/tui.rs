@@ -0,0 +1,547 @@
+//! Text mode widgets for boot loaders and installers.
+//!
+//! Widgets are drawn with the box drawing characters of code page 437
+//! through any `TextWriter`, and are composed by drawing one inside the
+//! inner rectangle of another, such as a `Menu` inside a `Frame`. Anything
+//! outside of the screen is clipped.
+
+use crate::{
+    colors::TextModeColor,
+    drawing::Rect,
+    writers::{ScreenCharacter, TextWriter},
+};
+
+const FULL_BLOCK: u8 = 0xDB;
+const LIGHT_SHADE: u8 = 0xB0;
+
+/// Implementations of this trait can be drawn to a `TextWriter`.
+pub trait Widget {
+    /// Draws the widget to `writer`.
+    fn draw<T: TextWriter>(&self, writer: &T);
+}
+
+/// The lines a `Frame` is drawn with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum BorderStyle {
+    /// Single lines, like `┌─┐`.
+    #[default]
+    Single,
+    /// Double lines, like `╔═╗`.
+    Double,
+}
+
+impl BorderStyle {
+    /// Returns the top left, top right, bottom left and bottom right
+    /// corners followed by the horizontal and vertical lines.
+    const fn characters(self) -> [u8; 6] {
+        match self {
+            BorderStyle::Single => [0xDA, 0xBF, 0xC0, 0xD9, 0xC4, 0xB3],
+            BorderStyle::Double => [0xC9, 0xBB, 0xC8, 0xBC, 0xCD, 0xBA],
+        }
+    }
+}
+
+/// A bordered box with an optional title centered in its top border.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "text-modes"))]
+/// # {
+/// use vga::colors::{Color16, TextModeColor};
+/// use vga::drawing::Rect;
+/// use vga::tui::{BorderStyle, Frame, Widget};
+/// use vga::writers::{Text80x25, TextWriter};
+///
+/// let text_mode = Text80x25::new();
+/// text_mode.set_mode();
+/// let color = TextModeColor::new(Color16::White, Color16::Blue);
+/// Frame::new(Rect::new(10, 5, 60, 15), color)
+///     .with_title("Setup")
+///     .with_border_style(BorderStyle::Double)
+///     .draw(&text_mode);
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Frame<'a> {
+    rect: Rect,
+    color: TextModeColor,
+    title: Option<&'a str>,
+    border_style: BorderStyle,
+}
+
+impl<'a> Frame<'a> {
+    /// Creates a new `Frame` covering `rect`, with single lines and no title.
+    pub const fn new(rect: Rect, color: TextModeColor) -> Frame<'a> {
+        Frame {
+            rect,
+            color,
+            title: None,
+            border_style: BorderStyle::Single,
+        }
+    }
+
+    /// Sets the title shown in the top border.
+    pub const fn with_title(mut self, title: &'a str) -> Frame<'a> {
+        self.title = Some(title);
+        self
+    }
+
+    /// Sets the lines the border is drawn with.
+    pub const fn with_border_style(mut self, border_style: BorderStyle) -> Frame<'a> {
+        self.border_style = border_style;
+        self
+    }
+
+    /// Returns the area covered by the `Frame`, including its border.
+    pub const fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Returns the area inside the border, for drawing other widgets in.
+    pub const fn get_inner_rect(&self) -> Rect {
+        Rect::new(
+            self.rect.get_x() + 1,
+            self.rect.get_y() + 1,
+            self.rect.get_width().saturating_sub(2),
+            self.rect.get_height().saturating_sub(2),
+        )
+    }
+}
+
+impl Widget for Frame<'_> {
+    fn draw<T: TextWriter>(&self, writer: &T) {
+        let (x, y) = (self.rect.get_x(), self.rect.get_y());
+        let (width, height) = (self.rect.get_width(), self.rect.get_height());
+        if width < 2 || height < 2 {
+            return;
+        }
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] =
+            self.border_style.characters();
+        let (right, bottom) = (x + width - 1, y + height - 1);
+
+        for column in x + 1..right {
+            put_char(writer, column, y, horizontal, self.color);
+            put_char(writer, column, bottom, horizontal, self.color);
+        }
+        for row in y + 1..bottom {
+            put_char(writer, x, row, vertical, self.color);
+            put_char(writer, right, row, vertical, self.color);
+        }
+        put_char(writer, x, y, top_left, self.color);
+        put_char(writer, right, y, top_right, self.color);
+        put_char(writer, x, bottom, bottom_left, self.color);
+        put_char(writer, right, bottom, bottom_right, self.color);
+        fill(writer, self.get_inner_rect(), self.color);
+
+        if let Some(title) = self.title {
+            // Leave a corner and a space on either side of the title.
+            let title_width = title.len().min(width.saturating_sub(4));
+            if title_width > 0 {
+                let title_x = x + (width - title_width) / 2;
+                put_char(writer, title_x - 1, y, b' ', self.color);
+                put_str(writer, title_x, y, title_width, title, self.color);
+                put_char(writer, title_x + title_width, y, b' ', self.color);
+            }
+        }
+    }
+}
+
+/// A list of items with one selected, scrolled to keep the selected
+/// item visible.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "text-modes"))]
+/// # {
+/// use vga::colors::{Color16, TextModeColor};
+/// use vga::drawing::Rect;
+/// use vga::tui::{Menu, Widget};
+/// use vga::writers::{Text80x25, TextWriter};
+///
+/// let text_mode = Text80x25::new();
+/// text_mode.set_mode();
+/// let color = TextModeColor::new(Color16::LightGrey, Color16::Black);
+/// let selected_color = TextModeColor::new(Color16::Black, Color16::LightGrey);
+/// let items = ["Boot", "Boot (safe mode)", "Reboot"];
+/// let mut menu = Menu::new(Rect::new(30, 10, 20, 3), &items, color, selected_color);
+/// menu.select_next();
+/// menu.draw(&text_mode);
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Menu<'a> {
+    rect: Rect,
+    items: &'a [&'a str],
+    selected: usize,
+    color: TextModeColor,
+    selected_color: TextModeColor,
+}
+
+impl<'a> Menu<'a> {
+    /// Creates a new `Menu` of `items` covering `rect`, with the first
+    /// item selected.
+    pub const fn new(
+        rect: Rect,
+        items: &'a [&'a str],
+        color: TextModeColor,
+        selected_color: TextModeColor,
+    ) -> Menu<'a> {
+        Menu {
+            rect,
+            items,
+            selected: 0,
+            color,
+            selected_color,
+        }
+    }
+
+    /// Returns the index of the selected item.
+    pub const fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Selects the item at `index`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `index` is out of range.
+    pub fn select(&mut self, index: usize) -> Result<(), &'static str> {
+        if index >= self.items.len() {
+            return Err("menu item is out of range");
+        }
+        self.selected = index;
+        Ok(())
+    }
+
+    /// Selects the next item, wrapping around to the first.
+    pub fn select_next(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + 1) % self.items.len();
+        }
+    }
+
+    /// Selects the previous item, wrapping around to the last.
+    pub fn select_previous(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+        }
+    }
+
+    fn first_visible(&self) -> usize {
+        (self.selected + 1).saturating_sub(self.rect.get_height())
+    }
+}
+
+impl Widget for Menu<'_> {
+    fn draw<T: TextWriter>(&self, writer: &T) {
+        let first_visible = self.first_visible();
+        for row in 0..self.rect.get_height() {
+            let index = first_visible + row;
+            let color = if index == self.selected {
+                self.selected_color
+            } else {
+                self.color
+            };
+            let item = self.items.get(index).copied().unwrap_or("");
+            put_str(
+                writer,
+                self.rect.get_x(),
+                self.rect.get_y() + row,
+                self.rect.get_width(),
+                item,
+                color,
+            );
+        }
+    }
+}
+
+/// A horizontal bar filled in proportion to the progress of a task.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "text-modes"))]
+/// # {
+/// use vga::colors::{Color16, TextModeColor};
+/// use vga::tui::{ProgressBar, Widget};
+/// use vga::writers::{Text80x25, TextWriter};
+///
+/// let text_mode = Text80x25::new();
+/// text_mode.set_mode();
+/// let color = TextModeColor::new(Color16::Green, Color16::Black);
+/// let mut progress_bar = ProgressBar::new(10, 12, 60, color);
+/// progress_bar.set_progress(3, 4);
+/// progress_bar.draw(&text_mode);
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ProgressBar {
+    x: usize,
+    y: usize,
+    width: usize,
+    color: TextModeColor,
+    value: usize,
+    max: usize,
+}
+
+impl ProgressBar {
+    /// Creates a new empty `ProgressBar` of `width` characters with its
+    /// left end at `(x, y)`.
+    pub const fn new(x: usize, y: usize, width: usize, color: TextModeColor) -> ProgressBar {
+        ProgressBar {
+            x,
+            y,
+            width,
+            color,
+            value: 0,
+            max: 1,
+        }
+    }
+
+    /// Sets the progress to `value` out of `max`, clamping `value` to `max`.
+    /// A `max` of 0 shows the bar as full.
+    pub fn set_progress(&mut self, value: usize, max: usize) {
+        self.max = max;
+        self.value = value.min(max);
+    }
+
+    /// Returns the progress in percent, rounded down.
+    pub fn get_percent(&self) -> usize {
+        (self.value * 100).checked_div(self.max).unwrap_or(100)
+    }
+
+    fn filled_width(&self) -> usize {
+        (self.width * self.value)
+            .checked_div(self.max)
+            .unwrap_or(self.width)
+    }
+}
+
+impl Widget for ProgressBar {
+    fn draw<T: TextWriter>(&self, writer: &T) {
+        let filled_width = self.filled_width();
+        for column in 0..self.width {
+            let character = if column < filled_width {
+                FULL_BLOCK
+            } else {
+                LIGHT_SHADE
+            };
+            put_char(writer, self.x + column, self.y, character, self.color);
+        }
+    }
+}
+
+/// A framed message centered on the screen, with one line per line of
+/// the message.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "text-modes"))]
+/// # {
+/// use vga::colors::{Color16, TextModeColor};
+/// use vga::tui::{MessageBox, Widget};
+/// use vga::writers::{Text80x25, TextWriter};
+///
+/// let text_mode = Text80x25::new();
+/// text_mode.set_mode();
+/// let color = TextModeColor::new(Color16::White, Color16::Red);
+/// MessageBox::new("Error", "No bootable partition found.\nPress any key.", color)
+///     .draw(&text_mode);
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MessageBox<'a> {
+    title: &'a str,
+    message: &'a str,
+    color: TextModeColor,
+}
+
+impl<'a> MessageBox<'a> {
+    /// Creates a new `MessageBox` showing `message` under `title`.
+    pub const fn new(title: &'a str, message: &'a str, color: TextModeColor) -> MessageBox<'a> {
+        MessageBox {
+            title,
+            message,
+            color,
+        }
+    }
+
+    /// Returns the `Frame` of the `MessageBox` on a `width` by `height` screen.
+    pub fn get_frame(&self, width: usize, height: usize) -> Frame<'a> {
+        let message_width = self.message.lines().map(str::len).max().unwrap_or(0);
+        let message_height = self.message.lines().count();
+        // Leave a space between the border and the message on either side.
+        let frame_width = (message_width.max(self.title.len()) + 4).min(width);
+        let frame_height = (message_height + 2).min(height);
+        let rect = Rect::new(
+            (width - frame_width) / 2,
+            (height - frame_height) / 2,
+            frame_width,
+            frame_height,
+        );
+        Frame::new(rect, self.color)
+            .with_title(self.title)
+            .with_border_style(BorderStyle::Double)
+    }
+}
+
+impl Widget for MessageBox<'_> {
+    fn draw<T: TextWriter>(&self, writer: &T) {
+        let frame = self.get_frame(writer.get_width(), writer.get_height());
+        frame.draw(writer);
+        let inner_rect = frame.get_inner_rect();
+        for (row, line) in self
+            .message
+            .lines()
+            .take(inner_rect.get_height())
+            .enumerate()
+        {
+            put_str(
+                writer,
+                inner_rect.get_x() + 1,
+                inner_rect.get_y() + row,
+                inner_rect.get_width().saturating_sub(2),
+                line,
+                self.color,
+            );
+        }
+    }
+}
+
+/// Writes `character` at `(x, y)`, unless it's outside of the screen.
+fn put_char<T: TextWriter>(writer: &T, x: usize, y: usize, character: u8, color: TextModeColor) {
+    if x < T::WIDTH && y < T::HEIGHT {
+        writer.write_character(x, y, ScreenCharacter::new(character, color));
+    }
+}
+
+/// Writes `s` to the `width` characters starting at `(x, y)`, truncating it
+/// or padding it with spaces. Bytes outside of the printable ascii range are
+/// written as `0xFE`.
+fn put_str<T: TextWriter>(
+    writer: &T,
+    x: usize,
+    y: usize,
+    width: usize,
+    s: &str,
+    color: TextModeColor,
+) {
+    let mut bytes = s.bytes();
+    for column in 0..width {
+        let character = match bytes.next() {
+            Some(byte @ 0x20..=0x7E) => byte,
+            Some(_) => 0xFE,
+            None => b' ',
+        };
+        put_char(writer, x + column, y, character, color);
+    }
+}
+
+/// Fills `rect` with the blank character of `writer` in `color`.
+fn fill<T: TextWriter>(writer: &T, rect: Rect, color: TextModeColor) {
+    let blank = writer.get_blank_character().get_character();
+    writer.fill_region(rect, ScreenCharacter::new(blank, color));
+}
+
+#[cfg(all(test, feature = "global", feature = "text-modes"))]
+mod test {
+    use super::*;
+    use crate::{
+        colors::Color16,
+        mock::lock_for_test,
+        writers::{code_page_437_to_char, Text80x25},
+    };
+    use alloc::string::String;
+
+    const COLOR: TextModeColor = TextModeColor::new(Color16::White, Color16::Blue);
+    const SELECTED_COLOR: TextModeColor = TextModeColor::new(Color16::Blue, Color16::White);
+
+    fn row(writer: &Text80x25, y: usize, columns: core::ops::Range<usize>) -> String {
+        columns
+            .map(|x| writer.read_character(x, y))
+            .map(|character| code_page_437_to_char(character.get_character()))
+            .collect()
+    }
+
+    #[test]
+    fn test_frame() {
+        let _guard = lock_for_test();
+        let writer = Text80x25::new();
+        writer.set_mode();
+        writer.clear_screen();
+        let frame = Frame::new(Rect::new(76, 0, 10, 3), COLOR).with_title("Hi");
+        frame.draw(&writer);
+        assert_eq!(row(&writer, 0, 76..80), "┌── ");
+        assert_eq!(row(&writer, 1, 76..80), "│   ");
+        assert_eq!(row(&writer, 2, 75..80), " └───");
+        assert_eq!(frame.get_inner_rect(), Rect::new(77, 1, 8, 1));
+
+        Frame::new(Rect::new(0, 0, 8, 3), COLOR)
+            .with_title("Title")
+            .with_border_style(BorderStyle::Double)
+            .draw(&writer);
+        assert_eq!(row(&writer, 0, 0..8), "╔ Titl ╗");
+        assert_eq!(row(&writer, 2, 0..8), "╚══════╝");
+    }
+
+    #[test]
+    fn test_menu() {
+        let _guard = lock_for_test();
+        let writer = Text80x25::new();
+        writer.set_mode();
+        writer.clear_screen();
+        let items = ["One", "Two", "Three"];
+        let mut menu = Menu::new(Rect::new(0, 0, 4, 2), &items, COLOR, SELECTED_COLOR);
+        menu.select_previous();
+        assert_eq!(menu.get_selected(), 2);
+        menu.draw(&writer);
+        assert_eq!(row(&writer, 0, 0..5), "Two  ");
+        assert_eq!(row(&writer, 1, 0..5), "Thre ");
+        assert_eq!(writer.read_character(0, 1).get_color(), SELECTED_COLOR);
+        assert_eq!(writer.read_character(0, 0).get_color(), COLOR);
+
+        menu.select_next();
+        assert_eq!(menu.get_selected(), 0);
+        menu.draw(&writer);
+        assert_eq!(row(&writer, 0, 0..4), "One ");
+        assert!(menu.select(3).is_err());
+    }
+
+    #[test]
+    fn test_progress_bar() {
+        let _guard = lock_for_test();
+        let writer = Text80x25::new();
+        writer.set_mode();
+        writer.clear_screen();
+        let mut progress_bar = ProgressBar::new(0, 0, 8, COLOR);
+        progress_bar.set_progress(5, 10);
+        assert_eq!(progress_bar.get_percent(), 50);
+        progress_bar.draw(&writer);
+        assert_eq!(row(&writer, 0, 0..8), "████░░░░");
+
+        progress_bar.set_progress(20, 10);
+        assert_eq!(progress_bar.get_percent(), 100);
+        progress_bar.set_progress(0, 0);
+        progress_bar.draw(&writer);
+        assert_eq!(row(&writer, 0, 0..8), "████████");
+    }
+
+    #[test]
+    fn test_message_box() {
+        let _guard = lock_for_test();
+        let writer = Text80x25::new();
+        writer.set_mode();
+        writer.clear_screen();
+        let message_box = MessageBox::new("Oops", "Disk\nnot found", COLOR);
+        let frame = message_box.get_frame(80, 25);
+        assert_eq!(frame.get_rect(), Rect::new(33, 10, 13, 4));
+        message_box.draw(&writer);
+        assert_eq!(row(&writer, 10, 33..46), "╔══ Oops ═══╗");
+        assert_eq!(row(&writer, 11, 33..46), "║ Disk      ║");
+        assert_eq!(row(&writer, 12, 33..46), "║ not found ║");
+        assert_eq!(row(&writer, 13, 33..46), "╚═══════════╝");
+    }
+}
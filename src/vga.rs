@@ -1,25 +1,125 @@
 //! Provides access to the vga graphics card.
 
 use super::{
+    colors::{Palette, TextModeColor, DEFAULT_PALETTE},
     configurations::{
-        VgaConfiguration, MODE_320X200X256_CONFIGURATION, MODE_320X240X256_CONFIGURATION,
-        MODE_40X25_CONFIGURATION, MODE_40X50_CONFIGURATION, MODE_640X480X16_CONFIGURATION,
-        MODE_80X25_CONFIGURATION,
+        ConfigurationAdjustments, ModeDefinition, RegisterOverrides, VgaConfiguration,
+        MODE_1280X800X256_CONFIGURATION, MODE_320X200X256_CONFIGURATION,
+        MODE_320X240X256_CONFIGURATION, MODE_40X25_CONFIGURATION, MODE_40X50_CONFIGURATION,
+        MODE_640X480X16_CONFIGURATION, MODE_80X25_CONFIGURATION,
     },
+    drawing::Rect,
     fonts::VgaFont,
+    planar::PlanarFrameBuffer,
     registers::{
-        AttributeControllerRegisters, ColorPaletteRegisters, CrtcControllerIndex,
+        AttributeControllerRegisters, CharacterMap, ColorPaletteRegisters, CrtcControllerIndex,
         CrtcControllerRegisters, EmulationMode, GeneralRegisters, GraphicsControllerIndex,
-        GraphicsControllerRegisters, PlaneMask, SequencerIndex, SequencerRegisters,
+        GraphicsControllerRegisters, MemoryMap, PlaneMask, RegisterAccess, SequencerIndex,
+        SequencerRegisters,
     },
+    writers::ScreenCharacter,
 };
-use crate::configurations::MODE_1280X800X256_CONFIGURATION;
+#[cfg(any(test, feature = "alloc"))]
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "global")]
 use conquer_once::spin::Lazy;
-use spinning_top::Spinlock;
+use core::fmt;
+use spinning_top::{Spinlock, SpinlockGuard};
+
+/// The 6 bit dac level used by `Vga::detect_monitor`, which is above the
+/// switch sense threshold of an unloaded output, but below the threshold of
+/// an output loaded by a monitor.
+const SENSE_LEVEL: u8 = 0x12;
 
 /// Provides mutable access to the vga graphics card.
+///
+/// Only available with the `global` feature, which is enabled by default.
+#[cfg(feature = "global")]
 pub static VGA: Lazy<Spinlock<Vga>> = Lazy::new(|| Spinlock::new(Vga::new()));
 
+/// The number of functions `Vga::on_mode_change` can register at once.
+pub const MAX_MODE_CHANGE_CALLBACKS: usize = 8;
+
+/// Identifies a callback registered with `Vga::on_mode_change`, to remove
+/// it with `Vga::remove_mode_change_callback`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ModeChangeCallbackId(usize);
+
+type ModeChangeCallback = fn(Option<VideoMode>);
+#[cfg(any(test, feature = "alloc"))]
+type ModeChangeClosure = Box<dyn FnMut(Option<VideoMode>) + Send>;
+
+/// A handle to the `Vga` used by a writer, either the global `VGA`
+/// static or a `Spinlock<Vga>` provided by the caller.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "global", derive(Default))]
+#[cfg_attr(
+    not(any(feature = "text-modes", feature = "graphics-modes", feature = "modex")),
+    allow(dead_code)
+)]
+pub(crate) struct VgaHandle<'a> {
+    vga: Option<&'a Spinlock<Vga>>,
+}
+
+#[cfg_attr(
+    not(any(feature = "text-modes", feature = "graphics-modes", feature = "modex")),
+    allow(dead_code)
+)]
+impl VgaHandle<'static> {
+    /// Creates a `VgaHandle` to the global `VGA` static.
+    #[cfg(feature = "global")]
+    pub(crate) const fn global() -> VgaHandle<'static> {
+        VgaHandle { vga: None }
+    }
+}
+
+#[cfg_attr(
+    not(any(feature = "text-modes", feature = "graphics-modes", feature = "modex")),
+    allow(dead_code)
+)]
+impl<'a> VgaHandle<'a> {
+    /// Creates a `VgaHandle` to `vga`.
+    pub(crate) const fn new(vga: &'a Spinlock<Vga>) -> VgaHandle<'a> {
+        VgaHandle { vga: Some(vga) }
+    }
+
+    /// Locks the `Vga` this handle refers to.
+    pub(crate) fn lock(&self) -> SpinlockGuard<'a, Vga> {
+        match self.vga {
+            Some(vga) => vga.lock(),
+            #[cfg(feature = "global")]
+            None => VGA.lock(),
+            #[cfg(not(feature = "global"))]
+            None => unreachable!("the global vga handle requires the `global` feature"),
+        }
+    }
+
+    /// Locks the `Vga` this handle refers to, or returns `None` if it's
+    /// already locked.
+    #[cfg_attr(not(feature = "text-modes"), allow(dead_code))]
+    pub(crate) fn try_lock(&self) -> Option<SpinlockGuard<'a, Vga>> {
+        match self.vga {
+            Some(vga) => vga.try_lock(),
+            #[cfg(feature = "global")]
+            None => VGA.try_lock(),
+            #[cfg(not(feature = "global"))]
+            None => unreachable!("the global vga handle requires the `global` feature"),
+        }
+    }
+}
+
+impl fmt::Debug for VgaHandle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.vga {
+            Some(vga) => f
+                .debug_tuple("VgaHandle")
+                .field(&(vga as *const _))
+                .finish(),
+            None => f.write_str("VgaHandle(VGA)"),
+        }
+    }
+}
+
 /// Represents the starting address of the frame buffer for
 /// various video modes.
 #[derive(Debug, Copy, Clone)]
@@ -34,12 +134,13 @@ pub enum FrameBuffer {
 }
 
 impl FrameBuffer {
-    fn new(memory_map_mode: u8, video_memory_start: usize) -> FrameBuffer {
-        match memory_map_mode {
-            0x1 => FrameBuffer::GraphicsMode(video_memory_start),
-            0x2 => FrameBuffer::MdaMode(video_memory_start + 0x10000),
-            0x3 => FrameBuffer::CgaMode(video_memory_start + 0x18000),
-            _ => panic!("{:X} is not a valid FrameBuffer map mode", memory_map_mode),
+    fn new(memory_map: MemoryMap, video_memory_start: usize) -> FrameBuffer {
+        let start = video_memory_start + memory_map.get_offset();
+        match memory_map {
+            MemoryMap::Window64K => FrameBuffer::GraphicsMode(start),
+            MemoryMap::Monochrome => FrameBuffer::MdaMode(start),
+            MemoryMap::Color => FrameBuffer::CgaMode(start),
+            MemoryMap::Window128K => panic!("{:?} is not a valid FrameBuffer map mode", memory_map),
         }
     }
 }
@@ -54,8 +155,102 @@ impl From<FrameBuffer> for usize {
     }
 }
 
+/// Returns a pointer to the frame buffer of `mode`, given the virtual
+/// address `0xA0000` is mapped at, without locking `VGA` or reading any
+/// registers. This is meant for dma engines and debuggers that must not
+/// contend with the writers for the lock.
+///
+/// # Safety
+///
+/// The returned pointer is only valid if `memory_start` is where the legacy
+/// window is mapped and the vga is actually in `mode`. Accesses through it
+/// aren't synchronized with the writers, so the caller must ensure they
+/// don't race with anything holding the lock.
+pub unsafe fn frame_buffer_ptr(mode: VideoMode, memory_start: usize) -> *mut u8 {
+    (memory_start + mode.memory_map().get_offset()) as *mut u8
+}
+
+/// Represents the caching mode the frame buffer is mapped with, as
+/// configured by the kernel through the MTRRs or the PAT.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MemoryType {
+    /// Represents uncacheable memory, the default for legacy vga memory.
+    Uncacheable,
+    /// Represents write-combining memory.
+    WriteCombining,
+    /// Represents write-through memory.
+    WriteThrough,
+    /// Represents write-protected memory.
+    WriteProtected,
+    /// Represents write-back memory.
+    WriteBack,
+}
+
+/// Represents the polarity of a sync pulse.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SyncPolarity {
+    /// Represents a positive sync pulse.
+    Positive,
+    /// Represents a negative sync pulse.
+    Negative,
+}
+
+/// Represents the dot clocks selectable through the miscellaneous output register.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DotClock {
+    /// Represents the 25.175 MHz clock, used for 640 pixel wide modes.
+    Clock25Mhz,
+    /// Represents the 28.322 MHz clock, used for 720 pixel wide text modes.
+    Clock28Mhz,
+}
+
+/// Represents the kind of monitor found by `Vga::detect_monitor`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MonitorType {
+    /// Represents a color monitor, which loads all three dac outputs.
+    Color,
+    /// Represents a monochrome monitor, which only loads the green output.
+    Monochrome,
+    /// Represents no monitor being connected.
+    None,
+}
+
+/// Represents the reasons `Vga::probe` finds no usable vga.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VgaError {
+    /// Represents every read returning `0xFF` and writes being ignored, as
+    /// on a system with no device decoding the vga ports.
+    NotPresent,
+    /// Represents a device that responds, but doesn't read back the value
+    /// written to the crtc registers, so it isn't a working vga.
+    ReadBackMismatch,
+}
+
+impl fmt::Display for VgaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VgaError::NotPresent => f.write_str("no vga responded to the registers"),
+            VgaError::ReadBackMismatch => {
+                f.write_str("the vga registers didn't read back the written value")
+            }
+        }
+    }
+}
+
+/// Represents the refresh rates available for the 400 line text modes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextRefreshRate {
+    /// Represents the standard 70 Hz timing, with 449 scan lines per frame
+    /// and the 400 line sync polarities.
+    Hz70,
+    /// Represents a 60 Hz timing, with the 525 scan lines per frame and the
+    /// sync polarities of 480 line modes, which some scan converters and
+    /// capture cards require.
+    Hz60,
+}
+
 /// Represents a specified vga video mode.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VideoMode {
     /// Represents text mode 40x25.
     Mode40x25,
@@ -73,6 +268,172 @@ pub enum VideoMode {
     Mode1280x800x256,
 }
 
+impl VideoMode {
+    /// Returns the `ModeInfo` describing this video mode.
+    pub const fn mode_info(self) -> ModeInfo {
+        match self {
+            VideoMode::Mode40x25 => ModeInfo::new(40, 25)
+                .with_bits_per_pixel(16)
+                .with_layout(MemoryLayout::Text),
+            VideoMode::Mode40x50 => ModeInfo::new(40, 50)
+                .with_bits_per_pixel(16)
+                .with_layout(MemoryLayout::Text),
+            VideoMode::Mode80x25 => ModeInfo::new(80, 25)
+                .with_bits_per_pixel(16)
+                .with_layout(MemoryLayout::Text),
+            VideoMode::Mode320x200x256 => {
+                ModeInfo::new(320, 200).with_layout(MemoryLayout::Chained)
+            }
+            VideoMode::Mode320x240x256 => {
+                ModeInfo::new(320, 240).with_layout(MemoryLayout::Unchained)
+            }
+            VideoMode::Mode640x480x16 => ModeInfo::new(640, 480)
+                .with_bits_per_pixel(4)
+                .with_layout(MemoryLayout::Planar),
+            VideoMode::Mode1280x800x256 => ModeInfo::new(1280, 800).with_bits_per_pixel(32),
+        }
+    }
+
+    /// Returns the `MemoryMap` the configuration of this video mode selects.
+    pub const fn memory_map(self) -> MemoryMap {
+        match self {
+            VideoMode::Mode40x25 | VideoMode::Mode40x50 | VideoMode::Mode80x25 => MemoryMap::Color,
+            VideoMode::Mode320x200x256
+            | VideoMode::Mode320x240x256
+            | VideoMode::Mode640x480x16
+            | VideoMode::Mode1280x800x256 => MemoryMap::Window64K,
+        }
+    }
+
+    /// Returns the `VgaConfiguration` setting this video mode, looked
+    /// up at compile time when `self` is a constant.
+    pub const fn configuration(self) -> &'static VgaConfiguration {
+        match self {
+            VideoMode::Mode40x25 => &MODE_40X25_CONFIGURATION,
+            VideoMode::Mode40x50 => &MODE_40X50_CONFIGURATION,
+            VideoMode::Mode80x25 => &MODE_80X25_CONFIGURATION,
+            VideoMode::Mode320x200x256 => &MODE_320X200X256_CONFIGURATION,
+            VideoMode::Mode320x240x256 => &MODE_320X240X256_CONFIGURATION,
+            VideoMode::Mode640x480x16 => &MODE_640X480X16_CONFIGURATION,
+            VideoMode::Mode1280x800x256 => &MODE_1280X800X256_CONFIGURATION,
+        }
+    }
+}
+
+/// Represents how a video mode lays out its frame buffer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MemoryLayout {
+    /// Represents interleaved character and attribute bytes.
+    Text,
+    /// Represents one bit per pixel in each of the four planes.
+    Planar,
+    /// Represents one byte per pixel, with chain-4 addressing.
+    Chained,
+    /// Represents one byte per pixel, with consecutive pixels in consecutive planes.
+    Unchained,
+    /// Represents consecutive pixels at consecutive addresses.
+    Linear,
+}
+
+/// Describes the active video mode at runtime, so generic code can
+/// adapt to it without knowing the writer type.
+///
+/// New properties may be added in minor releases, so a `ModeInfo` is
+/// built with `ModeInfo::new` and the `with_*` methods.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ModeInfo {
+    width: usize,
+    height: usize,
+    bits_per_pixel: u8,
+    layout: MemoryLayout,
+}
+
+impl ModeInfo {
+    /// Creates a new `ModeInfo` for a mode of the given size, with 8 bits
+    /// per pixel and a `MemoryLayout::Linear` frame buffer.
+    pub const fn new(width: usize, height: usize) -> ModeInfo {
+        ModeInfo {
+            width,
+            height,
+            bits_per_pixel: 8,
+            layout: MemoryLayout::Linear,
+        }
+    }
+
+    /// Sets the number of bits per pixel, or per character cell for text modes.
+    pub const fn with_bits_per_pixel(mut self, bits_per_pixel: u8) -> ModeInfo {
+        self.bits_per_pixel = bits_per_pixel;
+        self
+    }
+
+    /// Sets the layout of the frame buffer.
+    pub const fn with_layout(mut self, layout: MemoryLayout) -> ModeInfo {
+        self.layout = layout;
+        self
+    }
+
+    /// Returns the width in pixels, or in characters for text modes.
+    pub const fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height in pixels, or in characters for text modes.
+    pub const fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of bits per pixel, or per character cell for text modes.
+    pub const fn get_bits_per_pixel(&self) -> u8 {
+        self.bits_per_pixel
+    }
+
+    /// Returns the layout of the frame buffer.
+    pub const fn get_layout(&self) -> MemoryLayout {
+        self.layout
+    }
+}
+
+/// Represents workarounds for firmware and hardware quirks applied by
+/// the writers when setting a mode.
+///
+/// A `QuirkSet` is built from `QuirkSet::default` with the `with_*`
+/// methods.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct QuirkSet {
+    reload_palette: bool,
+}
+
+impl QuirkSet {
+    /// Sets whether the writers load the active palette after setting a
+    /// mode, since some bios mess up the palette when switching modes.
+    /// This is enabled by default, see `Vga::set_active_palette`.
+    pub const fn with_reload_palette(mut self, reload_palette: bool) -> QuirkSet {
+        self.reload_palette = reload_palette;
+        self
+    }
+
+    /// Returns whether the writers load the active palette after setting a mode.
+    pub const fn get_reload_palette(&self) -> bool {
+        self.reload_palette
+    }
+}
+
+impl Default for QuirkSet {
+    fn default() -> QuirkSet {
+        QuirkSet {
+            reload_palette: true,
+        }
+    }
+}
+
+#[cfg(any(feature = "instances", not(feature = "global")))]
+impl Default for Vga {
+    fn default() -> Vga {
+        Vga::new()
+    }
+}
+
 /// Represents a vga graphics card with it's common registers,
 /// as well as the most recent video mode.
 pub struct Vga {
@@ -88,22 +449,95 @@ pub struct Vga {
     pub crtc_controller_registers: CrtcControllerRegisters,
     /// Represents the color palette registers on vga hardware.
     pub color_palette_registers: ColorPaletteRegisters,
+    register_access: RegisterAccess,
     most_recent_video_mode: Option<VideoMode>,
+    mode_info: Option<ModeInfo>,
+    active_text_page: usize,
+    blank_character: ScreenCharacter,
+    cursor_follow: bool,
+    clip_rect: Option<Rect>,
+    active_palette: Palette<256>,
+    persist_palette: bool,
+    relock_crtc: bool,
     /// Memory start. 0xa0000 for physical memory mapping
     video_memory_start: usize,
+    frame_buffer_memory_type: MemoryType,
+    quirks: QuirkSet,
+    mode_generation: usize,
+    mode_change_callbacks:
+        [Option<(ModeChangeCallbackId, ModeChangeCallback)>; MAX_MODE_CHANGE_CALLBACKS],
+    #[cfg(any(test, feature = "alloc"))]
+    mode_change_closures: Vec<(ModeChangeCallbackId, ModeChangeClosure)>,
+    next_mode_change_callback_id: usize,
 }
 
 impl Vga {
-    fn new() -> Vga {
+    /// Creates a new `Vga` using the legacy vga ports and video memory
+    /// starting at `0xA0000`.
+    ///
+    /// Only public with the `instances` feature, or when the `global`
+    /// feature is disabled. Every `Vga` drives the same hardware ports,
+    /// so callers are responsible for not using several at once.
+    #[cfg(any(feature = "instances", not(feature = "global")))]
+    pub fn new() -> Vga {
+        Vga::with_access(RegisterAccess::Port)
+    }
+
+    #[cfg(not(any(feature = "instances", not(feature = "global"))))]
+    pub(crate) fn new() -> Vga {
+        Vga::with_access(RegisterAccess::Port)
+    }
+
+    /// Creates a new `Vga` accessing its registers as specified by
+    /// `register_access`, with video memory starting at `0xA0000`.
+    ///
+    /// Like `Vga::new`, only available with the `instances` feature
+    /// or when the `global` feature is disabled.
+    ///
+    /// # Examples
+    ///
+    /// Registers mapped at offset `0x400` of a bar mapped at `bar_start`:
+    ///
+    /// ```ignore
+    /// use vga::registers::RegisterAccess;
+    /// use vga::vga::Vga;
+    ///
+    /// let mut vga = Vga::with_register_access(RegisterAccess::Mmio(bar_start + 0x400));
+    /// ```
+    #[cfg(any(feature = "instances", not(feature = "global")))]
+    pub fn with_register_access(register_access: RegisterAccess) -> Vga {
+        Vga::with_access(register_access)
+    }
+
+    pub(crate) fn with_access(register_access: RegisterAccess) -> Vga {
         Vga {
-            general_registers: GeneralRegisters::new(),
-            sequencer_registers: SequencerRegisters::new(),
-            graphics_controller_registers: GraphicsControllerRegisters::new(),
-            attribute_controller_registers: AttributeControllerRegisters::new(),
-            crtc_controller_registers: CrtcControllerRegisters::new(),
-            color_palette_registers: ColorPaletteRegisters::new(),
+            general_registers: GeneralRegisters::new(register_access),
+            sequencer_registers: SequencerRegisters::new(register_access),
+            graphics_controller_registers: GraphicsControllerRegisters::new(register_access),
+            attribute_controller_registers: AttributeControllerRegisters::new(register_access),
+            crtc_controller_registers: CrtcControllerRegisters::new(register_access),
+            color_palette_registers: ColorPaletteRegisters::new(register_access),
+            register_access,
             most_recent_video_mode: None,
+            mode_info: None,
+            active_text_page: 0,
+            blank_character: ScreenCharacter::BLANK,
+            cursor_follow: false,
+            clip_rect: None,
+            active_palette: DEFAULT_PALETTE,
+            persist_palette: true,
+            relock_crtc: true,
+            #[cfg(not(any(test, feature = "test-backend")))]
             video_memory_start: 0xa0000,
+            #[cfg(any(test, feature = "test-backend"))]
+            video_memory_start: crate::mock::video_memory_start(),
+            frame_buffer_memory_type: MemoryType::Uncacheable,
+            quirks: QuirkSet::default(),
+            mode_generation: 0,
+            mode_change_callbacks: [None; MAX_MODE_CHANGE_CALLBACKS],
+            #[cfg(any(test, feature = "alloc"))]
+            mode_change_closures: Vec::new(),
+            next_mode_change_callback_id: 0,
         }
     }
 
@@ -114,35 +548,240 @@ impl Vga {
     /// # Example
     ///
     /// ```
+    /// # #[cfg(feature = "global")]
+    /// # {
     /// use vga;
     ///
     /// vga::vga::VGA.lock().set_memory_start(0xa0000);
+    /// # }
     /// ```
     pub fn set_memory_start(&mut self, video_memory_start: usize) {
         self.video_memory_start = video_memory_start;
     }
 
+    /// Tells the writers which `MemoryType` the frame buffer is mapped with,
+    /// so they can pick a matching copy strategy. On write-combining memory,
+    /// clears and unscaled blits of the linear modes use bulk copies or
+    /// streaming stores followed by a store fence, instead of one volatile
+    /// store per pixel.
+    ///
+    /// The default is `MemoryType::Uncacheable`. This doesn't change the
+    /// mapping itself, which is up to the kernel.
+    pub fn set_frame_buffer_memory_type(&mut self, memory_type: MemoryType) {
+        self.frame_buffer_memory_type = memory_type;
+    }
+
+    /// Returns the `MemoryType` the frame buffer is mapped with, as set by
+    /// `set_frame_buffer_memory_type`.
+    pub fn get_frame_buffer_memory_type(&self) -> MemoryType {
+        self.frame_buffer_memory_type
+    }
+
+    /// Sets the `QuirkSet` applied by the writers.
+    pub fn set_quirks(&mut self, quirks: QuirkSet) {
+        self.quirks = quirks;
+    }
+
+    /// Returns the `QuirkSet` applied by the writers.
+    pub fn get_quirks(&self) -> QuirkSet {
+        self.quirks
+    }
+
+    /// Loads `palette` into the color palette registers and makes it the
+    /// active palette, which the writers load again after setting a mode,
+    /// so it survives mode switches. The active palette starts out as
+    /// `DEFAULT_PALETTE`.
+    ///
+    /// **Note:** Palettes loaded directly with `color_palette_registers`
+    /// don't change the active palette.
+    pub fn set_active_palette(&mut self, palette: &Palette<256>) {
+        self.active_palette = *palette;
+        self.color_palette_registers.load_palette(palette);
+    }
+
+    /// Returns the active palette, see `set_active_palette`.
+    pub fn get_active_palette(&self) -> &Palette<256> {
+        &self.active_palette
+    }
+
+    /// Sets whether the writers load the active palette after setting a
+    /// mode, or always load `DEFAULT_PALETTE` like older versions did.
+    /// This is enabled by default.
+    pub fn set_persist_palette(&mut self, persist_palette: bool) {
+        self.persist_palette = persist_palette;
+    }
+
+    /// Returns whether the writers load the active palette after setting a mode.
+    pub fn get_persist_palette(&self) -> bool {
+        self.persist_palette
+    }
+
+    /// Sets whether the crtc registers `CR00` to `CR07` are locked again
+    /// after a mode is set, or its timings are changed with methods like
+    /// `set_text_refresh_rate`, so stray writes can't destroy the timings.
+    /// This is enabled by default.
+    pub fn set_relock_crtc(&mut self, relock_crtc: bool) {
+        self.relock_crtc = relock_crtc;
+    }
+
+    /// Returns whether the crtc registers `CR00` to `CR07` are locked
+    /// again after a mode is set.
+    pub fn get_relock_crtc(&self) -> bool {
+        self.relock_crtc
+    }
+
+    /// Loads the active palette into the color palette registers, or
+    /// `DEFAULT_PALETTE` if palette persistence is disabled.
+    pub fn load_active_palette(&mut self) {
+        if self.persist_palette {
+            self.color_palette_registers
+                .load_palette(&self.active_palette);
+        } else {
+            self.color_palette_registers.load_palette(&DEFAULT_PALETTE);
+        }
+    }
+
+    /// Returns how the registers of this `Vga` are accessed.
+    pub fn get_register_access(&self) -> RegisterAccess {
+        self.register_access
+    }
+
+    /// Returns the `MemoryType` the kernel should map the current frame buffer with.
+    ///
+    /// Graphics frame buffers are mostly written sequentially and benefit
+    /// from write-combining, while text frame buffers are read back when
+    /// scrolling and are best left uncacheable.
+    pub fn recommended_frame_buffer_memory_type(&mut self) -> MemoryType {
+        match self.get_frame_buffer() {
+            FrameBuffer::GraphicsMode(_) => MemoryType::WriteCombining,
+            FrameBuffer::CgaMode(_) | FrameBuffer::MdaMode(_) => MemoryType::Uncacheable,
+        }
+    }
+
     /// Sets the vga graphics card to the given `VideoMode`.
     pub fn set_video_mode(&mut self, video_mode: VideoMode) {
-        match video_mode {
-            VideoMode::Mode40x25 => self.set_video_mode_40x25(),
-            VideoMode::Mode40x50 => self.set_video_mode_40x50(),
-            VideoMode::Mode80x25 => self.set_video_mode_80x25(),
-            VideoMode::Mode320x200x256 => self.set_video_mode_320x200x256(),
-            VideoMode::Mode320x240x256 => self.set_video_mode_320x240x256(),
-            VideoMode::Mode640x480x16 => self.set_video_mode_640x480x16(),
-            VideoMode::Mode1280x800x256 => self.set_video_mode_1280x800x256(),
-        }
+        self.set_registers(video_mode.configuration(), &RegisterOverrides::default());
+        self.most_recent_video_mode = Some(video_mode);
+        self.mode_info = Some(video_mode.mode_info());
+        self.mode_changed();
+    }
+
+    /// Sets the vga graphics card to the custom mode described by
+    /// `definition`, such as one declared with `vga_mode!`, validating its
+    /// registers like `set_configuration`.
+    ///
+    /// The most recent video mode is reset to `None`, while `get_mode_info`
+    /// returns the `ModeInfo` of `definition`.
+    pub fn set_mode_definition(&mut self, definition: &ModeDefinition) -> ConfigurationAdjustments {
+        let adjustments = self.apply_configuration(definition.get_configuration());
+        self.mode_info = Some(definition.get_mode_info());
+        self.mode_changed();
+        adjustments
+    }
+
+    /// Sets the registers specified by `configuration`, after adjusting any
+    /// values that could damage a monitor or leave the vga unusable, and
+    /// returns which values were adjusted.
+    ///
+    /// The horizontal sync frequency is kept at or below
+    /// `MAX_HORIZONTAL_SYNC_FREQUENCY`, the refresh rate at or below
+    /// `MAX_VERTICAL_REFRESH_RATE`, external dot clocks are replaced by the
+    /// standard 25/28 MHz clocks, and the memory map and sequencer reset
+    /// registers are kept in a usable state. Since `configuration` doesn't
+    /// correspond to a `VideoMode`, the most recent video mode is reset to `None`.
+    pub fn set_configuration(
+        &mut self,
+        configuration: &VgaConfiguration,
+    ) -> ConfigurationAdjustments {
+        let adjustments = self.apply_configuration(configuration);
+        self.mode_changed();
+        adjustments
+    }
+
+    /// Sets the registers specified by `configuration` like `set_configuration`,
+    /// without running the mode change hook.
+    fn apply_configuration(
+        &mut self,
+        configuration: &VgaConfiguration,
+    ) -> ConfigurationAdjustments {
+        let emulation_mode = self.get_emulation_mode();
+        let clocking_mode = self.sequencer_registers.read(SequencerIndex::ClockingMode);
+        let crtc_controller_registers = &mut self.crtc_controller_registers;
+        let overrides = RegisterOverrides::new(
+            configuration,
+            |index| crtc_controller_registers.read(emulation_mode, index),
+            clocking_mode,
+        );
+        self.set_registers(configuration, &overrides);
+        self.most_recent_video_mode = None;
+        self.mode_info = None;
+        overrides.adjustments
+    }
+
+    /// Sets the registers specified by `configuration` without any validation.
+    ///
+    /// # Safety
+    ///
+    /// Sync timings outside of the range supported by the monitor can damage
+    /// older CRT monitors, and invalid memory map or sequencer values can
+    /// cause `get_frame_buffer` to panic or video memory to be lost. The caller
+    /// must ensure `configuration` is valid for the connected hardware.
+    pub unsafe fn set_configuration_unchecked(&mut self, configuration: &VgaConfiguration) {
+        self.set_registers(configuration, &RegisterOverrides::default());
+        self.most_recent_video_mode = None;
+        self.mode_info = None;
+        self.mode_changed();
+    }
+
+    /// Sets the registers specified by `configuration` like
+    /// `set_configuration_unchecked`, for an svga mode described by `mode_info`.
+    ///
+    /// # Safety
+    ///
+    /// The same as `set_configuration_unchecked`.
+    #[cfg(feature = "cirrus-device")]
+    pub(crate) unsafe fn set_extended_mode(
+        &mut self,
+        configuration: &VgaConfiguration,
+        mode_info: ModeInfo,
+    ) {
+        self.set_registers(configuration, &RegisterOverrides::default());
+        self.most_recent_video_mode = None;
+        self.mode_info = Some(mode_info);
+        self.mode_changed();
     }
 
     /// Gets the `FrameBuffer` address as specified by the
     /// `Miscellaneous Output Register`.
     pub fn get_frame_buffer(&mut self) -> FrameBuffer {
-        let miscellaneous_graphics = self
-            .graphics_controller_registers
-            .read(GraphicsControllerIndex::Miscellaneous);
-        let memory_map_mode = (miscellaneous_graphics >> 0x2) & 0x3;
-        FrameBuffer::new(memory_map_mode, self.video_memory_start)
+        let memory_map = self.get_memory_map();
+        FrameBuffer::new(memory_map, self.video_memory_start)
+    }
+
+    /// Returns the `MemoryMap` the vga currently responds to, which is
+    /// the part of the legacy window that must be mapped for the active mode.
+    pub fn get_memory_map(&mut self) -> MemoryMap {
+        self.graphics_controller_registers.get_memory_map()
+    }
+
+    /// Sets the `MemoryMap` the vga responds to.
+    ///
+    /// When only the part of the legacy window needed by the mode is mapped,
+    /// `set_memory_start` relocates the writers to it, given the virtual
+    /// address `0xA0000` would be at, which is the address the window is
+    /// mapped at minus `MemoryMap::get_offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for `MemoryMap::Window128K`, which `get_frame_buffer`
+    /// and the writers don't support.
+    pub fn set_memory_map(&mut self, memory_map: MemoryMap) -> Result<(), &'static str> {
+        if memory_map == MemoryMap::Window128K {
+            return Err("the 128K memory map isn't supported by the writers");
+        }
+        self.graphics_controller_registers
+            .set_memory_map(memory_map);
+        Ok(())
     }
 
     /// Returns the most recent video mode, or `None` if no
@@ -151,13 +790,618 @@ impl Vga {
         self.most_recent_video_mode
     }
 
+    /// Returns the `ModeInfo` of the most recent video mode or mode
+    /// definition, or `None` if neither has been set yet.
+    pub fn get_mode_info(&self) -> Option<ModeInfo> {
+        self.mode_info
+    }
+
+    /// Returns the number of times the mode was set, wrapping around on
+    /// overflow.
+    ///
+    /// Code keeping state derived from the mode, such as a console laid
+    /// out for its size, can compare it with a value read earlier to find
+    /// out whether the mode changed in the meantime.
+    pub fn get_mode_generation(&self) -> usize {
+        self.mode_generation
+    }
+
+    /// Registers `callback` to be called whenever the mode is set, with
+    /// the new `VideoMode`, or `None` for modes set with `set_configuration`
+    /// or `set_mode_definition`, so cached frame buffer pointers and
+    /// geometry can be invalidated.
+    ///
+    /// Callbacks are called in the order they were registered, after the
+    /// registers are set, while the `Vga` is still locked, so they must not
+    /// lock it themselves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `MAX_MODE_CHANGE_CALLBACKS` callbacks are
+    /// already registered.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "global")]
+    /// # {
+    /// use core::sync::atomic::{AtomicBool, Ordering};
+    /// use vga::vga::{VideoMode, VGA};
+    ///
+    /// static GEOMETRY_STALE: AtomicBool = AtomicBool::new(false);
+    ///
+    /// fn invalidate_geometry(_video_mode: Option<VideoMode>) {
+    ///     GEOMETRY_STALE.store(true, Ordering::Relaxed);
+    /// }
+    ///
+    /// let id = VGA.lock().on_mode_change(invalidate_geometry).unwrap();
+    /// VGA.lock().set_video_mode(VideoMode::Mode640x480x16);
+    /// assert!(GEOMETRY_STALE.load(Ordering::Relaxed));
+    /// VGA.lock().remove_mode_change_callback(id);
+    /// # }
+    /// ```
+    pub fn on_mode_change(
+        &mut self,
+        callback: fn(Option<VideoMode>),
+    ) -> Result<ModeChangeCallbackId, &'static str> {
+        let slot = self
+            .mode_change_callbacks
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or("too many mode change callbacks are registered")?;
+        let id = ModeChangeCallbackId(self.next_mode_change_callback_id);
+        self.next_mode_change_callback_id += 1;
+        *slot = Some((id, callback));
+        Ok(id)
+    }
+
+    /// Registers the closure `callback` to be called whenever the mode is
+    /// set, like `on_mode_change`. Closures are called after the functions
+    /// registered with `on_mode_change`, and aren't limited in number.
+    ///
+    /// Only available with the `alloc` feature.
+    #[cfg(any(test, feature = "alloc"))]
+    pub fn on_mode_change_closure<F>(&mut self, callback: F) -> ModeChangeCallbackId
+    where
+        F: FnMut(Option<VideoMode>) + Send + 'static,
+    {
+        let id = ModeChangeCallbackId(self.next_mode_change_callback_id);
+        self.next_mode_change_callback_id += 1;
+        self.mode_change_closures.push((id, Box::new(callback)));
+        id
+    }
+
+    /// Removes the mode change callback registered as `id`, returning
+    /// whether it was still registered.
+    pub fn remove_mode_change_callback(&mut self, id: ModeChangeCallbackId) -> bool {
+        if let Some(slot) = self
+            .mode_change_callbacks
+            .iter_mut()
+            .find(|slot| matches!(slot, Some((slot_id, _)) if *slot_id == id))
+        {
+            *slot = None;
+            return true;
+        }
+        #[cfg(any(test, feature = "alloc"))]
+        if let Some(index) = self
+            .mode_change_closures
+            .iter()
+            .position(|(closure_id, _)| *closure_id == id)
+        {
+            drop(self.mode_change_closures.remove(index));
+            return true;
+        }
+        false
+    }
+
+    /// Counts a mode change and calls the mode change callbacks.
+    fn mode_changed(&mut self) {
+        self.mode_generation = self.mode_generation.wrapping_add(1);
+        let video_mode = self.most_recent_video_mode;
+        for (_, callback) in self.mode_change_callbacks.iter().flatten() {
+            callback(video_mode);
+        }
+        #[cfg(any(test, feature = "alloc"))]
+        for (_, callback) in self.mode_change_closures.iter_mut() {
+            callback(video_mode);
+        }
+    }
+
+    /// Returns the text page the text writers draw to.
+    pub(crate) fn get_active_text_page(&self) -> usize {
+        self.active_text_page
+    }
+
+    /// Sets the text page the text writers draw to, which is reset to 0
+    /// whenever the registers are set.
+    pub(crate) fn set_active_text_page(&mut self, page: usize) {
+        self.active_text_page = page;
+    }
+
+    /// Returns the color the text writers clear and write with by default,
+    /// which is the color of the blank character.
+    pub(crate) fn get_default_text_attribute(&self) -> TextModeColor {
+        self.blank_character.get_color()
+    }
+
+    /// Sets the color the text writers clear and write with by default,
+    /// which is kept when the registers are set.
+    pub(crate) fn set_default_text_attribute(&mut self, color: TextModeColor) {
+        self.blank_character.set_color(color);
+    }
+
+    /// Returns the character the text writers clear the screen and fill
+    /// scrolled in rows with.
+    pub(crate) fn get_blank_character(&self) -> ScreenCharacter {
+        self.blank_character
+    }
+
+    /// Sets the character the text writers clear the screen and fill
+    /// scrolled in rows with, which is kept when the registers are set.
+    pub(crate) fn set_blank_character(&mut self, blank_character: ScreenCharacter) {
+        self.blank_character = blank_character;
+    }
+
+    /// Returns whether the text writers move the cursor after the
+    /// characters they write at a position.
+    pub(crate) fn get_cursor_follow(&self) -> bool {
+        self.cursor_follow
+    }
+
+    /// Sets whether the text writers move the cursor after the characters
+    /// they write at a position, which is kept when the registers are set.
+    pub(crate) fn set_cursor_follow(&mut self, cursor_follow: bool) {
+        self.cursor_follow = cursor_follow;
+    }
+
+    /// Returns the area the graphics writers clip drawing to, in addition
+    /// to the screen.
+    pub(crate) fn get_clip_rect(&self) -> Option<Rect> {
+        self.clip_rect
+    }
+
+    /// Sets the area the graphics writers clip drawing to, which is reset
+    /// to `None` whenever the registers are set.
+    pub(crate) fn set_clip_rect(&mut self, clip_rect: Option<Rect>) {
+        self.clip_rect = clip_rect;
+    }
+
+    /// Checks that a vga responds to this `Vga`'s registers, with a benign
+    /// write and read back of the cursor location low register, which is
+    /// restored afterwards.
+    ///
+    /// Systems without a vga, such as some virtual machines, read `0xFF`
+    /// from every port and ignore writes, so the writers would otherwise
+    /// draw into nothing or wait forever for a retrace.
+    ///
+    /// # Errors
+    ///
+    /// Returns `VgaError::NotPresent` if the register reads `0xFF` both
+    /// before and after the write, and `VgaError::ReadBackMismatch` if
+    /// the written value otherwise doesn't read back.
+    pub fn probe(&mut self) -> Result<(), VgaError> {
+        // The miscellaneous output register is at 0x3CC, inside the mmio
+        // window, so the emulation mode is read with the configured access.
+        let emulation_mode = self.get_emulation_mode();
+        let crtc = &mut self.crtc_controller_registers;
+        let cursor_location_low =
+            crtc.read(emulation_mode, CrtcControllerIndex::TextCursorLocationLow);
+        crtc.write(
+            emulation_mode,
+            CrtcControllerIndex::TextCursorLocationLow,
+            !cursor_location_low,
+        );
+        let read_back = crtc.read(emulation_mode, CrtcControllerIndex::TextCursorLocationLow);
+        crtc.write(
+            emulation_mode,
+            CrtcControllerIndex::TextCursorLocationLow,
+            cursor_location_low,
+        );
+        if read_back == !cursor_location_low {
+            Ok(())
+        } else if cursor_location_low == 0xFF && read_back == 0xFF {
+            Err(VgaError::NotPresent)
+        } else {
+            Err(VgaError::ReadBackMismatch)
+        }
+    }
+
     /// Returns the current `EmulationMode` as determined by the miscellaneous output register.
     pub fn get_emulation_mode(&mut self) -> EmulationMode {
         EmulationMode::from(self.general_registers.read_msr() & 0x1)
     }
 
+    /// Sets the `EmulationMode` through the miscellaneous output register,
+    /// which moves the crtc, feature control and input status 1 registers
+    /// between the monochrome (`0x3Bx`) and color (`0x3Dx`) addresses.
+    pub fn set_emulation_mode(&mut self, emulation_mode: EmulationMode) {
+        let miscellaneous_output = self.general_registers.read_msr() & 0xFE;
+        self.general_registers
+            .write_msr(miscellaneous_output | emulation_mode as u8);
+    }
+
+    /// Detects the kind of monitor connected, and sets the matching
+    /// `EmulationMode` instead of trusting the one left by the firmware.
+    ///
+    /// Every palette entry is set to a test level on some of the dac outputs,
+    /// and the switch sense bit is read while the display is enabled. The
+    /// monitor's termination keeps the outputs it loads below the sense
+    /// threshold, so the sense bit is only set if the outputs with the test
+    /// level are unconnected. The red and blue outputs are tested first,
+    /// which a monochrome monitor doesn't connect, then the green output.
+    /// The palette is restored afterwards, and the emulation mode is left
+    /// unchanged if no monitor is found.
+    pub fn detect_monitor(&mut self) -> MonitorType {
+        let mut palette = Palette::<256>::default();
+        self.color_palette_registers.read_palette(&mut palette);
+        let red_and_blue_sensed = self.sense_dac_level(SENSE_LEVEL, 0, SENSE_LEVEL);
+        let green_sensed = self.sense_dac_level(0, SENSE_LEVEL, 0);
+        self.color_palette_registers.load_palette(&palette);
+
+        let monitor_type = match (red_and_blue_sensed, green_sensed) {
+            (false, _) => MonitorType::Color,
+            (true, false) => MonitorType::Monochrome,
+            (true, true) => MonitorType::None,
+        };
+        match monitor_type {
+            MonitorType::Color => self.set_emulation_mode(EmulationMode::Cga),
+            MonitorType::Monochrome => self.set_emulation_mode(EmulationMode::Mda),
+            MonitorType::None => (),
+        }
+        monitor_type
+    }
+
+    /// Sets every palette entry to the given 6 bit color and returns the
+    /// switch sense bit, read while the display is enabled.
+    fn sense_dac_level(&mut self, red: u8, green: u8, blue: u8) -> bool {
+        let palette = Palette::<256>::new([(red, green, blue); 256]);
+        self.color_palette_registers.load_palette(&palette);
+
+        // Wait for the start of a frame, then for the display to be enabled.
+        let emulation_mode = self.get_emulation_mode();
+        while !self.general_registers.is_vertical_retrace(emulation_mode) {}
+        while self.general_registers.is_vertical_retrace(emulation_mode) {}
+        while self.general_registers.is_display_disabled(emulation_mode) {}
+        self.general_registers.get_switch_sense()
+    }
+
+    /// Sets the polarities of the horizontal and vertical sync pulses.
+    ///
+    /// Older monitors use the polarities to detect the number of scan lines:
+    /// negative horizontal and positive vertical for 400 lines, positive
+    /// horizontal and negative vertical for 350 lines, and both negative for 480 lines.
+    pub fn set_sync_polarity(&mut self, horizontal: SyncPolarity, vertical: SyncPolarity) {
+        let mut miscellaneous_output = self.general_registers.read_msr() & 0x3F;
+        if horizontal == SyncPolarity::Negative {
+            miscellaneous_output |= 0x40;
+        }
+        if vertical == SyncPolarity::Negative {
+            miscellaneous_output |= 0x80;
+        }
+        self.general_registers.write_msr(miscellaneous_output);
+    }
+
+    /// Returns the polarities of the horizontal and vertical sync pulses.
+    pub fn get_sync_polarity(&mut self) -> (SyncPolarity, SyncPolarity) {
+        let miscellaneous_output = self.general_registers.read_msr();
+        let polarity = |bit: u8| match miscellaneous_output & bit {
+            0 => SyncPolarity::Positive,
+            _ => SyncPolarity::Negative,
+        };
+        (polarity(0x40), polarity(0x80))
+    }
+
+    /// Selects the `DotClock`, holding the sequencer in reset while the
+    /// clock changes so video memory isn't corrupted.
+    pub fn set_dot_clock(&mut self, dot_clock: DotClock) {
+        let miscellaneous_output = self.general_registers.read_msr() & !0x0C;
+        let clock_select = match dot_clock {
+            DotClock::Clock25Mhz => 0x00,
+            DotClock::Clock28Mhz => 0x04,
+        };
+        self.sequencer_registers
+            .write(SequencerIndex::SequencerReset, 0x01);
+        self.general_registers
+            .write_msr(miscellaneous_output | clock_select);
+        self.sequencer_registers
+            .write(SequencerIndex::SequencerReset, 0x03);
+    }
+
+    /// Returns the selected `DotClock`, or `None` if an external clock is selected.
+    pub fn get_dot_clock(&mut self) -> Option<DotClock> {
+        match (self.general_registers.read_msr() >> 2) & 0x3 {
+            0x0 => Some(DotClock::Clock25Mhz),
+            0x1 => Some(DotClock::Clock28Mhz),
+            _ => None,
+        }
+    }
+
+    /// Sets the refresh rate of the current 400 line text mode, by changing
+    /// the vertical timing and the sync polarities. The crtc registers are
+    /// locked again afterwards if they were locked before, or if enabled
+    /// with `set_relock_crtc`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the current mode isn't a 400 line text mode.
+    pub fn set_text_refresh_rate(
+        &mut self,
+        refresh_rate: TextRefreshRate,
+    ) -> Result<(), &'static str> {
+        let emulation_mode = self.get_emulation_mode();
+        let miscellaneous_graphics = self
+            .graphics_controller_registers
+            .read(GraphicsControllerIndex::Miscellaneous);
+        let overflow = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::Overflow);
+        let vertical_display_enable_end = self.crtc_controller_registers.read(
+            emulation_mode,
+            CrtcControllerIndex::VerticalDisplayEnableEnd,
+        ) as usize
+            | (overflow as usize & 0x02) << 7
+            | (overflow as usize & 0x40) << 3;
+        if miscellaneous_graphics & 0x01 != 0 || vertical_display_enable_end != 399 {
+            return Err("the current mode isn't a 400 line text mode");
+        }
+
+        // (vertical total, sync start, sync end, blanking end, vertical sync polarity)
+        let (
+            vertical_total,
+            vertical_sync_start,
+            vertical_sync_end,
+            vertical_blanking_end,
+            polarity,
+        ) = match refresh_rate {
+            TextRefreshRate::Hz70 => (0x1BF, 0x19C, 0x0E, 0xB9, SyncPolarity::Positive),
+            TextRefreshRate::Hz60 => (0x20B, 0x1EA, 0x0C, 0x04, SyncPolarity::Negative),
+        };
+        let overflow = (overflow & !0xA5)
+            | ((vertical_total >> 8) & 0x01) as u8
+            | ((vertical_total >> 4) & 0x20) as u8
+            | ((vertical_sync_start >> 6) & 0x04) as u8
+            | ((vertical_sync_start >> 2) & 0x80) as u8;
+        // Keep the protect bit, so writing the sync end restores the lock.
+        let vertical_sync_end = (self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::VerticalSyncEnd)
+            & 0xF0)
+            | vertical_sync_end;
+
+        self.unlock_crtc_registers();
+        for (index, value) in [
+            (CrtcControllerIndex::VeritcalTotal, vertical_total as u8),
+            (CrtcControllerIndex::Overflow, overflow),
+            (
+                CrtcControllerIndex::VerticalSyncStart,
+                vertical_sync_start as u8,
+            ),
+            (CrtcControllerIndex::VerticalSyncEnd, vertical_sync_end),
+            (
+                CrtcControllerIndex::VerticalBlankingEnd,
+                vertical_blanking_end,
+            ),
+        ] {
+            self.crtc_controller_registers
+                .write(emulation_mode, index, value);
+        }
+        self.relock_crtc_registers();
+        self.set_sync_polarity(SyncPolarity::Negative, polarity);
+        Ok(())
+    }
+
+    /// Returns whether each pixel row is displayed twice, as in the
+    /// standard 200 and 240 line graphics modes.
+    pub fn get_scan_doubling(&mut self) -> bool {
+        let emulation_mode = self.get_emulation_mode();
+        let maximum_scan_line = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::MaximumScanLine);
+        maximum_scan_line & 0x80 != 0 || maximum_scan_line & 0x1F == 0x01
+    }
+
+    /// Sets whether each pixel row is displayed twice in graphics modes.
+    ///
+    /// With scan doubling, a 200 or 240 line mode fills a 400 or 480 line
+    /// frame. Without it, the rows are displayed once at the top of the
+    /// same frame and the remaining scan lines are blanked, which some fixed
+    /// frequency displays and scan converters handle better.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in text modes, where the maximum scan line register
+    /// holds the character height instead, and when doubling the rows
+    /// wouldn't fit in the vertical total, such as in 480 line modes.
+    pub fn set_scan_doubling(&mut self, enabled: bool) -> Result<(), &'static str> {
+        let emulation_mode = self.get_emulation_mode();
+        let maximum_scan_line = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::MaximumScanLine);
+        if maximum_scan_line & 0x1F > 0x01 {
+            return Err("scan doubling is only supported in graphics modes");
+        }
+
+        let scan_lines_per_row = if self.get_scan_doubling() { 2 } else { 1 };
+        let overflow = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::Overflow);
+        let vertical_display_enable_end = self.crtc_controller_registers.read(
+            emulation_mode,
+            CrtcControllerIndex::VerticalDisplayEnableEnd,
+        ) as usize
+            | (overflow as usize & 0x02) << 7
+            | (overflow as usize & 0x40) << 3;
+        let vertical_blanking_start = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::VerticalBlankingStart)
+            as usize
+            | (overflow as usize & 0x08) << 5
+            | (maximum_scan_line as usize & 0x20) << 4;
+        let vertical_total =
+            self.crtc_controller_registers
+                .read(emulation_mode, CrtcControllerIndex::VeritcalTotal) as usize
+                | (overflow as usize & 0x01) << 8
+                | (overflow as usize & 0x20) << 4;
+
+        // Keep the number of rows and the border below them the same.
+        let rows = (vertical_display_enable_end + 1) / scan_lines_per_row;
+        if enabled && rows * 2 > vertical_total {
+            return Err("scan doubled rows don't fit in the vertical total");
+        }
+        let border = vertical_blanking_start.saturating_sub(vertical_display_enable_end);
+        let (maximum_scan_line, vertical_display_enable_end) = if enabled {
+            ((maximum_scan_line & 0x60) | 0x01, rows * 2 - 1)
+        } else {
+            (maximum_scan_line & 0x60, rows - 1)
+        };
+        let vertical_blanking_start = vertical_display_enable_end + border;
+        let overflow = (overflow & !0x4A)
+            | ((vertical_display_enable_end >> 7) & 0x02) as u8
+            | ((vertical_display_enable_end >> 3) & 0x40) as u8
+            | ((vertical_blanking_start >> 5) & 0x08) as u8;
+        let maximum_scan_line =
+            (maximum_scan_line & !0x20) | ((vertical_blanking_start >> 4) & 0x20) as u8;
+
+        self.unlock_crtc_registers();
+        self.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::MaximumScanLine,
+            maximum_scan_line,
+        );
+        self.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::VerticalDisplayEnableEnd,
+            vertical_display_enable_end as u8,
+        );
+        self.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::VerticalBlankingStart,
+            vertical_blanking_start as u8,
+        );
+        self.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::Overflow,
+            overflow,
+        );
+        self.relock_crtc_registers();
+        Ok(())
+    }
+
+    /// Enables or disables the vertical retrace interrupt, raised on irq 2
+    /// (cascaded to irq 9) at the start of each vertical retrace.
+    ///
+    /// Enabling the interrupt also clears any pending one. Many modern
+    /// cards and emulators never raise it, so `is_vertical_interrupt_pending`
+    /// should be checked before relying on it.
+    pub fn set_vertical_interrupt(&mut self, enabled: bool) {
+        let emulation_mode = self.get_emulation_mode();
+        let vertical_sync_end = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::VerticalSyncEnd);
+        if enabled {
+            self.clear_vertical_interrupt();
+            self.crtc_controller_registers.write(
+                emulation_mode,
+                CrtcControllerIndex::VerticalSyncEnd,
+                (vertical_sync_end & !0x20) | 0x10,
+            );
+        } else {
+            self.crtc_controller_registers.write(
+                emulation_mode,
+                CrtcControllerIndex::VerticalSyncEnd,
+                vertical_sync_end | 0x20,
+            );
+        }
+    }
+
+    /// Returns whether the vertical retrace interrupt is enabled.
+    pub fn get_vertical_interrupt(&mut self) -> bool {
+        let emulation_mode = self.get_emulation_mode();
+        self.crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::VerticalSyncEnd)
+            & 0x20
+            == 0
+    }
+
+    /// Returns whether a vertical retrace interrupt is pending, which lets an
+    /// interrupt handler on a shared irq line tell if the vga raised it.
+    pub fn is_vertical_interrupt_pending(&mut self) -> bool {
+        self.general_registers.is_vertical_interrupt_pending()
+    }
+
+    /// Acknowledges a pending vertical retrace interrupt, so the next one
+    /// can be raised.
+    pub fn clear_vertical_interrupt(&mut self) {
+        let emulation_mode = self.get_emulation_mode();
+        let vertical_sync_end = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::VerticalSyncEnd);
+        // Writing 0 to bit 4 clears the interrupt, and writing 1 back
+        // lets the next one be raised.
+        self.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::VerticalSyncEnd,
+            vertical_sync_end & !0x10,
+        );
+        self.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::VerticalSyncEnd,
+            vertical_sync_end | 0x10,
+        );
+    }
+
     /// Loads a vga text mode font as specified by `vga_font`.
-    pub fn load_font(&mut self, vga_font: &VgaFont) {
+    ///
+    /// In text modes, when character map 0 is the primary map, the
+    /// character height of the crtc `MaximumScanLine` register is set to
+    /// the height of the font, so fonts of any height display correctly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without loading anything, when loading into the
+    /// secondary map like `load_font_into` fails.
+    pub fn load_font(&mut self, vga_font: &VgaFont) -> Result<(), &'static str> {
+        self.load_font_into(vga_font, CharacterMap::Map0)
+    }
+
+    /// Loads `vga_font` into the given character `map` like `load_font`.
+    ///
+    /// Together with `SequencerRegisters::set_character_maps`, this can
+    /// display 512 characters, by loading a second font into the secondary
+    /// map. Maps outside of the window of text modes are written through
+    /// the 64K window at `0xA0000`, like `clear_vram`.
+    ///
+    /// Only loading into the primary map of a text mode changes the
+    /// character height, along with the number of rows reported by
+    /// `get_mode_info`. Maps that aren't displayed are left alone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without loading anything, if `map` is only the
+    /// secondary map of a text mode and `vga_font` has a different
+    /// character height than the one displayed.
+    pub fn load_font_into(
+        &mut self,
+        vga_font: &VgaFont,
+        map: CharacterMap,
+    ) -> Result<(), &'static str> {
+        // Bit 0 of the graphics controller's miscellaneous register
+        // disables text mode.
+        let text_mode = self
+            .graphics_controller_registers
+            .read(GraphicsControllerIndex::Miscellaneous)
+            & 0x01
+            == 0;
+        let (primary, secondary) = self.sequencer_registers.get_character_maps();
+        let character_height = vga_font.get_character_height() as u8;
+        if text_mode
+            && map != primary
+            && map == secondary
+            && character_height != self.get_character_height()
+        {
+            return Err("the secondary character map must match the displayed character height");
+        }
+
         // Save registers
         let (
             plane_mask,
@@ -165,11 +1409,10 @@ impl Vga {
             read_plane_select,
             graphics_mode,
             miscellaneous_graphics,
-        ) = self.save_font_registers();
+        ) = self.save_plane_registers();
 
         // Switch to flat addressing
-        self.sequencer_registers
-            .write(SequencerIndex::MemoryMode, sequencer_memory_mode | 0x04);
+        self.sequencer_registers.set_odd_even(false);
 
         // Disable Even/Odd addressing
         self.graphics_controller_registers
@@ -179,24 +1422,195 @@ impl Vga {
             miscellaneous_graphics & !0x02,
         );
 
+        // Character maps past the 32K text mode window need the 64K one
+        let map_end = map.get_offset() + 256 * 32;
+        if map_end > self.get_memory_map().get_size() {
+            self.graphics_controller_registers
+                .set_memory_map(MemoryMap::Window64K);
+        }
+
         // Write font to plane
-        self.sequencer_registers.set_plane_mask(PlaneMask::PLANE2);
-
-        let frame_buffer = usize::from(self.get_frame_buffer()) as *mut u8;
-
-        for character in 0..vga_font.characters {
-            for row in 0..vga_font.character_height {
-                let offset = (character * 32) + row;
-                let font_offset = (character * vga_font.character_height) + row;
-                unsafe {
-                    frame_buffer
-                        .offset(offset as isize)
-                        .write_volatile(vga_font.font_data[font_offset as usize]);
-                }
+        let mut planes = PlanarFrameBuffer::new(self);
+        let font_data = vga_font.get_font_data();
+        for character in 0..vga_font.get_characters() {
+            for row in 0..vga_font.get_character_height() {
+                let offset = map.get_offset() + (character as usize * 32) + row as usize;
+                let font_offset = (character * vga_font.get_character_height()) + row;
+                planes.write_byte(PlaneMask::PLANE2, offset, font_data[font_offset as usize]);
             }
         }
+        drop(planes);
+
+        self.restore_plane_registers(
+            plane_mask,
+            sequencer_memory_mode,
+            read_plane_select,
+            graphics_mode,
+            miscellaneous_graphics,
+        );
+
+        if text_mode && map == primary {
+            self.set_character_height(character_height);
+            self.update_text_rows();
+        }
+        Ok(())
+    }
+
+    /// Loads `vga_font` like `load_font`, and adapts the text mode to its
+    /// character height.
+    ///
+    /// Besides the character height, this moves the cursor and underline
+    /// to the bottom of the new character cells, keeping a cursor that
+    /// starts at the top of the cell a block cursor, and an underline
+    /// below the cells hidden. The number of rows changes to fit the
+    /// displayed scan lines, which is reflected by `get_mode_info`, so
+    /// loading an 8x8 font in mode 80x25 results in 80x50.
+    ///
+    /// **Note:** Writers keep their fixed size, so `Text80x25` writes
+    /// the top 25 rows of 80x50.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in graphics modes, or if `vga_font` isn't valid
+    /// as checked by `VgaFont::new`.
+    pub fn set_text_font(&mut self, vga_font: &VgaFont) -> Result<(), &'static str> {
+        let miscellaneous_graphics = self
+            .graphics_controller_registers
+            .read(GraphicsControllerIndex::Miscellaneous);
+        if miscellaneous_graphics & 0x01 != 0 {
+            return Err("text fonts can only be set in text modes");
+        }
+
+        let emulation_mode = self.get_emulation_mode();
+        let old_height = self.get_character_height();
+        self.load_font(vga_font)?;
+        let height = vga_font.get_character_height() as u8;
+
+        let cursor_start = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::TextCursorStart);
+        let cursor_end = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::TextCursorEnd);
+        let underline_location = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::UnderlineLocation);
+        let cursor_start = match cursor_start & 0x1F {
+            0 => cursor_start,
+            _ => (cursor_start & !0x1F) | height.saturating_sub(2),
+        };
+        let cursor_end = (cursor_end & !0x1F) | (height - 1);
+        let underline_location = if underline_location & 0x1F < old_height {
+            (underline_location & !0x1F) | (height - 1)
+        } else {
+            underline_location
+        };
+        self.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::TextCursorStart,
+            cursor_start,
+        );
+        self.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::TextCursorEnd,
+            cursor_end,
+        );
+        self.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::UnderlineLocation,
+            underline_location,
+        );
+        Ok(())
+    }
+
+    /// Returns the character height of the crtc `MaximumScanLine` register.
+    fn get_character_height(&mut self) -> u8 {
+        let emulation_mode = self.get_emulation_mode();
+        (self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::MaximumScanLine)
+            & 0x1F)
+            + 1
+    }
+
+    /// Updates the rows of the text mode's `ModeInfo` to the displayed scan
+    /// lines divided by the character height.
+    fn update_text_rows(&mut self) {
+        if let Some(mode_info) = self.mode_info {
+            let emulation_mode = self.get_emulation_mode();
+            let overflow = self
+                .crtc_controller_registers
+                .read(emulation_mode, CrtcControllerIndex::Overflow);
+            let scan_lines = (self.crtc_controller_registers.read(
+                emulation_mode,
+                CrtcControllerIndex::VerticalDisplayEnableEnd,
+            ) as usize
+                | (overflow as usize & 0x02) << 7
+                | (overflow as usize & 0x40) << 3)
+                + 1;
+            let rows = scan_lines / usize::from(self.get_character_height());
+            self.mode_info = Some(
+                ModeInfo::new(mode_info.get_width(), rows)
+                    .with_bits_per_pixel(mode_info.get_bits_per_pixel())
+                    .with_layout(mode_info.get_layout()),
+            );
+        }
+    }
+
+    /// Sets the character height field of the crtc `MaximumScanLine` register.
+    fn set_character_height(&mut self, character_height: u8) {
+        let emulation_mode = self.get_emulation_mode();
+        let maximum_scan_line = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::MaximumScanLine);
+        let value = (maximum_scan_line & !0x1F) | (character_height - 1);
+        if value != maximum_scan_line {
+            self.crtc_controller_registers.write(
+                emulation_mode,
+                CrtcControllerIndex::MaximumScanLine,
+                value,
+            );
+        }
+    }
+
+    /// Clears all four planes of video memory, which also clears the font
+    /// of text modes.
+    ///
+    /// Every write goes to all planes at once, so only the 64K of one
+    /// plane are written. The vga is temporarily mapped at `0xA0000` for
+    /// this, so that window must be mapped when `set_memory_start`
+    /// relocated the frame buffer.
+    pub fn clear_vram(&mut self) {
+        self.clear_planes(PlaneMask::ALL_PLANES);
+    }
+
+    /// Clears the given `planes` of video memory, like `clear_vram`.
+    pub(crate) fn clear_planes(&mut self, planes: PlaneMask) {
+        let (
+            plane_mask,
+            sequencer_memory_mode,
+            read_plane_select,
+            graphics_mode,
+            miscellaneous_graphics,
+        ) = self.save_plane_registers();
+
+        // Address every plane with the same offset
+        self.sequencer_registers.set_chain4(false);
+        self.sequencer_registers.set_odd_even(false);
+        self.graphics_controller_registers
+            .write(GraphicsControllerIndex::GraphicsMode, graphics_mode & !0x10);
+        self.graphics_controller_registers.write(
+            GraphicsControllerIndex::Miscellaneous,
+            miscellaneous_graphics & !0x02,
+        );
+        self.graphics_controller_registers
+            .set_memory_map(MemoryMap::Window64K);
 
-        self.restore_font_registers(
+        let mut frame_buffer = PlanarFrameBuffer::new(self);
+        frame_buffer.fill(planes, 0..MemoryMap::Window64K.get_size(), 0);
+        drop(frame_buffer);
+
+        self.restore_plane_registers(
             plane_mask,
             sequencer_memory_mode,
             read_plane_select,
@@ -205,7 +1619,7 @@ impl Vga {
         );
     }
 
-    fn restore_font_registers(
+    fn restore_plane_registers(
         &mut self,
         plane_mask: u8,
         sequencer_memory_mode: u8,
@@ -227,7 +1641,7 @@ impl Vga {
         );
     }
 
-    fn save_font_registers(&mut self) -> (u8, u8, u8, u8, u8) {
+    fn save_plane_registers(&mut self) -> (u8, u8, u8, u8, u8) {
         (
             self.sequencer_registers.read(SequencerIndex::PlaneMask),
             self.sequencer_registers.read(SequencerIndex::MemoryMode),
@@ -240,30 +1654,46 @@ impl Vga {
         )
     }
 
-    fn set_registers(&mut self, configuration: &VgaConfiguration) {
+    fn set_registers(&mut self, configuration: &VgaConfiguration, overrides: &RegisterOverrides) {
         let emulation_mode = self.get_emulation_mode();
+        self.active_text_page = 0;
+        self.clip_rect = None;
 
         // Set miscellaneous output
-        self.general_registers
-            .write_msr(configuration.miscellaneous_output);
+        self.general_registers.write_msr(
+            overrides
+                .miscellaneous_output
+                .unwrap_or(configuration.miscellaneous_output),
+        );
 
         // Set the sequencer registers.
         for (index, value) in configuration.sequencer_registers {
-            self.sequencer_registers.write(*index, *value);
+            self.sequencer_registers
+                .write(*index, overrides.sequencer(*index, *value));
         }
 
         // Unlock the crtc registers.
-        self.unlock_crtc_registers(emulation_mode);
+        self.unlock_crtc_registers();
 
         // Set the crtc registers.
         for (index, value) in configuration.crtc_controller_registers {
-            self.crtc_controller_registers
-                .write(emulation_mode, *index, *value);
+            self.crtc_controller_registers.write(
+                emulation_mode,
+                *index,
+                overrides.crtc_controller(*index, *value),
+            );
+        }
+        for (index, value) in overrides.crtc_controller_overrides() {
+            if let Some(value) = value {
+                self.crtc_controller_registers
+                    .write(emulation_mode, index, value);
+            }
         }
 
         // Set the grx registers.
         for (index, value) in configuration.graphics_controller_registers {
-            self.graphics_controller_registers.write(*index, *value);
+            self.graphics_controller_registers
+                .write(*index, overrides.graphics_controller(*index, *value));
         }
 
         // Blank the screen so the palette registers are unlocked.
@@ -279,48 +1709,26 @@ impl Vga {
         // Unblank the screen so the palette registers are locked.
         self.attribute_controller_registers
             .unblank_screen(emulation_mode);
-    }
-
-    /// Sets the video card to Mode 40x25.
-    fn set_video_mode_40x25(&mut self) {
-        self.set_registers(&MODE_40X25_CONFIGURATION);
-        self.most_recent_video_mode = Some(VideoMode::Mode40x25);
-    }
-
-    /// Sets the video card to Mode 40x50.
-    fn set_video_mode_40x50(&mut self) {
-        self.set_registers(&MODE_40X50_CONFIGURATION);
-        self.most_recent_video_mode = Some(VideoMode::Mode40x50);
-    }
-
-    /// Sets the video card to Mode 80x25.
-    fn set_video_mode_80x25(&mut self) {
-        self.set_registers(&MODE_80X25_CONFIGURATION);
-        self.most_recent_video_mode = Some(VideoMode::Mode80x25);
-    }
-
-    /// Sets the video card to Mode 320x200x256.
-    fn set_video_mode_320x200x256(&mut self) {
-        self.set_registers(&MODE_320X200X256_CONFIGURATION);
-        self.most_recent_video_mode = Some(VideoMode::Mode320x200x256);
-    }
-
-    /// Sets the video card to Mode 320x200x256x.
-    fn set_video_mode_320x240x256(&mut self) {
-        self.set_registers(&MODE_320X240X256_CONFIGURATION);
-        self.most_recent_video_mode = Some(VideoMode::Mode320x240x256);
-    }
 
-    /// Sets the video card to Mode 640x480x16.
-    fn set_video_mode_640x480x16(&mut self) {
-        self.set_registers(&MODE_640X480X16_CONFIGURATION);
-        self.most_recent_video_mode = Some(VideoMode::Mode640x480x16);
+        self.relock_crtc_registers();
     }
 
-    /// Sets the video card to Mode 1280x800x256.
-    fn set_video_mode_1280x800x256(&mut self) {
-        self.set_registers(&MODE_1280X800X256_CONFIGURATION);
-        self.most_recent_video_mode = Some(VideoMode::Mode1280x800x256);
+    /// Locks the CRTC registers by setting bit 7 of the `Vertical Sync End`
+    /// register to 1 `(value | 0x80)`, so writes to `CR[00:07]` are ignored
+    /// until `unlock_crtc_registers` is called.
+    ///
+    /// Bit 4 of the Overflow Register (CR07), which holds bit 8 of the
+    /// line compare register, stays writeable.
+    pub fn lock_crtc_registers(&mut self) {
+        let emulation_mode = self.get_emulation_mode();
+        let vertical_sync_end = self
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::VerticalSyncEnd);
+        self.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::VerticalSyncEnd,
+            vertical_sync_end | 0x80,
+        );
     }
 
     /// Unlocks the CRTC registers by setting bit 7 to 0 `(value & 0x7F)`.
@@ -331,7 +1739,8 @@ impl Vga {
     /// 0 = Enable writes to registers `CR[00:07]`
     ///
     /// 1 = Disable writes to registers `CR[00:07]`
-    fn unlock_crtc_registers(&mut self, emulation_mode: EmulationMode) {
+    pub fn unlock_crtc_registers(&mut self) {
+        let emulation_mode = self.get_emulation_mode();
         // Setting bit 7 to 1 used to be required for `VGA`, but says it's
         // ignored in modern hardware. Setting it to 1 just to be safe for older
         // hardware. More information can be found here
@@ -355,4 +1764,11 @@ impl Vga {
             vertical_sync_end & 0x7F,
         );
     }
+
+    /// Locks the CRTC registers again if enabled with `set_relock_crtc`.
+    fn relock_crtc_registers(&mut self) {
+        if self.relock_crtc {
+            self.lock_crtc_registers();
+        }
+    }
 }
@@ -0,0 +1,105 @@
+//! Volatile bulk operations for video memory.
+//!
+//! Plain writes, such as `slice::fill` or `ptr::copy`, may be merged,
+//! reordered or elided by the compiler, since it doesn't know video memory
+//! is read by the display. These helpers write every element with a
+//! volatile store instead, and are useful for user blits as well.
+
+#[cfg(all(
+    any(test, feature = "test-backend"),
+    any(feature = "graphics-modes", feature = "modex")
+))]
+pub(crate) use crate::mock::fill_planar;
+#[cfg(any(test, feature = "test-backend"))]
+pub(crate) use crate::mock::{read_planar, write_planar};
+
+/// Writes `value` to `count` consecutive elements starting at `dst`,
+/// one volatile store per element.
+///
+/// # Safety
+///
+/// `dst` must be aligned and valid for `count` writes of `T`.
+///
+/// # Examples
+///
+/// ```
+/// use vga::volatile::volatile_fill;
+///
+/// let mut buffer = [0u16; 4];
+/// unsafe { volatile_fill(buffer.as_mut_ptr(), 0x0F20, 4) };
+/// assert_eq!(buffer, [0x0F20; 4]);
+/// ```
+pub unsafe fn volatile_fill<T: Copy>(dst: *mut T, value: T, count: usize) {
+    for offset in 0..count {
+        dst.add(offset).write_volatile(value);
+    }
+}
+
+/// Copies `count` elements from `src` to `dst` with volatile loads and
+/// stores. Like `ptr::copy`, the regions may overlap.
+///
+/// # Safety
+///
+/// `src` must be aligned and valid for `count` reads of `T`, and `dst`
+/// must be aligned and valid for `count` writes of `T`.
+///
+/// # Examples
+///
+/// ```
+/// use vga::volatile::volatile_copy;
+///
+/// let mut buffer = [1u8, 2, 3, 4, 0];
+/// let start = buffer.as_mut_ptr();
+/// unsafe { volatile_copy(start, start.add(1), 4) };
+/// assert_eq!(buffer, [1, 1, 2, 3, 4]);
+/// ```
+pub unsafe fn volatile_copy<T: Copy>(src: *const T, dst: *mut T, count: usize) {
+    if (dst as *const T) < src {
+        for offset in 0..count {
+            dst.add(offset)
+                .write_volatile(src.add(offset).read_volatile());
+        }
+    } else {
+        for offset in (0..count).rev() {
+            dst.add(offset)
+                .write_volatile(src.add(offset).read_volatile());
+        }
+    }
+}
+
+/// Reads the byte at `src` in the vga memory window, which also loads the
+/// latches of all four planes. The mock backend models this with its
+/// simulated planes.
+///
+/// # Safety
+///
+/// `src` must be valid for a read of `u8`.
+#[cfg(not(any(test, feature = "test-backend")))]
+pub(crate) unsafe fn read_planar(src: *const u8) -> u8 {
+    src.read_volatile()
+}
+
+/// Writes `value` to `dst` in the vga memory window, where it goes
+/// through the plane mask and write mode.
+///
+/// # Safety
+///
+/// `dst` must be aligned and valid for a write of `T`.
+#[cfg(not(any(test, feature = "test-backend")))]
+pub(crate) unsafe fn write_planar<T: Copy>(dst: *mut T, value: T) {
+    dst.write_volatile(value);
+}
+
+/// Writes `value` to `count` elements starting at `dst` in the vga memory
+/// window, like `write_planar`.
+///
+/// # Safety
+///
+/// `dst` must be aligned and valid for `count` writes of `T`.
+#[cfg(all(
+    not(any(test, feature = "test-backend")),
+    any(feature = "graphics-modes", feature = "modex")
+))]
+pub(crate) unsafe fn fill_planar<T: Copy>(dst: *mut T, value: T, count: usize) {
+    volatile_fill(dst, value, count);
+}
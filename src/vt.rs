@@ -0,0 +1,220 @@
+//! Virtual terminals sharing a single text mode.
+//!
+//! Every virtual terminal lives on its own text page, so its contents stay
+//! in video memory while it isn't displayed, and switching terminals only
+//! changes the displayed page. The cursor and color of each terminal are
+//! kept in `VirtualTerminals`, since the vga only has one hardware cursor.
+
+use crate::{colors::TextModeColor, writers::TextWriter};
+use core::fmt;
+
+/// The state of a single virtual terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VirtualTerminal {
+    cursor_position: (usize, usize),
+    color: TextModeColor,
+}
+
+impl VirtualTerminal {
+    /// Returns the cursor position of the terminal as `(x, y)`.
+    pub fn get_cursor_position(&self) -> (usize, usize) {
+        self.cursor_position
+    }
+
+    /// Returns the color text is written to the terminal with.
+    pub fn get_color(&self) -> TextModeColor {
+        self.color
+    }
+}
+
+/// `N` virtual terminals on the text pages of a `TextWriter`.
+///
+/// Writing to `VirtualTerminals` with `fmt::Write` writes to the displayed
+/// terminal, while `write_str_to` writes to any terminal.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "text-modes"))]
+/// # {
+/// use core::fmt::Write;
+/// use vga::colors::{Color16, TextModeColor};
+/// use vga::vt::VirtualTerminals;
+/// use vga::writers::Text80x25;
+///
+/// let color = TextModeColor::new(Color16::LightGrey, Color16::Black);
+/// let mut terminals = VirtualTerminals::<_, 4>::new(Text80x25::new(), color);
+/// terminals.write_str_to(1, "Hello from terminal 1").unwrap();
+/// terminals.switch_to(1).unwrap();
+/// writeln!(terminals, "!").unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct VirtualTerminals<T, const N: usize> {
+    writer: T,
+    terminals: [VirtualTerminal; N],
+    current: usize,
+}
+
+impl<T: TextWriter, const N: usize> VirtualTerminals<T, N> {
+    /// Creates `N` virtual terminals on the text pages of `writer`, which
+    /// text is written to with `color`.
+    ///
+    /// This sets the mode of `writer` and clears every terminal, and the
+    /// first terminal is displayed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is 0, or if the mode of `writer` has less than `N`
+    /// text pages.
+    pub fn new(writer: T, color: TextModeColor) -> VirtualTerminals<T, N> {
+        writer.set_mode();
+        assert!(
+            N > 0 && N <= writer.get_page_count(),
+            "not enough text pages for the virtual terminals"
+        );
+        for page in (0..N).rev() {
+            writer.set_active_page(page).unwrap();
+            writer.clear_screen();
+        }
+        writer.set_cursor_position(0, 0);
+        VirtualTerminals {
+            writer,
+            terminals: [VirtualTerminal {
+                cursor_position: (0, 0),
+                color,
+            }; N],
+            current: 0,
+        }
+    }
+
+    /// Returns the `TextWriter` the terminals are on.
+    pub fn get_writer(&self) -> &T {
+        &self.writer
+    }
+
+    /// Returns the index of the displayed terminal.
+    pub fn get_current(&self) -> usize {
+        self.current
+    }
+
+    /// Returns the state of the given `terminal`, or `None` if it doesn't exist.
+    pub fn get_terminal(&self, terminal: usize) -> Option<&VirtualTerminal> {
+        self.terminals.get(terminal)
+    }
+
+    /// Sets the color text is written to the given `terminal` with.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `terminal` isn't less than `N`.
+    pub fn set_color(&mut self, terminal: usize, color: TextModeColor) -> Result<(), &'static str> {
+        self.terminal_mut(terminal)?.color = color;
+        Ok(())
+    }
+
+    /// Displays the given `terminal` and moves the hardware cursor to its
+    /// cursor position.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `terminal` isn't less than `N`.
+    pub fn switch_to(&mut self, terminal: usize) -> Result<(), &'static str> {
+        let (x, y) = self.terminal_mut(terminal)?.cursor_position;
+        self.writer.set_active_page(terminal)?;
+        self.writer.set_displayed_page(terminal)?;
+        self.writer.set_cursor_position(x, y);
+        self.current = terminal;
+        Ok(())
+    }
+
+    /// Writes `s` to the given `terminal`, which doesn't need to be
+    /// displayed. Bytes outside of the printable ascii range are written as
+    /// `0xFE`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `terminal` isn't less than `N`.
+    pub fn write_str_to(&mut self, terminal: usize, s: &str) -> Result<(), &'static str> {
+        let VirtualTerminal {
+            cursor_position: (x, y),
+            color,
+        } = *self.terminal_mut(terminal)?;
+
+        self.writer.set_active_page(terminal)?;
+        self.writer.set_cursor_position(x, y);
+        for byte in s.bytes() {
+            match byte {
+                // Printable ascii or newline
+                0x20..=0x7E | b'\n' => self.writer.put_char(byte, color),
+                // Not part of the printable ascii range
+                _ => self.writer.put_char(0xFE, color),
+            }
+        }
+        self.terminals[terminal].cursor_position = self.writer.get_cursor_position();
+
+        // Put the hardware cursor back on the displayed terminal.
+        if terminal != self.current {
+            let (x, y) = self.terminals[self.current].cursor_position;
+            self.writer.set_active_page(self.current)?;
+            self.writer.set_cursor_position(x, y);
+        }
+        Ok(())
+    }
+
+    fn terminal_mut(&mut self, terminal: usize) -> Result<&mut VirtualTerminal, &'static str> {
+        self.terminals
+            .get_mut(terminal)
+            .ok_or("virtual terminal is out of range")
+    }
+}
+
+impl<T: TextWriter, const N: usize> fmt::Write for VirtualTerminals<T, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_str_to(self.current, s).map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(all(test, feature = "global", feature = "text-modes"))]
+mod test {
+    use super::*;
+    use crate::{
+        colors::Color16,
+        mock::lock_for_test,
+        writers::{ScreenCharacter, Text80x25},
+    };
+    use core::fmt::Write;
+
+    #[test]
+    fn test_virtual_terminals() {
+        let _guard = lock_for_test();
+        let color = TextModeColor::new(Color16::White, Color16::Blue);
+        let mut terminals = VirtualTerminals::<_, 3>::new(Text80x25::new(), color);
+        let writer = *terminals.get_writer();
+
+        terminals.write_str_to(2, "VT2").unwrap();
+        writeln!(terminals, "VT0").unwrap();
+        assert_eq!(writer.get_displayed_page(), 0);
+        assert_eq!(writer.get_cursor_position(), (0, 1));
+        assert_eq!(
+            writer.read_character(0, 0),
+            ScreenCharacter::new(b'V', color)
+        );
+        assert_eq!(
+            terminals.get_terminal(2).unwrap().get_cursor_position(),
+            (3, 0)
+        );
+
+        terminals.switch_to(2).unwrap();
+        assert_eq!(writer.get_displayed_page(), 2);
+        assert_eq!(writer.get_cursor_position(), (3, 0));
+        assert_eq!(writer.read_character(2, 0).get_character(), b'2');
+
+        assert_eq!(
+            terminals.switch_to(3),
+            Err("virtual terminal is out of range")
+        );
+        assert_eq!(terminals.get_current(), 2);
+        writer.set_mode();
+    }
+}
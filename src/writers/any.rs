@@ -0,0 +1,201 @@
+//! Object-safe variants of the writer traits, so the current display
+//! can be stored as a `&dyn AnyTextWriter` or `&dyn AnyGraphicsWriter`.
+//!
+//! These traits share method names with `TextWriter` and `GraphicsWriter`,
+//! so they aren't re-exported from `writers` to keep glob imports unambiguous.
+
+#[cfg(feature = "modex")]
+use super::Graphics320x240x256;
+#[cfg(feature = "graphics-modes")]
+use super::{Graphics1280x800x256, Graphics320x200x256, Graphics640x480x16};
+#[cfg(any(feature = "graphics-modes", feature = "modex"))]
+use super::{GraphicsWriter, PrimitiveDrawing, Screen};
+use super::{ScreenCharacter, TextWriter};
+use crate::{colors::TextModeColor, drawing::Point, vga::ModeInfo};
+
+/// An object-safe variant of `TextWriter`, implemented for every `TextWriter`.
+pub trait AnyTextWriter {
+    /// Returns the `ModeInfo` of the writer.
+    fn mode_info(&self) -> ModeInfo;
+
+    /// Sets the graphics device to the video mode of the writer.
+    fn set_mode(&self);
+
+    /// Clears the screen, see `TextWriter::clear_screen`.
+    fn clear_screen(&self);
+
+    /// Fills the screen by setting all cells to the given screen character.
+    fn fill_screen(&self, character: ScreenCharacter);
+
+    /// Returns the character used by `clear_screen` and scrolling, see
+    /// `TextWriter::set_blank_character`.
+    fn get_blank_character(&self) -> ScreenCharacter;
+
+    /// Returns the `ScreenCharacter` at the given `(x, y)` position.
+    fn read_character(&self, x: usize, y: usize) -> ScreenCharacter;
+
+    /// Prints the given `character` and `color` at `(x, y)`.
+    fn write_character(&self, x: usize, y: usize, screen_character: ScreenCharacter);
+
+    /// Writes `character` at the cursor position, see `TextWriter::put_char`.
+    fn put_char(&self, character: u8, color: TextModeColor);
+
+    /// Sets the current text cursor to the position specified by `x` and `y`.
+    fn set_cursor_position(&self, x: usize, y: usize);
+
+    /// Returns the current position of the text cursor as `(x, y)`.
+    fn get_cursor_position(&self) -> (usize, usize);
+}
+
+impl<T: TextWriter> AnyTextWriter for T {
+    fn mode_info(&self) -> ModeInfo {
+        self.get_mode_info()
+    }
+
+    fn set_mode(&self) {
+        TextWriter::set_mode(self);
+    }
+
+    fn clear_screen(&self) {
+        TextWriter::clear_screen(self);
+    }
+
+    fn fill_screen(&self, character: ScreenCharacter) {
+        TextWriter::fill_screen(self, character);
+    }
+
+    fn get_blank_character(&self) -> ScreenCharacter {
+        TextWriter::get_blank_character(self)
+    }
+
+    fn read_character(&self, x: usize, y: usize) -> ScreenCharacter {
+        TextWriter::read_character(self, x, y)
+    }
+
+    fn write_character(&self, x: usize, y: usize, screen_character: ScreenCharacter) {
+        TextWriter::write_character(self, x, y, screen_character);
+    }
+
+    fn put_char(&self, character: u8, color: TextModeColor) {
+        TextWriter::put_char(self, character, color);
+    }
+
+    fn set_cursor_position(&self, x: usize, y: usize) {
+        TextWriter::set_cursor_position(self, x, y);
+    }
+
+    fn get_cursor_position(&self) -> (usize, usize) {
+        TextWriter::get_cursor_position(self)
+    }
+}
+
+/// An object-safe variant of `GraphicsWriter` and `PrimitiveDrawing`,
+/// using `u32` colors.
+///
+/// Colors are truncated to the color type of the underlying writer, e.g.
+/// only the lowest 4 bits are used by `Graphics640x480x16`.
+pub trait AnyGraphicsWriter {
+    /// Returns the `ModeInfo` of the writer.
+    fn mode_info(&self) -> ModeInfo;
+
+    /// Sets the graphics device to the video mode of the writer.
+    fn set_mode(&self);
+
+    /// Clears the screen by setting all pixels to the specified `color`.
+    fn clear_screen(&self, color: u32);
+
+    /// Sets the given pixel at `(x, y)` to the given `color`.
+    fn set_pixel(&self, x: usize, y: usize, color: u32);
+
+    /// Draws a character at the given `(x, y)` coordinate to the specified `color`.
+    fn draw_character(&self, x: usize, y: usize, character: char, color: u32);
+
+    /// Draws a line from `start` to `end` with the specified `color`.
+    fn draw_line(&self, start: Point<isize>, end: Point<isize>, color: u32);
+
+    /// Draws a rectangle from `p1` to `p2` with the specified `color`.
+    fn draw_rect(&self, p1: Point<usize>, p2: Point<usize>, color: u32);
+}
+
+#[cfg(any(feature = "graphics-modes", feature = "modex"))]
+macro_rules! impl_any_graphics_writer {
+    ($writer:ident, $color:ty, $convert:expr) => {
+        impl AnyGraphicsWriter for $writer<'_> {
+            fn mode_info(&self) -> ModeInfo {
+                self.get_mode_info()
+            }
+
+            fn set_mode(&self) {
+                GraphicsWriter::set_mode(self);
+            }
+
+            fn clear_screen(&self, color: u32) {
+                GraphicsWriter::clear_screen(self, $convert(color));
+            }
+
+            fn set_pixel(&self, x: usize, y: usize, color: u32) {
+                GraphicsWriter::set_pixel(self, x, y, $convert(color));
+            }
+
+            fn draw_character(&self, x: usize, y: usize, character: char, color: u32) {
+                GraphicsWriter::draw_character(self, x, y, character, $convert(color));
+            }
+
+            fn draw_line(&self, start: Point<isize>, end: Point<isize>, color: u32) {
+                PrimitiveDrawing::<$color>::draw_line(self, start, end, $convert(color));
+            }
+
+            fn draw_rect(&self, p1: Point<usize>, p2: Point<usize>, color: u32) {
+                PrimitiveDrawing::<$color>::draw_rect(self, p1, p2, $convert(color));
+            }
+        }
+    };
+}
+
+#[cfg(feature = "graphics-modes")]
+impl_any_graphics_writer!(Graphics320x200x256, u8, |color: u32| color as u8);
+#[cfg(feature = "modex")]
+impl_any_graphics_writer!(Graphics320x240x256, u8, |color: u32| color as u8);
+#[cfg(feature = "graphics-modes")]
+impl_any_graphics_writer!(Graphics640x480x16, crate::colors::Color16, |color: u32| {
+    crate::colors::Color16::from_low_bits(color as u8)
+});
+#[cfg(feature = "graphics-modes")]
+impl_any_graphics_writer!(Graphics1280x800x256, u32, |color: u32| color);
+
+#[cfg(all(
+    test,
+    feature = "global",
+    feature = "text-modes",
+    feature = "graphics-modes"
+))]
+mod test {
+    use super::*;
+    use crate::{
+        colors::Color16,
+        mock::lock_for_test,
+        writers::{ScreenCharacter, Text80x25},
+    };
+
+    #[test]
+    fn test_dyn_writers() {
+        let _guard = lock_for_test();
+        let text_mode: &dyn AnyTextWriter = &Text80x25::new();
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        assert_eq!(text_mode.mode_info().get_width(), Text80x25::WIDTH);
+        assert_eq!(text_mode.read_character(0, 0), ScreenCharacter::BLANK);
+
+        let color = TextModeColor::new(Color16::White, Color16::Black);
+        text_mode.set_cursor_position(0, 0);
+        text_mode.put_char(b'A', color);
+        assert_eq!(text_mode.read_character(0, 0).get_character(), b'A');
+        assert_eq!(text_mode.get_cursor_position(), (1, 0));
+
+        let graphics_mode: &dyn AnyGraphicsWriter = &Graphics320x200x256::new();
+        graphics_mode.set_mode();
+        graphics_mode.clear_screen(0x1F);
+        graphics_mode.set_pixel(1, 0, 0x2F);
+        assert_eq!(&crate::mock::video_memory()[..3], &[0x1F, 0x2F, 0x1F]);
+    }
+}
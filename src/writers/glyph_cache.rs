@@ -0,0 +1,98 @@
+//! A cache of glyphs rendered to a mode's native layout.
+
+/// Identifies a glyph rendered in a given pair of colors.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct GlyphKey {
+    character: char,
+    foreground: u8,
+    background: u8,
+}
+
+/// The multiplier of the slot hash, which is 2^64 divided by the golden
+/// ratio, so keys differing in any field spread over the slots.
+const HASH_MULTIPLIER: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// A direct-mapped cache holding up to `N` glyphs rendered as `G`.
+///
+/// `N` must be a non-zero power of two, which is checked at compile time.
+pub(crate) struct GlyphCache<G: Copy, const N: usize> {
+    entries: [Option<(GlyphKey, G)>; N],
+}
+
+impl<G: Copy, const N: usize> GlyphCache<G, N> {
+    const SIZE_IS_POWER_OF_TWO: () = assert!(
+        N.is_power_of_two(),
+        "the glyph cache size must be a non-zero power of two"
+    );
+
+    /// Creates a new, empty `GlyphCache`.
+    pub(crate) const fn new() -> GlyphCache<G, N> {
+        let () = Self::SIZE_IS_POWER_OF_TWO;
+        GlyphCache { entries: [None; N] }
+    }
+
+    /// Returns the glyph for `character` in the given colors, calling
+    /// `render` to render it if it isn't cached yet.
+    pub(crate) fn get_or_render<F>(
+        &mut self,
+        character: char,
+        foreground: u8,
+        background: u8,
+        render: F,
+    ) -> G
+    where
+        F: FnOnce() -> G,
+    {
+        let key = GlyphKey {
+            character,
+            foreground,
+            background,
+        };
+        // The upper half of the product mixes every bit of the key.
+        let hash = (character as u64 | (foreground as u64) << 21 | (background as u64) << 25)
+            .wrapping_mul(HASH_MULTIPLIER);
+        let slot = (hash >> 32) as usize & (N - 1);
+        match self.entries[slot] {
+            Some((cached_key, glyph)) if cached_key == key => glyph,
+            _ => {
+                let glyph = render();
+                self.entries[slot] = Some((key, glyph));
+                glyph
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_or_render_caches_glyphs() {
+        let mut cache = GlyphCache::<u8, 4>::new();
+        let mut renders = 0;
+        for _ in 0..2 {
+            let glyph = cache.get_or_render('A', 1, 2, || {
+                renders += 1;
+                0x41
+            });
+            assert_eq!(glyph, 0x41);
+        }
+        assert_eq!(renders, 1);
+
+        let glyph = cache.get_or_render('A', 2, 1, || 0x42);
+        assert_eq!(glyph, 0x42);
+    }
+
+    #[test]
+    fn test_get_or_render_spreads_colors() {
+        // Each color combination of a character hashes to its own slot,
+        // so they don't all evict each other.
+        let mut cache = GlyphCache::<u8, 16>::new();
+        for color in 0..16 {
+            cache.get_or_render('A', color, 15 - color, || color);
+        }
+        let used = cache.entries.iter().filter(|entry| entry.is_some()).count();
+        assert!(used >= 8);
+    }
+}
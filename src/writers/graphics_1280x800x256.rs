@@ -1,13 +1,14 @@
-use core::slice::from_raw_parts_mut;
-
 use font8x8::UnicodeFonts;
 
-use crate::colors::DEFAULT_PALETTE;
+use crate::drawing::{BlitOptions, Point, Rect, Surface};
 use crate::registers::PlaneMask;
-use crate::vga::VGA;
+use crate::vga::{Vga, VgaError, VgaHandle, VideoMode};
 use crate::writers::PrimitiveDrawing;
 
-use super::{GraphicsWriter, Screen};
+use super::{
+    blit_linear, fill_frame_buffer, fill_linear_rect, GraphicsWriter, ModeOptions, Screen,
+};
+use spinning_top::{Spinlock, SpinlockGuard};
 
 const WIDTH: usize = 1280;
 const HEIGHT: usize = 800;
@@ -24,6 +25,8 @@ type ColorT = u32;
 /// Basic usage:
 ///
 /// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
 /// use vga::writers::{Graphics1280x800x256, GraphicsWriter, PrimitiveDrawing};
 ///
 /// let mode = Graphics1280x800x256::new();
@@ -37,24 +40,36 @@ type ColorT = u32;
 /// for (offset, character) in "Hello World!".chars().enumerate() {
 ///     mode.draw_character(118 + offset * 8, 27, character, 0xFF_00_FF);
 /// }
+/// # }
 /// ```
-#[derive(Debug, Clone, Copy, Default)]
-pub struct Graphics1280x800x256;
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "global", derive(Default))]
+pub struct Graphics1280x800x256<'a> {
+    vga: VgaHandle<'a>,
+}
 
-impl Screen for Graphics1280x800x256 {
+impl Screen for Graphics1280x800x256<'_> {
     const WIDTH: usize = WIDTH;
     const HEIGHT: usize = HEIGHT;
     const SIZE: usize = SIZE;
+    const VIDEO_MODE: VideoMode = VideoMode::Mode1280x800x256;
 }
 
-impl GraphicsWriter<ColorT> for Graphics1280x800x256 {
+impl GraphicsWriter<ColorT> for Graphics1280x800x256<'_> {
+    fn lock_vga(&self) -> SpinlockGuard<'_, Vga> {
+        self.vga.lock()
+    }
+
     fn clear_screen(&self, color: ColorT) {
         let frame_buffer = self.get_frame_buffer() as *mut ColorT;
-        VGA.lock()
-            .sequencer_registers
-            .set_plane_mask(PlaneMask::ALL_PLANES);
+        let memory_type = {
+            let mut vga = self.vga.lock();
+            vga.sequencer_registers
+                .set_plane_mask(PlaneMask::ALL_PLANES);
+            vga.get_frame_buffer_memory_type()
+        };
         unsafe {
-            from_raw_parts_mut(frame_buffer, PIXEL_COUNT).fill(color);
+            fill_frame_buffer(frame_buffer, color, PIXEL_COUNT, memory_type);
         }
     }
 
@@ -77,26 +92,87 @@ impl GraphicsWriter<ColorT> for Graphics1280x800x256 {
 
     fn set_pixel(&self, x: usize, y: usize, color: ColorT) {
         let frame_buffer = self.get_frame_buffer() as *mut ColorT;
-        let offset = WIDTH * y + x;
+        let offset = Self::offset_of(x, y);
         unsafe {
             frame_buffer.add(offset).write_volatile(color);
         }
     }
 
     fn set_mode(&self) {
-        let mut vga = VGA.lock();
+        self.set_mode_with_options(ModeOptions::default());
+    }
+
+    fn set_mode_with_options(&self, options: ModeOptions) {
+        let mut vga = self.vga.lock();
 
         // Some bios mess up the palette when switching modes,
         // so explicitly set it.
-        vga.color_palette_registers.load_palette(&DEFAULT_PALETTE);
+        if options.get_load_palette() && vga.get_quirks().get_reload_palette() {
+            vga.load_active_palette();
+        }
+        drop(vga);
+
+        // The linear frame buffer is all the video memory of this mode.
+        if options.get_clear() || options.get_clear_vram() {
+            self.clear_screen(0);
+        }
     }
 }
 
-impl PrimitiveDrawing<ColorT> for Graphics1280x800x256 {}
+impl PrimitiveDrawing<ColorT> for Graphics1280x800x256<'_> {
+    fn draw_rect(&self, p1: Point<usize>, p2: Point<usize>, color: ColorT) {
+        fill_linear_rect(self, p1, p2, color);
+    }
+
+    fn blit_with_options(
+        &self,
+        src: &Surface<'_, ColorT>,
+        src_rect: Rect,
+        dst_rect: Rect,
+        options: BlitOptions,
+    ) {
+        blit_linear(self, src, src_rect, dst_rect, options);
+    }
+}
+
+impl Graphics1280x800x256<'static> {
+    /// Creates a new `Graphics1280x800x256` using the global `VGA`.
+    #[cfg(feature = "global")]
+    pub const fn new() -> Graphics1280x800x256<'static> {
+        Graphics1280x800x256 {
+            vga: VgaHandle::global(),
+        }
+    }
+
+    /// Creates a new `Graphics1280x800x256` using the global `VGA`, after checking that
+    /// a vga is present with `Vga::probe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VgaError` from `Vga::probe` if no vga responds.
+    #[cfg(feature = "global")]
+    pub fn try_new() -> Result<Graphics1280x800x256<'static>, VgaError> {
+        VgaHandle::global().lock().probe()?;
+        Ok(Graphics1280x800x256::new())
+    }
+}
+
+impl<'a> Graphics1280x800x256<'a> {
+    /// Creates a new `Graphics1280x800x256` using `vga` instead of the global `VGA`.
+    pub const fn with_vga(vga: &'a Spinlock<Vga>) -> Graphics1280x800x256<'a> {
+        Graphics1280x800x256 {
+            vga: VgaHandle::new(vga),
+        }
+    }
 
-impl Graphics1280x800x256 {
-    /// Creates a new `Graphics1280x800x256`.
-    pub const fn new() -> Graphics1280x800x256 {
-        Graphics1280x800x256
+    /// Creates a new `Graphics1280x800x256` using `vga`, after checking that a vga is
+    /// present with `Vga::probe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VgaError` from `Vga::probe` if no vga responds.
+    pub fn try_with_vga(vga: &'a Spinlock<Vga>) -> Result<Graphics1280x800x256<'a>, VgaError> {
+        vga.lock().probe()?;
+        Ok(Graphics1280x800x256::with_vga(vga))
     }
 }
@@ -1,10 +1,13 @@
-use super::{GraphicsWriter, Screen};
+use super::{
+    blit_linear, fill_frame_buffer, fill_linear_rect, GraphicsWriter, ModeOptions, Screen,
+};
 use crate::writers::PrimitiveDrawing;
 use crate::{
-    colors::DEFAULT_PALETTE,
-    vga::{VideoMode, VGA},
+    drawing::{BlitOptions, Point, Rect, Surface},
+    vga::{Vga, VgaError, VgaHandle, VideoMode},
 };
 use font8x8::UnicodeFonts;
+use spinning_top::{Spinlock, SpinlockGuard};
 
 const WIDTH: usize = 320;
 const HEIGHT: usize = 200;
@@ -17,6 +20,8 @@ const SIZE: usize = WIDTH * HEIGHT;
 /// Basic usage:
 ///
 /// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
 /// use vga::colors::Color16;
 /// use vga::writers::{Graphics320x200x256, GraphicsWriter, PrimitiveDrawing};
 ///
@@ -32,24 +37,39 @@ const SIZE: usize = WIDTH * HEIGHT;
 ///     mode.draw_character(118 + offset * 8, 27, character, 255);
 /// }
 /// mode.draw_rect((300, 180), (320, 200), 255);
+/// # }
 /// ```
-#[derive(Debug, Clone, Copy, Default)]
-pub struct Graphics320x200x256;
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "global", derive(Default))]
+pub struct Graphics320x200x256<'a> {
+    vga: VgaHandle<'a>,
+}
 
-impl Screen for Graphics320x200x256 {
+impl Screen for Graphics320x200x256<'_> {
     const WIDTH: usize = WIDTH;
     const HEIGHT: usize = HEIGHT;
     const SIZE: usize = SIZE;
+    const VIDEO_MODE: VideoMode = VideoMode::Mode320x200x256;
 }
 
-impl GraphicsWriter<u8> for Graphics320x200x256 {
+impl GraphicsWriter<u8> for Graphics320x200x256<'_> {
+    fn lock_vga(&self) -> SpinlockGuard<'_, Vga> {
+        self.vga.lock()
+    }
+
     fn clear_screen(&self, color: u8) {
+        let memory_type = self.vga.lock().get_frame_buffer_memory_type();
         unsafe {
-            self.get_frame_buffer().write_bytes(color, Self::SIZE);
+            fill_frame_buffer(
+                self.get_frame_buffer() as *mut u32,
+                u32::from_ne_bytes([color; 4]),
+                Self::SIZE / 4,
+                memory_type,
+            );
         }
     }
     fn set_pixel(&self, x: usize, y: usize, color: u8) {
-        let offset = (y * WIDTH) + x;
+        let offset = Self::offset_of(x, y);
         unsafe {
             self.get_frame_buffer().add(offset).write_volatile(color);
         }
@@ -71,20 +91,83 @@ impl GraphicsWriter<u8> for Graphics320x200x256 {
         }
     }
     fn set_mode(&self) {
-        let mut vga = VGA.lock();
+        self.set_mode_with_options(ModeOptions::default());
+    }
+
+    fn set_mode_with_options(&self, options: ModeOptions) {
+        let mut vga = self.vga.lock();
         vga.set_video_mode(VideoMode::Mode320x200x256);
+        if options.get_clear_vram() {
+            vga.clear_vram();
+        }
 
         // Some bios mess up the palette when switching modes,
         // so explicitly set it.
-        vga.color_palette_registers.load_palette(&DEFAULT_PALETTE);
+        if options.get_load_palette() && vga.get_quirks().get_reload_palette() {
+            vga.load_active_palette();
+        }
+        drop(vga);
+
+        if options.get_clear() {
+            self.clear_screen(0);
+        }
     }
 }
 
-impl PrimitiveDrawing<u8> for Graphics320x200x256 {}
+impl PrimitiveDrawing<u8> for Graphics320x200x256<'_> {
+    fn draw_rect(&self, p1: Point<usize>, p2: Point<usize>, color: u8) {
+        fill_linear_rect(self, p1, p2, color);
+    }
+
+    fn blit_with_options(
+        &self,
+        src: &Surface<'_, u8>,
+        src_rect: Rect,
+        dst_rect: Rect,
+        options: BlitOptions,
+    ) {
+        blit_linear(self, src, src_rect, dst_rect, options);
+    }
+}
+
+impl Graphics320x200x256<'static> {
+    /// Creates a new `Graphics320x200x256` using the global `VGA`.
+    #[cfg(feature = "global")]
+    pub const fn new() -> Graphics320x200x256<'static> {
+        Graphics320x200x256 {
+            vga: VgaHandle::global(),
+        }
+    }
+
+    /// Creates a new `Graphics320x200x256` using the global `VGA`, after checking that
+    /// a vga is present with `Vga::probe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VgaError` from `Vga::probe` if no vga responds.
+    #[cfg(feature = "global")]
+    pub fn try_new() -> Result<Graphics320x200x256<'static>, VgaError> {
+        VgaHandle::global().lock().probe()?;
+        Ok(Graphics320x200x256::new())
+    }
+}
+
+impl<'a> Graphics320x200x256<'a> {
+    /// Creates a new `Graphics320x200x256` using `vga` instead of the global `VGA`.
+    pub const fn with_vga(vga: &'a Spinlock<Vga>) -> Graphics320x200x256<'a> {
+        Graphics320x200x256 {
+            vga: VgaHandle::new(vga),
+        }
+    }
 
-impl Graphics320x200x256 {
-    /// Creates a new `Graphics320x200x256`.
-    pub const fn new() -> Graphics320x200x256 {
-        Graphics320x200x256
+    /// Creates a new `Graphics320x200x256` using `vga`, after checking that a vga is
+    /// present with `Vga::probe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VgaError` from `Vga::probe` if no vga responds.
+    pub fn try_with_vga(vga: &'a Spinlock<Vga>) -> Result<Graphics320x200x256<'a>, VgaError> {
+        vga.lock().probe()?;
+        Ok(Graphics320x200x256::with_vga(vga))
     }
 }
@@ -1,15 +1,22 @@
-use super::{GraphicsWriter, Screen};
+use super::{fill_frame_buffer, rect_ranges, GraphicsWriter, ModeOptions, Screen};
 use crate::writers::PrimitiveDrawing;
 use crate::{
-    colors::DEFAULT_PALETTE,
+    drawing::Point,
+    planar::PlanarFrameBuffer,
     registers::PlaneMask,
-    vga::{VideoMode, VGA},
+    vga::{Vga, VgaError, VgaHandle, VideoMode},
+    volatile::write_planar,
 };
+use core::ops::Range;
 use font8x8::UnicodeFonts;
+use spinning_top::{Spinlock, SpinlockGuard};
 
 const WIDTH: usize = 320;
 const HEIGHT: usize = 240;
 const SIZE: usize = (WIDTH * HEIGHT) / 4;
+const WIDTH_IN_BYTES: usize = WIDTH / 4;
+/// The number of bytes of each plane in the 64K frame buffer window.
+const PLANE_SIZE: usize = 0x10000;
 
 /// A basic interface for interacting with vga graphics mode 320x200x256.
 ///
@@ -18,6 +25,8 @@ const SIZE: usize = (WIDTH * HEIGHT) / 4;
 /// Basic usage:
 ///
 /// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
 /// use vga::colors::Color16;
 /// use vga::writers::{Graphics320x240x256, GraphicsWriter, PrimitiveDrawing};
 ///
@@ -33,36 +42,54 @@ const SIZE: usize = (WIDTH * HEIGHT) / 4;
 ///     mode.draw_character(118 + offset * 8, 27, character, 255);
 /// }
 /// mode.draw_rect((300, 180), (320, 240), 255);
+/// # }
 /// ```
-#[derive(Debug, Clone, Copy, Default)]
-pub struct Graphics320x240x256;
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "global", derive(Default))]
+pub struct Graphics320x240x256<'a> {
+    vga: VgaHandle<'a>,
+}
 
-impl Screen for Graphics320x240x256 {
+impl Screen for Graphics320x240x256<'_> {
     const WIDTH: usize = WIDTH;
     const HEIGHT: usize = HEIGHT;
     const SIZE: usize = SIZE;
+    const VIDEO_MODE: VideoMode = VideoMode::Mode320x240x256;
 }
 
-impl GraphicsWriter<u8> for Graphics320x240x256 {
+impl GraphicsWriter<u8> for Graphics320x240x256<'_> {
+    fn lock_vga(&self) -> SpinlockGuard<'_, Vga> {
+        self.vga.lock()
+    }
+
     fn clear_screen(&self, color: u8) {
         let frame_buffer = self.get_frame_buffer();
-        VGA.lock()
-            .sequencer_registers
-            .set_plane_mask(PlaneMask::ALL_PLANES);
+        let memory_type = {
+            let mut vga = self.vga.lock();
+            vga.sequencer_registers
+                .set_plane_mask(PlaneMask::ALL_PLANES);
+            vga.get_frame_buffer_memory_type()
+        };
         unsafe {
-            frame_buffer.write_bytes(color, Self::SIZE);
+            fill_frame_buffer(
+                frame_buffer as *mut u32,
+                u32::from_ne_bytes([color; 4]),
+                Self::SIZE / 4,
+                memory_type,
+            );
         }
     }
 
     fn set_pixel(&self, x: usize, y: usize, color: u8) {
         let frame_buffer = self.get_frame_buffer();
         unsafe {
-            let offset = (WIDTH * y + x) / 4;
+            let offset = Self::offset_of(x, y) / 4;
             let plane_mask = 0x1 << (x & 3);
-            VGA.lock()
+            self.vga
+                .lock()
                 .sequencer_registers
                 .set_plane_mask(PlaneMask::from_bits(plane_mask).unwrap());
-            frame_buffer.add(offset).write_volatile(color);
+            write_planar(frame_buffer.add(offset), color);
         }
     }
     fn draw_character(&self, x: usize, y: usize, character: char, color: u8) {
@@ -82,20 +109,156 @@ impl GraphicsWriter<u8> for Graphics320x240x256 {
         }
     }
     fn set_mode(&self) {
-        let mut vga = VGA.lock();
+        self.set_mode_with_options(ModeOptions::default());
+    }
+
+    fn set_mode_with_options(&self, options: ModeOptions) {
+        let mut vga = self.vga.lock();
         vga.set_video_mode(VideoMode::Mode320x240x256);
+        if options.get_clear_vram() {
+            vga.clear_vram();
+        }
+
+        // Make sure the planes are unchained, in case the mode was
+        // patched or the bios left chain-4 enabled.
+        vga.sequencer_registers.set_chain4(false);
+        vga.sequencer_registers.set_odd_even(false);
 
         // Some bios mess up the palette when switching modes,
         // so explicitly set it.
-        vga.color_palette_registers.load_palette(&DEFAULT_PALETTE);
+        if options.get_load_palette() && vga.get_quirks().get_reload_palette() {
+            vga.load_active_palette();
+        }
+        drop(vga);
+
+        if options.get_clear() {
+            self.clear_screen(0);
+        }
     }
 }
 
-impl PrimitiveDrawing<u8> for Graphics320x240x256 {}
+impl PrimitiveDrawing<u8> for Graphics320x240x256<'_> {
+    /// Fills the rectangle a span at a time, see `fill_span`.
+    fn draw_rect(&self, p1: Point<usize>, p2: Point<usize>, color: u8) {
+        let (columns, rows) = rect_ranges(p1, p2, self.get_clip_bounds());
+        let mut vga = self.vga.lock();
+        let mut planes = PlanarFrameBuffer::new(&mut vga);
+        for y in rows {
+            fill_span(&mut planes, y, columns.clone(), color);
+        }
+    }
+}
 
-impl Graphics320x240x256 {
-    /// Creates a new `Graphics320x240x256`.
-    pub const fn new() -> Graphics320x240x256 {
-        Graphics320x240x256
+impl Graphics320x240x256<'static> {
+    /// Creates a new `Graphics320x240x256` using the global `VGA`.
+    #[cfg(feature = "global")]
+    pub const fn new() -> Graphics320x240x256<'static> {
+        Graphics320x240x256 {
+            vga: VgaHandle::global(),
+        }
+    }
+
+    /// Creates a new `Graphics320x240x256` using the global `VGA`, after checking that
+    /// a vga is present with `Vga::probe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VgaError` from `Vga::probe` if no vga responds.
+    #[cfg(feature = "global")]
+    pub fn try_new() -> Result<Graphics320x240x256<'static>, VgaError> {
+        VgaHandle::global().lock().probe()?;
+        Ok(Graphics320x240x256::new())
+    }
+}
+
+impl<'a> Graphics320x240x256<'a> {
+    /// Creates a new `Graphics320x240x256` using `vga` instead of the global `VGA`.
+    pub const fn with_vga(vga: &'a Spinlock<Vga>) -> Graphics320x240x256<'a> {
+        Graphics320x240x256 {
+            vga: VgaHandle::new(vga),
+        }
+    }
+
+    /// Creates a new `Graphics320x240x256` using `vga`, after checking that a vga is
+    /// present with `Vga::probe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VgaError` from `Vga::probe` if no vga responds.
+    pub fn try_with_vga(vga: &'a Spinlock<Vga>) -> Result<Graphics320x240x256<'a>, VgaError> {
+        vga.lock().probe()?;
+        Ok(Graphics320x240x256::with_vga(vga))
+    }
+
+    /// Fills row `y` from `x1` up to `x2` with `color`.
+    ///
+    /// Every byte of video memory holds 4 horizontally adjacent pixels, one
+    /// per plane, so the span is written a byte at a time with all planes
+    /// enabled, only masking planes for the partial groups at either end.
+    pub fn fill_span(&self, y: usize, x1: usize, x2: usize, color: u8) {
+        let mut vga = self.vga.lock();
+        let mut planes = PlanarFrameBuffer::new(&mut vga);
+        fill_span(&mut planes, y, x1.min(x2)..x1.max(x2), color);
+    }
+
+    /// Copies the `width` by `height` rectangle with its top left corner at
+    /// `src` to `dst`, using the latches to copy 4 pixels per byte.
+    ///
+    /// Rows past the bottom of the screen address the off-screen video
+    /// memory, up to row 818, so a background kept on another page can be
+    /// restored without going through system memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either x coordinate or `width` isn't a multiple of 4, or if
+    /// either rectangle doesn't fit in the frame buffer window.
+    pub fn copy_rect(&self, src: Point<usize>, dst: Point<usize>, width: usize, height: usize) {
+        assert!(
+            (src.0 | dst.0 | width) & 0x03 == 0,
+            "x coordinates and width must be multiples of 4"
+        );
+        assert!(
+            src.0.max(dst.0) + width <= WIDTH
+                && (src.1.max(dst.1) + height) * WIDTH_IN_BYTES <= PLANE_SIZE,
+            "rectangle doesn't fit in the frame buffer window"
+        );
+        let mut vga = self.vga.lock();
+        let mut planes = PlanarFrameBuffer::new(&mut vga);
+        let offset =
+            |corner: Point<usize>, row: usize| (corner.1 + row) * WIDTH_IN_BYTES + corner.0 / 4;
+        let copy_row =
+            |row: usize| planes.copy_latched(offset(src, row), offset(dst, row), width / 4);
+        // Copy bottom up when moving down, so overlapping rows aren't
+        // overwritten before they're copied.
+        if dst.1 > src.1 {
+            (0..height).rev().for_each(copy_row);
+        } else {
+            (0..height).for_each(copy_row);
+        }
+    }
+}
+
+/// Fills the `columns` of row `y` with `color`, 4 pixels per byte written.
+fn fill_span(planes: &mut PlanarFrameBuffer<'_>, y: usize, columns: Range<usize>, color: u8) {
+    if columns.is_empty() {
+        return;
+    }
+
+    let row_offset = Graphics320x240x256::offset_of(0, y) / 4;
+    let first_group = columns.start / 4;
+    let last_group = (columns.end - 1) / 4;
+    for group in first_group..=last_group {
+        let mut plane_mask = 0x0F;
+        if group == first_group {
+            plane_mask &= 0x0F << (columns.start & 0x03);
+        }
+        if group == last_group {
+            plane_mask &= 0x0F >> (3 - ((columns.end - 1) & 0x03));
+        }
+        planes.write_byte(
+            PlaneMask::from_bits_truncate(plane_mask),
+            row_offset + group,
+            color,
+        );
     }
 }
@@ -1,18 +1,29 @@
-use super::{GraphicsWriter, Screen};
+use super::{
+    fill_frame_buffer, glyph_cache::GlyphCache, rect_ranges, GraphicsWriter, ModeOptions, Screen,
+};
 use crate::writers::PrimitiveDrawing;
 use crate::{
-    colors::{Color16, DEFAULT_PALETTE},
-    drawing::{Bresenham, Point},
-    registers::{PlaneMask, WriteMode},
-    vga::{VideoMode, VGA},
+    colors::Color16,
+    drawing::{clip_line, Bresenham, Point, Rect},
+    planar::PlanarFrameBuffer,
+    registers::{GraphicsMode, PlaneMask, ReadMode, WriteMode},
+    vga::{Vga, VgaError, VgaHandle, VideoMode},
+    volatile::{read_planar, write_planar},
 };
+use core::{fmt, ops::Range};
 use font8x8::UnicodeFonts;
+use spinning_top::{Spinlock, SpinlockGuard};
 
 const WIDTH: usize = 640;
 const HEIGHT: usize = 480;
 const SIZE: usize = (WIDTH * HEIGHT) / 8;
 const WIDTH_IN_BYTES: usize = WIDTH / 8;
 
+/// A glyph rendered as one byte per row for each of the four planes.
+type PlanarGlyph = [[u8; 8]; 4];
+
+static GLYPH_CACHE: Spinlock<GlyphCache<PlanarGlyph, 256>> = Spinlock::new(GlyphCache::new());
+
 /// A basic interface for interacting with vga graphics mode 640x480x16
 ///
 /// # Examples
@@ -20,6 +31,8 @@ const WIDTH_IN_BYTES: usize = WIDTH / 8;
 /// Basic usage:
 ///
 /// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
 /// use vga::colors::Color16;
 /// use vga::writers::{Graphics640x480x16, GraphicsWriter, PrimitiveDrawing};
 ///
@@ -35,34 +48,43 @@ const WIDTH_IN_BYTES: usize = WIDTH / 8;
 ///     mode.draw_character(270 + offset * 8, 72, character, Color16::White)
 /// }
 /// mode.draw_rect((90, 70), (530, 410), Color16::Yellow);
+/// # }
 /// ```
-#[derive(Debug, Clone, Copy, Default)]
-pub struct Graphics640x480x16;
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "global", derive(Default))]
+pub struct Graphics640x480x16<'a> {
+    vga: VgaHandle<'a>,
+}
 
-impl Screen for Graphics640x480x16 {
+impl Screen for Graphics640x480x16<'_> {
     const WIDTH: usize = WIDTH;
     const HEIGHT: usize = HEIGHT;
     const SIZE: usize = SIZE;
+    const VIDEO_MODE: VideoMode = VideoMode::Mode640x480x16;
 }
 
-impl GraphicsWriter<Color16> for Graphics640x480x16 {
+impl GraphicsWriter<Color16> for Graphics640x480x16<'_> {
+    fn lock_vga(&self) -> SpinlockGuard<'_, Vga> {
+        self.vga.lock()
+    }
+
     fn clear_screen(&self, color: Color16) {
         self.set_write_mode_2();
+        let memory_type = self.vga.lock().get_frame_buffer_memory_type();
         unsafe {
-            self.get_frame_buffer()
-                .write_bytes(u8::from(color), Self::SIZE);
+            // Write mode 2 sets every pixel of each byte written to its color.
+            fill_frame_buffer(
+                self.get_frame_buffer() as *mut u32,
+                u32::from_ne_bytes([u8::from(color); 4]),
+                Self::SIZE / 4,
+                memory_type,
+            );
         }
     }
 
     fn draw_character(&self, x: usize, y: usize, character: char, color: Color16) {
         self.set_write_mode_2();
-        let character = match font8x8::BASIC_FONTS.get(character) {
-            Some(character) => character,
-            // Default to a filled block if the character isn't found
-            None => font8x8::unicode::BLOCK_UNICODE[8].byte_array(),
-        };
-
-        for (row, byte) in character.iter().enumerate() {
+        for (row, byte) in font_rows(character).iter().enumerate() {
             for bit in 0..8 {
                 match *byte & 1 << bit {
                     0 => (),
@@ -82,32 +104,295 @@ impl GraphicsWriter<Color16> for Graphics640x480x16 {
     }
 
     fn set_mode(&self) {
-        let mut vga = VGA.lock();
+        self.set_mode_with_options(ModeOptions::default());
+    }
+
+    fn set_mode_with_options(&self, options: ModeOptions) {
+        let mut vga = self.vga.lock();
         vga.set_video_mode(VideoMode::Mode640x480x16);
+        if options.get_clear_vram() {
+            vga.clear_vram();
+        }
 
         // Some bios mess up the palette when switching modes,
         // so explicitly set it.
-        vga.color_palette_registers.load_palette(&DEFAULT_PALETTE);
+        if options.get_load_palette() && vga.get_quirks().get_reload_palette() {
+            vga.load_active_palette();
+        }
+        drop(vga);
+
+        if options.get_clear() {
+            self.clear_screen(Color16::Black);
+        }
     }
 }
 
-impl PrimitiveDrawing<Color16> for Graphics640x480x16 {
+impl PrimitiveDrawing<Color16> for Graphics640x480x16<'_> {
+    /// Draws horizontal and vertical lines with `fill_rect`, and any other
+    /// line a pixel at a time.
     fn draw_line(&self, start: Point<isize>, end: Point<isize>, color: Color16) {
-        self.set_write_mode_0(color);
-        for (x, y) in Bresenham::new(start, end) {
-            self._set_pixel(x as usize, y as usize, color);
+        if let Some((start, end)) = clip_line(start, end, self.get_clip_bounds()) {
+            let (x1, x2) = (start.0.min(end.0) as usize, start.0.max(end.0) as usize);
+            let (y1, y2) = (start.1.min(end.1) as usize, start.1.max(end.1) as usize);
+            if x1 == x2 || y1 == y2 {
+                self.fill_rect(x1..x2 + 1, y1..y2 + 1, color);
+                return;
+            }
+
+            self.set_write_mode_0(color);
+            for (x, y) in Bresenham::new(start, end) {
+                self._set_pixel(x as usize, y as usize, color);
+            }
         }
     }
+
+    /// Fills the rectangle a byte at a time, see `fill_rect`.
+    fn draw_rect(&self, p1: Point<usize>, p2: Point<usize>, color: Color16) {
+        let (columns, rows) = rect_ranges(p1, p2, self.get_clip_bounds());
+        self.fill_rect(columns, rows, color);
+    }
 }
 
-impl Graphics640x480x16 {
-    /// Creates a new `Graphics640x480x16`.
-    pub const fn new() -> Graphics640x480x16 {
-        Graphics640x480x16
+impl Graphics640x480x16<'static> {
+    /// Creates a new `Graphics640x480x16` using the global `VGA`.
+    #[cfg(feature = "global")]
+    pub const fn new() -> Graphics640x480x16<'static> {
+        Graphics640x480x16 {
+            vga: VgaHandle::global(),
+        }
+    }
+
+    /// Creates a new `Graphics640x480x16` using the global `VGA`, after checking that
+    /// a vga is present with `Vga::probe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VgaError` from `Vga::probe` if no vga responds.
+    #[cfg(feature = "global")]
+    pub fn try_new() -> Result<Graphics640x480x16<'static>, VgaError> {
+        VgaHandle::global().lock().probe()?;
+        Ok(Graphics640x480x16::new())
+    }
+}
+
+impl<'a> Graphics640x480x16<'a> {
+    /// Creates a new `Graphics640x480x16` using `vga` instead of the global `VGA`.
+    pub const fn with_vga(vga: &'a Spinlock<Vga>) -> Graphics640x480x16<'a> {
+        Graphics640x480x16 {
+            vga: VgaHandle::new(vga),
+        }
+    }
+
+    /// Creates a new `Graphics640x480x16` using `vga`, after checking that a vga is
+    /// present with `Vga::probe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VgaError` from `Vga::probe` if no vga responds.
+    pub fn try_with_vga(vga: &'a Spinlock<Vga>) -> Result<Graphics640x480x16<'a>, VgaError> {
+        vga.lock().probe()?;
+        Ok(Graphics640x480x16::with_vga(vga))
+    }
+
+    /// Draws a character at the given `(x, y)` coordinate, with `foreground`
+    /// pixels set to `foreground` and the remaining pixels set to `background`.
+    ///
+    /// Rendered glyphs are cached per character and colors, and written a
+    /// plane at a time, which is much faster than `draw_character` when
+    /// `x` is a multiple of 8. Otherwise, or if the glyph is partly outside
+    /// of the clip bounds, this falls back to drawing each visible pixel.
+    pub fn draw_glyph(
+        &self,
+        x: usize,
+        y: usize,
+        character: char,
+        foreground: Color16,
+        background: Color16,
+    ) {
+        let glyph_rect = Rect::new(x, y, 8, 8);
+        let visible = glyph_rect.intersection(self.get_clip_bounds());
+        if visible.is_empty() {
+            return;
+        }
+        if x & 0x07 != 0 || visible != glyph_rect {
+            self.set_write_mode_2();
+            for (row, byte) in font_rows(character).iter().enumerate() {
+                for bit in 0..8 {
+                    if !visible.contains(x + bit, y + row) {
+                        continue;
+                    }
+                    let color = match *byte & 1 << bit {
+                        0 => background,
+                        _ => foreground,
+                    };
+                    self._set_pixel(x + bit, y + row, color);
+                }
+            }
+            return;
+        }
+
+        let glyph = GLYPH_CACHE.lock().get_or_render(
+            character,
+            u8::from(foreground),
+            u8::from(background),
+            || render_planar_glyph(character, foreground, background),
+        );
+        let mut vga = self.vga.lock();
+        let mut planes = PlanarFrameBuffer::new(&mut vga);
+        for (plane, rows) in glyph.iter().enumerate() {
+            let plane_mask = PlaneMask::from_bits_truncate(1 << plane);
+            for (row, byte) in rows.iter().enumerate() {
+                let offset = x / 8 + (y + row) * WIDTH_IN_BYTES;
+                planes.write_byte(plane_mask, offset, *byte);
+            }
+        }
+    }
+
+    /// Fills row `y` from `x1` up to `x2` with `color`, clipped to the
+    /// clip bounds.
+    ///
+    /// Every byte of each plane holds 8 horizontally adjacent pixels, so
+    /// the span is written a byte at a time with the set/reset registers,
+    /// only masking pixels with the bit mask at either end.
+    pub fn fill_span(&self, y: usize, x1: usize, x2: usize, color: Color16) {
+        let (columns, rows) = rect_ranges((x1, y), (x2, y + 1), self.get_clip_bounds());
+        self.fill_rect(columns, rows, color);
+    }
+
+    /// Draws a horizontal line on row `y` from `x1` to `x2`, including
+    /// both ends, with `color`, clipped to the clip bounds.
+    pub fn hline(&self, y: usize, x1: usize, x2: usize, color: Color16) {
+        let (x1, x2) = (x1.min(x2), x1.max(x2));
+        self.fill_span(y, x1, x2 + 1, color);
+    }
+
+    /// Draws a vertical line on column `x` from `y1` to `y2`, including
+    /// both ends, with `color`, clipped to the clip bounds.
+    ///
+    /// The bit mask is only set once, so each pixel costs a single read
+    /// and write of video memory.
+    pub fn vline(&self, x: usize, y1: usize, y2: usize, color: Color16) {
+        let (y1, y2) = (y1.min(y2), y1.max(y2));
+        let (columns, rows) = rect_ranges((x, y1), (x + 1, y2 + 1), self.get_clip_bounds());
+        self.fill_rect(columns, rows, color);
+    }
+
+    /// Returns an iterator over the `(x, y)` of every pixel of `color`
+    /// within `region`, in row-major order.
+    ///
+    /// The pixels are found with read mode 1, where a single read compares
+    /// 8 pixels of all planes against the color, so it's much faster than
+    /// reading the planes back. The vga driver stays locked and in read
+    /// mode 1 until the iterator is dropped, which restores the registers.
+    ///
+    /// # Examples
+    ///
+    /// Checking whether a sprite would overlap a wall:
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "global")]
+    /// # {
+    /// use vga::colors::Color16;
+    /// use vga::drawing::Rect;
+    /// use vga::writers::Graphics640x480x16;
+    ///
+    /// let mode = Graphics640x480x16::new();
+    /// let sprite = Rect::new(100, 100, 16, 16);
+    /// let collides = mode
+    ///     .find_pixels_matching(Color16::Brown, sprite)
+    ///     .next()
+    ///     .is_some();
+    /// # }
+    /// ```
+    pub fn find_pixels_matching(&self, color: Color16, region: Rect) -> MatchingPixels<'_> {
+        let region = region.intersection(Rect::new(0, 0, WIDTH, HEIGHT));
+        let mut vga = self.vga.lock();
+        let graphics = &mut vga.graphics_controller_registers;
+        let saved_graphics_mode = graphics.get_graphics_mode();
+        let saved_color_compare = graphics.get_color_compare();
+        let saved_color_dont_care = graphics.get_color_dont_care();
+        graphics.set_color_compare(color);
+        graphics.set_color_dont_care(PlaneMask::ALL_PLANES);
+        graphics.set_read_mode(ReadMode::Mode1);
+
+        let frame_buffer = usize::from(vga.get_frame_buffer()) as *mut u8;
+        let columns = region.get_x()..region.get_x() + region.get_width();
+        let rows = region.get_y()..region.get_y() + region.get_height();
+        MatchingPixels {
+            vga,
+            frame_buffer,
+            byte: columns.start / 8,
+            columns,
+            rows,
+            matches: 0,
+            match_offset: 0,
+            saved_graphics_mode,
+            saved_color_compare,
+            saved_color_dont_care,
+        }
+    }
+
+    /// Fills the `columns` of `rows` a byte at a time with the set/reset
+    /// registers, masking the pixels outside of them at the edges with the
+    /// bit mask. The whole bytes in between are filled a row at a time with
+    /// `fill_frame_buffer`, since they don't need the latches.
+    fn fill_rect(&self, columns: Range<usize>, rows: Range<usize>, color: Color16) {
+        if columns.is_empty() || rows.is_empty() {
+            return;
+        }
+
+        self.set_write_mode_0(color);
+        let frame_buffer = self.get_frame_buffer();
+        let first_byte = columns.start / 8;
+        let last_byte = (columns.end - 1) / 8;
+        let first_mask = 0xFF >> (columns.start & 0x07);
+        let last_mask = 0xFF << (7 - ((columns.end - 1) & 0x07));
+        let mut vga = self.vga.lock();
+        let memory_type = vga.get_frame_buffer_memory_type();
+        let mut fill_masked = |byte: usize, bit_mask: u8| {
+            vga.graphics_controller_registers.set_bit_mask(bit_mask);
+            for y in rows.clone() {
+                let offset = byte + y * WIDTH_IN_BYTES;
+                unsafe {
+                    // Load the latches, so masked pixels keep their color.
+                    read_planar(frame_buffer.add(offset));
+                    write_planar(frame_buffer.add(offset), 0xFF);
+                }
+            }
+        };
+
+        let mut whole_bytes = first_byte..last_byte + 1;
+        if first_byte == last_byte {
+            fill_masked(first_byte, first_mask & last_mask);
+            whole_bytes = first_byte..first_byte;
+        } else {
+            if first_mask != 0xFF {
+                fill_masked(first_byte, first_mask);
+                whole_bytes.start += 1;
+            }
+            if last_mask != 0xFF {
+                fill_masked(last_byte, last_mask);
+                whole_bytes.end -= 1;
+            }
+        }
+        vga.graphics_controller_registers.set_bit_mask(0xFF);
+        if whole_bytes.is_empty() {
+            return;
+        }
+        for y in rows {
+            unsafe {
+                fill_frame_buffer(
+                    frame_buffer.add(whole_bytes.start + y * WIDTH_IN_BYTES),
+                    0xFF,
+                    whole_bytes.len(),
+                    memory_type,
+                );
+            }
+        }
     }
 
     fn set_write_mode_0(self, color: Color16) {
-        let mut vga = VGA.lock();
+        let mut vga = self.vga.lock();
         vga.graphics_controller_registers.write_set_reset(color);
         vga.graphics_controller_registers
             .write_enable_set_reset(0xF);
@@ -116,7 +401,7 @@ impl Graphics640x480x16 {
     }
 
     fn set_write_mode_2(self) {
-        let mut vga = VGA.lock();
+        let mut vga = self.vga.lock();
         vga.graphics_controller_registers
             .set_write_mode(WriteMode::Mode2);
         vga.graphics_controller_registers.set_bit_mask(0xFF);
@@ -129,12 +414,115 @@ impl Graphics640x480x16 {
         let frame_buffer = self.get_frame_buffer();
         let offset = x / 8 + y * WIDTH_IN_BYTES;
         let pixel_mask = 0x80 >> (x & 0x07);
-        VGA.lock()
+        self.vga
+            .lock()
             .graphics_controller_registers
             .set_bit_mask(pixel_mask);
         unsafe {
-            frame_buffer.add(offset).read_volatile();
-            frame_buffer.add(offset).write_volatile(u8::from(color));
+            read_planar(frame_buffer.add(offset));
+            write_planar(frame_buffer.add(offset), u8::from(color));
         }
     }
 }
+
+/// An iterator over the pixels of a color, created with
+/// `Graphics640x480x16::find_pixels_matching`.
+pub struct MatchingPixels<'a> {
+    vga: SpinlockGuard<'a, Vga>,
+    frame_buffer: *mut u8,
+    columns: Range<usize>,
+    rows: Range<usize>,
+    byte: usize,
+    matches: u8,
+    match_offset: usize,
+    saved_graphics_mode: GraphicsMode,
+    saved_color_compare: Color16,
+    saved_color_dont_care: PlaneMask,
+}
+
+impl Iterator for MatchingPixels<'_> {
+    type Item = Point<usize>;
+
+    fn next(&mut self) -> Option<Point<usize>> {
+        while self.matches == 0 {
+            if self.columns.is_empty() || self.rows.is_empty() {
+                return None;
+            }
+
+            let first_byte = self.columns.start / 8;
+            let last_byte = (self.columns.end - 1) / 8;
+            let mut bit_mask = 0xFF;
+            if self.byte == first_byte {
+                bit_mask &= 0xFF >> (self.columns.start & 0x07);
+            }
+            if self.byte == last_byte {
+                bit_mask &= 0xFF << (7 - ((self.columns.end - 1) & 0x07));
+            }
+            let offset = self.byte + self.rows.start * WIDTH_IN_BYTES;
+            // In read mode 1, every set bit is a pixel matching the color.
+            self.matches = unsafe { read_planar(self.frame_buffer.add(offset)) } & bit_mask;
+            self.match_offset = offset;
+
+            self.byte += 1;
+            if self.byte > last_byte {
+                self.byte = first_byte;
+                self.rows.start += 1;
+            }
+        }
+
+        let bit = self.matches.leading_zeros() as usize;
+        self.matches &= !(0x80 >> bit);
+        Some((
+            self.match_offset % WIDTH_IN_BYTES * 8 + bit,
+            self.match_offset / WIDTH_IN_BYTES,
+        ))
+    }
+}
+
+impl fmt::Debug for MatchingPixels<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MatchingPixels")
+            .field("frame_buffer", &self.frame_buffer)
+            .field("columns", &self.columns)
+            .field("rows", &self.rows)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for MatchingPixels<'_> {
+    fn drop(&mut self) {
+        let graphics = &mut self.vga.graphics_controller_registers;
+        graphics.set_graphics_mode(self.saved_graphics_mode);
+        graphics.set_color_compare(self.saved_color_compare);
+        graphics.set_color_dont_care(self.saved_color_dont_care);
+    }
+}
+
+/// Renders `character` with the given colors as one byte per row for each plane.
+fn render_planar_glyph(character: char, foreground: Color16, background: Color16) -> PlanarGlyph {
+    let character = font_rows(character);
+    let (foreground, background) = (u8::from(foreground), u8::from(background));
+    let mut glyph = [[0; 8]; 4];
+    for (plane, rows) in glyph.iter_mut().enumerate() {
+        for (row, byte) in rows.iter_mut().enumerate() {
+            // font8x8 stores the leftmost pixel in the lowest bit.
+            let pixels = character[row].reverse_bits();
+            if foreground & (1 << plane) != 0 {
+                *byte |= pixels;
+            }
+            if background & (1 << plane) != 0 {
+                *byte |= !pixels;
+            }
+        }
+    }
+    glyph
+}
+
+/// Returns the font8x8 rows of `character`.
+fn font_rows(character: char) -> [u8; 8] {
+    match font8x8::BASIC_FONTS.get(character) {
+        Some(character) => character,
+        // Default to a filled block if the character isn't found
+        None => font8x8::unicode::BLOCK_UNICODE[8].byte_array(),
+    }
+}
@@ -0,0 +1,586 @@
+//! A text console drawn on top of `Graphics640x480x16`.
+
+use super::{Graphics640x480x16, GraphicsWriter, Screen};
+use crate::{
+    colors::Color16,
+    planar::PlanarFrameBuffer,
+    registers::{CrtcControllerIndex, PlaneMask},
+    vga::{MemoryLayout, ModeInfo},
+};
+use core::{fmt, ops::Range};
+
+const CHARACTER_WIDTH: usize = 8;
+const CHARACTER_HEIGHT: usize = 8;
+const COLUMNS: usize = Graphics640x480x16::WIDTH / CHARACTER_WIDTH;
+const ROWS: usize = Graphics640x480x16::HEIGHT / CHARACTER_HEIGHT;
+const BYTES_PER_LINE: usize = Graphics640x480x16::WIDTH / 8;
+const PLANE_SIZE: usize = 0x10000;
+
+/// Represents how a `GraphicsConsole` scrolls its contents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScrollMethod {
+    /// Scrolls by moving the crtc start address, treating video memory
+    /// as a ring of scan lines wrapped with the line compare register.
+    StartAddress,
+    /// Scrolls by copying the contents of the frame buffer up.
+    Copy,
+}
+
+impl ScrollMethod {
+    /// Returns the fastest `ScrollMethod` supported by the memory layout
+    /// described by `mode_info`.
+    ///
+    /// Start address scrolling needs every scan line of the screen to fit
+    /// in one plane, which is only the case for planar and unchained modes.
+    pub fn for_mode(mode_info: ModeInfo) -> ScrollMethod {
+        let bytes_per_line = match mode_info.get_layout() {
+            MemoryLayout::Planar => mode_info.get_width() / 8,
+            MemoryLayout::Unchained => mode_info.get_width() / 4,
+            _ => return ScrollMethod::Copy,
+        };
+        if bytes_per_line * mode_info.get_height() <= PLANE_SIZE {
+            ScrollMethod::StartAddress
+        } else {
+            ScrollMethod::Copy
+        }
+    }
+}
+
+/// Represents the shape of the cursor of a `GraphicsConsole`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CursorStyle {
+    /// The bottom two scan lines of the cell.
+    Underline,
+    /// The whole cell.
+    Block,
+    /// The two leftmost columns of the cell.
+    Bar,
+}
+
+impl CursorStyle {
+    /// Returns the scan lines of the cell the cursor covers, and the
+    /// bit mask of the pixels it covers on each of them.
+    fn lines_and_bit_mask(self) -> (Range<usize>, u8) {
+        match self {
+            CursorStyle::Underline => (CHARACTER_HEIGHT - 2..CHARACTER_HEIGHT, 0xFF),
+            CursorStyle::Block => (0..CHARACTER_HEIGHT, 0xFF),
+            CursorStyle::Bar => (0..CHARACTER_HEIGHT, 0xC0),
+        }
+    }
+}
+
+/// Represents the options a `GraphicsConsole` is created with.
+///
+/// `ConsoleOptions` are built from `ConsoleOptions::default` with the
+/// `with_*` methods.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ConsoleOptions {
+    foreground: Color16,
+    background: Color16,
+    scroll_method: Option<ScrollMethod>,
+}
+
+impl ConsoleOptions {
+    /// Sets the foreground color, `Color16::White` by default.
+    pub const fn with_foreground(mut self, foreground: Color16) -> ConsoleOptions {
+        self.foreground = foreground;
+        self
+    }
+
+    /// Sets the background color, `Color16::Black` by default.
+    pub const fn with_background(mut self, background: Color16) -> ConsoleOptions {
+        self.background = background;
+        self
+    }
+
+    /// Sets the `ScrollMethod`. By default, the fastest method supported
+    /// by the mode is picked with `ScrollMethod::for_mode`.
+    pub const fn with_scroll_method(mut self, scroll_method: ScrollMethod) -> ConsoleOptions {
+        self.scroll_method = Some(scroll_method);
+        self
+    }
+
+    /// Returns the foreground color.
+    pub const fn get_foreground(&self) -> Color16 {
+        self.foreground
+    }
+
+    /// Returns the background color.
+    pub const fn get_background(&self) -> Color16 {
+        self.background
+    }
+
+    /// Returns the `ScrollMethod`, or `None` if it's picked based on the mode.
+    pub const fn get_scroll_method(&self) -> Option<ScrollMethod> {
+        self.scroll_method
+    }
+}
+
+impl Default for ConsoleOptions {
+    fn default() -> ConsoleOptions {
+        ConsoleOptions {
+            foreground: Color16::White,
+            background: Color16::Black,
+            scroll_method: None,
+        }
+    }
+}
+
+/// A basic text console for vga graphics mode 640x480x16, using
+/// 8x8 characters for 80 columns and 60 rows.
+///
+/// Like the hardware cursor of the text modes, the console can draw a
+/// cursor in a `CursorStyle` and color of its own. The cell under the
+/// cursor is saved when it's drawn and restored when it's erased, and
+/// `blink_cursor` is meant to be called periodically to blink it.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
+/// use core::fmt::Write;
+/// use vga::colors::Color16;
+/// use vga::writers::GraphicsConsole;
+///
+/// let mut console = GraphicsConsole::new(Color16::White, Color16::Black);
+/// console.set_mode();
+/// writeln!(console, "Hello World!").unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GraphicsConsole<'a> {
+    writer: Graphics640x480x16<'a>,
+    column: usize,
+    row: usize,
+    foreground: Color16,
+    background: Color16,
+    scroll_method: ScrollMethod,
+    top_line: usize,
+    cursor: SoftwareCursor,
+}
+
+/// The state of the cursor drawn by a `GraphicsConsole`.
+#[derive(Debug, Clone, Copy)]
+struct SoftwareCursor {
+    style: CursorStyle,
+    color: Color16,
+    visible: bool,
+    blink_on: bool,
+    /// The planes of the cell under the cursor while it's drawn.
+    saved_cell: Option<[[u8; 4]; CHARACTER_HEIGHT]>,
+}
+
+#[cfg(feature = "global")]
+impl GraphicsConsole<'static> {
+    /// Creates a new `GraphicsConsole` drawing in the given colors.
+    pub fn new(foreground: Color16, background: Color16) -> GraphicsConsole<'static> {
+        GraphicsConsole::with_options(
+            ConsoleOptions::default()
+                .with_foreground(foreground)
+                .with_background(background),
+        )
+    }
+
+    /// Creates a new `GraphicsConsole` with the given `ConsoleOptions`.
+    pub fn with_options(options: ConsoleOptions) -> GraphicsConsole<'static> {
+        GraphicsConsole::with_writer(Graphics640x480x16::new(), options)
+    }
+}
+
+impl<'a> GraphicsConsole<'a> {
+    /// Creates a new `GraphicsConsole` drawing with `writer`, which allows
+    /// using a `Vga` other than the global `VGA`.
+    pub fn with_writer(
+        writer: Graphics640x480x16<'a>,
+        options: ConsoleOptions,
+    ) -> GraphicsConsole<'a> {
+        GraphicsConsole {
+            writer,
+            column: 0,
+            row: 0,
+            foreground: options.foreground,
+            background: options.background,
+            scroll_method: options
+                .scroll_method
+                .unwrap_or_else(|| ScrollMethod::for_mode(writer.get_mode_info())),
+            top_line: 0,
+            cursor: SoftwareCursor {
+                style: CursorStyle::Underline,
+                color: options.foreground,
+                visible: false,
+                blink_on: true,
+                saved_cell: None,
+            },
+        }
+    }
+
+    /// Sets the graphics device to mode 640x480x16 and clears the console.
+    pub fn set_mode(&mut self) {
+        self.writer.set_mode();
+        self.clear();
+    }
+
+    /// Clears the console to the background color and moves the
+    /// cursor to the top left corner.
+    pub fn clear(&mut self) {
+        self.cursor.saved_cell = None;
+        self.writer.clear_screen(self.background);
+        self.set_top_line(0);
+        self.column = 0;
+        self.row = 0;
+        self.draw_cursor();
+    }
+
+    /// Sets the colors used for characters written from now on.
+    pub fn set_colors(&mut self, foreground: Color16, background: Color16) {
+        self.foreground = foreground;
+        self.background = background;
+    }
+
+    /// Returns the `ScrollMethod` of the console.
+    pub fn get_scroll_method(&self) -> ScrollMethod {
+        self.scroll_method
+    }
+
+    /// Sets the `ScrollMethod` of the console, clearing it.
+    pub fn set_scroll_method(&mut self, scroll_method: ScrollMethod) {
+        self.scroll_method = scroll_method;
+        self.clear();
+    }
+
+    /// Returns the current position of the cursor as `(column, row)`.
+    pub fn get_cursor_position(&self) -> (usize, usize) {
+        (self.column, self.row)
+    }
+
+    /// Moves the cursor to `(column, row)`, clamped to the console.
+    pub fn set_cursor_position(&mut self, column: usize, row: usize) {
+        self.erase_cursor();
+        self.column = column.min(COLUMNS - 1);
+        self.row = row.min(ROWS - 1);
+        self.draw_cursor();
+    }
+
+    /// Shows or hides the cursor, which is hidden by default.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.erase_cursor();
+        self.cursor.visible = visible;
+        self.draw_cursor();
+    }
+
+    /// Returns the `CursorStyle` of the cursor.
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.cursor.style
+    }
+
+    /// Sets the `CursorStyle` of the cursor, which is
+    /// `CursorStyle::Underline` by default.
+    pub fn set_cursor_style(&mut self, style: CursorStyle) {
+        self.erase_cursor();
+        self.cursor.style = style;
+        self.draw_cursor();
+    }
+
+    /// Returns the color the cursor is drawn in.
+    pub fn get_cursor_color(&self) -> Color16 {
+        self.cursor.color
+    }
+
+    /// Sets the color the cursor is drawn in, which is the foreground
+    /// color the console was created with by default.
+    pub fn set_cursor_color(&mut self, color: Color16) {
+        self.erase_cursor();
+        self.cursor.color = color;
+        self.draw_cursor();
+    }
+
+    /// Toggles the blink phase of a visible cursor, erasing or drawing it.
+    ///
+    /// The console doesn't keep time, so this should be called
+    /// periodically, such as every 16 frames like the hardware cursor
+    /// of the text modes.
+    pub fn blink_cursor(&mut self) {
+        self.erase_cursor();
+        self.cursor.blink_on = !self.cursor.blink_on;
+        self.draw_cursor();
+    }
+
+    /// Writes `byte` at the cursor position and advances the cursor,
+    /// moving to the next line on `b'\n'` and scrolling when needed.
+    pub fn write_byte(&mut self, byte: u8) {
+        self.erase_cursor();
+        if byte == b'\n' {
+            self.new_line();
+        } else {
+            self.draw_cell(self.column, self.row, byte as char);
+            self.column += 1;
+            if self.column == COLUMNS {
+                self.new_line();
+            }
+        }
+        self.draw_cursor();
+    }
+
+    /// Returns the offset of the first scan line of the cell at
+    /// `(column, row)` in each plane.
+    fn cell_offset(&self, column: usize, row: usize) -> usize {
+        let y = (self.top_line + row * CHARACTER_HEIGHT) % Graphics640x480x16::HEIGHT;
+        y * BYTES_PER_LINE + column
+    }
+
+    /// Saves the cell at the cursor position and draws the cursor over it,
+    /// if the cursor is visible and in the on phase of blinking.
+    fn draw_cursor(&mut self) {
+        if !self.cursor.visible || !self.cursor.blink_on || self.cursor.saved_cell.is_some() {
+            return;
+        }
+        let offset = self.cell_offset(self.column, self.row);
+        let (lines, bit_mask) = self.cursor.style.lines_and_bit_mask();
+        let color = PlaneMask::from_bits_truncate(u8::from(self.cursor.color));
+
+        let mut vga = self.writer.lock_vga();
+        let mut planes = PlanarFrameBuffer::new(&mut vga);
+        let mut saved_cell = [[0; 4]; CHARACTER_HEIGHT];
+        for (line, saved_line) in saved_cell.iter_mut().enumerate() {
+            *saved_line = planes.read_planes(offset + line * BYTES_PER_LINE);
+        }
+        for line in lines {
+            let offset = offset + line * BYTES_PER_LINE;
+            if !color.is_empty() {
+                planes.write_byte_masked(color, offset, 0xFF, bit_mask);
+            }
+            if color != PlaneMask::ALL_PLANES {
+                planes.write_byte_masked(!color & PlaneMask::ALL_PLANES, offset, 0x00, bit_mask);
+            }
+        }
+        self.cursor.saved_cell = Some(saved_cell);
+    }
+
+    /// Restores the cell under the cursor if the cursor is drawn.
+    fn erase_cursor(&mut self) {
+        let saved_cell = match self.cursor.saved_cell.take() {
+            Some(saved_cell) => saved_cell,
+            None => return,
+        };
+        let offset = self.cell_offset(self.column, self.row);
+        let mut vga = self.writer.lock_vga();
+        let mut planes = PlanarFrameBuffer::new(&mut vga);
+        for (line, saved_line) in saved_cell.iter().enumerate() {
+            for (plane, byte) in saved_line.iter().enumerate() {
+                let plane_mask = PlaneMask::from_bits_truncate(1 << plane);
+                planes.write_byte(plane_mask, offset + line * BYTES_PER_LINE, *byte);
+            }
+        }
+    }
+
+    fn new_line(&mut self) {
+        self.column = 0;
+        if self.row + 1 < ROWS {
+            self.row += 1;
+            return;
+        }
+
+        match self.scroll_method {
+            ScrollMethod::StartAddress => {
+                self.set_top_line((self.top_line + CHARACTER_HEIGHT) % Graphics640x480x16::HEIGHT)
+            }
+            ScrollMethod::Copy => self.copy_scroll(),
+        }
+        for column in 0..COLUMNS {
+            self.draw_cell(column, ROWS - 1, ' ');
+        }
+    }
+
+    fn draw_cell(&self, column: usize, row: usize, character: char) {
+        let y = (self.top_line + row * CHARACTER_HEIGHT) % Graphics640x480x16::HEIGHT;
+        self.writer.draw_glyph(
+            column * CHARACTER_WIDTH,
+            y,
+            character,
+            self.foreground,
+            self.background,
+        );
+    }
+
+    /// Moves every row up by one using write mode 1, which copies all
+    /// four planes at once through the latches. The write mode and plane
+    /// mask are restored afterwards.
+    fn copy_scroll(&self) {
+        let mut vga = self.writer.lock_vga();
+        let row_size = BYTES_PER_LINE * CHARACTER_HEIGHT;
+        PlanarFrameBuffer::new(&mut vga).copy_latched(
+            row_size,
+            0,
+            BYTES_PER_LINE * Graphics640x480x16::HEIGHT - row_size,
+        );
+    }
+
+    /// Displays the screen starting at scan line `top_line`, wrapping
+    /// back to scan line 0 with the line compare register.
+    fn set_top_line(&mut self, top_line: usize) {
+        self.top_line = top_line;
+        let start_address = top_line * BYTES_PER_LINE;
+        let line_compare = match top_line {
+            0 => 0x3FF,
+            _ => Graphics640x480x16::HEIGHT - top_line - 1,
+        };
+
+        let mut vga = self.writer.lock_vga();
+        let emulation_mode = vga.get_emulation_mode();
+        let crtc = &mut vga.crtc_controller_registers;
+        crtc.write(
+            emulation_mode,
+            CrtcControllerIndex::StartAddressHigh,
+            (start_address >> 8) as u8,
+        );
+        crtc.write(
+            emulation_mode,
+            CrtcControllerIndex::StartAddressLow,
+            start_address as u8,
+        );
+        crtc.write(
+            emulation_mode,
+            CrtcControllerIndex::LineCompare,
+            line_compare as u8,
+        );
+        let overflow = crtc.read(emulation_mode, CrtcControllerIndex::Overflow) & !0x10;
+        crtc.write(
+            emulation_mode,
+            CrtcControllerIndex::Overflow,
+            overflow | ((line_compare >> 4) & 0x10) as u8,
+        );
+        let maximum_scan_line =
+            crtc.read(emulation_mode, CrtcControllerIndex::MaximumScanLine) & !0x40;
+        crtc.write(
+            emulation_mode,
+            CrtcControllerIndex::MaximumScanLine,
+            maximum_scan_line | ((line_compare >> 3) & 0x40) as u8,
+        );
+    }
+}
+
+impl fmt::Write for GraphicsConsole<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::{
+        mock::{lock_for_test, MOCK_HARDWARE},
+        registers::GraphicsControllerIndex,
+    };
+
+    fn start_address_and_line_compare() -> (usize, usize) {
+        let hardware = MOCK_HARDWARE.lock();
+        let start_address =
+            (hardware.crtc_controller(CrtcControllerIndex::StartAddressHigh) as usize) << 8
+                | hardware.crtc_controller(CrtcControllerIndex::StartAddressLow) as usize;
+        let line_compare = hardware.crtc_controller(CrtcControllerIndex::LineCompare) as usize
+            | ((hardware.crtc_controller(CrtcControllerIndex::Overflow) as usize & 0x10) << 4)
+            | ((hardware.crtc_controller(CrtcControllerIndex::MaximumScanLine) as usize & 0x40)
+                << 3);
+        (start_address, line_compare)
+    }
+
+    #[test]
+    fn test_start_address_scrolling() {
+        let _guard = lock_for_test();
+        let mut console = GraphicsConsole::new(Color16::White, Color16::Black);
+        console.set_mode();
+        assert_eq!(console.get_scroll_method(), ScrollMethod::StartAddress);
+        assert_eq!(start_address_and_line_compare(), (0, 0x3FF));
+
+        for _ in 0..ROWS {
+            console.write_byte(b'\n');
+        }
+        assert_eq!(console.get_cursor_position(), (0, ROWS - 1));
+        assert_eq!(
+            start_address_and_line_compare(),
+            (
+                CHARACTER_HEIGHT * BYTES_PER_LINE,
+                480 - CHARACTER_HEIGHT - 1
+            )
+        );
+    }
+
+    #[test]
+    fn test_copy_scrolling() {
+        let _guard = lock_for_test();
+        let options = ConsoleOptions::default().with_scroll_method(ScrollMethod::Copy);
+        let mut console = GraphicsConsole::with_options(options);
+        console.set_mode();
+
+        for _ in 0..ROWS - 1 {
+            console.write_byte(b'\n');
+        }
+        let graphics_mode = MOCK_HARDWARE
+            .lock()
+            .graphics_controller(GraphicsControllerIndex::GraphicsMode);
+        console.copy_scroll();
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .graphics_controller(GraphicsControllerIndex::GraphicsMode),
+            graphics_mode
+        );
+
+        console.write_byte(b'\n');
+        assert_eq!(console.get_cursor_position(), (0, ROWS - 1));
+        assert_eq!(start_address_and_line_compare(), (0, 0x3FF));
+    }
+
+    #[test]
+    fn test_cursor() {
+        let _guard = lock_for_test();
+        let mut console = GraphicsConsole::new(Color16::White, Color16::Black);
+        console.set_mode();
+        console.write_byte(b'A');
+        console.set_cursor_position(0, 0);
+        let cell = |console: &GraphicsConsole<'_>| {
+            let frame_buffer = console.writer.get_frame_buffer();
+            let mut cell = [0; CHARACTER_HEIGHT];
+            for (line, byte) in cell.iter_mut().enumerate() {
+                *byte = unsafe { frame_buffer.add(line * BYTES_PER_LINE).read_volatile() };
+            }
+            cell
+        };
+        let glyph = cell(&console);
+
+        console.set_cursor_style(CursorStyle::Block);
+        console.set_cursor_visible(true);
+        assert_eq!(cell(&console), [0xFF; CHARACTER_HEIGHT]);
+
+        console.blink_cursor();
+        assert_eq!(cell(&console), glyph);
+        console.blink_cursor();
+        assert_eq!(cell(&console), [0xFF; CHARACTER_HEIGHT]);
+
+        console.set_cursor_position(1, 0);
+        assert_eq!(cell(&console), glyph);
+        assert_eq!(console.get_cursor_position(), (1, 0));
+    }
+
+    #[test]
+    fn test_scroll_method_for_mode() {
+        use crate::vga::VideoMode;
+
+        assert_eq!(
+            ScrollMethod::for_mode(VideoMode::Mode320x240x256.mode_info()),
+            ScrollMethod::StartAddress
+        );
+        assert_eq!(
+            ScrollMethod::for_mode(VideoMode::Mode1280x800x256.mode_info()),
+            ScrollMethod::Copy
+        );
+    }
+}
@@ -1,29 +1,75 @@
 //! Writers for common vga modes.
+pub mod any;
+#[cfg(feature = "graphics-modes")]
+mod glyph_cache;
+#[cfg(feature = "graphics-modes")]
 mod graphics_1280x800x256;
+#[cfg(feature = "graphics-modes")]
 mod graphics_320x200x256;
+#[cfg(feature = "modex")]
 mod graphics_320x240x256;
+#[cfg(feature = "graphics-modes")]
 mod graphics_640x480x16;
+#[cfg(feature = "graphics-modes")]
+mod graphics_console;
+mod pixel_batch;
+mod rotated;
+#[cfg(feature = "text-modes")]
 mod text_40x25;
+#[cfg(feature = "text-modes")]
 mod text_40x50;
+#[cfg(feature = "text-modes")]
 mod text_80x25;
+#[cfg(all(feature = "bochs-device", feature = "fonts-8x16"))]
+mod text_on_graphics;
+mod text_snapshot;
+#[cfg(feature = "modex")]
+mod virtual_desktop;
+mod window;
 
 use super::{
     colors::{Color16, TextModeColor},
-    drawing::Point,
+    drawing::{clip_line, BlitOptions, Point, Rect, Surface},
     registers::CrtcControllerIndex,
-    vga::{Vga, VGA},
+    vga::{ModeInfo, Vga, VideoMode},
+    volatile::{volatile_copy, volatile_fill},
 };
-use core::slice::from_raw_parts_mut;
+#[cfg(feature = "graphics-modes")]
+use crate::drawing::Rotation;
+#[cfg(any(feature = "graphics-modes", feature = "modex"))]
+use crate::{vga::MemoryType, volatile::fill_planar};
+#[cfg(feature = "graphics-modes")]
+use core::ptr;
+use core::{fmt, iter::Map, marker::PhantomData, ops::Range, ptr::addr_of_mut};
 use spinning_top::SpinlockGuard;
 
 use crate::drawing::Bresenham;
+#[cfg(feature = "graphics-modes")]
 pub use graphics_1280x800x256::Graphics1280x800x256;
+#[cfg(feature = "graphics-modes")]
 pub use graphics_320x200x256::Graphics320x200x256;
+#[cfg(feature = "modex")]
 pub use graphics_320x240x256::Graphics320x240x256;
-pub use graphics_640x480x16::Graphics640x480x16;
+#[cfg(feature = "graphics-modes")]
+pub use graphics_640x480x16::{Graphics640x480x16, MatchingPixels};
+#[cfg(feature = "graphics-modes")]
+pub use graphics_console::{ConsoleOptions, CursorStyle, GraphicsConsole, ScrollMethod};
+pub use pixel_batch::PixelBatch;
+pub use rotated::Rotated;
+#[cfg(feature = "text-modes")]
 pub use text_40x25::Text40x25;
+#[cfg(feature = "text-modes")]
 pub use text_40x50::Text40x50;
+#[cfg(feature = "text-modes")]
 pub use text_80x25::Text80x25;
+#[cfg(all(feature = "bochs-device", feature = "fonts-8x16"))]
+pub use text_on_graphics::TextOnGraphics;
+pub use text_snapshot::{
+    code_page_437_to_char, TextCellRow, TextCells, TextRow, TextRows, TextSnapshot,
+};
+#[cfg(feature = "modex")]
+pub use virtual_desktop::VirtualDesktop;
+pub use window::WindowWriter;
 
 /// Represents a `ScreenCharacter` in vga text modes.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -34,11 +80,38 @@ pub struct ScreenCharacter {
 }
 
 impl ScreenCharacter {
+    /// A space in light grey on black, the color the bios clears text
+    /// modes with.
+    pub const BLANK: ScreenCharacter =
+        ScreenCharacter::new(b' ', TextModeColor::new(Color16::LightGrey, Color16::Black));
+
+    /// A full block, code page 437 character `0xDB`, in light grey on black.
+    pub const FULL_BLOCK: ScreenCharacter =
+        ScreenCharacter::new(0xDB, TextModeColor::new(Color16::LightGrey, Color16::Black));
+
     /// Creates a new `ScreenCharacter` with the specified `character` and `TextModeColor`.
     pub const fn new(character: u8, color: TextModeColor) -> ScreenCharacter {
         ScreenCharacter { character, color }
     }
 
+    /// Returns the `ScreenCharacter` with its character set to `character`.
+    pub const fn with_character(mut self, character: u8) -> ScreenCharacter {
+        self.character = character;
+        self
+    }
+
+    /// Returns the `ScreenCharacter` with its foreground color set to `foreground`.
+    pub const fn with_foreground(mut self, foreground: Color16) -> ScreenCharacter {
+        self.color = self.color.with_foreground(foreground);
+        self
+    }
+
+    /// Returns the `ScreenCharacter` with its background color set to `background`.
+    pub const fn with_background(mut self, background: Color16) -> ScreenCharacter {
+        self.color = self.color.with_background(background);
+        self
+    }
+
     /// Returns the `character` associated with the `ScreenCharacter`.
     pub fn get_character(self) -> u8 {
         self.character
@@ -48,12 +121,27 @@ impl ScreenCharacter {
     pub fn get_color(self) -> TextModeColor {
         self.color
     }
+
+    /// Sets the `character` associated with the `ScreenCharacter`.
+    pub fn set_character(&mut self, character: u8) {
+        self.character = character;
+    }
+
+    /// Sets the `color` associated with the `ScreenCharacter`.
+    pub fn set_color(&mut self, color: TextModeColor) {
+        self.color = color;
+    }
 }
 
-static BLANK_CHARACTER: ScreenCharacter = ScreenCharacter {
-    character: b' ',
-    color: TextModeColor::new(Color16::Yellow, Color16::Black),
-};
+impl Default for ScreenCharacter {
+    /// Returns `ScreenCharacter::BLANK`.
+    fn default() -> ScreenCharacter {
+        ScreenCharacter::BLANK
+    }
+}
+
+/// An iterator over every `(x, y)` position of a `Screen`.
+pub type ScreenPoints = Map<Range<usize>, fn(usize) -> Point<usize>>;
 
 /// A helper trait used to interact with various vga screens.
 pub trait Screen {
@@ -63,6 +151,145 @@ pub trait Screen {
     const HEIGHT: usize;
     /// The size (total area) of the `Screen`.
     const SIZE: usize;
+    /// The `VideoMode` of the `Screen`.
+    const VIDEO_MODE: VideoMode;
+
+    /// Returns the width of the `Screen`.
+    fn get_width(&self) -> usize {
+        Self::WIDTH
+    }
+
+    /// Returns the height of the `Screen`.
+    fn get_height(&self) -> usize {
+        Self::HEIGHT
+    }
+
+    /// Returns the `ModeInfo` of the `Screen`.
+    fn get_mode_info(&self) -> ModeInfo {
+        Self::VIDEO_MODE.mode_info()
+    }
+
+    /// Returns the row-major offset of the cell or pixel at `(x, y)`.
+    ///
+    /// **Note:** This is the index of the cell or pixel, not a byte offset
+    /// into the frame buffer, which differs in planar modes.
+    fn offset_of(x: usize, y: usize) -> usize {
+        Self::WIDTH * y + x
+    }
+
+    /// Returns the `(x, y)` position of the cell or pixel at the
+    /// row-major `offset`.
+    fn point_of(offset: usize) -> Point<usize> {
+        (offset % Self::WIDTH, offset / Self::WIDTH)
+    }
+
+    /// Returns the column-major offset of the cell or pixel at `(x, y)`.
+    fn column_major_offset_of(x: usize, y: usize) -> usize {
+        Self::HEIGHT * x + y
+    }
+
+    /// Returns the `(x, y)` position of the cell or pixel at the
+    /// column-major `offset`.
+    fn column_major_point_of(offset: usize) -> Point<usize> {
+        (offset / Self::HEIGHT, offset % Self::HEIGHT)
+    }
+
+    /// Returns an iterator over the `y` coordinates of every row.
+    fn rows() -> Range<usize> {
+        0..Self::HEIGHT
+    }
+
+    /// Returns an iterator over the `x` coordinates of every column.
+    fn columns() -> Range<usize> {
+        0..Self::WIDTH
+    }
+
+    /// Returns an iterator over every `(x, y)` position in row-major order.
+    fn points() -> ScreenPoints {
+        (0..Self::WIDTH * Self::HEIGHT).map(Self::point_of)
+    }
+
+    /// Returns an iterator over every `(x, y)` position in column-major order.
+    fn column_major_points() -> ScreenPoints {
+        (0..Self::WIDTH * Self::HEIGHT).map(Self::column_major_point_of)
+    }
+}
+
+/// Represents the options a mode is set with by `set_mode_with_options`.
+///
+/// `ModeOptions` are built from `ModeOptions::default` with the `with_*`
+/// methods.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ModeOptions {
+    load_palette: bool,
+    load_font: bool,
+    clear: bool,
+    clear_vram: bool,
+}
+
+impl ModeOptions {
+    /// Sets whether the active palette is loaded after setting the mode,
+    /// which is enabled by default. It's still skipped if the
+    /// `QuirkSet` of the `Vga` doesn't reload the palette.
+    pub const fn with_load_palette(mut self, load_palette: bool) -> ModeOptions {
+        self.load_palette = load_palette;
+        self
+    }
+
+    /// Sets whether text modes upload their font after setting the mode,
+    /// which is enabled by default.
+    pub const fn with_load_font(mut self, load_font: bool) -> ModeOptions {
+        self.load_font = load_font;
+        self
+    }
+
+    /// Sets whether the screen is cleared after setting the mode, which
+    /// is disabled by default.
+    pub const fn with_clear(mut self, clear: bool) -> ModeOptions {
+        self.clear = clear;
+        self
+    }
+
+    /// Sets whether video memory is cleared after setting the mode, which
+    /// is disabled by default. Enabling it ensures nothing of the previous
+    /// mode is displayed. Text modes keep their font when it isn't loaded.
+    ///
+    /// See `Vga::clear_vram` for the window this uses.
+    pub const fn with_clear_vram(mut self, clear_vram: bool) -> ModeOptions {
+        self.clear_vram = clear_vram;
+        self
+    }
+
+    /// Returns whether the active palette is loaded.
+    pub const fn get_load_palette(&self) -> bool {
+        self.load_palette
+    }
+
+    /// Returns whether text modes upload their font.
+    pub const fn get_load_font(&self) -> bool {
+        self.load_font
+    }
+
+    /// Returns whether the screen is cleared.
+    pub const fn get_clear(&self) -> bool {
+        self.clear
+    }
+
+    /// Returns whether video memory is cleared.
+    pub const fn get_clear_vram(&self) -> bool {
+        self.clear_vram
+    }
+}
+
+impl Default for ModeOptions {
+    fn default() -> ModeOptions {
+        ModeOptions {
+            load_palette: true,
+            load_font: true,
+            clear: false,
+            clear_vram: false,
+        }
+    }
 }
 
 /// A helper trait used to interact with various vga text modes.
@@ -71,32 +298,218 @@ pub trait TextWriter: Screen {
     /// the `TextWriter` implementation.
     fn set_mode(&self);
 
-    /// Returns the start of the `FrameBuffer` as `*mut ScreenCharacter`
-    /// as well as a lock to the vga driver. This ensures the vga
-    /// driver stays locked while the frame buffer is in use.
-    fn get_frame_buffer(&self) -> (SpinlockGuard<Vga>, *mut ScreenCharacter) {
-        let mut vga = VGA.lock();
-        let frame_buffer = vga.get_frame_buffer();
-        (vga, usize::from(frame_buffer) as *mut ScreenCharacter)
+    /// Sets the graphics device to the video mode of this `TextWriter`,
+    /// loading the palette and font and clearing the screen as specified
+    /// by `options`. Skipping the palette and font saves the time taken to
+    /// upload them on real hardware, when they're managed by the caller.
+    ///
+    /// The writers of this crate honor every option, while the default
+    /// implementation calls `set_mode` and only honors clearing the screen.
+    fn set_mode_with_options(&self, options: ModeOptions) {
+        self.set_mode();
+        if options.get_clear() {
+            self.clear_screen();
+        }
+    }
+
+    /// Locks the `Vga` used by this `TextWriter`.
+    fn lock_vga(&self) -> SpinlockGuard<'_, Vga>;
+
+    /// Locks the `Vga` used by this `TextWriter`, or returns `None` if it's
+    /// already locked.
+    ///
+    /// The default implementation calls `lock_vga`, which spins until the
+    /// `Vga` is unlocked, so implementations should override it when they can.
+    fn try_lock_vga(&self) -> Option<SpinlockGuard<'_, Vga>> {
+        Some(self.lock_vga())
+    }
+
+    /// Returns the start of the active page of the `FrameBuffer` as
+    /// `*mut ScreenCharacter` as well as a lock to the vga driver. This
+    /// ensures the vga driver stays locked while the frame buffer is in use.
+    fn get_frame_buffer(&self) -> (SpinlockGuard<'_, Vga>, *mut ScreenCharacter) {
+        let mut vga = self.lock_vga();
+        let frame_buffer = active_page::<Self>(&mut vga);
+        (vga, frame_buffer)
+    }
+
+    /// Returns the number of cells reserved for each text page, which is
+    /// `SIZE` rounded up to a power of two, the same as the bios.
+    fn page_size() -> usize {
+        Self::SIZE.next_power_of_two()
+    }
+
+    /// Returns the number of text pages that fit in the frame buffer window.
+    fn get_page_count(&self) -> usize {
+        let memory_map = self.lock_vga().get_memory_map();
+        memory_map.get_size() / 2 / Self::page_size()
+    }
+
+    /// Returns the text page drawn to by this writer, and any other text
+    /// writer using the same `Vga`.
+    fn get_active_page(&self) -> usize {
+        self.lock_vga().get_active_text_page()
+    }
+
+    /// Sets the text page drawn to, which doesn't need to be the displayed
+    /// page, so a page can be composed off-screen. The active page is reset
+    /// to 0 when the mode is set.
+    ///
+    /// Cursor positions are relative to the active page. Since there is only
+    /// one hardware cursor, it's only visible while the active page is the
+    /// displayed page.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `page` isn't less than `get_page_count`.
+    fn set_active_page(&self, page: usize) -> Result<(), &'static str> {
+        if page >= self.get_page_count() {
+            return Err("text page is out of range");
+        }
+        self.lock_vga().set_active_text_page(page);
+        Ok(())
+    }
+
+    /// Returns the text page currently displayed, as determined by the
+    /// crtc start address.
+    fn get_displayed_page(&self) -> usize {
+        read_start_address(&mut self.lock_vga()) / Self::page_size()
+    }
+
+    /// Displays the given text `page` by setting the crtc start address,
+    /// undoing any `smooth_scroll_up`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `page` isn't less than `get_page_count`.
+    fn set_displayed_page(&self, page: usize) -> Result<(), &'static str> {
+        if page >= self.get_page_count() {
+            return Err("text page is out of range");
+        }
+        let mut vga = self.lock_vga();
+        write_start_address(&mut vga, page * Self::page_size());
+        write_preset_row_scan(&mut vga, 0);
+        Ok(())
     }
 
-    /// Clears the screen by setting all cells to `b' '` with
-    /// a background color of `Color16::Black` and a foreground
-    /// color of `Color16::Yellow`.
+    /// Scrolls the displayed screen up by `pixels` scan lines, with the crtc
+    /// preset row scan for scan lines within a character row and the start
+    /// address for whole rows, so no video memory is copied.
+    ///
+    /// The rows scrolled into view are the ones following the displayed
+    /// rows in video memory, so text written below the screen glides up
+    /// from the bottom. For a smooth animation, scroll a few scan lines per
+    /// frame, such as after `frame_counter::wait_frames`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the screen would scroll past the end of the
+    /// frame buffer window.
+    fn smooth_scroll_up(&self, pixels: usize) -> Result<(), &'static str> {
+        let mut vga = self.lock_vga();
+        let emulation_mode = vga.get_emulation_mode();
+        let character_height = (vga
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::MaximumScanLine)
+            & 0x1F) as usize
+            + 1;
+        let preset_row_scan = vga
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::PresetRowScan)
+            & 0x1F;
+        let scan_lines = preset_row_scan as usize + pixels;
+        let row_scan = scan_lines % character_height;
+        let start_address =
+            read_start_address(&mut vga) + scan_lines / character_height * Self::WIDTH;
+
+        // Part of the row below the screen is displayed while the row scan isn't 0.
+        let displayed_rows = if row_scan == 0 {
+            Self::HEIGHT
+        } else {
+            Self::HEIGHT + 1
+        };
+        let window_size = vga.get_memory_map().get_size() / 2;
+        if start_address + displayed_rows * Self::WIDTH > window_size {
+            return Err("can't scroll past the end of the frame buffer window");
+        }
+        write_start_address(&mut vga, start_address);
+        write_preset_row_scan(&mut vga, row_scan as u8);
+        Ok(())
+    }
+
+    /// Clears the screen by setting all cells to the blank character,
+    /// see `set_blank_character`.
     fn clear_screen(&self) {
-        self.fill_screen(BLANK_CHARACTER);
+        let blank = self.get_blank_character();
+        self.fill_screen(blank);
+    }
+
+    /// Returns the character used by `clear_screen`, `clear_region` and
+    /// scrolling.
+    fn get_blank_character(&self) -> ScreenCharacter {
+        self.lock_vga().get_blank_character()
+    }
+
+    /// Sets the character used by `clear_screen`, `clear_region` and
+    /// scrolling, which is `ScreenCharacter::BLANK` by default, or a shaded
+    /// block like `0xB0` for a retro look. Its color is the default
+    /// attribute, see `set_default_attribute`.
+    ///
+    /// Consoles and widgets built on the text writers, such as `tui` frames
+    /// and `ReflowConsole`, fill empty cells with its character in colors
+    /// of their own.
+    ///
+    /// Like the default attribute, the blank character is kept by the `Vga`
+    /// and when a mode is set.
+    fn set_blank_character(&self, blank_character: ScreenCharacter) {
+        self.lock_vga().set_blank_character(blank_character);
+    }
+
+    /// Returns the color used by `clear_screen`, scrolling and `put_str`.
+    fn get_default_attribute(&self) -> TextModeColor {
+        self.lock_vga().get_default_text_attribute()
+    }
+
+    /// Sets the color used by `clear_screen`, scrolling and `put_str`,
+    /// which is light grey on black by default.
+    ///
+    /// The default attribute is kept by the `Vga`, so it applies to every
+    /// text writer using the same `Vga`, and it's kept when a mode is set.
+    fn set_default_attribute(&self, color: TextModeColor) {
+        self.lock_vga().set_default_text_attribute(color);
     }
 
     /// Fills the screen by setting all cells to the given screen character.
     fn fill_screen(&self, character: ScreenCharacter) {
         let (_vga, frame_buffer) = self.get_frame_buffer();
-        for i in 0..Self::SIZE {
+        unsafe {
+            volatile_fill(frame_buffer, character, Self::SIZE);
+        }
+    }
+
+    /// Sets every cell in `rect`, clipped to the screen, to the given
+    /// screen character.
+    fn fill_region(&self, rect: Rect, character: ScreenCharacter) {
+        let rect = rect.intersection(Rect::new(0, 0, Self::WIDTH, Self::HEIGHT));
+        let (_vga, frame_buffer) = self.get_frame_buffer();
+        for y in rect.get_y()..rect.get_y() + rect.get_height() {
             unsafe {
-                frame_buffer.add(i).write_volatile(character);
+                volatile_fill(
+                    frame_buffer.add(Self::offset_of(rect.get_x(), y)),
+                    character,
+                    rect.get_width(),
+                );
             }
         }
     }
 
+    /// Clears every cell in `rect`, clipped to the screen, to the blank
+    /// character, see `set_blank_character`.
+    fn clear_region(&self, rect: Rect) {
+        let blank = self.get_blank_character();
+        self.fill_region(rect, blank);
+    }
+
     /// Disables the cursor in vga text modes.
     fn disable_cursor(&self) {
         let (mut vga, _frame_buffer) = self.get_frame_buffer();
@@ -128,19 +541,105 @@ pub trait TextWriter: Screen {
     /// Returns the `ScreenCharacter` at the given `(x, y)` position.
     fn read_character(&self, x: usize, y: usize) -> ScreenCharacter {
         let (_vga, frame_buffer) = self.get_frame_buffer();
-        let offset = Self::WIDTH * y + x;
+        let offset = Self::offset_of(x, y);
         unsafe { frame_buffer.add(offset).read_volatile() }
     }
 
+    /// Reads the `ScreenCharacter` at the given `(x, y)` position, passes
+    /// it to `f` and writes it back, holding the lock to the vga driver
+    /// the whole time.
+    ///
+    /// Unlike `write_character`, this never moves the cursor.
+    fn modify_character(&self, x: usize, y: usize, f: impl FnOnce(&mut ScreenCharacter))
+    where
+        Self: Sized,
+    {
+        let (_vga, frame_buffer) = self.get_frame_buffer();
+        let offset = Self::offset_of(x, y);
+        unsafe {
+            let cell = frame_buffer.add(offset);
+            let mut screen_character = cell.read_volatile();
+            f(&mut screen_character);
+            cell.write_volatile(screen_character);
+        }
+    }
+
+    /// Returns an iterator over the `(x, y, ScreenCharacter)` of every cell
+    /// of the active page in row-major order, holding the lock to the vga
+    /// driver until it's dropped.
+    fn iter_cells(&self) -> TextCells<'_>
+    where
+        Self: Sized,
+    {
+        let (vga, frame_buffer) = self.get_frame_buffer();
+        TextCells::new(vga, frame_buffer, Self::WIDTH, Self::HEIGHT)
+    }
+
+    /// Returns an iterator over copies of the rows of the active page, each
+    /// an iterator over the `(x, y, ScreenCharacter)` of its cells, holding
+    /// the lock to the vga driver until it's dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the screen is wider than 80 columns.
+    fn iter_rows(&self) -> TextRows<'_>
+    where
+        Self: Sized,
+    {
+        TextRows::new(self.iter_cells())
+    }
+
+    /// Returns an iterator over the rows of the screen, each an iterator
+    /// over the `(character, foreground, background)` of its cells.
+    fn snapshot(&self) -> TextSnapshot<'_, Self>
+    where
+        Self: Sized,
+    {
+        TextSnapshot::new(self)
+    }
+
+    /// Writes the characters on the screen to `writer` as plain text, one
+    /// line per row with trailing spaces removed, such as to log the final
+    /// state of the screen over a serial port.
+    fn write_snapshot(&self, writer: &mut impl fmt::Write) -> fmt::Result
+    where
+        Self: Sized,
+    {
+        text_snapshot::write_snapshot(self.snapshot(), writer)
+    }
+
+    /// Returns the bottom scan line of a character cell, as specified by
+    /// `CrtcControllerIndex::MaximumScanLine`, which is one less than the
+    /// height of the font, e.g. 15 for an 8x16 font.
+    fn max_scan_line(&self) -> u8 {
+        let (mut vga, _frame_buffer) = self.get_frame_buffer();
+        let emulation_mode = vga.get_emulation_mode();
+        vga.crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::MaximumScanLine)
+            & 0x1F
+    }
+
     /// Sets the size of the cursor, as specified by `scan_line_start` and `scan_line_end`.
     ///
     /// This field controls the appearance of the text mode cursor by specifying the scan
     /// line location within a character cell. The top most scan line is 0, with the bottom
-    /// determined by `CrtcControllerIndex::MaxiumumScanLine (usually 15)`.
+    /// returned by `max_scan_line` (usually 15).
     /// If `scan_line_start > scan_line_end`, the cursor isn't drawn.
-    fn set_cursor(&self, scan_line_start: u8, scan_line_end: u8) {
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `scan_line_start` or `scan_line_end` is below the
+    /// bottom scan line of the current font, which would hide the cursor.
+    fn set_cursor(&self, scan_line_start: u8, scan_line_end: u8) -> Result<(), &'static str> {
         let (mut vga, _frame_buffer) = self.get_frame_buffer();
         let emulation_mode = vga.get_emulation_mode();
+        let max_scan_line = vga
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::MaximumScanLine)
+            & 0x1F;
+        if scan_line_start > max_scan_line || scan_line_end > max_scan_line {
+            return Err("the cursor scan lines are below the character cell");
+        }
         let cursor_start = vga
             .crtc_controller_registers
             .read(emulation_mode, CrtcControllerIndex::TextCursorStart)
@@ -159,35 +658,589 @@ pub trait TextWriter: Screen {
             CrtcControllerIndex::TextCursorEnd,
             cursor_end | scan_line_end,
         );
+        Ok(())
     }
 
-    /// Sets the current text cursor to the position specified by
-    /// `x` and `y`.
-    fn set_cursor_position(&self, x: usize, y: usize) {
-        let offset = Self::WIDTH * y + x;
+    /// Returns the scan line of the character cell the underline of
+    /// underlined characters is drawn on, or `None` if it's below the
+    /// cell and hidden.
+    fn get_underline_location(&self) -> Option<u8> {
         let (mut vga, _frame_buffer) = self.get_frame_buffer();
         let emulation_mode = vga.get_emulation_mode();
-        let cursor_start = offset & 0xFF;
-        let cursor_end = (offset >> 8) & 0xFF;
-        vga.crtc_controller_registers.write(
-            emulation_mode,
-            CrtcControllerIndex::TextCursorLocationLow,
-            cursor_start as u8,
-        );
+        let maximum_scan_line = vga
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::MaximumScanLine)
+            & 0x1F;
+        let underline_location = vga
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::UnderlineLocation)
+            & 0x1F;
+        if underline_location <= maximum_scan_line {
+            Some(underline_location)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the scan line of the character cell the underline of
+    /// underlined characters is drawn on, or hides it with `None`.
+    ///
+    /// Characters are underlined when their color is
+    /// `TextModeColor::underlined`, which the color text modes hide
+    /// by default, while monochrome text modes underline them on the
+    /// bottom scan line.
+    fn set_underline_location(&self, scan_line: Option<u8>) {
+        let (mut vga, _frame_buffer) = self.get_frame_buffer();
+        let emulation_mode = vga.get_emulation_mode();
+        let underline_location = vga
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::UnderlineLocation)
+            & 0xE0;
         vga.crtc_controller_registers.write(
             emulation_mode,
-            CrtcControllerIndex::TextCursorLocationHigh,
-            cursor_end as u8,
+            CrtcControllerIndex::UnderlineLocation,
+            underline_location | scan_line.map_or(0x1F, |scan_line| scan_line & 0x1F),
         );
     }
 
+    /// Sets the current text cursor to the position specified by
+    /// `x` and `y` of the active page.
+    fn set_cursor_position(&self, x: usize, y: usize) {
+        let offset = Self::offset_of(x, y);
+        let (mut vga, _frame_buffer) = self.get_frame_buffer();
+        let page_start = vga.get_active_text_page() * Self::page_size();
+        write_cursor_offset(&mut vga, page_start + offset);
+    }
+
+    /// Returns the current position of the text cursor on the active
+    /// page as `(x, y)`.
+    fn get_cursor_position(&self) -> (usize, usize) {
+        let (mut vga, _frame_buffer) = self.get_frame_buffer();
+        let page_start = vga.get_active_text_page() * Self::page_size();
+        let offset = read_cursor_offset(&mut vga).saturating_sub(page_start);
+        Self::point_of(offset)
+    }
+
+    /// Prints the given `character` and `color` at the current cursor
+    /// position and advances the cursor.
+    ///
+    /// `b'\n'` moves the cursor to the start of the next line. When the
+    /// cursor moves past the end of the last line, the screen is scrolled
+    /// up by one line.
+    fn put_char(&self, character: u8, color: TextModeColor) {
+        put_char_locked::<Self>(&mut self.lock_vga(), character, color);
+    }
+
+    /// Prints `s` with the default attribute like `put_char`, writing
+    /// bytes outside of the printable ascii range as `0xFE`.
+    ///
+    /// The text writers of this crate also implement `fmt::Write` with
+    /// this method.
+    fn put_str(&self, s: &str) {
+        let color = self.get_default_attribute();
+        put_str_locked::<Self>(&mut self.lock_vga(), s, color);
+    }
+
+    /// Sets the color of every cell in `rect`, clipped to the screen,
+    /// keeping their characters.
+    fn set_region_color(&self, rect: Rect, color: TextModeColor) {
+        update_region_colors(self, rect, |_| color);
+    }
+
+    /// Swaps the foreground and background color of every cell in `rect`,
+    /// clipped to the screen, keeping their characters. Inverting a region
+    /// twice restores it, which makes this suitable for highlighting a
+    /// selection.
+    fn invert_region(&self, rect: Rect) {
+        update_region_colors(self, rect, TextModeColor::inverted);
+    }
+
+    /// Returns whether `write_character` and `write_str_at` move the
+    /// cursor after the characters they write.
+    fn get_cursor_follow(&self) -> bool {
+        self.lock_vga().get_cursor_follow()
+    }
+
+    /// Sets whether `write_character` and `write_str_at` move the cursor
+    /// after the characters they write, scrolling the screen up when
+    /// writing past the end of the last line like `put_char`. This is
+    /// disabled by default.
+    ///
+    /// The setting is kept by the `Vga`, so it applies to every text
+    /// writer using the same `Vga`, and it's kept when a mode is set.
+    fn set_cursor_follow(&self, cursor_follow: bool) {
+        self.lock_vga().set_cursor_follow(cursor_follow);
+    }
+
     /// Prints the given `character` and `color` at `(x, y)`.
+    ///
+    /// If cursor follow is enabled, the cursor is moved after the character.
     fn write_character(&self, x: usize, y: usize, screen_character: ScreenCharacter) {
-        let (_vga, frame_buffer) = self.get_frame_buffer();
-        let offset = Self::WIDTH * y + x;
+        let (mut vga, frame_buffer) = self.get_frame_buffer();
+        let offset = Self::offset_of(x, y);
         unsafe {
             frame_buffer.add(offset).write_volatile(screen_character);
         }
+        if vga.get_cursor_follow() {
+            follow_cursor::<Self>(&mut vga, frame_buffer, offset + 1);
+        }
+    }
+
+    /// Prints `s` with `color` starting at `(x, y)`, continuing on the next
+    /// line at the end of each line and after `b'\n'`, and writing bytes
+    /// outside of the printable ascii range as `0xFE`.
+    ///
+    /// If cursor follow is enabled, the cursor is moved after the text and
+    /// the screen is scrolled up when it reaches past the last line.
+    /// Otherwise, whatever doesn't fit on the screen is left out.
+    fn write_str_at(&self, x: usize, y: usize, s: &str, color: TextModeColor) {
+        let (mut vga, frame_buffer) = self.get_frame_buffer();
+        let cursor_follow = vga.get_cursor_follow();
+        let blank = vga.get_blank_character();
+        let mut offset = Self::offset_of(x, y);
+        for byte in s.bytes() {
+            if offset >= Self::SIZE {
+                if !cursor_follow {
+                    return;
+                }
+                scroll_up(frame_buffer, Self::WIDTH, Self::SIZE, blank);
+                offset -= Self::WIDTH;
+            }
+            match byte {
+                b'\n' => offset = Self::offset_of(0, Self::point_of(offset).1 + 1),
+                byte => {
+                    let character = match byte {
+                        // Printable ascii
+                        0x20..=0x7E => byte,
+                        // Not part of the printable ascii range
+                        _ => 0xFE,
+                    };
+                    unsafe {
+                        frame_buffer
+                            .add(offset)
+                            .write_volatile(ScreenCharacter::new(character, color));
+                    }
+                    offset += 1;
+                }
+            }
+        }
+        if cursor_follow {
+            follow_cursor::<Self>(&mut vga, frame_buffer, offset);
+        }
+    }
+}
+
+/// Writes text at the cursor position of a `TextWriter` like `put_str`,
+/// through one lock of its `Vga` held until the `TextLine` is dropped.
+pub(crate) struct TextLine<'a, T: ?Sized> {
+    vga: SpinlockGuard<'a, Vga>,
+    color: TextModeColor,
+    writer: PhantomData<&'a T>,
+}
+
+impl<'a, T: TextWriter + ?Sized> TextLine<'a, T> {
+    /// Creates a new `TextLine` writing to `writer` with `color`, or
+    /// returns `None` if the `Vga` of `writer` is already locked.
+    pub(crate) fn try_new(writer: &'a T, color: TextModeColor) -> Option<TextLine<'a, T>> {
+        Some(TextLine {
+            vga: writer.try_lock_vga()?,
+            color,
+            writer: PhantomData,
+        })
+    }
+}
+
+impl<T: TextWriter + ?Sized> fmt::Write for TextLine<'_, T> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        put_str_locked::<T>(&mut self.vga, s, self.color);
+        Ok(())
+    }
+}
+
+/// Returns the start of the active page of the frame buffer of `vga`,
+/// which is already locked, for the text mode `T`.
+fn active_page<T: TextWriter + ?Sized>(vga: &mut Vga) -> *mut ScreenCharacter {
+    let frame_buffer = usize::from(vga.get_frame_buffer()) as *mut ScreenCharacter;
+    let page_start = vga.get_active_text_page() * T::page_size();
+    unsafe { frame_buffer.add(page_start) }
+}
+
+/// Prints `character` like `TextWriter::put_char` of the text mode `T`,
+/// through the already locked `vga`.
+fn put_char_locked<T: TextWriter + ?Sized>(vga: &mut Vga, character: u8, color: TextModeColor) {
+    let frame_buffer = active_page::<T>(vga);
+    let page_start = vga.get_active_text_page() * T::page_size();
+    let mut offset = read_cursor_offset(vga).wrapping_sub(page_start);
+    if offset >= T::SIZE {
+        offset = T::SIZE - T::WIDTH;
+    }
+
+    match character {
+        b'\n' => offset = T::offset_of(0, T::point_of(offset).1 + 1),
+        character => {
+            unsafe {
+                frame_buffer
+                    .add(offset)
+                    .write_volatile(ScreenCharacter::new(character, color));
+            }
+            offset += 1;
+        }
+    }
+
+    if offset >= T::SIZE {
+        let blank = vga.get_blank_character();
+        scroll_up(frame_buffer, T::WIDTH, T::SIZE, blank);
+        offset -= T::WIDTH;
+    }
+
+    write_cursor_offset(vga, page_start + offset);
+}
+
+/// Prints `s` with `color` like `put_char_locked`, writing bytes outside
+/// of the printable ascii range as `0xFE`.
+fn put_str_locked<T: TextWriter + ?Sized>(vga: &mut Vga, s: &str, color: TextModeColor) {
+    for byte in s.bytes() {
+        match byte {
+            // Printable ascii or newline
+            0x20..=0x7E | b'\n' => put_char_locked::<T>(vga, byte, color),
+            // Not part of the printable ascii range
+            _ => put_char_locked::<T>(vga, 0xFE, color),
+        }
+    }
+}
+
+/// Replaces the color of every cell in `rect`, clipped to the screen of
+/// `writer`, with `f` of its color, only accessing the attribute bytes.
+fn update_region_colors<T: TextWriter + ?Sized>(
+    writer: &T,
+    rect: Rect,
+    f: impl Fn(TextModeColor) -> TextModeColor,
+) {
+    let rect = rect.intersection(Rect::new(0, 0, T::WIDTH, T::HEIGHT));
+    let (_vga, frame_buffer) = writer.get_frame_buffer();
+    for y in rect.get_y()..rect.get_y() + rect.get_height() {
+        for x in rect.get_x()..rect.get_x() + rect.get_width() {
+            unsafe {
+                let color = addr_of_mut!((*frame_buffer.add(T::offset_of(x, y))).color);
+                color.write_volatile(f(color.read_volatile()));
+            }
+        }
+    }
+}
+
+/// Moves the cursor to `offset` of the active page, scrolling the screen
+/// up first if `offset` is past the end of the last line.
+fn follow_cursor<T: TextWriter + ?Sized>(
+    vga: &mut Vga,
+    frame_buffer: *mut ScreenCharacter,
+    mut offset: usize,
+) {
+    if offset >= T::SIZE {
+        let blank = vga.get_blank_character();
+        scroll_up(frame_buffer, T::WIDTH, T::SIZE, blank);
+        offset -= T::WIDTH;
+    }
+    let page_start = vga.get_active_text_page() * T::page_size();
+    write_cursor_offset(vga, page_start + offset);
+}
+
+fn read_cursor_offset(vga: &mut Vga) -> usize {
+    let emulation_mode = vga.get_emulation_mode();
+    let cursor_low = vga
+        .crtc_controller_registers
+        .read(emulation_mode, CrtcControllerIndex::TextCursorLocationLow);
+    let cursor_high = vga
+        .crtc_controller_registers
+        .read(emulation_mode, CrtcControllerIndex::TextCursorLocationHigh);
+    (cursor_high as usize) << 8 | cursor_low as usize
+}
+
+fn write_cursor_offset(vga: &mut Vga, offset: usize) {
+    let emulation_mode = vga.get_emulation_mode();
+    let cursor_start = offset & 0xFF;
+    let cursor_end = (offset >> 8) & 0xFF;
+    vga.crtc_controller_registers.write(
+        emulation_mode,
+        CrtcControllerIndex::TextCursorLocationLow,
+        cursor_start as u8,
+    );
+    vga.crtc_controller_registers.write(
+        emulation_mode,
+        CrtcControllerIndex::TextCursorLocationHigh,
+        cursor_end as u8,
+    );
+}
+
+fn read_start_address(vga: &mut Vga) -> usize {
+    let emulation_mode = vga.get_emulation_mode();
+    let start_address_high = vga
+        .crtc_controller_registers
+        .read(emulation_mode, CrtcControllerIndex::StartAddressHigh);
+    let start_address_low = vga
+        .crtc_controller_registers
+        .read(emulation_mode, CrtcControllerIndex::StartAddressLow);
+    (start_address_high as usize) << 8 | start_address_low as usize
+}
+
+fn write_start_address(vga: &mut Vga, start_address: usize) {
+    let emulation_mode = vga.get_emulation_mode();
+    vga.crtc_controller_registers.write(
+        emulation_mode,
+        CrtcControllerIndex::StartAddressHigh,
+        (start_address >> 8) as u8,
+    );
+    vga.crtc_controller_registers.write(
+        emulation_mode,
+        CrtcControllerIndex::StartAddressLow,
+        start_address as u8,
+    );
+}
+
+fn write_preset_row_scan(vga: &mut Vga, row_scan: u8) {
+    let emulation_mode = vga.get_emulation_mode();
+    let preset_row_scan = vga
+        .crtc_controller_registers
+        .read(emulation_mode, CrtcControllerIndex::PresetRowScan);
+    vga.crtc_controller_registers.write(
+        emulation_mode,
+        CrtcControllerIndex::PresetRowScan,
+        (preset_row_scan & !0x1F) | row_scan,
+    );
+}
+
+/// Moves every line of the text frame buffer up by one, filling the last
+/// line with `blank`.
+fn scroll_up(
+    frame_buffer: *mut ScreenCharacter,
+    width: usize,
+    size: usize,
+    blank: ScreenCharacter,
+) {
+    unsafe {
+        volatile_copy(frame_buffer.add(width), frame_buffer, size - width);
+        volatile_fill(frame_buffer.add(size - width), blank, width);
+    }
+}
+
+/// Fills `count` elements of the frame buffer with `value`, when the frame
+/// buffer is mapped write-combining using streaming stores for words and
+/// plain stores otherwise, followed by a store fence.
+///
+/// # Safety
+///
+/// `frame_buffer` must be aligned and valid for `count` writes of `T`.
+#[cfg(any(feature = "graphics-modes", feature = "modex"))]
+unsafe fn fill_frame_buffer<T: Copy>(
+    frame_buffer: *mut T,
+    value: T,
+    count: usize,
+    memory_type: MemoryType,
+) {
+    match memory_type {
+        #[cfg(target_arch = "x86_64")]
+        MemoryType::WriteCombining => {
+            use core::arch::x86_64::{_mm_sfence, _mm_stream_si32};
+            use core::mem::{align_of, size_of, transmute_copy};
+            if size_of::<T>() == 4 && align_of::<T>() == 4 {
+                let value = transmute_copy::<T, i32>(&value);
+                for offset in 0..count {
+                    _mm_stream_si32(frame_buffer.add(offset) as *mut i32, value);
+                }
+            } else {
+                for offset in 0..count {
+                    frame_buffer.add(offset).write(value);
+                }
+            }
+            // Flush the write-combining buffers before any register access.
+            _mm_sfence();
+        }
+        _ => fill_planar(frame_buffer, value, count),
+    }
+}
+
+/// Copies `src` to the frame buffer at `dst`, with a bulk copy followed by a
+/// store fence when the frame buffer is mapped write-combining.
+///
+/// # Safety
+///
+/// `dst` must be aligned and valid for `src.len()` writes of `C`.
+#[cfg(feature = "graphics-modes")]
+unsafe fn copy_to_frame_buffer<C: Copy>(src: &[C], dst: *mut C, memory_type: MemoryType) {
+    match memory_type {
+        #[cfg(target_arch = "x86_64")]
+        MemoryType::WriteCombining => {
+            ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len());
+            // Flush the write-combining buffers before any register access.
+            core::arch::x86_64::_mm_sfence();
+        }
+        _ => volatile_copy(src.as_ptr(), dst, src.len()),
+    }
+}
+
+/// Copies `src` to the frame buffer at `dst` in reverse order, so the last
+/// element of `src` is written first, followed by a store fence when the
+/// frame buffer is mapped write-combining.
+///
+/// # Safety
+///
+/// `dst` must be aligned and valid for `src.len()` writes of `C`.
+#[cfg(feature = "graphics-modes")]
+unsafe fn copy_reversed_to_frame_buffer<C: Copy>(src: &[C], dst: *mut C, memory_type: MemoryType) {
+    match memory_type {
+        #[cfg(target_arch = "x86_64")]
+        MemoryType::WriteCombining => {
+            for (offset, value) in src.iter().rev().enumerate() {
+                dst.add(offset).write(*value);
+            }
+            // Flush the write-combining buffers before any register access.
+            core::arch::x86_64::_mm_sfence();
+        }
+        _ => {
+            for (offset, value) in src.iter().rev().enumerate() {
+                dst.add(offset).write_volatile(*value);
+            }
+        }
+    }
+}
+
+/// Returns the columns and rows of the rectangle from `p1` up to `p2`,
+/// whichever corners they are, clipped to `bounds`.
+pub(crate) fn rect_ranges(
+    p1: Point<usize>,
+    p2: Point<usize>,
+    bounds: Rect,
+) -> (Range<usize>, Range<usize>) {
+    let (x, y) = (p1.0.min(p2.0), p1.1.min(p2.1));
+    let rect = Rect::new(x, y, p1.0.max(p2.0) - x, p1.1.max(p2.1) - y).intersection(bounds);
+    (
+        rect.get_x()..rect.get_x() + rect.get_width(),
+        rect.get_y()..rect.get_y() + rect.get_height(),
+    )
+}
+
+/// Fills the rectangle from `p1` up to `p2` of a linear frame buffer,
+/// storing one `C` per pixel, a row at a time with `fill_frame_buffer`.
+#[cfg(feature = "graphics-modes")]
+pub(crate) fn fill_linear_rect<W, C>(writer: &W, p1: Point<usize>, p2: Point<usize>, color: C)
+where
+    W: PrimitiveDrawing<C>,
+    C: Copy,
+{
+    let memory_type = writer.lock_vga().get_frame_buffer_memory_type();
+    let frame_buffer = writer.get_frame_buffer() as *mut C;
+    let (columns, rows) = rect_ranges(p1, p2, writer.get_clip_bounds());
+    if columns.is_empty() {
+        return;
+    }
+    for y in rows {
+        unsafe {
+            fill_frame_buffer(
+                frame_buffer.add(W::offset_of(columns.start, y)),
+                color,
+                columns.len(),
+                memory_type,
+            );
+        }
+    }
+}
+
+/// Blits to a linear frame buffer, storing one `C` per pixel. Blits that
+/// aren't scaled, flipped or rotated copy whole rows with
+/// `copy_to_frame_buffer`, and unscaled blits rotated by 180 degrees write
+/// whole rows backwards with `copy_reversed_to_frame_buffer`. The others
+/// fall back to `blit_pixels`.
+#[cfg(feature = "graphics-modes")]
+pub(crate) fn blit_linear<W, C>(
+    writer: &W,
+    src: &Surface<'_, C>,
+    src_rect: Rect,
+    dst_rect: Rect,
+    options: BlitOptions,
+) where
+    W: PrimitiveDrawing<C>,
+    C: Copy,
+{
+    let rotate180 = BlitOptions::default().with_rotation(Rotation::Rotate180);
+    if (options != BlitOptions::default() && options != rotate180)
+        || src_rect.get_width() != dst_rect.get_width()
+        || src_rect.get_height() != dst_rect.get_height()
+    {
+        return blit_pixels(writer, src, src_rect, dst_rect, options);
+    }
+    assert!(
+        src_rect.intersection(src.get_rect()) == src_rect,
+        "source rect is outside of the surface"
+    );
+    let visible = dst_rect.intersection(writer.get_clip_bounds());
+    if visible.is_empty() {
+        return;
+    }
+    let memory_type = writer.lock_vga().get_frame_buffer_memory_type();
+    let frame_buffer = writer.get_frame_buffer() as *mut C;
+    if options == rotate180 {
+        // The rightmost visible column comes from the leftmost source column.
+        let src_x = src_rect.get_x() + dst_rect.get_x() + dst_rect.get_width()
+            - visible.get_x()
+            - visible.get_width();
+        let last_y = src_rect.get_y() + src_rect.get_height() - 1;
+        for y in visible.get_y()..visible.get_y() + visible.get_height() {
+            let src_y = last_y - (y - dst_rect.get_y());
+            let row = &src.get_row(src_y)[src_x..src_x + visible.get_width()];
+            unsafe {
+                copy_reversed_to_frame_buffer(
+                    row,
+                    frame_buffer.add(W::offset_of(visible.get_x(), y)),
+                    memory_type,
+                );
+            }
+        }
+        return;
+    }
+    let src_x = src_rect.get_x() + visible.get_x() - dst_rect.get_x();
+    for y in visible.get_y()..visible.get_y() + visible.get_height() {
+        let src_y = src_rect.get_y() + y - dst_rect.get_y();
+        let row = &src.get_row(src_y)[src_x..src_x + visible.get_width()];
+        unsafe {
+            copy_to_frame_buffer(
+                row,
+                frame_buffer.add(W::offset_of(visible.get_x(), y)),
+                memory_type,
+            );
+        }
+    }
+}
+
+/// Blits one pixel at a time with `set_pixel`, which works for any memory
+/// layout. This is the default implementation of
+/// `PrimitiveDrawing::blit_with_options`.
+pub(crate) fn blit_pixels<W, C>(
+    writer: &W,
+    src: &Surface<'_, C>,
+    src_rect: Rect,
+    dst_rect: Rect,
+    options: BlitOptions,
+) where
+    W: PrimitiveDrawing<C> + ?Sized,
+    C: Copy,
+{
+    assert!(
+        src_rect.intersection(src.get_rect()) == src_rect,
+        "source rect is outside of the surface"
+    );
+    if src_rect.is_empty() {
+        return;
+    }
+    let (width, height) = (src_rect.get_width(), src_rect.get_height());
+    let (rotated_width, rotated_height) = options.rotated_size(width, height);
+    let visible = dst_rect.intersection(writer.get_clip_bounds());
+    for y in visible.get_y()..visible.get_y() + visible.get_height() {
+        let rotated_y = (y - dst_rect.get_y()) * rotated_height / dst_rect.get_height();
+        for x in visible.get_x()..visible.get_x() + visible.get_width() {
+            let rotated_x = (x - dst_rect.get_x()) * rotated_width / dst_rect.get_width();
+            let (src_x, src_y) = options.source_point(rotated_x, rotated_y, width, height);
+            let color = src.get_pixel(src_rect.get_x() + src_x, src_rect.get_y() + src_y);
+            writer.set_pixel(x, y, color);
+        }
     }
 }
 
@@ -205,9 +1258,56 @@ pub trait GraphicsWriter<Color: Copy> {
     /// Sets the graphics device to a `VideoMode`.
     fn set_mode(&self);
 
+    /// Sets the graphics device to the `VideoMode` of this `GraphicsWriter`,
+    /// loading the palette and clearing the screen to `Color::default` as
+    /// specified by `options`.
+    ///
+    /// The writers of this crate honor every option, while the default
+    /// implementation calls `set_mode` and only honors clearing the screen.
+    fn set_mode_with_options(&self, options: ModeOptions)
+    where
+        Color: Default,
+    {
+        self.set_mode();
+        if options.get_clear() {
+            self.clear_screen(Color::default());
+        }
+    }
+
+    /// Locks the `Vga` used by this `GraphicsWriter`.
+    fn lock_vga(&self) -> SpinlockGuard<'_, Vga>;
+
     /// Returns the frame buffer for this vga mode.
     fn get_frame_buffer(&self) -> *mut u8 {
-        usize::from(VGA.lock().get_frame_buffer()) as *mut u8
+        usize::from(self.lock_vga().get_frame_buffer()) as *mut u8
+    }
+
+    /// Locks the `Vga` once and calls `f` with a `PixelBatch` for drawing
+    /// many pixels without locking and setting up the write mode for each.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[cfg(all(feature = "global", feature = "graphics-modes"))]
+    /// # {
+    /// use vga::colors::Color16;
+    /// use vga::writers::{Graphics640x480x16, GraphicsWriter};
+    ///
+    /// let mode = Graphics640x480x16::new();
+    /// mode.set_mode();
+    /// mode.with_frame_buffer(|batch| {
+    ///     for x in (0..640).step_by(8) {
+    ///         batch.draw_line((x, 0), (639 - x, 479), Color16::LightBlue);
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    fn with_frame_buffer<R>(&self, f: impl FnOnce(&mut PixelBatch<'_, Color>) -> R) -> R
+    where
+        Self: Screen + Sized,
+    {
+        let layout = Self::VIDEO_MODE.mode_info().get_layout();
+        f(&mut PixelBatch::new(self.lock_vga(), layout, Self::WIDTH))
     }
 }
 
@@ -216,22 +1316,1137 @@ pub trait PrimitiveDrawing<C>: GraphicsWriter<C> + Screen
 where
     C: Copy,
 {
-    /// Draws a line from `start` to `end` with the specified `color`.
+    /// Returns the area drawing is clipped to, in addition to the screen.
+    fn get_clip_rect(&self) -> Option<Rect> {
+        self.lock_vga().get_clip_rect()
+    }
+
+    /// Sets the area lines, rectangles and blits are clipped to, in addition
+    /// to the screen, or `None` to only clip them to the screen.
+    ///
+    /// The clip rect is kept by the `Vga`, so it applies to every writer
+    /// using the same `Vga`, and it's reset whenever a mode is set.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[cfg(all(feature = "global", feature = "graphics-modes"))]
+    /// # {
+    /// use vga::drawing::Rect;
+    /// use vga::writers::{Graphics320x200x256, GraphicsWriter, PrimitiveDrawing};
+    ///
+    /// let mode = Graphics320x200x256::new();
+    /// mode.set_mode();
+    /// mode.set_clip_rect(Some(Rect::new(0, 0, 160, 100)));
+    /// // Only the top left quarter of the line is drawn.
+    /// mode.draw_line((0, 0), (319, 199), 15);
+    /// mode.set_clip_rect(None);
+    /// # }
+    /// ```
+    fn set_clip_rect(&self, clip_rect: Option<Rect>) {
+        self.lock_vga().set_clip_rect(clip_rect);
+    }
+
+    /// Returns the area drawing is clipped to, which is the clip rect
+    /// within the screen.
+    fn get_clip_bounds(&self) -> Rect {
+        let screen = Rect::new(0, 0, Self::WIDTH, Self::HEIGHT);
+        match self.get_clip_rect() {
+            Some(clip_rect) => clip_rect.intersection(screen),
+            None => screen,
+        }
+    }
+
+    /// Draws a line from `start` to `end` with the specified `color`,
+    /// clipped to the clip bounds.
     fn draw_line(&self, start: Point<isize>, end: Point<isize>, color: C) {
-        for (x, y) in Bresenham::new(start, end) {
-            self.set_pixel(x as usize, y as usize, color);
+        if let Some((start, end)) = clip_line(start, end, self.get_clip_bounds()) {
+            for (x, y) in Bresenham::new(start, end) {
+                self.set_pixel(x as usize, y as usize, color);
+            }
         }
     }
 
-    /// Draws a rectangle from `p1` to `p2` with the specified `color`.
+    /// Draws a filled rectangle from `p1` up to, but not including, `p2`
+    /// with the specified `color`, clipped to the clip bounds.
+    ///
+    /// The default implementation sets one pixel at a time, which works for
+    /// any memory layout. Writers override it with faster fills for theirs.
     fn draw_rect(&self, p1: Point<usize>, p2: Point<usize>, color: C) {
-        let frame_buffer = self.get_frame_buffer() as *mut C;
-        let line_width = p2.0.abs_diff(p1.0);
-
-        (p1.1..p2.1)
-            .map(|y| Self::WIDTH * y + p1.0)
-            .map(|offset| unsafe { frame_buffer.add(offset) })
-            .map(|ptr| unsafe { from_raw_parts_mut(ptr, line_width) })
-            .for_each(|line| line.fill(color));
+        let (columns, rows) = rect_ranges(p1, p2, self.get_clip_bounds());
+        for y in rows {
+            for x in columns.clone() {
+                self.set_pixel(x, y, color);
+            }
+        }
+    }
+
+    /// Copies `src_rect` of `src` to `dst_rect` of the screen, scaling it
+    /// to the size of `dst_rect` by repeating or skipping pixels, and
+    /// clipping it to the clip bounds.
+    ///
+    /// # Examples
+    ///
+    /// Drawing a 16x16 sprite at double size:
+    ///
+    /// ```no_run
+    /// # #[cfg(all(feature = "global", feature = "graphics-modes"))]
+    /// # {
+    /// use vga::drawing::{Rect, Surface};
+    /// use vga::writers::{Graphics320x200x256, GraphicsWriter, PrimitiveDrawing};
+    ///
+    /// let pixels = [0x0Fu8; 16 * 16];
+    /// let sprite = Surface::new(&pixels, 16, 16);
+    /// let mode = Graphics320x200x256::new();
+    /// mode.set_mode();
+    /// mode.blit_scaled(&sprite, sprite.get_rect(), Rect::new(100, 50, 32, 32));
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src_rect` isn't within `src`.
+    fn blit_scaled(&self, src: &Surface<'_, C>, src_rect: Rect, dst_rect: Rect) {
+        self.blit_with_options(src, src_rect, dst_rect, BlitOptions::default());
+    }
+
+    /// Copies `src_rect` of `src` to `dst_rect` of the screen like
+    /// `blit_scaled`, flipping and rotating it as set in `options`.
+    ///
+    /// # Examples
+    ///
+    /// Drawing a sprite facing left, and one turned on its side:
+    ///
+    /// ```no_run
+    /// # #[cfg(all(feature = "global", feature = "graphics-modes"))]
+    /// # {
+    /// use vga::drawing::{BlitOptions, Rect, Rotation, Surface};
+    /// use vga::writers::{Graphics320x200x256, GraphicsWriter, PrimitiveDrawing};
+    ///
+    /// let pixels = [0x0Fu8; 16 * 24];
+    /// let sprite = Surface::new(&pixels, 16, 24);
+    /// let mode = Graphics320x200x256::new();
+    /// mode.set_mode();
+    ///
+    /// let facing_left = BlitOptions::default().with_flip_horizontal(true);
+    /// mode.blit_with_options(&sprite, sprite.get_rect(), Rect::new(0, 0, 16, 24), facing_left);
+    ///
+    /// let on_its_side = BlitOptions::default().with_rotation(Rotation::Rotate90);
+    /// mode.blit_with_options(&sprite, sprite.get_rect(), Rect::new(40, 0, 24, 16), on_its_side);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src_rect` isn't within `src`.
+    fn blit_with_options(
+        &self,
+        src: &Surface<'_, C>,
+        src_rect: Rect,
+        dst_rect: Rect,
+        options: BlitOptions,
+    ) {
+        blit_pixels(self, src, src_rect, dst_rect, options);
+    }
+
+    /// Returns a `WindowWriter` drawing into `rect` of the screen, with
+    /// coordinates relative to its top left corner.
+    fn window(&self, rect: Rect) -> WindowWriter<'_, Self, C>
+    where
+        Self: Sized,
+    {
+        WindowWriter::new(self, rect)
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "global",
+    any(feature = "text-modes", feature = "graphics-modes", feature = "modex")
+))]
+mod test {
+    use super::*;
+    #[cfg(any(feature = "text-modes", feature = "graphics-modes"))]
+    use crate::colors::Color16;
+    use crate::mock::{lock_for_test, MOCK_HARDWARE};
+    #[cfg(feature = "modex")]
+    use crate::registers::SequencerIndex;
+    #[cfg(feature = "graphics-modes")]
+    use crate::{drawing::Rotation, vga::VGA};
+    #[cfg(any(feature = "graphics-modes", feature = "modex"))]
+    use crate::{mock::video_planes, registers::GraphicsControllerIndex};
+    #[cfg(feature = "text-modes")]
+    use alloc::vec::Vec;
+    #[cfg(feature = "text-modes")]
+    use core::fmt::Write;
+    #[cfg(all(feature = "text-modes", feature = "graphics-modes"))]
+    use spinning_top::Spinlock;
+
+    /// Returns the color of the pixel at `(x, y)` of `Graphics640x480x16`,
+    /// assembled from a bit of each simulated plane.
+    #[cfg(feature = "graphics-modes")]
+    fn planar_pixel(x: usize, y: usize) -> u8 {
+        let planes = video_planes();
+        let offset = y * 80 + x / 8;
+        (0..4).fold(0, |color, plane| {
+            color | ((planes.plane(plane)[offset] >> (7 - x % 8)) & 0x01) << plane
+        })
+    }
+
+    /// Returns the color of the pixel at `(x, y)` of `Graphics320x240x256`
+    /// from the simulated plane it's stored in.
+    #[cfg(feature = "modex")]
+    fn unchained_pixel(x: usize, y: usize) -> u8 {
+        video_planes().plane(x % 4)[y * 80 + x / 4]
+    }
+
+    #[cfg(feature = "text-modes")]
+    const BLANK_CHARACTER: ScreenCharacter = ScreenCharacter::BLANK;
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_put_char_wraps_and_scrolls() {
+        let _guard = lock_for_test();
+        let text_mode = Text40x25::new();
+        let color = TextModeColor::new(Color16::White, Color16::Black);
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        text_mode.set_cursor_position(Text40x25::WIDTH - 1, 0);
+
+        text_mode.put_char(b'A', color);
+        assert_eq!(text_mode.get_cursor_position(), (0, 1));
+
+        text_mode.set_cursor_position(Text40x25::WIDTH - 1, Text40x25::HEIGHT - 1);
+        text_mode.put_char(b'B', color);
+        assert_eq!(text_mode.get_cursor_position(), (0, Text40x25::HEIGHT - 1));
+        assert_eq!(
+            text_mode
+                .read_character(Text40x25::WIDTH - 1, Text40x25::HEIGHT - 2)
+                .get_character(),
+            b'B'
+        );
+        assert_eq!(
+            text_mode.read_character(Text40x25::WIDTH - 1, 0),
+            BLANK_CHARACTER
+        );
+
+        text_mode.put_char(b'\n', color);
+        assert_eq!(text_mode.get_cursor_position(), (0, Text40x25::HEIGHT - 1));
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_text_pages() {
+        let _guard = lock_for_test();
+        let text_mode = Text80x25::new();
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        assert_eq!(text_mode.get_page_count(), 8);
+        assert_eq!(
+            text_mode.set_active_page(8),
+            Err("text page is out of range")
+        );
+
+        let character =
+            ScreenCharacter::new(b'P', TextModeColor::new(Color16::White, Color16::Black));
+        text_mode.set_active_page(1).unwrap();
+        text_mode.write_character(0, 0, character);
+        text_mode.set_cursor_position(3, 2);
+        assert_eq!(text_mode.get_cursor_position(), (3, 2));
+        text_mode.set_displayed_page(1).unwrap();
+        assert_eq!(text_mode.get_displayed_page(), 1);
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::StartAddressHigh),
+                0x08
+            );
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::TextCursorLocationHigh),
+                0x08
+            );
+        }
+
+        text_mode.set_active_page(0).unwrap();
+        assert_eq!(text_mode.read_character(0, 0), BLANK_CHARACTER);
+        text_mode.set_mode();
+        assert_eq!(text_mode.get_active_page(), 0);
+        assert_eq!(text_mode.get_displayed_page(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_smooth_scroll_up() {
+        let _guard = lock_for_test();
+        let text_mode = Text80x25::new();
+        text_mode.set_mode();
+        let crtc = |index| MOCK_HARDWARE.lock().crtc_controller(index);
+
+        text_mode.smooth_scroll_up(5).unwrap();
+        assert_eq!(crtc(CrtcControllerIndex::PresetRowScan), 5);
+        assert_eq!(crtc(CrtcControllerIndex::StartAddressLow), 0);
+
+        text_mode.smooth_scroll_up(20).unwrap();
+        assert_eq!(crtc(CrtcControllerIndex::PresetRowScan), 9);
+        assert_eq!(crtc(CrtcControllerIndex::StartAddressLow), 80);
+
+        assert_eq!(
+            text_mode.smooth_scroll_up(16 * 200),
+            Err("can't scroll past the end of the frame buffer window")
+        );
+        assert_eq!(crtc(CrtcControllerIndex::PresetRowScan), 9);
+
+        text_mode.set_displayed_page(0).unwrap();
+        assert_eq!(crtc(CrtcControllerIndex::PresetRowScan), 0);
+        assert_eq!(crtc(CrtcControllerIndex::StartAddressLow), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_default_attribute() {
+        let _guard = lock_for_test();
+        let mut text_mode = Text80x25::new();
+        let color = TextModeColor::new(Color16::LightGreen, Color16::Blue);
+        text_mode.set_default_attribute(color);
+        text_mode.set_mode();
+        assert_eq!(text_mode.get_default_attribute(), color);
+        text_mode.clear_screen();
+        assert_eq!(
+            text_mode.read_character(79, 24),
+            ScreenCharacter::new(b' ', color)
+        );
+
+        text_mode.set_cursor_position(0, Text80x25::HEIGHT - 1);
+        text_mode.put_char(b'A', TextModeColor::new(Color16::White, Color16::Red));
+        write!(text_mode, "\n{}\u{e9}", 1).unwrap();
+        assert_eq!(text_mode.read_character(0, 23).get_character(), b'A');
+        assert_eq!(
+            text_mode.read_character(0, 24),
+            ScreenCharacter::new(b'1', color)
+        );
+        // Each byte of the two byte character is replaced.
+        assert_eq!(text_mode.read_character(2, 24).get_character(), 0xFE);
+        assert_eq!(
+            text_mode.read_character(3, 24),
+            ScreenCharacter::new(b' ', color)
+        );
+
+        text_mode.set_default_attribute(BLANK_CHARACTER.get_color());
+        text_mode.set_mode();
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_blank_character() {
+        let _guard = lock_for_test();
+        assert_eq!(ScreenCharacter::default(), ScreenCharacter::BLANK);
+        let blank = ScreenCharacter::BLANK
+            .with_character(b'.')
+            .with_foreground(Color16::LightCyan)
+            .with_background(Color16::Blue);
+        assert_eq!(
+            blank,
+            ScreenCharacter::new(b'.', TextModeColor::new(Color16::LightCyan, Color16::Blue))
+        );
+
+        let text_mode = Text40x25::new();
+        text_mode.set_mode();
+        text_mode.set_blank_character(blank);
+        assert_eq!(text_mode.get_default_attribute(), blank.get_color());
+        text_mode.clear_screen();
+        assert_eq!(text_mode.read_character(39, 24), blank);
+
+        // Setting the default attribute keeps the character.
+        let color = TextModeColor::new(Color16::White, Color16::Red);
+        text_mode.set_default_attribute(color);
+        assert_eq!(
+            text_mode.get_blank_character(),
+            blank
+                .with_foreground(Color16::White)
+                .with_background(Color16::Red)
+        );
+
+        text_mode.set_blank_character(ScreenCharacter::BLANK);
+        text_mode.set_mode();
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_clear_region() {
+        let _guard = lock_for_test();
+        let text_mode = Text40x25::new();
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        let shaded = ScreenCharacter::BLANK
+            .with_character(0xB0)
+            .with_foreground(Color16::DarkGrey);
+        text_mode.set_blank_character(shaded);
+
+        // Regions are clipped to the screen.
+        text_mode.clear_region(Rect::new(38, 23, 5, 5));
+        assert_eq!(text_mode.read_character(38, 23), shaded);
+        assert_eq!(text_mode.read_character(39, 24), shaded);
+        assert_eq!(text_mode.read_character(37, 23), BLANK_CHARACTER);
+        assert_eq!(text_mode.read_character(38, 22), BLANK_CHARACTER);
+
+        // Rows scrolled in are filled with the blank character too.
+        text_mode.set_cursor_position(0, Text40x25::HEIGHT - 1);
+        text_mode.put_char(b'\n', text_mode.get_default_attribute());
+        assert_eq!(text_mode.read_character(0, 24), shaded);
+        assert_eq!(text_mode.read_character(38, 22), shaded);
+        assert_eq!(text_mode.read_character(0, 23), BLANK_CHARACTER);
+
+        text_mode.set_blank_character(ScreenCharacter::BLANK);
+        text_mode.set_mode();
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_set_cursor_bounds() {
+        let _guard = lock_for_test();
+        let text_mode = Text80x25::new();
+        text_mode.set_mode();
+        assert_eq!(text_mode.max_scan_line(), 15);
+
+        let cursor = || {
+            let hardware = MOCK_HARDWARE.lock();
+            (
+                hardware.crtc_controller(CrtcControllerIndex::TextCursorStart) & 0x1F,
+                hardware.crtc_controller(CrtcControllerIndex::TextCursorEnd) & 0x1F,
+            )
+        };
+        text_mode.set_cursor(0, 15).unwrap();
+        assert_eq!(cursor(), (0, 15));
+        assert!(text_mode.set_cursor(14, 16).is_err());
+        assert!(text_mode.set_cursor(0x20, 15).is_err());
+        assert_eq!(cursor(), (0, 15));
+
+        text_mode.set_mode();
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_cursor_follow() {
+        let _guard = lock_for_test();
+        let text_mode = Text40x25::new();
+        let color = TextModeColor::new(Color16::White, Color16::Black);
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        text_mode.set_cursor_position(0, 0);
+
+        // Without cursor follow, the cursor stays and text is cut off.
+        text_mode.write_str_at(38, 24, "abc", color);
+        assert_eq!(text_mode.get_cursor_position(), (0, 0));
+        assert_eq!(text_mode.read_character(39, 24).get_character(), b'b');
+        assert_eq!(text_mode.read_character(39, 23), BLANK_CHARACTER);
+
+        text_mode.set_cursor_follow(true);
+        assert!(text_mode.get_cursor_follow());
+        text_mode.write_character(4, 2, ScreenCharacter::new(b'x', color));
+        assert_eq!(text_mode.get_cursor_position(), (5, 2));
+        // Wrapping at the end of the line and the newline each start a new line.
+        text_mode.write_str_at(38, 3, "ab\nc\u{7}", color);
+        assert_eq!(text_mode.read_character(0, 4), BLANK_CHARACTER);
+        assert_eq!(text_mode.read_character(0, 5).get_character(), b'c');
+        assert_eq!(text_mode.read_character(1, 5).get_character(), 0xFE);
+        assert_eq!(text_mode.get_cursor_position(), (2, 5));
+
+        // Writing the last cell scrolls the screen up.
+        text_mode.write_character(39, 24, ScreenCharacter::new(b'z', color));
+        assert_eq!(text_mode.get_cursor_position(), (0, 24));
+        assert_eq!(text_mode.read_character(39, 23).get_character(), b'z');
+        assert_eq!(text_mode.read_character(4, 1).get_character(), b'x');
+        text_mode.write_str_at(39, 24, "12", color);
+        assert_eq!(text_mode.read_character(39, 23).get_character(), b'1');
+        assert_eq!(text_mode.read_character(0, 24).get_character(), b'2');
+        assert_eq!(text_mode.get_cursor_position(), (1, 24));
+
+        text_mode.set_cursor_follow(false);
+        text_mode.set_mode();
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_region_colors() {
+        let _guard = lock_for_test();
+        let text_mode = Text80x25::new();
+        let color = TextModeColor::new(Color16::White, Color16::Blue);
+        let highlight = TextModeColor::new(Color16::Black, Color16::Cyan);
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        text_mode.write_str_at(0, 0, "select", color);
+
+        text_mode.set_region_color(Rect::new(2, 0, 3, 1), highlight);
+        assert_eq!(
+            text_mode.read_character(2, 0),
+            ScreenCharacter::new(b'l', highlight)
+        );
+        assert_eq!(text_mode.read_character(5, 0).get_color(), color);
+
+        // Regions are clipped to the screen.
+        text_mode.invert_region(Rect::new(4, 0, 100, 30));
+        assert_eq!(
+            text_mode.read_character(4, 0),
+            ScreenCharacter::new(b'c', highlight.inverted())
+        );
+        assert_eq!(
+            text_mode.read_character(5, 0),
+            ScreenCharacter::new(b't', color.inverted())
+        );
+        assert_eq!(
+            text_mode.read_character(79, 24).get_color(),
+            BLANK_CHARACTER.get_color().inverted()
+        );
+        assert_eq!(text_mode.read_character(3, 0).get_color(), highlight);
+        text_mode.set_mode();
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_modify_character() {
+        let _guard = lock_for_test();
+        let text_mode = Text80x25::new();
+        let color = TextModeColor::new(Color16::White, Color16::Blue);
+        text_mode.set_mode();
+        text_mode.write_character(3, 4, ScreenCharacter::new(b'a', color));
+
+        text_mode.modify_character(3, 4, |screen_character| {
+            let character = screen_character.get_character();
+            screen_character.set_character(character.to_ascii_uppercase());
+        });
+        assert_eq!(
+            text_mode.read_character(3, 4),
+            ScreenCharacter::new(b'A', color)
+        );
+
+        text_mode.modify_character(3, 4, |screen_character| {
+            screen_character.set_color(screen_character.get_color().inverted());
+        });
+        assert_eq!(
+            text_mode.read_character(3, 4),
+            ScreenCharacter::new(b'A', color.inverted())
+        );
+        text_mode.clear_screen();
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_iter_cells_and_rows() {
+        let _guard = lock_for_test();
+        let text_mode = Text40x25::new();
+        let color = TextModeColor::new(Color16::Yellow, Color16::Black);
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        text_mode.write_str_at(38, 1, "abc", color);
+
+        let cells = text_mode.iter_cells();
+        assert_eq!(cells.len(), 40 * 25);
+        let written: Vec<_> = cells
+            .filter(|(_, _, screen_character)| screen_character.get_character() != b' ')
+            .collect();
+        assert_eq!(
+            written,
+            [
+                (38, 1, ScreenCharacter::new(b'a', color)),
+                (39, 1, ScreenCharacter::new(b'b', color)),
+                (0, 2, ScreenCharacter::new(b'c', color)),
+            ]
+        );
+
+        let rows: Vec<_> = text_mode.iter_rows().collect();
+        assert_eq!(rows.len(), 25);
+        assert_eq!(rows[2].get_y(), 2);
+        assert_eq!(rows[2].as_slice().len(), 40);
+        assert_eq!(rows[2].as_slice()[0], ScreenCharacter::new(b'c', color));
+        let row: Vec<_> = rows[1].clone().skip(38).collect();
+        assert_eq!(
+            row,
+            [
+                (38, 1, ScreenCharacter::new(b'a', color)),
+                (39, 1, ScreenCharacter::new(b'b', color)),
+            ]
+        );
+        text_mode.clear_screen();
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_underline_location() {
+        let _guard = lock_for_test();
+        let text_mode = Text80x25::new();
+        text_mode.set_mode();
+        assert_eq!(text_mode.get_underline_location(), None);
+
+        text_mode.set_underline_location(Some(15));
+        assert_eq!(text_mode.get_underline_location(), Some(15));
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .crtc_controller(CrtcControllerIndex::UnderlineLocation),
+            0x0F
+        );
+        text_mode.set_underline_location(None);
+        assert_eq!(text_mode.get_underline_location(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_offset_of_and_point_of() {
+        assert_eq!(Text80x25::offset_of(3, 2), 163);
+        assert_eq!(Text80x25::point_of(163), (3, 2));
+        assert_eq!(Text80x25::column_major_offset_of(3, 2), 77);
+        assert_eq!(Text80x25::column_major_point_of(77), (3, 2));
+        assert_eq!(Text80x25::points().nth(81), Some((1, 1)));
+        assert_eq!(Text80x25::column_major_points().nth(26), Some((1, 1)));
+        assert_eq!(Text80x25::points().count(), Text80x25::SIZE);
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_mode_info() {
+        let _guard = lock_for_test();
+        let graphics_mode = Graphics640x480x16::new();
+        graphics_mode.set_mode();
+
+        let mode_info = graphics_mode.get_mode_info();
+        assert_eq!(VGA.lock().get_mode_info(), Some(mode_info));
+        assert_eq!(mode_info.get_width(), graphics_mode.get_width());
+        assert_eq!(mode_info.get_height(), graphics_mode.get_height());
+        assert_eq!(mode_info.get_bits_per_pixel(), 4);
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_clear_screen_write_combining() {
+        let _guard = lock_for_test();
+        let graphics_mode = Graphics320x200x256::new();
+        graphics_mode.set_mode();
+        assert_eq!(
+            VGA.lock().recommended_frame_buffer_memory_type(),
+            MemoryType::WriteCombining
+        );
+
+        VGA.lock()
+            .set_frame_buffer_memory_type(MemoryType::WriteCombining);
+        graphics_mode.clear_screen(0x2A);
+        VGA.lock()
+            .set_frame_buffer_memory_type(MemoryType::Uncacheable);
+
+        let frame_buffer = &crate::mock::video_memory()[..Graphics320x200x256::SIZE];
+        assert!(frame_buffer.iter().all(|byte| *byte == 0x2A));
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_blit_write_combining() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        let pixels = [1, 2, 3, 4, 5, 6];
+        let surface = Surface::new(&pixels, 3, 2);
+        let frame_buffer = mode.get_frame_buffer();
+        let row = |y| -> [u8; 4] {
+            let mut row = [0; 4];
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let offset = Graphics320x200x256::offset_of(316 + x, y);
+                *pixel = unsafe { frame_buffer.add(offset).read() };
+            }
+            row
+        };
+
+        for &memory_type in &[MemoryType::Uncacheable, MemoryType::WriteCombining] {
+            VGA.lock().set_frame_buffer_memory_type(memory_type);
+            mode.clear_screen(0);
+            mode.blit_scaled(&surface, surface.get_rect(), Rect::new(316, 0, 3, 2));
+            assert_eq!(row(0), [1, 2, 3, 0]);
+            assert_eq!(row(1), [4, 5, 6, 0]);
+
+            // Blits are clipped to the screen.
+            mode.blit_scaled(&surface, Rect::new(1, 0, 2, 2), Rect::new(319, 199, 2, 2));
+            assert_eq!(row(199), [0, 0, 0, 2]);
+
+            let rotate180 = BlitOptions::default().with_rotation(Rotation::Rotate180);
+            mode.blit_with_options(
+                &surface,
+                surface.get_rect(),
+                Rect::new(317, 2, 3, 2),
+                rotate180,
+            );
+            assert_eq!(row(2), [0, 6, 5, 4]);
+            assert_eq!(row(3), [0, 3, 2, 1]);
+            mode.blit_with_options(
+                &surface,
+                surface.get_rect(),
+                Rect::new(318, 4, 3, 2),
+                rotate180,
+            );
+            assert_eq!(row(4), [0, 0, 6, 5]);
+            assert_eq!(row(5), [0, 0, 3, 2]);
+
+            mode.draw_rect((317, 6), (319, 8), 9);
+            assert_eq!(row(6), [0, 9, 9, 0]);
+            assert_eq!(row(7), [0, 9, 9, 0]);
+            assert_eq!(row(8), [0, 0, 0, 0]);
+        }
+        VGA.lock()
+            .set_frame_buffer_memory_type(MemoryType::Uncacheable);
+    }
+
+    #[test]
+    #[cfg(all(feature = "text-modes", feature = "graphics-modes"))]
+    fn test_with_vga() {
+        let _guard = lock_for_test();
+        Graphics640x480x16::new().set_mode();
+
+        let vga = Spinlock::new(Vga::new());
+        let text_mode = Text80x25::with_vga(&vga);
+        let character =
+            ScreenCharacter::new(b'V', TextModeColor::new(Color16::White, Color16::Blue));
+        text_mode.set_mode();
+        text_mode.write_character(1, 0, character);
+
+        assert_eq!(text_mode.read_character(1, 0), character);
+        assert_eq!(
+            vga.lock().get_mode_info(),
+            Some(VideoMode::Mode80x25.mode_info())
+        );
+        assert_eq!(
+            VGA.lock().get_mode_info(),
+            Some(VideoMode::Mode640x480x16.mode_info())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "text-modes")]
+    fn test_write_snapshot() {
+        use alloc::string::String;
+
+        let _guard = lock_for_test();
+        let text_mode = Text40x25::new();
+        let color = TextModeColor::new(Color16::Yellow, Color16::Blue);
+        text_mode.set_mode();
+        text_mode.clear_screen();
+        for (x, byte) in b"Hi \xC9\xCD!".iter().enumerate() {
+            text_mode.write_character(x + 1, 1, ScreenCharacter::new(*byte, color));
+        }
+
+        let row = text_mode.snapshot().nth(1).unwrap().nth(4).unwrap();
+        assert_eq!(row, ('╔', Color16::Yellow, Color16::Blue));
+
+        let mut dump = String::new();
+        text_mode.write_snapshot(&mut dump).unwrap();
+        assert_eq!(dump.lines().count(), Text40x25::HEIGHT);
+        assert!(dump.starts_with("\n Hi ╔═!\n"));
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_draw_rect() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+        mode.draw_rect((12, 5), (10, 7), 3);
+        let frame_buffer = mode.get_frame_buffer();
+        let pixel = |x, y| unsafe {
+            frame_buffer
+                .add(Graphics320x200x256::offset_of(x, y))
+                .read()
+        };
+        assert_eq!(
+            [pixel(9, 5), pixel(10, 5), pixel(11, 6), pixel(12, 6)],
+            [0, 3, 3, 0]
+        );
+        assert_eq!([pixel(10, 4), pixel(10, 7)], [0, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_blit_scaled() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+        let pixels = [1, 2, 3, 4, 5, 6];
+        let surface = Surface::new(&pixels, 3, 2);
+        let frame_buffer = mode.get_frame_buffer();
+        let row = |y| -> [u8; 5] {
+            let mut row = [0; 5];
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let offset = Graphics320x200x256::offset_of(315 + x, y);
+                *pixel = unsafe { frame_buffer.add(offset).read() };
+            }
+            row
+        };
+
+        mode.blit_scaled(&surface, Rect::new(1, 0, 2, 2), Rect::new(316, 0, 4, 4));
+        assert_eq!(row(0), [0, 2, 2, 3, 3]);
+        assert_eq!(row(1), [0, 2, 2, 3, 3]);
+        assert_eq!(row(3), [0, 5, 5, 6, 6]);
+        assert_eq!(row(4), [0; 5]);
+
+        mode.blit_scaled(&surface, surface.get_rect(), Rect::new(318, 198, 3, 1));
+        assert_eq!(row(198)[3..], [1, 2]);
+        assert_eq!(row(199), [0; 5]);
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_blit_with_options() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+        let pixels = [1, 2, 3, 4, 5, 6];
+        let surface = Surface::new(&pixels, 3, 2);
+        let frame_buffer = mode.get_frame_buffer();
+        let rect = |width: usize, height: usize| -> [[u8; 3]; 3] {
+            let mut rect = [[0; 3]; 3];
+            for (y, row) in rect.iter_mut().enumerate().take(height) {
+                for (x, pixel) in row.iter_mut().enumerate().take(width) {
+                    let offset = Graphics320x200x256::offset_of(x, y);
+                    *pixel = unsafe { frame_buffer.add(offset).read() };
+                }
+            }
+            rect
+        };
+        let blit = |options: BlitOptions, width, height| {
+            mode.blit_with_options(
+                &surface,
+                surface.get_rect(),
+                Rect::new(0, 0, width, height),
+                options,
+            );
+            rect(width, height)
+        };
+
+        let flipped = BlitOptions::default().with_flip_horizontal(true);
+        assert_eq!(blit(flipped, 3, 2), [[3, 2, 1], [6, 5, 4], [0; 3]]);
+        let flipped = BlitOptions::default().with_flip_vertical(true);
+        assert_eq!(blit(flipped, 3, 2), [[4, 5, 6], [1, 2, 3], [0; 3]]);
+        let rotated = BlitOptions::default().with_rotation(Rotation::Rotate90);
+        assert_eq!(blit(rotated, 2, 3), [[4, 1, 0], [5, 2, 0], [6, 3, 0]]);
+        let rotated = rotated.with_rotation(Rotation::Rotate180);
+        assert_eq!(blit(rotated, 3, 2), [[6, 5, 4], [3, 2, 1], [0; 3]]);
+        let rotated = rotated.with_rotation(Rotation::Rotate270);
+        assert_eq!(blit(rotated, 2, 3), [[3, 6, 0], [2, 5, 0], [1, 4, 0]]);
+        let both = rotated.with_flip_horizontal(true);
+        assert_eq!(blit(both, 2, 3), [[1, 4, 0], [2, 5, 0], [3, 6, 0]]);
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_clip_rect() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+        let frame_buffer = mode.get_frame_buffer();
+        let pixel = |x, y| unsafe {
+            frame_buffer
+                .add(Graphics320x200x256::offset_of(x, y))
+                .read()
+        };
+
+        mode.draw_line((-100, 10), (1000, 10), 1);
+        assert_eq!([pixel(0, 10), pixel(319, 10), pixel(0, 11)], [1, 1, 0]);
+
+        mode.set_clip_rect(Some(Rect::new(10, 20, 10, 10)));
+        assert_eq!(mode.get_clip_bounds(), Rect::new(10, 20, 10, 10));
+        mode.draw_line((0, 25), (319, 25), 2);
+        assert_eq!([pixel(9, 25), pixel(10, 25), pixel(19, 25)], [0, 2, 2]);
+        assert_eq!(pixel(20, 25), 0);
+        mode.draw_rect((15, 0), (100, 22), 3);
+        assert_eq!([pixel(15, 20), pixel(19, 21), pixel(20, 21)], [3, 3, 0]);
+        assert_eq!([pixel(15, 19), pixel(15, 22)], [0, 0]);
+
+        mode.set_mode();
+        assert_eq!(mode.get_clip_rect(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_draw_glyph_clipped() {
+        let _guard = lock_for_test();
+        let mode = Graphics640x480x16::new();
+        mode.set_mode();
+        mode.clear_screen(Color16::Black);
+        let red = u8::from(Color16::Red);
+
+        mode.set_clip_rect(Some(Rect::new(0, 0, 12, 4)));
+        mode.draw_glyph(8, 0, ' ', Color16::White, Color16::Red);
+        mode.set_clip_rect(None);
+        assert_eq!([planar_pixel(8, 0), planar_pixel(11, 3)], [red, red]);
+        assert_eq!([planar_pixel(12, 0), planar_pixel(8, 4)], [0x00, 0x00]);
+
+        // Glyphs at the edge of the screen don't wrap to the next row.
+        mode.draw_glyph(636, 476, ' ', Color16::White, Color16::Red);
+        mode.draw_glyph(640, 0, ' ', Color16::White, Color16::Red);
+        assert_eq!([planar_pixel(636, 476), planar_pixel(639, 479)], [red, red]);
+        assert_eq!([planar_pixel(0, 1), planar_pixel(0, 477)], [0x00, 0x00]);
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_draw_rect_planar() {
+        let _guard = lock_for_test();
+        let mode = Graphics640x480x16::new();
+        mode.set_mode();
+        mode.clear_screen(Color16::Black);
+        mode.draw_rect((6, 1), (17, 2), Color16::Yellow);
+        let yellow = u8::from(Color16::Yellow);
+        assert_eq!(
+            [
+                planar_pixel(5, 1),
+                planar_pixel(6, 1),
+                planar_pixel(16, 1),
+                planar_pixel(17, 1)
+            ],
+            [0x00, yellow, yellow, 0x00]
+        );
+        assert_eq!([planar_pixel(6, 0), planar_pixel(6, 2)], [0x00, 0x00]);
+
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::SetReset),
+            u8::from(Color16::Yellow)
+        );
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::EnableSetReset),
+            0x0F
+        );
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::BitMask),
+            0xFF
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_lines_planar() {
+        let _guard = lock_for_test();
+        let mode = Graphics640x480x16::new();
+        mode.set_mode();
+        mode.clear_screen(Color16::Black);
+        let red = u8::from(Color16::Red);
+        let bit_mask = || {
+            MOCK_HARDWARE
+                .lock()
+                .graphics_controller(GraphicsControllerIndex::BitMask)
+        };
+
+        mode.hline(2, 20, 3, Color16::Red);
+        assert_eq!(
+            [
+                planar_pixel(2, 2),
+                planar_pixel(3, 2),
+                planar_pixel(20, 2),
+                planar_pixel(21, 2)
+            ],
+            [0x00, red, red, 0x00]
+        );
+        assert_eq!(bit_mask(), 0xFF);
+
+        mode.vline(9, 5, 3, Color16::Red);
+        assert_eq!(
+            [
+                planar_pixel(9, 3),
+                planar_pixel(9, 5),
+                planar_pixel(9, 6),
+                planar_pixel(8, 4)
+            ],
+            [red, red, 0x00, 0x00]
+        );
+
+        mode.fill_span(7, 630, 700, Color16::Red);
+        assert_eq!(
+            [
+                planar_pixel(629, 7),
+                planar_pixel(630, 7),
+                planar_pixel(639, 7),
+                planar_pixel(0, 8)
+            ],
+            [0x00, red, red, 0x00]
+        );
+
+        mode.draw_line((100, 10), (200, 10), Color16::Red);
+        assert_eq!(
+            [
+                planar_pixel(99, 10),
+                planar_pixel(100, 10),
+                planar_pixel(200, 10),
+                planar_pixel(201, 10)
+            ],
+            [0x00, red, red, 0x00]
+        );
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .graphics_controller(GraphicsControllerIndex::SetReset),
+            u8::from(Color16::Red)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_find_pixels_matching() {
+        let _guard = lock_for_test();
+        let mode = Graphics640x480x16::new();
+        mode.set_mode();
+        mode.clear_screen(Color16::Black);
+        mode.set_pixel(14, 2, Color16::LightCyan);
+        mode.set_pixel(15, 2, Color16::Cyan);
+        for x in 16..20 {
+            mode.set_pixel(x, 3, Color16::Cyan);
+        }
+        mode.set_pixel(20, 3, Color16::Cyan);
+
+        let mut pixels = mode.find_pixels_matching(Color16::Cyan, Rect::new(10, 2, 10, 2));
+        assert_eq!(pixels.next(), Some((15, 2)));
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            // Write mode 2 is kept from clearing the screen.
+            assert_eq!(
+                hardware.graphics_controller(GraphicsControllerIndex::GraphicsMode),
+                0x0A
+            );
+            assert_eq!(
+                hardware.graphics_controller(GraphicsControllerIndex::ColorCompare),
+                u8::from(Color16::Cyan)
+            );
+            assert_eq!(
+                hardware.graphics_controller(GraphicsControllerIndex::ColorDontCare),
+                0x0F
+            );
+        }
+        assert!(pixels.eq([(16, 3), (17, 3), (18, 3), (19, 3)]));
+
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::GraphicsMode),
+            0x02
+        );
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::ColorCompare),
+            0x00
+        );
+        drop(hardware);
+        mode.clear_screen(Color16::Black);
+    }
+
+    #[test]
+    #[cfg(feature = "modex")]
+    fn test_draw_rect_unchained() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x240x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+        mode.draw_rect((5, 2), (14, 3), 7);
+        assert_eq!(
+            [
+                unchained_pixel(4, 2),
+                unchained_pixel(5, 2),
+                unchained_pixel(13, 2),
+                unchained_pixel(14, 2),
+                unchained_pixel(5, 1)
+            ],
+            [0, 7, 7, 0, 0]
+        );
+        assert_eq!(
+            MOCK_HARDWARE.lock().sequencer(SequencerIndex::PlaneMask),
+            0x0F
+        );
+
+        mode.fill_span(3, 9, 2, 5);
+        assert_eq!(
+            [
+                unchained_pixel(1, 3),
+                unchained_pixel(2, 3),
+                unchained_pixel(8, 3),
+                unchained_pixel(9, 3)
+            ],
+            [0, 5, 5, 0]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "modex")]
+    fn test_copy_rect_unchained() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x240x256::new();
+        mode.set_mode();
+        mode.fill_span(241, 1, 2, 9);
+        mode.fill_span(241, 2, 3, 10);
+        mode.copy_rect((0, 240), (16, 8), 32, 16);
+        assert_eq!([unchained_pixel(17, 9), unchained_pixel(18, 9)], [9, 10]);
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::GraphicsMode),
+            VideoMode::Mode320x240x256
+                .configuration()
+                .graphics_controller_register(GraphicsControllerIndex::GraphicsMode)
+                .unwrap()
+        );
+        assert_eq!(hardware.sequencer(SequencerIndex::PlaneMask), 0x0F);
+    }
+
+    #[test]
+    #[cfg(feature = "modex")]
+    #[should_panic]
+    fn test_copy_rect_unaligned() {
+        Graphics320x240x256::new().copy_rect((2, 0), (0, 0), 8, 8);
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_with_frame_buffer() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+        mode.with_frame_buffer(|batch| batch.draw_line((1, 1), (3, 1), 9));
+        let frame_buffer = mode.get_frame_buffer();
+        let pixel = |x, y| unsafe {
+            frame_buffer
+                .add(Graphics320x200x256::offset_of(x, y))
+                .read()
+        };
+        assert_eq!(
+            [pixel(0, 1), pixel(1, 1), pixel(3, 1), pixel(4, 1)],
+            [0, 9, 9, 0]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "graphics-modes")]
+    fn test_with_frame_buffer_planar() {
+        let _guard = lock_for_test();
+        let mode = Graphics640x480x16::new();
+        mode.set_mode();
+        mode.clear_screen(Color16::Black);
+        mode.with_frame_buffer(|batch| {
+            batch.set_pixel(3, 0, Color16::Red);
+            assert_eq!(
+                MOCK_HARDWARE
+                    .lock()
+                    .graphics_controller(GraphicsControllerIndex::BitMask),
+                0x10
+            );
+            batch.set_pixel(4, 0, Color16::Blue);
+        });
+        assert_eq!(
+            [planar_pixel(2, 0), planar_pixel(3, 0), planar_pixel(4, 0)],
+            [0x00, u8::from(Color16::Red), u8::from(Color16::Blue)]
+        );
+
+        let hardware = MOCK_HARDWARE.lock();
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::GraphicsMode) & 0x03,
+            0x02
+        );
+        assert_eq!(
+            hardware.graphics_controller(GraphicsControllerIndex::BitMask),
+            0xFF
+        );
     }
 }
@@ -0,0 +1,122 @@
+use crate::{
+    drawing::{Bresenham, Point},
+    registers::{PlaneMask, WriteMode},
+    vga::{MemoryLayout, Vga},
+    volatile::{read_planar, write_planar},
+};
+use core::{fmt, marker::PhantomData};
+use spinning_top::SpinlockGuard;
+
+/// A handle for drawing many pixels while the `Vga` stays locked, created
+/// with `GraphicsWriter::with_frame_buffer`.
+///
+/// The write mode is set up once for the whole batch, and registers are
+/// only written when a pixel needs a different bit or plane mask than the
+/// one before it, which makes drawing much faster than calling
+/// `GraphicsWriter::set_pixel` for every pixel.
+pub struct PixelBatch<'a, C> {
+    vga: SpinlockGuard<'a, Vga>,
+    frame_buffer: *mut u8,
+    layout: MemoryLayout,
+    width: usize,
+    mask: Option<u8>,
+    _color: PhantomData<C>,
+}
+
+impl<'a, C: Copy> PixelBatch<'a, C> {
+    pub(crate) fn new(mut vga: SpinlockGuard<'a, Vga>, layout: MemoryLayout, width: usize) -> Self {
+        let frame_buffer = usize::from(vga.get_frame_buffer()) as *mut u8;
+        if layout == MemoryLayout::Planar {
+            vga.graphics_controller_registers
+                .set_write_mode(WriteMode::Mode2);
+            vga.sequencer_registers
+                .set_plane_mask(PlaneMask::ALL_PLANES);
+        }
+        PixelBatch {
+            vga,
+            frame_buffer,
+            layout,
+            width,
+            mask: None,
+            _color: PhantomData,
+        }
+    }
+
+    /// Sets the given pixel at `(x, y)` to the given `color`.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: C) {
+        let offset = y * self.width + x;
+        unsafe {
+            match self.layout {
+                MemoryLayout::Planar => {
+                    self.set_mask(0x80 >> (x & 0x07));
+                    let pixel = self.frame_buffer.add(offset / 8);
+                    // Load the latches, so the other pixels of the byte are kept.
+                    read_planar(pixel);
+                    write_planar(pixel.cast::<C>(), color);
+                }
+                MemoryLayout::Unchained => {
+                    self.set_mask(1 << (x & 0x03));
+                    write_planar(self.frame_buffer.add(offset / 4).cast::<C>(), color);
+                }
+                _ => self
+                    .frame_buffer
+                    .cast::<C>()
+                    .add(offset)
+                    .write_volatile(color),
+            }
+        }
+    }
+
+    /// Draws a line from `start` to `end` with the specified `color`.
+    pub fn draw_line(&mut self, start: Point<isize>, end: Point<isize>, color: C) {
+        for (x, y) in Bresenham::new(start, end) {
+            self.set_pixel(x as usize, y as usize, color);
+        }
+    }
+
+    /// Returns the locked `Vga`, such as to change the palette mid-batch.
+    ///
+    /// **Note:** The batch assumes the write mode and masks it set up
+    /// stay unchanged.
+    pub fn get_vga(&mut self) -> &mut Vga {
+        &mut self.vga
+    }
+
+    fn set_mask(&mut self, mask: u8) {
+        if self.mask == Some(mask) {
+            return;
+        }
+        self.mask = Some(mask);
+        match self.layout {
+            MemoryLayout::Planar => self.vga.graphics_controller_registers.set_bit_mask(mask),
+            _ => self
+                .vga
+                .sequencer_registers
+                .set_plane_mask(PlaneMask::from_bits_truncate(mask)),
+        }
+    }
+}
+
+impl<C> fmt::Debug for PixelBatch<'_, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PixelBatch")
+            .field("frame_buffer", &self.frame_buffer)
+            .field("layout", &self.layout)
+            .field("width", &self.width)
+            .field("mask", &self.mask)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C> Drop for PixelBatch<'_, C> {
+    fn drop(&mut self) {
+        match self.layout {
+            MemoryLayout::Planar => self.vga.graphics_controller_registers.set_bit_mask(0xFF),
+            MemoryLayout::Unchained => self
+                .vga
+                .sequencer_registers
+                .set_plane_mask(PlaneMask::ALL_PLANES),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! A writer drawing rotated, for screens mounted on their side or upside down.
+
+use super::{GraphicsWriter, ModeOptions, PrimitiveDrawing, Screen};
+use crate::{
+    drawing::{BlitOptions, Point, Rect, Rotation, Surface},
+    vga::Vga,
+};
+use font8x8::UnicodeFonts;
+use spinning_top::SpinlockGuard;
+
+/// A `GraphicsWriter` drawing onto another writer rotated clockwise by a
+/// `Rotation`, such as for a portrait mounted display.
+///
+/// Coordinates are those of the rotated screen, so with `Rotate90` and
+/// `Rotate270` the width and height of the writer are swapped. Lines,
+/// rectangles and blits are rotated as a whole and drawn with the fast
+/// paths of the writer, while other drawing goes a pixel at a time.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "graphics-modes"))]
+/// # {
+/// use vga::drawing::Rotation;
+/// use vga::writers::{Graphics320x200x256, GraphicsWriter, Rotated};
+///
+/// let mode = Graphics320x200x256::new();
+/// mode.set_mode();
+///
+/// let portrait = Rotated::new(mode, Rotation::Rotate90);
+/// portrait.clear_screen(0);
+/// portrait.draw_rect((10, 10), (190, 20), 4);
+/// portrait.draw_character(10, 30, 'A', 15);
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Rotated<W> {
+    writer: W,
+    rotation: Rotation,
+}
+
+impl<W> Rotated<W> {
+    /// Creates a new `Rotated` drawing onto `writer` rotated by `rotation`.
+    pub const fn new(writer: W, rotation: Rotation) -> Rotated<W> {
+        Rotated { writer, rotation }
+    }
+
+    /// Returns the rotation the writer draws with.
+    pub const fn get_rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Returns the writer drawn onto.
+    pub const fn get_writer(&self) -> &W {
+        &self.writer
+    }
+}
+
+impl<W> Rotated<W> {
+    /// Returns the width of the rotated screen.
+    pub fn get_width(&self) -> usize
+    where
+        W: Screen,
+    {
+        match self.rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => W::WIDTH,
+            Rotation::Rotate90 | Rotation::Rotate270 => W::HEIGHT,
+        }
+    }
+
+    /// Returns the height of the rotated screen.
+    pub fn get_height(&self) -> usize
+    where
+        W: Screen,
+    {
+        match self.rotation {
+            Rotation::Rotate0 | Rotation::Rotate180 => W::HEIGHT,
+            Rotation::Rotate90 | Rotation::Rotate270 => W::WIDTH,
+        }
+    }
+
+    /// Draws a line from `start` to `end` with the specified `color`,
+    /// clipped to the clip bounds of the writer.
+    pub fn draw_line<C>(&self, start: Point<isize>, end: Point<isize>, color: C)
+    where
+        W: PrimitiveDrawing<C>,
+        C: Copy,
+    {
+        let (width, height) = (W::WIDTH as isize, W::HEIGHT as isize);
+        let rotate = |(x, y): Point<isize>| match self.rotation {
+            Rotation::Rotate0 => (x, y),
+            Rotation::Rotate90 => (width - 1 - y, x),
+            Rotation::Rotate180 => (width - 1 - x, height - 1 - y),
+            Rotation::Rotate270 => (y, height - 1 - x),
+        };
+        self.writer.draw_line(rotate(start), rotate(end), color);
+    }
+
+    /// Draws a filled rectangle from `p1` up to, but not including, `p2`
+    /// with the specified `color`, clipped to the clip bounds of the writer.
+    pub fn draw_rect<C>(&self, p1: Point<usize>, p2: Point<usize>, color: C)
+    where
+        W: PrimitiveDrawing<C>,
+        C: Copy,
+    {
+        let (x, y) = (p1.0.min(p2.0), p1.1.min(p2.1));
+        let rect = Rect::new(x, y, p1.0.max(p2.0) - x, p1.1.max(p2.1) - y);
+        let rect = self.rotate_rect(rect);
+        if !rect.is_empty() {
+            self.writer.draw_rect(
+                (rect.get_x(), rect.get_y()),
+                (
+                    rect.get_x() + rect.get_width(),
+                    rect.get_y() + rect.get_height(),
+                ),
+                color,
+            );
+        }
+    }
+
+    /// Copies `src_rect` of `src` to `dst_rect` of the rotated screen like
+    /// `PrimitiveDrawing::blit_with_options`.
+    ///
+    /// Blits within the rotated screen are drawn with the rotations added
+    /// up, so `Rotate180` gets the row copies of linear modes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src_rect` isn't within `src`.
+    pub fn blit_with_options<C>(
+        &self,
+        src: &Surface<'_, C>,
+        src_rect: Rect,
+        dst_rect: Rect,
+        options: BlitOptions,
+    ) where
+        W: PrimitiveDrawing<C>,
+        C: Copy,
+    {
+        let screen = Rect::new(0, 0, self.get_width(), self.get_height());
+        if dst_rect.intersection(screen) == dst_rect {
+            let rotation = add_rotations(options.get_rotation(), self.rotation);
+            self.writer.blit_with_options(
+                src,
+                src_rect,
+                self.rotate_rect(dst_rect),
+                options.with_rotation(rotation),
+            );
+            return;
+        }
+
+        assert!(
+            src_rect.intersection(src.get_rect()) == src_rect,
+            "source rect is outside of the surface"
+        );
+        if src_rect.is_empty() {
+            return;
+        }
+        let (width, height) = (src_rect.get_width(), src_rect.get_height());
+        let (rotated_width, rotated_height) = options.rotated_size(width, height);
+        let visible = dst_rect.intersection(screen);
+        for y in visible.get_y()..visible.get_y() + visible.get_height() {
+            let rotated_y = (y - dst_rect.get_y()) * rotated_height / dst_rect.get_height();
+            for x in visible.get_x()..visible.get_x() + visible.get_width() {
+                let rotated_x = (x - dst_rect.get_x()) * rotated_width / dst_rect.get_width();
+                let (src_x, src_y) = options.source_point(rotated_x, rotated_y, width, height);
+                let color = src.get_pixel(src_rect.get_x() + src_x, src_rect.get_y() + src_y);
+                self.set_pixel(x, y, color);
+            }
+        }
+    }
+
+    /// Copies `src_rect` of `src` to `dst_rect` of the rotated screen like
+    /// `PrimitiveDrawing::blit_scaled`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src_rect` isn't within `src`.
+    pub fn blit_scaled<C>(&self, src: &Surface<'_, C>, src_rect: Rect, dst_rect: Rect)
+    where
+        W: PrimitiveDrawing<C>,
+        C: Copy,
+    {
+        self.blit_with_options(src, src_rect, dst_rect, BlitOptions::default());
+    }
+
+    /// Returns the area of the writer covered by `rect` of the rotated
+    /// screen, clipping `rect` to the rotated screen.
+    fn rotate_rect(&self, rect: Rect) -> Rect
+    where
+        W: Screen,
+    {
+        let rect = rect.intersection(Rect::new(0, 0, self.get_width(), self.get_height()));
+        let (x, y) = (rect.get_x(), rect.get_y());
+        let (width, height) = (rect.get_width(), rect.get_height());
+        match self.rotation {
+            Rotation::Rotate0 => rect,
+            Rotation::Rotate90 => Rect::new(W::WIDTH - (y + height), x, height, width),
+            Rotation::Rotate180 => Rect::new(
+                W::WIDTH - (x + width),
+                W::HEIGHT - (y + height),
+                width,
+                height,
+            ),
+            Rotation::Rotate270 => Rect::new(y, W::HEIGHT - (x + width), height, width),
+        }
+    }
+}
+
+impl<W: PrimitiveDrawing<C>, C: Copy> GraphicsWriter<C> for Rotated<W> {
+    fn lock_vga(&self) -> SpinlockGuard<'_, Vga> {
+        self.writer.lock_vga()
+    }
+
+    fn clear_screen(&self, color: C) {
+        self.writer.clear_screen(color);
+    }
+
+    fn draw_character(&self, x: usize, y: usize, character: char, color: C) {
+        let character = match font8x8::BASIC_FONTS.get(character) {
+            Some(character) => character,
+            // Default to a filled block if the character isn't found
+            None => font8x8::unicode::BLOCK_UNICODE[8].byte_array(),
+        };
+
+        for (row, byte) in character.iter().enumerate() {
+            for bit in 0..8 {
+                match *byte & 1 << bit {
+                    0 => (),
+                    _ => self.set_pixel(x + bit, y + row, color),
+                }
+            }
+        }
+    }
+
+    fn set_pixel(&self, x: usize, y: usize, color: C) {
+        if x >= self.get_width() || y >= self.get_height() {
+            return;
+        }
+        let (x, y) = match self.rotation {
+            Rotation::Rotate0 => (x, y),
+            Rotation::Rotate90 => (W::WIDTH - 1 - y, x),
+            Rotation::Rotate180 => (W::WIDTH - 1 - x, W::HEIGHT - 1 - y),
+            Rotation::Rotate270 => (y, W::HEIGHT - 1 - x),
+        };
+        self.writer.set_pixel(x, y, color);
+    }
+
+    fn set_mode(&self) {
+        self.writer.set_mode();
+    }
+
+    fn set_mode_with_options(&self, options: ModeOptions)
+    where
+        C: Default,
+    {
+        self.writer.set_mode_with_options(options);
+    }
+}
+
+/// Returns the rotation of rotating by `first` and then by `second`.
+fn add_rotations(first: Rotation, second: Rotation) -> Rotation {
+    let quarter_turns = |rotation| match rotation {
+        Rotation::Rotate0 => 0,
+        Rotation::Rotate90 => 1,
+        Rotation::Rotate180 => 2,
+        Rotation::Rotate270 => 3,
+    };
+    match (quarter_turns(first) + quarter_turns(second)) % 4 {
+        0 => Rotation::Rotate0,
+        1 => Rotation::Rotate90,
+        2 => Rotation::Rotate180,
+        _ => Rotation::Rotate270,
+    }
+}
+
+#[cfg(all(test, feature = "global", feature = "graphics-modes"))]
+mod test {
+    use super::*;
+    use crate::{
+        mock::{lock_for_test, video_memory},
+        writers::Graphics320x200x256,
+    };
+
+    fn pixel(x: usize, y: usize) -> u8 {
+        video_memory()[Graphics320x200x256::offset_of(x, y)]
+    }
+
+    #[test]
+    fn test_rotated_pixels_and_rects() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+
+        let portrait = Rotated::new(mode, Rotation::Rotate90);
+        assert_eq!((portrait.get_width(), portrait.get_height()), (200, 320));
+        portrait.set_pixel(0, 0, 1);
+        portrait.set_pixel(3, 5, 2);
+        portrait.set_pixel(200, 0, 3);
+        assert_eq!(pixel(319, 0), 1);
+        assert_eq!(pixel(314, 3), 2);
+
+        portrait.draw_rect((2, 4), (5, 6), 4);
+        assert_eq!(pixel(315, 2), 4);
+        assert_eq!(pixel(314, 4), 4);
+        assert_eq!(pixel(313, 4), 0);
+        assert_eq!(pixel(315, 5), 0);
+
+        portrait.draw_line((10, 0), (10, 9), 5);
+        assert_eq!(pixel(319, 10), 5);
+        assert_eq!(pixel(310, 10), 5);
+
+        let upside_down = Rotated::new(mode, Rotation::Rotate180);
+        upside_down.set_pixel(0, 0, 6);
+        assert_eq!(pixel(319, 199), 6);
+        mode.clear_screen(0);
+    }
+
+    #[test]
+    fn test_rotated_blits() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        let pixels = [1, 2, 3, 4, 5, 6];
+        let surface = Surface::new(&pixels, 3, 2);
+
+        for rotation in [
+            Rotation::Rotate0,
+            Rotation::Rotate90,
+            Rotation::Rotate180,
+            Rotation::Rotate270,
+        ] {
+            let rotated = Rotated::new(mode, rotation);
+            mode.clear_screen(0);
+            rotated.blit_scaled(&surface, surface.get_rect(), Rect::new(7, 9, 3, 2));
+            // Partly off the rotated screen, so it's drawn a pixel at a time.
+            let (width, height) = (rotated.get_width(), rotated.get_height());
+            rotated.blit_scaled(
+                &surface,
+                surface.get_rect(),
+                Rect::new(width - 2, height - 1, 3, 2),
+            );
+            for (x, y, color) in [(7, 9, 1), (9, 9, 3), (7, 10, 4), (9, 10, 6)] {
+                let (x, y) = match rotation {
+                    Rotation::Rotate0 => (x, y),
+                    Rotation::Rotate90 => (319 - y, x),
+                    Rotation::Rotate180 => (319 - x, 199 - y),
+                    Rotation::Rotate270 => (y, 199 - x),
+                };
+                assert_eq!(pixel(x, y), color);
+            }
+            let (x, y) = match rotation {
+                Rotation::Rotate0 => (318, 199),
+                Rotation::Rotate90 => (0, 198),
+                Rotation::Rotate180 => (1, 0),
+                Rotation::Rotate270 => (319, 1),
+            };
+            assert_eq!(pixel(x, y), 1);
+        }
+        mode.clear_screen(0);
+    }
+}
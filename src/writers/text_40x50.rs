@@ -1,9 +1,11 @@
-use super::{Screen, TextWriter};
+use super::{ModeOptions, Screen, TextWriter};
 use crate::{
-    colors::DEFAULT_PALETTE,
     fonts::TEXT_8X8_FONT,
-    vga::{VideoMode, VGA},
+    registers::PlaneMask,
+    vga::{Vga, VgaError, VgaHandle, VideoMode},
 };
+use core::fmt;
+use spinning_top::{Spinlock, SpinlockGuard};
 
 const WIDTH: usize = 40;
 const HEIGHT: usize = 50;
@@ -16,6 +18,8 @@ const SIZE: usize = WIDTH * HEIGHT;
 /// Basic usage:
 ///
 /// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
 /// use vga::colors::{Color16, TextModeColor};
 /// use vga::writers::{ScreenCharacter, TextWriter, Text40x50};
 ///
@@ -26,32 +30,108 @@ const SIZE: usize = WIDTH * HEIGHT;
 /// text_mode.set_mode();
 /// text_mode.clear_screen();
 /// text_mode.write_character(0, 0, screen_character);
+/// # }
 /// ```
-#[derive(Debug, Clone, Copy, Default)]
-pub struct Text40x50;
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "global", derive(Default))]
+pub struct Text40x50<'a> {
+    vga: VgaHandle<'a>,
+}
 
-impl Screen for Text40x50 {
+impl Screen for Text40x50<'_> {
     const WIDTH: usize = WIDTH;
     const HEIGHT: usize = HEIGHT;
     const SIZE: usize = SIZE;
+    const VIDEO_MODE: VideoMode = VideoMode::Mode40x50;
 }
 
-impl TextWriter for Text40x50 {
+impl TextWriter for Text40x50<'_> {
+    fn lock_vga(&self) -> SpinlockGuard<'_, Vga> {
+        self.vga.lock()
+    }
+
+    fn try_lock_vga(&self) -> Option<SpinlockGuard<'_, Vga>> {
+        self.vga.try_lock()
+    }
+
     /// Sets the graphics device to `VideoMode::Mode40x50`.
     fn set_mode(&self) {
-        let mut vga = VGA.lock();
+        self.set_mode_with_options(ModeOptions::default());
+    }
+
+    fn set_mode_with_options(&self, options: ModeOptions) {
+        let mut vga = self.vga.lock();
         vga.set_video_mode(VideoMode::Mode40x50);
+        if options.get_clear_vram() {
+            if options.get_load_font() {
+                vga.clear_vram();
+            } else {
+                vga.clear_planes(PlaneMask::PLANE0 | PlaneMask::PLANE1);
+            }
+        }
 
         // Some bios mess up the palette when switching modes,
         // so explicitly set it.
-        vga.color_palette_registers.load_palette(&DEFAULT_PALETTE);
-        vga.load_font(&TEXT_8X8_FONT);
+        if options.get_load_palette() && vga.get_quirks().get_reload_palette() {
+            vga.load_active_palette();
+        }
+        if options.get_load_font() {
+            vga.load_font(&TEXT_8X8_FONT).unwrap();
+        }
+        drop(vga);
+
+        if options.get_clear() {
+            self.clear_screen();
+        }
+    }
+}
+
+impl fmt::Write for Text40x50<'_> {
+    /// Prints `s` with the default attribute, see `TextWriter::put_str`.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.put_str(s);
+        Ok(())
     }
 }
 
-impl Text40x50 {
-    /// Creates a new `Text40x50`.
-    pub const fn new() -> Text40x50 {
-        Text40x50
+impl Text40x50<'static> {
+    /// Creates a new `Text40x50` using the global `VGA`.
+    #[cfg(feature = "global")]
+    pub const fn new() -> Text40x50<'static> {
+        Text40x50 {
+            vga: VgaHandle::global(),
+        }
+    }
+
+    /// Creates a new `Text40x50` using the global `VGA`, after checking that
+    /// a vga is present with `Vga::probe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VgaError` from `Vga::probe` if no vga responds.
+    #[cfg(feature = "global")]
+    pub fn try_new() -> Result<Text40x50<'static>, VgaError> {
+        VgaHandle::global().lock().probe()?;
+        Ok(Text40x50::new())
+    }
+}
+
+impl<'a> Text40x50<'a> {
+    /// Creates a new `Text40x50` using `vga` instead of the global `VGA`.
+    pub const fn with_vga(vga: &'a Spinlock<Vga>) -> Text40x50<'a> {
+        Text40x50 {
+            vga: VgaHandle::new(vga),
+        }
+    }
+
+    /// Creates a new `Text40x50` using `vga`, after checking that a vga is
+    /// present with `Vga::probe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VgaError` from `Vga::probe` if no vga responds.
+    pub fn try_with_vga(vga: &'a Spinlock<Vga>) -> Result<Text40x50<'a>, VgaError> {
+        vga.lock().probe()?;
+        Ok(Text40x50::with_vga(vga))
     }
 }
@@ -0,0 +1,324 @@
+//! A text console drawn on the linear frame buffer of a `BochsDevice`.
+
+use crate::{
+    devices::BochsDevice,
+    fonts::{VgaFont, TEXT_8X16_FONT},
+    volatile::volatile_copy,
+};
+use core::fmt;
+
+const CHARACTER_WIDTH: usize = 8;
+const CURSOR_HEIGHT: usize = 2;
+
+/// A text console for the high resolution modes of a `BochsDevice`,
+/// drawing 8 pixel wide glyphs from a `VgaFont` scaled by a whole factor.
+///
+/// Colors are given as `0x00RRGGBB` and converted to the pixel format
+/// of the device's current `Bpp`. The console scrolls by moving the
+/// contents of the frame buffer up.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// use core::fmt::Write;
+/// use vga::devices::{BochsDevice, PhysicalAddress, Resolution, VirtualAddress};
+/// use vga::writers::TextOnGraphics;
+///
+/// let mut device = BochsDevice::new(
+///     PhysicalAddress::new(0xFD00_0000),
+///     VirtualAddress::new(0xFD00_0000),
+/// );
+/// device.set_resolution(Resolution::new(1024, 768)).unwrap();
+/// let mut console = TextOnGraphics::new(device);
+/// writeln!(console, "Hello World!").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TextOnGraphics {
+    device: BochsDevice,
+    font: &'static VgaFont,
+    scale: usize,
+    foreground: u32,
+    background: u32,
+    column: usize,
+    row: usize,
+    cursor_visible: bool,
+}
+
+impl TextOnGraphics {
+    /// Creates a new `TextOnGraphics` drawing white on black with
+    /// the standard 8x16 font on `device`, and clears the screen.
+    pub fn new(device: BochsDevice) -> TextOnGraphics {
+        let mut console = TextOnGraphics {
+            device,
+            font: &TEXT_8X16_FONT,
+            scale: 1,
+            foreground: 0x00FF_FFFF,
+            background: 0x0000_0000,
+            column: 0,
+            row: 0,
+            cursor_visible: false,
+        };
+        console.clear();
+        console
+    }
+
+    /// Returns the `BochsDevice` drawn on.
+    pub fn get_device(&self) -> &BochsDevice {
+        &self.device
+    }
+
+    /// Returns the `BochsDevice` drawn on, such as to change its mode,
+    /// which should be followed by `clear`.
+    pub fn get_device_mut(&mut self) -> &mut BochsDevice {
+        &mut self.device
+    }
+
+    /// Consumes the console, returning the `BochsDevice` drawn on.
+    pub fn into_device(self) -> BochsDevice {
+        self.device
+    }
+
+    /// Sets the `font` used for characters, drawn `scale` times their size,
+    /// and clears the screen.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale` is 0.
+    pub fn set_font(&mut self, font: &'static VgaFont, scale: usize) {
+        assert!(scale > 0, "scale must be at least 1");
+        self.font = font;
+        self.scale = scale;
+        self.clear();
+    }
+
+    /// Sets the colors, as `0x00RRGGBB`, used for characters written from now on.
+    pub fn set_colors(&mut self, foreground: u32, background: u32) {
+        self.foreground = foreground;
+        self.background = background;
+    }
+
+    /// Returns the number of columns of characters that fit on the screen.
+    pub fn get_columns(&self) -> usize {
+        self.device.get_resolution().get_width() / self.get_cell_width()
+    }
+
+    /// Returns the number of rows of characters that fit on the screen.
+    pub fn get_rows(&self) -> usize {
+        self.device.get_resolution().get_height() / self.get_cell_height()
+    }
+
+    /// Returns the current position of the cursor as `(column, row)`.
+    pub fn get_cursor_position(&self) -> (usize, usize) {
+        (self.column, self.row)
+    }
+
+    /// Moves the cursor to `(column, row)`, clamped to the screen.
+    pub fn set_cursor_position(&mut self, column: usize, row: usize) {
+        self.toggle_cursor();
+        self.column = column.min(self.get_columns().saturating_sub(1));
+        self.row = row.min(self.get_rows().saturating_sub(1));
+        self.toggle_cursor();
+    }
+
+    /// Shows or hides the cursor, drawn as an inverted underline.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        if visible != self.cursor_visible {
+            self.cursor_visible = true;
+            self.toggle_cursor();
+            self.cursor_visible = visible;
+        }
+    }
+
+    /// Clears the screen to the background color and moves the
+    /// cursor to the top left corner.
+    pub fn clear(&mut self) {
+        let color = self.device.get_bits_per_pixel().pack_color(self.background);
+        self.device.clear_screen(color);
+        self.column = 0;
+        self.row = 0;
+        self.toggle_cursor();
+    }
+
+    /// Writes `byte` at the cursor position and advances the cursor,
+    /// moving to the next line on `b'\n'` and scrolling when needed.
+    pub fn write_byte(&mut self, byte: u8) {
+        self.toggle_cursor();
+        if byte == b'\n' {
+            self.new_line();
+        } else {
+            self.draw_cell(self.column, self.row, byte);
+            self.column += 1;
+            if self.column >= self.get_columns() {
+                self.new_line();
+            }
+        }
+        self.toggle_cursor();
+    }
+
+    fn get_cell_width(&self) -> usize {
+        CHARACTER_WIDTH * self.scale
+    }
+
+    fn get_cell_height(&self) -> usize {
+        usize::from(self.font.get_character_height()) * self.scale
+    }
+
+    fn new_line(&mut self) {
+        self.column = 0;
+        if self.row + 1 < self.get_rows() {
+            self.row += 1;
+            return;
+        }
+
+        self.scroll();
+        let cell_height = self.get_cell_height();
+        let color = self.device.get_bits_per_pixel().pack_color(self.background);
+        self.device.fill_rect(
+            0,
+            self.row * cell_height,
+            self.device.get_resolution().get_width(),
+            cell_height,
+            color,
+        );
+    }
+
+    /// Moves every row up by one, leaving the last row as it was.
+    fn scroll(&self) {
+        let row_size = self.device.get_bytes_per_line() * self.get_cell_height();
+        let frame_buffer = self.device.get_virtual_address().as_mut_ptr::<u8>();
+        unsafe {
+            volatile_copy(
+                frame_buffer.add(row_size),
+                frame_buffer,
+                row_size * (self.get_rows() - 1),
+            );
+        }
+    }
+
+    fn draw_cell(&self, column: usize, row: usize, character: u8) {
+        let bpp = self.device.get_bits_per_pixel();
+        let foreground = bpp.pack_color(self.foreground);
+        let background = bpp.pack_color(self.background);
+        let character_height = usize::from(self.font.get_character_height());
+        let glyph_start = usize::from(character) * character_height;
+        let glyph = &self.font.get_font_data()[glyph_start..glyph_start + character_height];
+        let x = column * self.get_cell_width();
+        let y = row * self.get_cell_height();
+        for (line, bits) in glyph.iter().enumerate() {
+            for bit in 0..CHARACTER_WIDTH {
+                let color = if bits & (0x80 >> bit) != 0 {
+                    foreground
+                } else {
+                    background
+                };
+                self.device.fill_rect(
+                    x + bit * self.scale,
+                    y + line * self.scale,
+                    self.scale,
+                    self.scale,
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Inverts the underline at the cursor position if the cursor is
+    /// visible, so calling this twice restores the cell.
+    fn toggle_cursor(&self) {
+        if !self.cursor_visible || self.get_rows() == 0 || self.get_columns() == 0 {
+            return;
+        }
+        let bytes_per_pixel = self.device.get_bits_per_pixel().get_bytes_per_pixel();
+        let bytes_per_line = self.device.get_bytes_per_line();
+        let cursor_height = CURSOR_HEIGHT * self.scale;
+        let x = self.column * self.get_cell_width() * bytes_per_pixel;
+        let y = (self.row + 1) * self.get_cell_height() - cursor_height;
+        let frame_buffer = self.device.get_virtual_address().as_mut_ptr::<u8>();
+        for y in y..y + cursor_height {
+            for x in x..x + self.get_cell_width() * bytes_per_pixel {
+                unsafe {
+                    let byte = frame_buffer.add(y * bytes_per_line + x);
+                    byte.write_volatile(!byte.read_volatile());
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Write for TextOnGraphics {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        devices::{PhysicalAddress, Resolution, VirtualAddress},
+        mock::lock_for_test,
+    };
+
+    const WIDTH: usize = 16;
+    const HEIGHT: usize = 32;
+
+    fn is_foreground_row(frame_buffer: &[u32], row: usize, character: u8) -> bool {
+        let bits = TEXT_8X16_FONT.get_font_data()[usize::from(character) * 16 + 5];
+        (0..CHARACTER_WIDTH).all(|bit| {
+            let expected = if bits & (0x80 >> bit) != 0 {
+                0x00FF_FFFF
+            } else {
+                0x0000_0000
+            };
+            frame_buffer[(row * 16 + 5) * WIDTH + bit] == expected
+        })
+    }
+
+    #[test]
+    fn test_write_and_scroll() {
+        let _guard = lock_for_test();
+        let mut frame_buffer = [0xFFFF_FFFFu32; WIDTH * HEIGHT];
+        let virtual_address = VirtualAddress::new(frame_buffer.as_mut_ptr() as usize);
+        let mut device = BochsDevice::new(PhysicalAddress::new(0xFD00_0000), virtual_address);
+        device
+            .set_resolution(Resolution::new(WIDTH, HEIGHT))
+            .unwrap();
+
+        let mut console = TextOnGraphics::new(device);
+        assert_eq!((console.get_columns(), console.get_rows()), (2, 2));
+        console.write_byte(b'A');
+        console.write_byte(b'\n');
+        console.write_byte(b'B');
+        assert_eq!(console.get_cursor_position(), (1, 1));
+        console.write_byte(b'\n');
+        assert_eq!(console.get_cursor_position(), (0, 1));
+
+        assert!(is_foreground_row(&frame_buffer, 0, b'B'));
+        assert!(frame_buffer[WIDTH * 16..].iter().all(|pixel| *pixel == 0));
+    }
+
+    #[test]
+    fn test_cursor() {
+        let _guard = lock_for_test();
+        let mut frame_buffer = [0u32; WIDTH * HEIGHT];
+        let virtual_address = VirtualAddress::new(frame_buffer.as_mut_ptr() as usize);
+        let mut device = BochsDevice::new(PhysicalAddress::new(0xFD00_0000), virtual_address);
+        device
+            .set_resolution(Resolution::new(WIDTH, HEIGHT))
+            .unwrap();
+
+        let mut console = TextOnGraphics::new(device);
+        console.set_cursor_visible(true);
+        console.set_cursor_position(1, 0);
+
+        assert_eq!(frame_buffer[14 * WIDTH + 8], 0xFFFF_FFFF);
+        assert_eq!(frame_buffer[15 * WIDTH + 15], 0xFFFF_FFFF);
+        assert_eq!(frame_buffer[15 * WIDTH + 7], 0x0000_0000);
+        assert_eq!(frame_buffer[13 * WIDTH + 8], 0x0000_0000);
+    }
+}
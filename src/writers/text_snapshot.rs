@@ -0,0 +1,239 @@
+//! Reading back the contents of vga text modes.
+
+use super::{ScreenCharacter, TextWriter};
+use crate::{
+    colors::{Color16, TextModeColor},
+    vga::Vga,
+};
+use core::{fmt, ops::Range};
+use spinning_top::SpinlockGuard;
+
+/// The most columns a `TextCellRow` holds, the width of the 80 column text modes.
+const MAX_COLUMNS: usize = 80;
+
+/// The unicode characters of code page 437, the character set of the
+/// default vga fonts, with `0x00` shown as a space.
+const CODE_PAGE_437: [char; 256] = [
+    ' ', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼', //
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼', //
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/', //
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?', //
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', //
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^', '_', //
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', //
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⌂', //
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+/// Returns the unicode character displayed for the code page 437 `byte`.
+pub fn code_page_437_to_char(byte: u8) -> char {
+    CODE_PAGE_437[byte as usize]
+}
+
+/// An iterator over the rows of a text mode screen, created with
+/// `TextWriter::snapshot`.
+#[derive(Debug)]
+pub struct TextSnapshot<'a, T: TextWriter> {
+    writer: &'a T,
+    rows: Range<usize>,
+}
+
+impl<'a, T: TextWriter> TextSnapshot<'a, T> {
+    pub(crate) fn new(writer: &'a T) -> TextSnapshot<'a, T> {
+        TextSnapshot {
+            writer,
+            rows: T::rows(),
+        }
+    }
+}
+
+impl<'a, T: TextWriter> Iterator for TextSnapshot<'a, T> {
+    type Item = TextRow<'a, T>;
+
+    fn next(&mut self) -> Option<TextRow<'a, T>> {
+        let y = self.rows.next()?;
+        Some(TextRow {
+            writer: self.writer,
+            y,
+            columns: T::columns(),
+        })
+    }
+}
+
+/// An iterator over the `(character, foreground, background)` cells of
+/// a single row of a text mode screen.
+#[derive(Debug)]
+pub struct TextRow<'a, T: TextWriter> {
+    writer: &'a T,
+    y: usize,
+    columns: Range<usize>,
+}
+
+impl<T: TextWriter> Iterator for TextRow<'_, T> {
+    type Item = (char, Color16, Color16);
+
+    fn next(&mut self) -> Option<(char, Color16, Color16)> {
+        let x = self.columns.next()?;
+        let screen_character = self.writer.read_character(x, self.y);
+        let color = screen_character.get_color();
+        Some((
+            code_page_437_to_char(screen_character.get_character()),
+            color.get_foreground(),
+            color.get_background(),
+        ))
+    }
+}
+
+/// An iterator over the `(x, y, ScreenCharacter)` cells of a text mode
+/// screen in row-major order, created with `TextWriter::iter_cells`.
+///
+/// The vga driver stays locked until the iterator is dropped, so every
+/// cell is read from the same state of the screen.
+pub struct TextCells<'a> {
+    _vga: SpinlockGuard<'a, Vga>,
+    frame_buffer: *mut ScreenCharacter,
+    width: usize,
+    offsets: Range<usize>,
+}
+
+impl<'a> TextCells<'a> {
+    pub(crate) fn new(
+        vga: SpinlockGuard<'a, Vga>,
+        frame_buffer: *mut ScreenCharacter,
+        width: usize,
+        height: usize,
+    ) -> TextCells<'a> {
+        TextCells {
+            _vga: vga,
+            frame_buffer,
+            width,
+            offsets: 0..width * height,
+        }
+    }
+}
+
+impl Iterator for TextCells<'_> {
+    type Item = (usize, usize, ScreenCharacter);
+
+    fn next(&mut self) -> Option<(usize, usize, ScreenCharacter)> {
+        let offset = self.offsets.next()?;
+        let screen_character = unsafe { self.frame_buffer.add(offset).read_volatile() };
+        Some((offset % self.width, offset / self.width, screen_character))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.offsets.size_hint()
+    }
+}
+
+impl ExactSizeIterator for TextCells<'_> {}
+
+impl fmt::Debug for TextCells<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TextCells")
+            .field("frame_buffer", &self.frame_buffer)
+            .field("width", &self.width)
+            .field("offsets", &self.offsets)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An iterator over the rows of a text mode screen, created with
+/// `TextWriter::iter_rows`, which keeps the vga driver locked like
+/// `TextCells`.
+#[derive(Debug)]
+pub struct TextRows<'a> {
+    cells: TextCells<'a>,
+}
+
+impl<'a> TextRows<'a> {
+    pub(crate) fn new(cells: TextCells<'a>) -> TextRows<'a> {
+        assert!(
+            cells.width <= MAX_COLUMNS,
+            "text rows can hold at most 80 columns"
+        );
+        TextRows { cells }
+    }
+}
+
+impl Iterator for TextRows<'_> {
+    type Item = TextCellRow;
+
+    fn next(&mut self) -> Option<TextCellRow> {
+        if self.cells.offsets.is_empty() {
+            return None;
+        }
+        let width = self.cells.width;
+        let blank = ScreenCharacter::new(0, TextModeColor::new(Color16::Black, Color16::Black));
+        let mut row = TextCellRow {
+            y: self.cells.offsets.start / width,
+            cells: [blank; MAX_COLUMNS],
+            columns: 0..width,
+        };
+        for (x, _, screen_character) in self.cells.by_ref().take(width) {
+            row.cells[x] = screen_character;
+        }
+        Some(row)
+    }
+}
+
+/// A copy of a single row of a text mode screen, which iterates over its
+/// `(x, y, ScreenCharacter)` cells.
+#[derive(Debug, Clone)]
+pub struct TextCellRow {
+    y: usize,
+    cells: [ScreenCharacter; MAX_COLUMNS],
+    columns: Range<usize>,
+}
+
+impl TextCellRow {
+    /// Returns the row number of the `TextCellRow`.
+    pub fn get_y(&self) -> usize {
+        self.y
+    }
+
+    /// Returns the `ScreenCharacter`s of the whole row.
+    pub fn as_slice(&self) -> &[ScreenCharacter] {
+        &self.cells[..self.columns.end]
+    }
+}
+
+impl Iterator for TextCellRow {
+    type Item = (usize, usize, ScreenCharacter);
+
+    fn next(&mut self) -> Option<(usize, usize, ScreenCharacter)> {
+        let x = self.columns.next()?;
+        Some((x, self.y, self.cells[x]))
+    }
+}
+
+/// Writes the characters of `snapshot` to `writer`, one line per row
+/// with trailing spaces removed.
+pub(crate) fn write_snapshot<T: TextWriter>(
+    snapshot: TextSnapshot<'_, T>,
+    writer: &mut impl fmt::Write,
+) -> fmt::Result {
+    for row in snapshot {
+        let mut spaces = 0;
+        for (character, _, _) in row {
+            if character == ' ' {
+                spaces += 1;
+                continue;
+            }
+            for _ in 0..spaces {
+                writer.write_char(' ')?;
+            }
+            spaces = 0;
+            writer.write_char(character)?;
+        }
+        writer.write_char('\n')?;
+    }
+    Ok(())
+}
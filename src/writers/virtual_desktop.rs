@@ -0,0 +1,269 @@
+use super::{fill_frame_buffer, Graphics320x240x256, GraphicsWriter, ModeOptions, Screen};
+use crate::{
+    registers::{AttributeControllerIndex, CrtcControllerIndex, PlaneMask},
+    vga::Vga,
+    volatile::write_planar,
+};
+use font8x8::UnicodeFonts;
+use spinning_top::{Spinlock, SpinlockGuard};
+
+/// The size of video memory across all four planes.
+const VIDEO_MEMORY_SIZE: usize = 0x40000;
+
+/// A virtual screen larger than vga graphics mode 320x240x256, of which a
+/// 320x240 viewport is displayed using the crtc start address and
+/// horizontal pixel panning.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// # #[cfg(feature = "global")]
+/// # {
+/// use vga::writers::{GraphicsWriter, VirtualDesktop};
+///
+/// let desktop = VirtualDesktop::new(512, 480);
+/// desktop.set_mode();
+/// desktop.clear_screen(0);
+/// desktop.set_pixel(500, 400, 255);
+/// desktop.set_viewport(180, 240);
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct VirtualDesktop<'a> {
+    mode: Graphics320x240x256<'a>,
+    virtual_width: usize,
+    virtual_height: usize,
+}
+
+#[cfg(feature = "global")]
+impl VirtualDesktop<'static> {
+    /// Creates a new `VirtualDesktop` of `virtual_width` by `virtual_height` pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `virtual_width` isn't a multiple of 8 between 320 and 2040,
+    /// if `virtual_height` is less than 240, or if the virtual screen doesn't
+    /// fit in the 256K of video memory.
+    pub fn new(virtual_width: usize, virtual_height: usize) -> VirtualDesktop<'static> {
+        VirtualDesktop::with_mode(Graphics320x240x256::new(), virtual_width, virtual_height)
+    }
+}
+
+impl<'a> VirtualDesktop<'a> {
+    /// Creates a new `VirtualDesktop` like `VirtualDesktop::new`, using `vga`
+    /// instead of the global `VGA`.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as `VirtualDesktop::new`.
+    pub fn with_vga(
+        vga: &'a Spinlock<Vga>,
+        virtual_width: usize,
+        virtual_height: usize,
+    ) -> VirtualDesktop<'a> {
+        VirtualDesktop::with_mode(
+            Graphics320x240x256::with_vga(vga),
+            virtual_width,
+            virtual_height,
+        )
+    }
+
+    fn with_mode(
+        mode: Graphics320x240x256<'a>,
+        virtual_width: usize,
+        virtual_height: usize,
+    ) -> VirtualDesktop<'a> {
+        assert!(
+            virtual_width & 0x7 == 0
+                && (Graphics320x240x256::WIDTH..=2040).contains(&virtual_width),
+            "virtual width must be a multiple of 8 between 320 and 2040"
+        );
+        assert!(
+            virtual_height >= Graphics320x240x256::HEIGHT,
+            "virtual height must be at least 240"
+        );
+        assert!(
+            virtual_width * virtual_height <= VIDEO_MEMORY_SIZE,
+            "virtual screen doesn't fit in video memory"
+        );
+        VirtualDesktop {
+            mode,
+            virtual_width,
+            virtual_height,
+        }
+    }
+
+    /// Returns the size of the virtual screen as `(width, height)`.
+    pub fn get_virtual_size(&self) -> (usize, usize) {
+        (self.virtual_width, self.virtual_height)
+    }
+
+    /// Displays the viewport with its top left corner at `(x, y)` of the
+    /// virtual screen, clamped so the viewport stays within it.
+    ///
+    /// The new start address takes effect on the next frame.
+    pub fn set_viewport(&self, x: usize, y: usize) {
+        let x = x.min(self.virtual_width - Graphics320x240x256::WIDTH);
+        let y = y.min(self.virtual_height - Graphics320x240x256::HEIGHT);
+        let start_address = (y * self.virtual_width + x) / 4;
+
+        let mut vga = self.mode.lock_vga();
+        let emulation_mode = vga.get_emulation_mode();
+        vga.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::StartAddressHigh,
+            (start_address >> 8) as u8,
+        );
+        vga.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::StartAddressLow,
+            start_address as u8,
+        );
+        // In 256 color modes, the panning is specified in half pixels.
+        vga.attribute_controller_registers.write(
+            emulation_mode,
+            AttributeControllerIndex::HorizontalPixelPanning,
+            ((x & 0x3) * 2) as u8,
+        );
+        vga.attribute_controller_registers
+            .unblank_screen(emulation_mode);
+    }
+
+    /// Returns the top left corner of the viewport as `(x, y)`.
+    pub fn get_viewport(&self) -> (usize, usize) {
+        let mut vga = self.mode.lock_vga();
+        let emulation_mode = vga.get_emulation_mode();
+        let start_address = (vga
+            .crtc_controller_registers
+            .read(emulation_mode, CrtcControllerIndex::StartAddressHigh)
+            as usize)
+            << 8
+            | vga
+                .crtc_controller_registers
+                .read(emulation_mode, CrtcControllerIndex::StartAddressLow) as usize;
+        let panning = vga.attribute_controller_registers.read(
+            emulation_mode,
+            AttributeControllerIndex::HorizontalPixelPanning,
+        ) as usize;
+        vga.attribute_controller_registers
+            .unblank_screen(emulation_mode);
+        let offset = start_address * 4 + ((panning / 2) & 0x3);
+        (offset % self.virtual_width, offset / self.virtual_width)
+    }
+}
+
+impl GraphicsWriter<u8> for VirtualDesktop<'_> {
+    fn lock_vga(&self) -> SpinlockGuard<'_, Vga> {
+        self.mode.lock_vga()
+    }
+
+    fn clear_screen(&self, color: u8) {
+        let frame_buffer = self.get_frame_buffer();
+        let memory_type = {
+            let mut vga = self.mode.lock_vga();
+            vga.sequencer_registers
+                .set_plane_mask(PlaneMask::ALL_PLANES);
+            vga.get_frame_buffer_memory_type()
+        };
+        unsafe {
+            fill_frame_buffer(
+                frame_buffer as *mut u32,
+                u32::from_ne_bytes([color; 4]),
+                self.virtual_width * self.virtual_height / 16,
+                memory_type,
+            );
+        }
+    }
+
+    fn set_pixel(&self, x: usize, y: usize, color: u8) {
+        let frame_buffer = self.get_frame_buffer();
+        unsafe {
+            let offset = (y * self.virtual_width + x) / 4;
+            let plane_mask = 0x1 << (x & 3);
+            self.mode
+                .lock_vga()
+                .sequencer_registers
+                .set_plane_mask(PlaneMask::from_bits(plane_mask).unwrap());
+            write_planar(frame_buffer.add(offset), color);
+        }
+    }
+
+    fn draw_character(&self, x: usize, y: usize, character: char, color: u8) {
+        let character = match font8x8::BASIC_FONTS.get(character) {
+            Some(character) => character,
+            // Default to a filled block if the character isn't found
+            None => font8x8::unicode::BLOCK_UNICODE[8].byte_array(),
+        };
+
+        for (row, byte) in character.iter().enumerate() {
+            for bit in 0..8 {
+                match *byte & 1 << bit {
+                    0 => (),
+                    _ => self.set_pixel(x + bit, y + row, color),
+                }
+            }
+        }
+    }
+
+    fn set_mode(&self) {
+        self.set_mode_with_options(ModeOptions::default());
+    }
+
+    /// Sets the mode like `set_mode`, clearing the whole virtual screen
+    /// if specified by `options`.
+    fn set_mode_with_options(&self, options: ModeOptions) {
+        self.mode.set_mode_with_options(options.with_clear(false));
+        let mut vga = self.mode.lock_vga();
+        let emulation_mode = vga.get_emulation_mode();
+        // In byte mode, the offset is the number of bytes per line divided by 2.
+        vga.crtc_controller_registers.write(
+            emulation_mode,
+            CrtcControllerIndex::Offset,
+            (self.virtual_width / 8) as u8,
+        );
+        drop(vga);
+        self.set_viewport(0, 0);
+
+        if options.get_clear() {
+            self.clear_screen(0);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "global"))]
+mod test {
+    use super::*;
+    use crate::mock::{lock_for_test, MOCK_HARDWARE};
+
+    #[test]
+    fn test_set_viewport() {
+        let _guard = lock_for_test();
+        let desktop = VirtualDesktop::new(512, 480);
+        desktop.set_mode();
+        assert_eq!(
+            MOCK_HARDWARE
+                .lock()
+                .crtc_controller(CrtcControllerIndex::Offset),
+            64
+        );
+
+        desktop.set_viewport(6, 2);
+        assert_eq!(desktop.get_viewport(), (6, 2));
+        {
+            let hardware = MOCK_HARDWARE.lock();
+            assert_eq!(
+                hardware.crtc_controller(CrtcControllerIndex::StartAddressLow),
+                ((2 * 512 + 4) / 4) as u8
+            );
+            assert_eq!(
+                hardware.attribute_controller(AttributeControllerIndex::HorizontalPixelPanning),
+                4
+            );
+        }
+
+        desktop.set_viewport(1000, 1000);
+        assert_eq!(desktop.get_viewport(), (512 - 320, 480 - 240));
+    }
+}
@@ -0,0 +1,184 @@
+//! A writer restricted to a rectangular window of another writer.
+
+use super::{GraphicsWriter, ModeOptions, PrimitiveDrawing};
+use crate::{
+    drawing::{Bresenham, Point, Rect},
+    vga::Vga,
+};
+use core::marker::PhantomData;
+use font8x8::UnicodeFonts;
+use spinning_top::SpinlockGuard;
+
+/// A `GraphicsWriter` drawing into a rectangular window of another writer,
+/// created with `PrimitiveDrawing::window`.
+///
+/// Coordinates are relative to the top left corner of the window, and
+/// anything drawn outside of the window is clipped.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```no_run
+/// # #[cfg(all(feature = "global", feature = "graphics-modes"))]
+/// # {
+/// use vga::drawing::Rect;
+/// use vga::writers::{Graphics320x200x256, GraphicsWriter, PrimitiveDrawing};
+///
+/// let mode = Graphics320x200x256::new();
+/// mode.set_mode();
+/// mode.clear_screen(0);
+///
+/// let window = mode.window(Rect::new(160, 0, 160, 100));
+/// window.clear_screen(1);
+/// window.draw_character(4, 4, 'A', 15);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WindowWriter<'w, W, C> {
+    writer: &'w W,
+    rect: Rect,
+    phantom: PhantomData<C>,
+}
+
+impl<'w, W: PrimitiveDrawing<C>, C: Copy> WindowWriter<'w, W, C> {
+    /// Creates a new `WindowWriter` drawing into `rect` of `writer`,
+    /// clipped to the screen.
+    pub fn new(writer: &'w W, rect: Rect) -> WindowWriter<'w, W, C> {
+        WindowWriter {
+            writer,
+            rect: rect.intersection(Rect::new(0, 0, W::WIDTH, W::HEIGHT)),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the area of the screen covered by the window.
+    pub fn get_rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Returns the width of the window.
+    pub fn get_width(&self) -> usize {
+        self.rect.get_width()
+    }
+
+    /// Returns the height of the window.
+    pub fn get_height(&self) -> usize {
+        self.rect.get_height()
+    }
+
+    /// Draws a line from `start` to `end` with the specified `color`,
+    /// clipped to the window.
+    pub fn draw_line(&self, start: Point<isize>, end: Point<isize>, color: C) {
+        for (x, y) in Bresenham::new(start, end) {
+            if x >= 0 && y >= 0 {
+                self.set_pixel(x as usize, y as usize, color);
+            }
+        }
+    }
+
+    /// Fills the rectangle from `p1` to `p2` with the specified `color`,
+    /// clipped to the window.
+    pub fn draw_rect(&self, p1: Point<usize>, p2: Point<usize>, color: C) {
+        let rect = Rect::new(
+            self.rect.get_x() + p1.0,
+            self.rect.get_y() + p1.1,
+            p2.0.saturating_sub(p1.0),
+            p2.1.saturating_sub(p1.1),
+        );
+        self.fill(rect.intersection(self.rect), color);
+    }
+
+    /// Fills `rect`, in screen coordinates, a line at a time using the
+    /// writer's `draw_rect`.
+    fn fill(&self, rect: Rect, color: C) {
+        if rect.is_empty() {
+            return;
+        }
+        self.writer.draw_rect(
+            (rect.get_x(), rect.get_y()),
+            (
+                rect.get_x() + rect.get_width(),
+                rect.get_y() + rect.get_height(),
+            ),
+            color,
+        );
+    }
+}
+
+impl<W: PrimitiveDrawing<C>, C: Copy> GraphicsWriter<C> for WindowWriter<'_, W, C> {
+    fn lock_vga(&self) -> SpinlockGuard<'_, Vga> {
+        self.writer.lock_vga()
+    }
+
+    /// Clears the window by setting all of its pixels to `color`.
+    fn clear_screen(&self, color: C) {
+        self.fill(self.rect, color);
+    }
+
+    fn draw_character(&self, x: usize, y: usize, character: char, color: C) {
+        let character = match font8x8::BASIC_FONTS.get(character) {
+            Some(character) => character,
+            // Default to a filled block if the character isn't found
+            None => font8x8::unicode::BLOCK_UNICODE[8].byte_array(),
+        };
+
+        for (row, byte) in character.iter().enumerate() {
+            for bit in 0..8 {
+                match *byte & 1 << bit {
+                    0 => (),
+                    _ => self.set_pixel(x + bit, y + row, color),
+                }
+            }
+        }
+    }
+
+    fn set_pixel(&self, x: usize, y: usize, color: C) {
+        if x < self.rect.get_width() && y < self.rect.get_height() {
+            self.writer
+                .set_pixel(self.rect.get_x() + x, self.rect.get_y() + y, color);
+        }
+    }
+
+    fn set_mode(&self) {
+        self.writer.set_mode();
+    }
+
+    fn set_mode_with_options(&self, options: ModeOptions)
+    where
+        C: Default,
+    {
+        self.writer.set_mode_with_options(options);
+    }
+}
+
+#[cfg(all(test, feature = "global", feature = "graphics-modes"))]
+mod test {
+    use super::*;
+    use crate::{
+        mock::lock_for_test,
+        writers::{Graphics320x200x256, Screen},
+    };
+
+    #[test]
+    fn test_window_clips() {
+        let _guard = lock_for_test();
+        let mode = Graphics320x200x256::new();
+        mode.set_mode();
+        mode.clear_screen(0);
+
+        let window = mode.window(Rect::new(300, 190, 40, 40));
+        assert_eq!(window.get_rect(), Rect::new(300, 190, 20, 10));
+        window.clear_screen(7);
+        window.set_pixel(1, 2, 9);
+        window.set_pixel(25, 2, 9);
+
+        let video_memory = crate::mock::video_memory();
+        let offset = |x: usize, y: usize| Graphics320x200x256::offset_of(x, y);
+        assert_eq!(video_memory[offset(299, 190)], 0);
+        assert_eq!(video_memory[offset(300, 190)], 7);
+        assert_eq!(video_memory[offset(319, 199)], 7);
+        assert_eq!(video_memory[offset(301, 192)], 9);
+        assert_eq!(video_memory[offset(5, 193)], 0);
+    }
+}
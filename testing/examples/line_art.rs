@@ -0,0 +1,52 @@
+//! Draws string art out of straight lines in the 640x480x16 mode.
+//!
+//! Run with `cargo run --example line_art` in the `testing` directory.
+
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+use testing::{gdt, hlt_loop, interrupts, serial_println};
+use vga::colors::Color16;
+use vga::writers::{Graphics640x480x16, GraphicsWriter, PrimitiveDrawing};
+
+const STEPS: isize = 32;
+
+#[no_mangle] // don't mangle the name of this function
+pub extern "C" fn _start() -> ! {
+    gdt::init();
+    interrupts::init_idt();
+
+    let mode = Graphics640x480x16::new();
+    mode.set_mode();
+    mode.clear_screen(Color16::Black);
+
+    // Each corner connects points along its two edges, curving the
+    // envelope of the lines.
+    let (right, bottom) = (639, 479);
+    let colors = [
+        Color16::LightRed,
+        Color16::Yellow,
+        Color16::LightGreen,
+        Color16::LightCyan,
+    ];
+    for step in 0..=STEPS {
+        let x = right * step / STEPS;
+        let y = bottom * step / STEPS;
+        mode.draw_line((0, y), (x, bottom), colors[0]);
+        mode.draw_line((right, bottom - y), (right - x, 0), colors[1]);
+        mode.draw_line((x, 0), (0, bottom - y), colors[2]);
+        mode.draw_line((right - x, bottom), (right, y), colors[3]);
+    }
+    for (offset, character) in "vga line art".chars().enumerate() {
+        mode.draw_character(272 + offset * 8, 236, character, Color16::White);
+    }
+
+    hlt_loop();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    serial_println!("{}", info);
+    hlt_loop();
+}
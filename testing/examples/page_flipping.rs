@@ -0,0 +1,74 @@
+//! Bounces a box around the 320x240x256 mode (mode X), drawing each frame
+//! on the hidden one of two pages and flipping to it once it's done, so
+//! the box never flickers.
+//!
+//! Run with `cargo run --example page_flipping` in the `testing` directory.
+
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+use testing::{gdt, hlt_loop, interrupts, serial_println};
+use vga::frame_counter::wait_frames;
+use vga::vga::VGA;
+use vga::writers::{Graphics320x240x256, GraphicsWriter, Screen, VirtualDesktop};
+
+const WIDTH: usize = Graphics320x240x256::WIDTH;
+const HEIGHT: usize = Graphics320x240x256::HEIGHT;
+const BOX_SIZE: usize = 32;
+const BACKGROUND: u8 = 0x01;
+const BOX_COLOR: u8 = 0x0E;
+
+/// Fills the box with its top left corner at `(x, y)` of `page`.
+fn fill_box(desktop: &VirtualDesktop<'_>, page: usize, (x, y): (usize, usize), color: u8) {
+    for row in y..y + BOX_SIZE {
+        for column in x..x + BOX_SIZE {
+            desktop.set_pixel(column, page * HEIGHT + row, color);
+        }
+    }
+}
+
+#[no_mangle] // don't mangle the name of this function
+pub extern "C" fn _start() -> ! {
+    gdt::init();
+    interrupts::init_idt();
+
+    // Two pages, one above the other.
+    let desktop = VirtualDesktop::new(WIDTH, HEIGHT * 2);
+    desktop.set_mode();
+    desktop.clear_screen(BACKGROUND);
+
+    let (mut x, mut y) = (0, 0);
+    let (mut dx, mut dy) = (2isize, 1isize);
+    // Where the box was last drawn on each page.
+    let mut drawn = [None; 2];
+    let mut back_page = 1;
+    loop {
+        if let Some(position) = drawn[back_page] {
+            fill_box(&desktop, back_page, position, BACKGROUND);
+        }
+        fill_box(&desktop, back_page, (x, y), BOX_COLOR);
+        drawn[back_page] = Some((x, y));
+
+        // The new start address takes effect on the next frame, so wait
+        // for it before drawing on the page that was just displayed.
+        desktop.set_viewport(0, back_page * HEIGHT);
+        wait_frames(&mut VGA.lock(), 1);
+        back_page ^= 1;
+
+        if x as isize + dx < 0 || x as isize + dx > (WIDTH - BOX_SIZE) as isize {
+            dx = -dx;
+        }
+        if y as isize + dy < 0 || y as isize + dy > (HEIGHT - BOX_SIZE) as isize {
+            dy = -dy;
+        }
+        x = (x as isize + dx) as usize;
+        y = (y as isize + dy) as usize;
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    serial_println!("{}", info);
+    hlt_loop();
+}
@@ -0,0 +1,84 @@
+//! Draws a plasma in the 320x200x256 mode (mode 13h) and animates it by
+//! rotating the palette every frame.
+//!
+//! Run with `cargo run --example plasma` in the `testing` directory.
+
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+use testing::{gdt, hlt_loop, interrupts, serial_println};
+use vga::colors::Palette;
+use vga::frame_counter::wait_frames;
+use vga::vga::VGA;
+use vga::writers::{Graphics320x200x256, GraphicsWriter, Screen};
+
+/// The sine of `i * 360 / 256` degrees, scaled to `-64..=64`.
+static SINE: [i8; 256] = sine_table();
+
+/// Builds the sine table with Bhaskara I's approximation, which is exact
+/// enough at this scale and needs no floating point.
+const fn sine_table() -> [i8; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        // The angle within a half turn, in degrees.
+        let degrees = (i % 128) as i32 * 180 / 128;
+        let product = degrees * (180 - degrees);
+        let sine = 4 * product * 64 / (40500 - product);
+        table[i] = if i < 128 { sine as i8 } else { -sine as i8 };
+        i += 1;
+    }
+    table
+}
+
+/// Returns the palette index of the plasma at `(x, y)`.
+fn plasma(x: usize, y: usize) -> u8 {
+    let sine = |i: usize| i32::from(SINE[i & 0xFF]);
+    let value = sine(x * 2) + sine(y * 3) + sine((x + y) * 2) + sine(x * 5 / 2 + y / 2);
+    // The sum is within -256..=256, so halve it into the palette.
+    ((value + 256) / 2) as u8
+}
+
+/// Returns the colors of smooth red, green and blue waves out of phase.
+fn plasma_colors() -> [(u8, u8, u8); 256] {
+    let mut colors = [(0, 0, 0); 256];
+    for (index, color) in colors.iter_mut().enumerate() {
+        let channel = |channel: usize| {
+            let sine = i32::from(SINE[(index + channel * 85) & 0xFF]);
+            // The palette registers hold 6 bit values.
+            ((sine + 64) * 63 / 128) as u8
+        };
+        *color = (channel(0), channel(1), channel(2));
+    }
+    colors
+}
+
+#[no_mangle] // don't mangle the name of this function
+pub extern "C" fn _start() -> ! {
+    gdt::init();
+    interrupts::init_idt();
+
+    let mode = Graphics320x200x256::new();
+    mode.set_mode();
+    for y in Graphics320x200x256::rows() {
+        for x in Graphics320x200x256::columns() {
+            mode.set_pixel(x, y, plasma(x, y));
+        }
+    }
+
+    let mut colors = plasma_colors();
+    loop {
+        colors.rotate_left(1);
+        let mut vga = VGA.lock();
+        wait_frames(&mut vga, 1);
+        vga.color_palette_registers
+            .load_palette(&Palette::new(colors));
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    serial_println!("{}", info);
+    hlt_loop();
+}
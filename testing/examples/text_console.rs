@@ -0,0 +1,65 @@
+//! Prints numbered lines to the 80x25 text mode, scrolling the screen
+//! once it's full.
+//!
+//! Run with `cargo run --example text_console` in the `testing` directory.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use testing::{gdt, hlt_loop, interrupts, serial_println};
+use vga::colors::{Color16, TextModeColor};
+use vga::frame_counter::wait_frames;
+use vga::vga::VGA;
+use vga::writers::{Text80x25, TextWriter};
+
+/// Formats text into a `TextWriter` with `put_char`.
+struct Console<T: TextWriter> {
+    writer: T,
+    color: TextModeColor,
+}
+
+impl<T: TextWriter> Write for Console<T> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.writer.put_char(byte, self.color);
+        }
+        Ok(())
+    }
+}
+
+#[no_mangle] // don't mangle the name of this function
+pub extern "C" fn _start() -> ! {
+    gdt::init();
+    interrupts::init_idt();
+
+    let text_mode = Text80x25::new();
+    text_mode.set_mode();
+    text_mode.clear_screen();
+    text_mode.set_cursor_position(0, 0);
+
+    let colors = [Color16::Yellow, Color16::LightCyan, Color16::LightGreen];
+    let mut console = Console {
+        writer: text_mode,
+        color: TextModeColor::new(colors[0], Color16::Black),
+    };
+    for line in 0.. {
+        console.color = TextModeColor::new(colors[line % colors.len()], Color16::Black);
+        writeln!(
+            console,
+            "line {}: the screen scrolls up once it's full",
+            line
+        )
+        .unwrap();
+        wait_frames(&mut VGA.lock(), 10);
+    }
+
+    hlt_loop();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    serial_println!("{}", info);
+    hlt_loop();
+}
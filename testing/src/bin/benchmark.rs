@@ -0,0 +1,166 @@
+//! Measures how many pixels per second the drawing primitives of the
+//! writers achieve in each graphics mode, and reports them over serial.
+//!
+//! Run with `cargo run --bin benchmark` in the `testing` directory. Like
+//! the tests, it exits qemu with exit code 33 when it's done.
+
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+use testing::timing::{read_tsc, tsc_frequency};
+use testing::{exit_qemu, gdt, interrupts, serial_println, QemuExitCode};
+use vga::colors::Color16;
+use vga::drawing::{Rect, Surface};
+use vga::writers::{
+    Graphics320x200x256, Graphics320x240x256, Graphics640x480x16, PrimitiveDrawing,
+};
+
+const SURFACE_WIDTH: usize = 64;
+const SURFACE_HEIGHT: usize = 64;
+const SURFACE_SIZE: usize = SURFACE_WIDTH * SURFACE_HEIGHT;
+const RECT_REPETITIONS: u64 = 4;
+
+#[no_mangle] // don't mangle the name of this function
+pub extern "C" fn _start() -> ! {
+    gdt::init();
+    interrupts::init_idt();
+
+    let tsc_frequency = tsc_frequency();
+    serial_println!("time stamp counter: {} Hz", tsc_frequency);
+
+    run_benchmarks(
+        "640x480x16",
+        &Graphics640x480x16::new(),
+        [Color16::LightBlue, Color16::Yellow],
+        tsc_frequency,
+    );
+    run_benchmarks(
+        "320x200x256",
+        &Graphics320x200x256::new(),
+        [0x20, 0x2C],
+        tsc_frequency,
+    );
+    run_benchmarks(
+        "320x240x256",
+        &Graphics320x240x256::new(),
+        [0x20, 0x2C],
+        tsc_frequency,
+    );
+
+    exit_qemu(QemuExitCode::Success);
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    testing::test_panic_handler(info)
+}
+
+/// Runs every benchmark for the mode of `writer`, drawing with `colors`.
+fn run_benchmarks<W, C>(mode: &str, writer: &W, colors: [C; 2], tsc_frequency: u64)
+where
+    W: PrimitiveDrawing<C>,
+    C: Copy,
+{
+    let (width, height) = (W::WIDTH, W::HEIGHT);
+    let screen_pixels = (width * height) as u64;
+    writer.set_mode();
+
+    measure(mode, "set_pixel", screen_pixels, tsc_frequency, || {
+        for y in W::rows() {
+            for x in W::columns() {
+                writer.set_pixel(x, y, colors[(x ^ y) & 1]);
+            }
+        }
+    });
+
+    measure(
+        mode,
+        "batched set_pixel",
+        screen_pixels,
+        tsc_frequency,
+        || {
+            writer.with_frame_buffer(|batch| {
+                for y in W::rows() {
+                    for x in W::columns() {
+                        batch.set_pixel(x, y, colors[(x ^ y) & 1]);
+                    }
+                }
+            })
+        },
+    );
+
+    // A fan of lines between the top and bottom edges.
+    let lines = (0..width).step_by(4);
+    let line_pixels = lines
+        .clone()
+        .map(|x| ((width - 1).abs_diff(2 * x)).max(height - 1) as u64 + 1)
+        .sum();
+    measure(mode, "draw_line", line_pixels, tsc_frequency, || {
+        for x in lines.clone() {
+            let end = (width - 1 - x) as isize;
+            writer.draw_line((x as isize, 0), (end, height as isize - 1), colors[x & 1]);
+        }
+    });
+
+    measure(
+        mode,
+        "batched draw_line",
+        line_pixels,
+        tsc_frequency,
+        || {
+            writer.with_frame_buffer(|batch| {
+                for x in lines.clone() {
+                    let end = (width - 1 - x) as isize;
+                    batch.draw_line((x as isize, 0), (end, height as isize - 1), colors[x & 1]);
+                }
+            })
+        },
+    );
+
+    let rect_pixels = screen_pixels * RECT_REPETITIONS;
+    measure(mode, "draw_rect", rect_pixels, tsc_frequency, || {
+        for repetition in 0..RECT_REPETITIONS as usize {
+            writer.draw_rect((0, 0), (width, height), colors[repetition & 1]);
+        }
+    });
+
+    let pixels: [C; SURFACE_SIZE] = core::array::from_fn(|i| colors[(i ^ (i / SURFACE_WIDTH)) & 1]);
+    let surface = Surface::new(&pixels, SURFACE_WIDTH, SURFACE_HEIGHT);
+    let (columns, rows) = (width / SURFACE_WIDTH, height / SURFACE_HEIGHT);
+    let blit_pixels = (columns * rows * SURFACE_SIZE) as u64;
+    measure(mode, "blit", blit_pixels, tsc_frequency, || {
+        for row in 0..rows {
+            for column in 0..columns {
+                let dst_rect = Rect::new(
+                    column * SURFACE_WIDTH,
+                    row * SURFACE_HEIGHT,
+                    SURFACE_WIDTH,
+                    SURFACE_HEIGHT,
+                );
+                writer.blit_scaled(&surface, surface.get_rect(), dst_rect);
+            }
+        }
+    });
+
+    measure(mode, "scaled blit", screen_pixels, tsc_frequency, || {
+        writer.blit_scaled(&surface, surface.get_rect(), Rect::new(0, 0, width, height));
+    });
+}
+
+/// Calls `f` and reports how many of `pixels` it drew per second.
+fn measure(mode: &str, name: &str, pixels: u64, tsc_frequency: u64, f: impl FnOnce()) {
+    let start = read_tsc();
+    f();
+    let cycles = (read_tsc() - start).max(1);
+    let pixels_per_second = u128::from(pixels) * u128::from(tsc_frequency) / u128::from(cycles);
+    serial_println!(
+        "{} {}: {} pixels in {} cycles, {} pixels/s",
+        mode,
+        name,
+        pixels,
+        cycles,
+        pixels_per_second
+    );
+}
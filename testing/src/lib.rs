@@ -10,6 +10,8 @@ use core::panic::PanicInfo;
 pub mod gdt;
 pub mod interrupts;
 pub mod serial;
+pub mod timing;
+pub mod vram;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
@@ -0,0 +1,43 @@
+//! Measuring elapsed time with the time stamp counter, calibrated against
+//! the programmable interval timer.
+
+use core::arch::x86_64::_rdtsc;
+use x86_64::instructions::port::Port;
+
+/// The frequency of the programmable interval timer in Hz.
+const PIT_FREQUENCY: u64 = 1_193_182;
+/// The time the time stamp counter is calibrated over, in milliseconds.
+const CALIBRATION_MILLISECONDS: u64 = 50;
+
+/// Returns the current value of the time stamp counter.
+pub fn read_tsc() -> u64 {
+    unsafe { _rdtsc() }
+}
+
+/// Returns the frequency of the time stamp counter in Hz, by counting
+/// its ticks while channel 2 of the programmable interval timer counts
+/// down from a known value.
+pub fn tsc_frequency() -> u64 {
+    let mut speaker_control: Port<u8> = Port::new(0x61);
+    let mut pit_command: Port<u8> = Port::new(0x43);
+    let mut pit_channel2: Port<u8> = Port::new(0x42);
+    let count = PIT_FREQUENCY * CALIBRATION_MILLISECONDS / 1000;
+
+    unsafe {
+        // Enable the channel 2 gate, with the speaker disconnected.
+        let control = speaker_control.read();
+        speaker_control.write((control & !0x02) | 0x01);
+        // Channel 2, low and high byte, mode 0 (interrupt on terminal count).
+        pit_command.write(0xB0);
+        pit_channel2.write(count as u8);
+        pit_channel2.write((count >> 8) as u8);
+
+        let start = read_tsc();
+        // Bit 5 reflects the output of channel 2, set on terminal count.
+        while speaker_control.read() & 0x20 == 0 {}
+        let end = read_tsc();
+
+        speaker_control.write(control);
+        (end - start) * 1000 / CALIBRATION_MILLISECONDS
+    }
+}
@@ -0,0 +1,70 @@
+//! Reading back video memory, so tests can check the pixels drawn by the
+//! writers and not only the registers they set.
+
+use core::convert::TryFrom;
+use vga::planar::PlanarFrameBuffer;
+use vga::registers::ReadPlane;
+use vga::vga::{MemoryLayout, Vga};
+
+/// Returns the color index of the pixel at `(x, y)` in the active
+/// graphics mode.
+///
+/// Panics if no mode is set or the active mode is a text mode.
+pub fn read_pixel(vga: &mut Vga, x: usize, y: usize) -> u8 {
+    let mode_info = vga.get_mode_info().expect("no video mode set");
+    assert!(
+        x < mode_info.get_width() && y < mode_info.get_height(),
+        "pixel ({}, {}) is off screen",
+        x,
+        y
+    );
+    let offset = y * mode_info.get_width() + x;
+    match mode_info.get_layout() {
+        MemoryLayout::Planar => {
+            let planes = PlanarFrameBuffer::new(vga).read_planes(offset / 8);
+            let bit = 7 - (x & 0x07);
+            planes.iter().enumerate().fold(0, |color, (plane, byte)| {
+                color | (((byte >> bit) & 0x01) << plane)
+            })
+        }
+        MemoryLayout::Unchained => {
+            let plane = ReadPlane::try_from((x & 0x03) as u8).unwrap();
+            PlanarFrameBuffer::new(vga).read_byte(plane, offset / 4)
+        }
+        MemoryLayout::Chained | MemoryLayout::Linear => {
+            let frame_buffer = usize::from(vga.get_frame_buffer()) as *const u8;
+            unsafe { frame_buffer.add(offset).read_volatile() }
+        }
+        MemoryLayout::Text => panic!("can't read pixels in a text mode"),
+    }
+}
+
+/// Asserts every pixel in the rectangle from `p1` up to `p2`, as drawn
+/// by `PrimitiveDrawing::draw_rect`, has the color index `color`.
+pub fn assert_rect(vga: &mut Vga, p1: (usize, usize), p2: (usize, usize), color: u8) {
+    for y in p1.1..p2.1 {
+        for x in p1.0..p2.0 {
+            let actual = read_pixel(vga, x, y);
+            assert_eq!(
+                actual, color,
+                "pixel ({}, {}) is {:#04X}, expected {:#04X}",
+                x, y, actual, color
+            );
+        }
+    }
+}
+
+/// Returns the number of pixels in the active graphics mode with the
+/// color index `color`.
+pub fn count_pixels(vga: &mut Vga, color: u8) -> usize {
+    let mode_info = vga.get_mode_info().expect("no video mode set");
+    let mut count = 0;
+    for y in 0..mode_info.get_height() {
+        for x in 0..mode_info.get_width() {
+            if read_pixel(vga, x, y) == color {
+                count += 1;
+            }
+        }
+    }
+    count
+}
@@ -0,0 +1,124 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![reexport_test_harness_main = "test_main"]
+#![test_runner(testing::test_runner)]
+
+use core::panic::PanicInfo;
+use testing::vram::{assert_rect, count_pixels, read_pixel};
+use testing::{gdt, interrupts, serial_print, serial_println};
+use vga::colors::Color16;
+use vga::vga::VGA;
+use vga::writers::{
+    Graphics320x200x256, Graphics320x240x256, Graphics640x480x16, GraphicsWriter, PrimitiveDrawing,
+};
+
+#[no_mangle] // don't mangle the name of this function
+pub extern "C" fn _start() -> ! {
+    init();
+    test_main();
+
+    loop {}
+}
+
+fn init() {
+    gdt::init();
+    interrupts::init_idt();
+    unsafe { interrupts::PICS.lock().initialize() };
+    x86_64::instructions::interrupts::enable();
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    testing::test_panic_handler(info)
+}
+
+#[test_case]
+fn draw_640x480x16() {
+    serial_print!("draw 640x480x16... ");
+
+    let mode = Graphics640x480x16::new();
+    mode.set_mode();
+    mode.clear_screen(Color16::Blue);
+    mode.set_pixel(3, 5, Color16::Yellow);
+    mode.draw_line((8, 10), (23, 10), Color16::White);
+    mode.draw_rect((100, 100), (110, 104), Color16::LightRed);
+
+    let mut vga = VGA.lock();
+    assert_eq!(read_pixel(&mut vga, 3, 5), u8::from(Color16::Yellow));
+    assert_eq!(read_pixel(&mut vga, 2, 5), u8::from(Color16::Blue));
+    assert_eq!(read_pixel(&mut vga, 4, 5), u8::from(Color16::Blue));
+    assert_rect(&mut vga, (8, 10), (24, 11), u8::from(Color16::White));
+    assert_eq!(read_pixel(&mut vga, 7, 10), u8::from(Color16::Blue));
+    assert_eq!(read_pixel(&mut vga, 24, 10), u8::from(Color16::Blue));
+    assert_rect(
+        &mut vga,
+        (100, 100),
+        (110, 104),
+        u8::from(Color16::LightRed),
+    );
+    assert_eq!(read_pixel(&mut vga, 110, 100), u8::from(Color16::Blue));
+    assert_eq!(read_pixel(&mut vga, 639, 479), u8::from(Color16::Blue));
+
+    serial_println!("[ok]");
+}
+
+#[test_case]
+fn draw_character_640x480x16() {
+    serial_print!("draw character 640x480x16... ");
+
+    let mode = Graphics640x480x16::new();
+    mode.set_mode();
+    mode.clear_screen(Color16::Black);
+    mode.draw_character(16, 16, '_', Color16::Green);
+
+    let mut vga = VGA.lock();
+    // The underscore is a single row at the bottom of the glyph.
+    assert_rect(&mut vga, (16, 16), (24, 23), u8::from(Color16::Black));
+    assert_rect(&mut vga, (16, 23), (24, 24), u8::from(Color16::Green));
+
+    serial_println!("[ok]");
+}
+
+#[test_case]
+fn draw_320x200x256() {
+    serial_print!("draw 320x200x256... ");
+
+    let mode = Graphics320x200x256::new();
+    mode.set_mode();
+    mode.clear_screen(0x00);
+    mode.draw_rect((10, 20), (20, 30), 0x2A);
+    mode.set_pixel(319, 199, 0xFE);
+
+    let mut vga = VGA.lock();
+    assert_rect(&mut vga, (10, 20), (20, 30), 0x2A);
+    assert_eq!(count_pixels(&mut vga, 0x2A), 100);
+    assert_eq!(read_pixel(&mut vga, 319, 199), 0xFE);
+    assert_eq!(read_pixel(&mut vga, 0, 0), 0x00);
+
+    serial_println!("[ok]");
+}
+
+#[test_case]
+fn draw_320x240x256() {
+    serial_print!("draw 320x240x256... ");
+
+    let mode = Graphics320x240x256::new();
+    mode.set_mode();
+    mode.clear_screen(0x00);
+    // Consecutive pixels are in consecutive planes.
+    for x in 0..4 {
+        mode.set_pixel(40 + x, 50, 0x10 + x as u8);
+    }
+    mode.draw_line((0, 239), (319, 239), 0x0F);
+
+    let mut vga = VGA.lock();
+    for x in 0..4 {
+        assert_eq!(read_pixel(&mut vga, 40 + x, 50), 0x10 + x as u8);
+    }
+    assert_eq!(read_pixel(&mut vga, 44, 50), 0x00);
+    assert_rect(&mut vga, (0, 239), (320, 240), 0x0F);
+    assert_rect(&mut vga, (0, 238), (320, 239), 0x00);
+
+    serial_println!("[ok]");
+}
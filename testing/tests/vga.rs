@@ -6,11 +6,12 @@
 
 use core::panic::PanicInfo;
 use testing::{gdt, interrupts, serial_print, serial_println};
-use vga::colors::{DEFAULT_PALETTE, PALETTE_SIZE};
+use vga::colors::{Palette, DEFAULT_PALETTE};
 use vga::configurations::{
     VgaConfiguration, MODE_1280X800X256_CONFIGURATION, MODE_40X25_CONFIGURATION,
     MODE_40X50_CONFIGURATION, MODE_640X480X16_CONFIGURATION, MODE_80X25_CONFIGURATION,
 };
+use vga::registers::CrtcControllerIndex;
 use vga::vga::{Vga, VideoMode, VGA};
 
 #[no_mangle] // don't mangle the name of this function
@@ -92,14 +93,12 @@ fn set_mode_1280x800x256() {
 fn load_palette() {
     serial_print!("load palette... ");
 
-    let mut palette = [0u8; PALETTE_SIZE];
+    let mut palette = Palette::<256>::default();
     let mut vga = VGA.lock();
     vga.color_palette_registers.load_palette(&DEFAULT_PALETTE);
     vga.color_palette_registers.read_palette(&mut palette);
 
-    for i in 0..PALETTE_SIZE {
-        assert_eq!(palette[i], DEFAULT_PALETTE[i]);
-    }
+    assert_eq!(palette, DEFAULT_PALETTE);
 
     serial_println!("[ok]");
 }
@@ -116,9 +115,14 @@ fn check_registers(vga: &mut Vga, configuration: &VgaConfiguration) {
     }
 
     for (index, value) in configuration.crtc_controller_registers {
+        // The crtc registers are locked again after setting a mode.
+        let value = match index {
+            CrtcControllerIndex::VerticalSyncEnd if vga.get_relock_crtc() => *value | 0x80,
+            _ => *value,
+        };
         assert_eq!(
             vga.crtc_controller_registers.read(emulation_mode, *index),
-            *value
+            value
         );
     }
 